@@ -0,0 +1,309 @@
+//! JSON job format, shared by the server's `POST /optimize` and the CLI's
+//! `--stdin`.
+
+use crate::report::ReportOptions;
+use crate::solver::{Solver, check_kerf};
+use crate::types::{
+    AreaDemand, CutDirection, Demand, PieceGrain, Rect, RotationConstraint, StockGrain,
+    deserialize_u32_from_number,
+};
+use serde::{Deserialize, Serialize};
+
+/// Default of [`OptimizeRequest::allow_rotate`].
+pub const DEFAULT_ALLOW_ROTATE: bool = true;
+
+#[derive(Deserialize, Serialize)]
+pub struct StockRequest {
+    #[serde(deserialize_with = "deserialize_u32_from_number")]
+    pub length: u32,
+    #[serde(deserialize_with = "deserialize_u32_from_number")]
+    pub width: u32,
+    #[serde(default)]
+    pub grain: StockGrain,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct OptimizeRequest {
+    pub stock: StockRequest,
+    pub cuts: Vec<CutRequest>,
+    #[serde(default, deserialize_with = "deserialize_u32_from_number")]
+    pub kerf: u32,
+    #[serde(default)]
+    pub cut_direction: CutDirection,
+    #[serde(default = "default_allow_rotate")]
+    pub allow_rotate: bool,
+    #[serde(default)]
+    pub cluster_labels: bool,
+    /// Strips ordered by total area, cut after the regular pieces.
+    #[serde(default)]
+    pub area_cuts: Vec<AreaDemand>,
+    /// Soft grain: off-grain placements allowed at this score penalty.
+    #[serde(default)]
+    pub grain_penalty: Option<u64>,
+    /// Ignore the grain of pieces that fit the sheet only across it.
+    #[serde(default)]
+    pub relax_grain_on_infeasible: bool,
+    /// Sheet names become `<prefix>-Sheet-N`.
+    #[serde(default)]
+    pub sheet_label_prefix: Option<String>,
+    /// Added to sheet numbers, to continue a batch across jobs.
+    #[serde(default)]
+    pub sheet_offset: usize,
+    /// Increasing offcut area bounds (mm²) for `offcut_histogram`.
+    #[serde(default)]
+    pub offcut_buckets: Option<Vec<u64>>,
+    #[serde(default)]
+    pub on_infeasible: OnInfeasible,
+    /// Lenient mode for generated jobs: cuts with `qty` 0 are dropped and
+    /// listed as rejected instead of failing validation.
+    #[serde(default)]
+    pub skip_zero_qty: bool,
+}
+
+/// What to do with cuts that don't fit the stock.
+#[derive(Deserialize, Serialize, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum OnInfeasible {
+    /// Fail validation.
+    #[default]
+    Reject,
+    /// Solve without them and list them as rejected.
+    Skip,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct CutRequest {
+    pub rect: Rect,
+    #[serde(deserialize_with = "deserialize_u32_from_number")]
+    pub qty: u32,
+    #[serde(default)]
+    pub grain: PieceGrain,
+    #[serde(default)]
+    pub material: Option<String>,
+    /// Extra gap around the piece: `[left, right, top, bottom]`.
+    #[serde(default)]
+    pub clearance: [u32; 4],
+    #[serde(default)]
+    pub label: Option<String>,
+    #[serde(default)]
+    pub directional: bool,
+    /// Machine setup: same-hint pieces are kept together where possible.
+    #[serde(default)]
+    pub group_hint: Option<u32>,
+    /// Pieces joined into one panel: all cut in the same orientation.
+    #[serde(default)]
+    pub grain_group: Option<u32>,
+    /// Kept away from large offcuts where possible.
+    #[serde(default)]
+    pub fragile: bool,
+    /// Extra pieces cut out of the waste only, never on a new sheet.
+    #[serde(default)]
+    pub optional_overflow: u32,
+}
+
+fn default_allow_rotate() -> bool {
+    DEFAULT_ALLOW_ROTATE
+}
+
+/// A request field that failed validation, e.g. `cuts[2].rect.length`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FieldError {
+    pub field: String,
+    pub message: String,
+}
+
+/// A cut left out with `on_infeasible: "skip"`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RejectedCut {
+    /// Position in the request's `cuts`.
+    pub index: usize,
+    pub rect: Rect,
+    pub qty: u32,
+    pub label: Option<String>,
+    pub reason: String,
+}
+
+impl OptimizeRequest {
+    pub fn stock(&self) -> Rect {
+        Rect::new(self.stock.length, self.stock.width)
+    }
+
+    /// Check the deserialized request, collecting every invalid field.
+    pub fn validate(&self) -> Vec<FieldError> {
+        let mut errors = Vec::new();
+        let mut error = |field: String, message: String| errors.push(FieldError { field, message });
+
+        let stock = self.stock();
+        if stock.length == 0 {
+            error("stock.length".to_string(), "must be non-zero".to_string());
+        }
+        if stock.width == 0 {
+            error("stock.width".to_string(), "must be non-zero".to_string());
+        }
+        if let Err(e) = check_kerf(stock, self.kerf) {
+            error("kerf".to_string(), e.to_string());
+        }
+
+        for (i, c) in self.cuts.iter().enumerate() {
+            if c.rect.length == 0 {
+                error(
+                    format!("cuts[{i}].rect.length"),
+                    "must be non-zero".to_string(),
+                );
+            }
+            if c.rect.width == 0 {
+                error(
+                    format!("cuts[{i}].rect.width"),
+                    "must be non-zero".to_string(),
+                );
+            }
+            if c.qty == 0 && !self.skip_zero_qty {
+                error(format!("cuts[{i}].qty"), "must be non-zero".to_string());
+            }
+            if c.rect.length == 0 || c.rect.width == 0 || stock.length == 0 || stock.width == 0 {
+                continue;
+            }
+            if c.qty == 0 {
+                // Dropped by take_demands, whether it fits or not
+                continue;
+            }
+            match self.cut_fits(c) {
+                Err(conflict) => error(format!("cuts[{i}].grain"), conflict),
+                Ok(false) if self.on_infeasible == OnInfeasible::Reject => {
+                    error(format!("cuts[{i}].rect"), self.does_not_fit(c))
+                }
+                Ok(_) => {}
+            }
+        }
+
+        for (i, a) in self.area_cuts.iter().enumerate() {
+            if a.width == 0 {
+                error(
+                    format!("area_cuts[{i}].width"),
+                    "must be non-zero".to_string(),
+                );
+            }
+        }
+        if let Some(buckets) = &self.offcut_buckets
+            && buckets.windows(2).any(|w| w[0] >= w[1])
+        {
+            error(
+                "offcut_buckets".to_string(),
+                "must be in increasing order".to_string(),
+            );
+        }
+        errors
+    }
+
+    /// Whether cut `c` fits the stock in an orientation its grain and the
+    /// request allow. `Err` is a grain conflict.
+    pub fn cut_fits(&self, c: &CutRequest) -> Result<bool, String> {
+        let stock = self.stock();
+        // Soft grain never restricts rotation; relaxed grain only when the
+        // piece wouldn't fit otherwise
+        let stock_grain = if self.grain_penalty.is_some() || self.relax_grain_on_infeasible {
+            StockGrain::None
+        } else {
+            self.stock.grain
+        };
+        let allow_rotate = self.allow_rotate && !c.directional;
+        let rotation = RotationConstraint::from_grain(stock_grain, c.grain, allow_rotate)
+            .map_err(|conflict| conflict.to_string())?
+            .with_cut_direction(self.cut_direction, c.rect);
+        let footprint = c.rect.with_clearance(c.clearance);
+        Ok(match rotation {
+            RotationConstraint::NoRotate => footprint.fits_in(&stock),
+            RotationConstraint::ForceRotate => footprint.rotated().fits_in(&stock),
+            RotationConstraint::Free => {
+                footprint.fits_in(&stock) || footprint.rotated().fits_in(&stock)
+            }
+        })
+    }
+
+    pub fn does_not_fit(&self, c: &CutRequest) -> String {
+        format!(
+            "piece {}x{} does not fit in stock {}x{}",
+            c.rect.length, c.rect.width, self.stock.length, self.stock.width
+        )
+    }
+
+    /// Move the cuts out as demands. With `on_infeasible: "skip"`, those
+    /// that don't fit are left out and returned as rejected, as are those
+    /// with `qty` 0 with `skip_zero_qty`.
+    pub fn take_demands(&mut self) -> (Vec<Demand>, Vec<RejectedCut>) {
+        let mut rejected = Vec::new();
+        let mut cuts = std::mem::take(&mut self.cuts);
+        let mut index = 0;
+        cuts.retain(|c| {
+            let reason = if c.qty == 0 && self.skip_zero_qty {
+                Some("quantity is zero".to_string())
+            } else if self.on_infeasible == OnInfeasible::Skip && self.cut_fits(c) == Ok(false) {
+                Some(self.does_not_fit(c))
+            } else {
+                None
+            };
+            let keep = reason.is_none();
+            if let Some(reason) = reason {
+                rejected.push(RejectedCut {
+                    index,
+                    rect: c.rect,
+                    qty: c.qty,
+                    label: c.label.clone(),
+                    reason,
+                });
+            }
+            index += 1;
+            keep
+        });
+        let demands = cuts
+            .into_iter()
+            .map(|c| Demand {
+                material: c.material,
+                label: c.label,
+                directional: c.directional,
+                group_hint: c.group_hint,
+                grain_group: c.grain_group,
+                fragile: c.fragile,
+                optional_overflow: c.optional_overflow,
+                ..Demand::new(c.rect, c.qty)
+                    .with_allow_rotate(self.allow_rotate)
+                    .with_grain(c.grain)
+                    .with_clearance(c.clearance)
+            })
+            .collect();
+        (demands, rejected)
+    }
+
+    /// Solver for `demands` on this job's stock, with its options.
+    pub fn solver(&self, demands: Vec<Demand>) -> Solver {
+        Solver::new(
+            self.stock(),
+            self.kerf,
+            self.cut_direction,
+            self.stock.grain,
+            demands,
+        )
+        .with_cluster_labels(self.cluster_labels)
+        .with_area_demands(self.area_cuts.clone())
+        .with_grain_penalty(self.grain_penalty)
+        .with_relax_grain_on_infeasible(self.relax_grain_on_infeasible)
+    }
+
+    /// Sheet naming of the job, for [`Solution::to_report`](crate::types::Solution::to_report).
+    pub fn report_options(&self) -> ReportOptions {
+        ReportOptions {
+            sheet_label_prefix: self.sheet_label_prefix.clone(),
+            sheet_offset: self.sheet_offset,
+            ..Default::default()
+        }
+    }
+}
+
+/// `Rejected:` section listing the skipped cuts, appended to text reports.
+pub fn rejected_report(rejected: &[RejectedCut]) -> String {
+    let mut out = String::from("\nRejected:\n");
+    for r in rejected {
+        out.push_str(&format!("  cuts[{}]: {}\n", r.index, r.reason));
+    }
+    out
+}
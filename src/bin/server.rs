@@ -1,50 +1,147 @@
 use axum::{
     Json, Router,
-    http::StatusCode,
+    body::Body,
+    extract::{Query, State},
+    http::{StatusCode, header},
+    response::{IntoResponse, Response},
     routing::{get, post},
 };
-use cut_optimizer::solver::Solver;
+use cut_optimizer::api::{
+    DEFAULT_ALLOW_ROTATE, FieldError, OptimizeRequest, RejectedCut, rejected_report,
+};
+use cut_optimizer::guillotine::ScoreStrategy;
+use cut_optimizer::report::sheet_label;
+use cut_optimizer::solver::{SolveError, Solver};
 use cut_optimizer::types::{
-    CutDirection, Demand, PieceGrain, Rect, RotationConstraint, Solution, StockGrain,
-    deserialize_u32_from_number,
+    AreaDemand, BomRow, CutDirection, Demand, Offcut, PieceGrain, Rect, Solution, StockGrain,
+    Warning,
 };
+use http_body_util::BodyExt;
 use serde::{Deserialize, Serialize};
+use std::fmt::Write;
+use std::future::Future;
+use std::sync::{LazyLock, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::Semaphore;
 use tower_http::trace::{DefaultMakeSpan, DefaultOnResponse, TraceLayer};
 use tracing::Level;
 
-#[derive(Deserialize, Serialize)]
-struct StockRequest {
-    #[serde(deserialize_with = "deserialize_u32_from_number")]
-    length: u32,
-    #[serde(deserialize_with = "deserialize_u32_from_number")]
-    width: u32,
-    #[serde(default)]
-    grain: StockGrain,
+/// Bounds how many solves run at once on the blocking thread pool, so a
+/// flood of heavy requests queues instead of exhausting threads.
+/// Configurable via `$MAX_CONCURRENT_SOLVES`, defaults to the CPU count.
+static SOLVE_PERMITS: LazyLock<Semaphore> = LazyLock::new(|| {
+    let permits = std::env::var("MAX_CONCURRENT_SOLVES")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or_else(|| std::thread::available_parallelism().map_or(4, |n| n.get()));
+    Semaphore::new(permits)
+});
+
+/// Counters and histograms served by `GET /metrics`, updated by `optimize`.
+static METRICS: Mutex<Metrics> = Mutex::new(Metrics {
+    requests: 0,
+    solve_seconds: Histogram::new(&[0.01, 0.05, 0.1, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0]),
+    sheet_count: Histogram::new(&[1.0, 2.0, 5.0, 10.0, 20.0, 50.0, 100.0]),
+    waste_percent: Histogram::new(&[5.0, 10.0, 20.0, 30.0, 50.0, 75.0]),
+});
+
+struct Metrics {
+    requests: u64,
+    solve_seconds: Histogram,
+    sheet_count: Histogram,
+    waste_percent: Histogram,
 }
 
-#[derive(Deserialize, Serialize)]
-struct OptimizeRequest {
-    stock: StockRequest,
-    cuts: Vec<CutRequest>,
-    #[serde(default, deserialize_with = "deserialize_u32_from_number")]
-    kerf: u32,
-    #[serde(default)]
-    cut_direction: CutDirection,
-    #[serde(default = "default_true")]
-    allow_rotate: bool,
+/// Prometheus histogram with fixed upper bounds.
+struct Histogram {
+    bounds: &'static [f64],
+    /// Observations per bucket, not cumulative; the last one is `+Inf`.
+    counts: Vec<u64>,
+    sum: f64,
 }
 
-#[derive(Deserialize, Serialize)]
-struct CutRequest {
-    rect: Rect,
-    #[serde(deserialize_with = "deserialize_u32_from_number")]
-    qty: u32,
+impl Histogram {
+    const fn new(bounds: &'static [f64]) -> Self {
+        Histogram {
+            bounds,
+            counts: Vec::new(),
+            sum: 0.0,
+        }
+    }
+
+    fn observe(&mut self, value: f64) {
+        self.counts.resize(self.bounds.len() + 1, 0);
+        self.counts[self.bounds.partition_point(|&b| b < value)] += 1;
+        self.sum += value;
+    }
+
+    fn write(&self, out: &mut String, name: &str, help: &str) {
+        writeln!(out, "# HELP {name} {help}").unwrap();
+        writeln!(out, "# TYPE {name} histogram").unwrap();
+        let mut total = 0;
+        for (i, bound) in self.bounds.iter().enumerate() {
+            total += self.counts.get(i).copied().unwrap_or(0);
+            writeln!(out, "{name}_bucket{{le=\"{bound}\"}} {total}").unwrap();
+        }
+        total += self.counts.last().copied().unwrap_or(0);
+        writeln!(out, "{name}_bucket{{le=\"+Inf\"}} {total}").unwrap();
+        writeln!(out, "{name}_sum {}", self.sum).unwrap();
+        writeln!(out, "{name}_count {total}").unwrap();
+    }
+}
+
+impl Metrics {
+    /// Prometheus text exposition format.
+    fn render(&self) -> String {
+        let mut out = String::new();
+        out.push_str("# HELP cut_optimizer_optimize_requests_total Requests to /optimize.\n");
+        out.push_str("# TYPE cut_optimizer_optimize_requests_total counter\n");
+        writeln!(
+            out,
+            "cut_optimizer_optimize_requests_total {}",
+            self.requests
+        )
+        .unwrap();
+        self.solve_seconds.write(
+            &mut out,
+            "cut_optimizer_solve_duration_seconds",
+            "Time spent solving, queueing excluded.",
+        );
+        self.sheet_count.write(
+            &mut out,
+            "cut_optimizer_sheet_count",
+            "Sheets per solution.",
+        );
+        self.waste_percent.write(
+            &mut out,
+            "cut_optimizer_waste_percent",
+            "Waste percentage per solution.",
+        );
+        out
+    }
+}
+
+async fn metrics() -> Response {
+    let body = METRICS.lock().unwrap().render();
+    ([(header::CONTENT_TYPE, "text/plain; version=0.0.4")], body).into_response()
+}
+
+#[derive(Deserialize)]
+struct OptimizeQuery {
+    /// Only return aggregate stats, with an empty `sheets` list.
+    #[serde(default)]
+    summary: bool,
     #[serde(default)]
-    grain: PieceGrain,
+    format: ResponseFormat,
 }
 
-fn default_true() -> bool {
-    true
+#[derive(Deserialize, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+enum ResponseFormat {
+    #[default]
+    Json,
+    /// Plain-text report, as printed by the CLI.
+    Text,
 }
 
 #[derive(Serialize)]
@@ -52,86 +149,444 @@ struct OptimizeResponse {
     sheets: Vec<SheetResponse>,
     stock: Rect,
     sheet_count: usize,
+    stock_usage: Vec<StockUsage>,
     waste_percent: f64,
+    /// Offcut counts per `offcut_buckets` bound, plus one for larger ones;
+    /// `null` unless buckets were requested.
+    offcut_histogram: Option<Vec<usize>>,
+    /// Placed pieces per size and label.
+    bill_of_materials: Vec<BomRow>,
+    warnings: Vec<Warning>,
+    quality: Quality,
+    /// Cuts left out with `on_infeasible: "skip"`.
+    rejected: Vec<RejectedCut>,
+}
+
+/// Which tier of the solve ladder produced the response.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum Quality {
+    /// Greedy plus branch and bound, finished within the first budget.
+    Optimal,
+    /// Greedy only, after the full solve ran out of time.
+    Greedy,
+    /// Area lower bound on the sheet count; no layout.
+    Estimate,
+}
+
+/// Time allowed to each tier of the solve ladder.
+#[derive(Debug, Clone, Copy)]
+struct SolveBudgets {
+    full: Duration,
+    greedy: Duration,
+}
+
+impl SolveBudgets {
+    /// From `$FULL_SOLVE_TIMEOUT_MS` and `$GREEDY_SOLVE_TIMEOUT_MS`
+    /// (defaults: 5 s and 20 s).
+    fn from_env() -> Self {
+        let ms = |var: &str, default: u64| {
+            Duration::from_millis(
+                std::env::var(var)
+                    .ok()
+                    .and_then(|v| v.parse().ok())
+                    .unwrap_or(default),
+            )
+        };
+        Self {
+            full: ms("FULL_SOLVE_TIMEOUT_MS", 5_000),
+            greedy: ms("GREEDY_SOLVE_TIMEOUT_MS", 20_000),
+        }
+    }
+}
+
+enum LadderOutcome {
+    Solved(Solution, Quality),
+    /// Lower bound on the number of sheets.
+    Estimate(usize),
+}
+
+/// Full solve within `budgets.full`, else greedy only within
+/// `budgets.greedy`, else the area lower bound.
+fn solve_ladder(
+    make_solver: impl Fn() -> Solver,
+    budgets: SolveBudgets,
+) -> Result<LadderOutcome, SolveError> {
+    let tiers = [
+        (true, budgets.full, Quality::Optimal),
+        (false, budgets.greedy, Quality::Greedy),
+    ];
+    for (branch_and_bound, budget, quality) in tiers {
+        let result = make_solver()
+            .with_branch_and_bound(branch_and_bound)
+            .with_deadline(Some(Instant::now() + budget))
+            .try_solve();
+        match result {
+            Ok(solution) => return Ok(LadderOutcome::Solved(solution, quality)),
+            Err(SolveError::DeadlineExceeded) => {
+                tracing::warn!(?quality, "solve tier timed out");
+            }
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(LadderOutcome::Estimate(make_solver().lower_bound_sheets()))
+}
+
+#[derive(Serialize)]
+struct StockUsage {
+    stock: Rect,
+    count: usize,
 }
 
 #[derive(Serialize)]
 struct SheetResponse {
-    placements: Vec<cut_optimizer::types::Placement>,
+    label: String,
+    placements: Vec<PlacementResponse>,
     waste_area: u64,
+    material: Option<String>,
+    cut_direction: CutDirection,
+    used_bounds: UsedBounds,
+    /// Free regions left on the sheet, for placing extra parts by hand.
+    available_regions: Vec<Offcut>,
+}
+
+/// A placement with its size both as laid out (`rect`) and as demanded.
+#[derive(Serialize)]
+struct PlacementResponse {
+    #[serde(flatten)]
+    placement: cut_optimizer::types::Placement,
+    original_size: Rect,
+}
+
+/// Smallest rectangle enclosing all placements of a sheet.
+#[derive(Serialize)]
+struct UsedBounds {
+    x: u32,
+    y: u32,
+    rect: Rect,
+}
+
+#[derive(Serialize)]
+struct CapabilitiesResponse {
+    options: CapabilityOptions,
+    defaults: CapabilityDefaults,
+}
+
+#[derive(Serialize)]
+struct CapabilityOptions {
+    cut_direction: Vec<CutDirection>,
+    stock_grain: Vec<StockGrain>,
+    piece_grain: Vec<PieceGrain>,
+    strategy: Vec<ScoreStrategy>,
+}
+
+#[derive(Serialize)]
+struct CapabilityDefaults {
+    kerf: u32,
+    cut_direction: CutDirection,
+    stock_grain: StockGrain,
+    piece_grain: PieceGrain,
+    allow_rotate: bool,
+}
+
+async fn capabilities() -> Json<CapabilitiesResponse> {
+    Json(CapabilitiesResponse {
+        options: CapabilityOptions {
+            cut_direction: CutDirection::ALL.to_vec(),
+            stock_grain: StockGrain::ALL.to_vec(),
+            piece_grain: PieceGrain::ALL.to_vec(),
+            strategy: ScoreStrategy::ALL.to_vec(),
+        },
+        defaults: CapabilityDefaults {
+            kerf: 0,
+            cut_direction: CutDirection::default(),
+            stock_grain: StockGrain::default(),
+            piece_grain: PieceGrain::default(),
+            allow_rotate: DEFAULT_ALLOW_ROTATE,
+        },
+    })
+}
+
+#[derive(Serialize)]
+struct ValidationErrors {
+    errors: Vec<FieldError>,
+}
+
+/// 422 listing the invalid fields of `req`, if any.
+fn validate(req: &OptimizeRequest) -> Result<(), (StatusCode, Json<ValidationErrors>)> {
+    let errors = req.validate();
+    if errors.is_empty() {
+        return Ok(());
+    }
+    Err((
+        StatusCode::UNPROCESSABLE_ENTITY,
+        Json(ValidationErrors { errors }),
+    ))
+}
+
+/// Area of every demanded piece and area cut, in mm².
+fn requested_area(demands: &[Demand], area_cuts: &[AreaDemand]) -> u128 {
+    demands
+        .iter()
+        .map(|d| d.rect.area() as u128 * d.qty as u128)
+        .chain(area_cuts.iter().map(|a| a.total_area as u128))
+        .sum()
+}
+
+#[derive(Serialize)]
+struct EstimateResponse {
+    /// Area lower bound on `sheet_count`, see `Solver::lower_bound_sheets`.
+    lower_bound_sheets: usize,
+    total_piece_area: u128,
+    stock_area: u64,
+}
+
+/// `POST /estimate`: the sheet count `/optimize` needs at least for the
+/// same request, from areas alone, for instant quotes. Nothing is solved.
+async fn estimate(Json(mut req): Json<OptimizeRequest>) -> Result<Response, Response> {
+    validate(&req).map_err(IntoResponse::into_response)?;
+    let stock = req.stock();
+    let (demands, _) = req.take_demands();
+    let total_piece_area = requested_area(&demands, &req.area_cuts);
+    Ok(Json(EstimateResponse {
+        lower_bound_sheets: req.solver(demands).lower_bound_sheets(),
+        total_piece_area,
+        stock_area: stock.area(),
+    })
+    .into_response())
 }
 
 async fn optimize(
+    State(budgets): State<SolveBudgets>,
+    Query(query): Query<OptimizeQuery>,
     Json(req): Json<OptimizeRequest>,
-) -> Result<Json<OptimizeResponse>, (StatusCode, String)> {
+) -> Result<Response, Response> {
     tracing::info!(
         body = serde_json::to_string(&req).unwrap_or_default(),
         "POST /optimize"
     );
+    solve_request(budgets, query, req).await
+}
 
-    let stock = Rect::new(req.stock.length, req.stock.width);
-    let stock_grain = req.stock.grain;
+/// Longest line `POST /optimize-stream` accepts, so a body without
+/// newlines can't grow the buffer unbounded.
+const MAX_NDJSON_LINE: usize = 64 * 1024;
 
-    if stock.length == 0 || stock.width == 0 {
-        return Err((
-            StatusCode::BAD_REQUEST,
-            "stock dimensions must be non-zero".to_string(),
-        ));
+/// `POST /optimize-stream`: the request as newline-delimited JSON, the
+/// first line holding every field but `cuts`, then one cut per line. Lines
+/// are parsed as they arrive, so only the parsed cuts are held in memory.
+async fn optimize_stream(
+    State(budgets): State<SolveBudgets>,
+    Query(query): Query<OptimizeQuery>,
+    mut body: Body,
+) -> Result<Response, Response> {
+    let bad_request = |message: String| (StatusCode::BAD_REQUEST, message).into_response();
+    let mut req = None;
+    let mut buf = Vec::new();
+    let mut line_no = 0;
+    while let Some(frame) = body.frame().await {
+        let frame = frame.map_err(|e| bad_request(e.to_string()))?;
+        let Ok(data) = frame.into_data() else {
+            continue;
+        };
+        buf.extend_from_slice(&data);
+        while let Some(end) = buf.iter().position(|&b| b == b'\n') {
+            line_no += 1;
+            let line: Vec<u8> = buf.drain(..=end).collect();
+            add_ndjson_line(&mut req, &line, line_no).map_err(bad_request)?;
+        }
+        if buf.len() > MAX_NDJSON_LINE {
+            return Err(bad_request(format!(
+                "line {}: longer than {} bytes",
+                line_no + 1,
+                MAX_NDJSON_LINE
+            )));
+        }
     }
+    add_ndjson_line(&mut req, &buf, line_no + 1).map_err(bad_request)?;
+    let req = req.ok_or_else(|| bad_request("empty body".to_string()))?;
 
-    let demands: Vec<Demand> = req
-        .cuts
-        .into_iter()
-        .map(|c| {
-            if c.rect.length == 0 || c.rect.width == 0 {
-                return Err("cut dimensions must be non-zero".to_string());
-            }
-            if c.qty == 0 {
-                return Err("cut quantity must be non-zero".to_string());
+    tracing::info!(cuts = req.cuts.len(), "POST /optimize-stream");
+    solve_request(budgets, query, req).await
+}
+
+/// Parse line `line_no` of an `/optimize-stream` body into `req`: the
+/// request itself for the first non-blank line, a cut for the others.
+fn add_ndjson_line(
+    req: &mut Option<OptimizeRequest>,
+    line: &[u8],
+    line_no: usize,
+) -> Result<(), String> {
+    let line = line.trim_ascii();
+    if line.is_empty() {
+        return Ok(());
+    }
+    let error = |e: serde_json::Error| format!("line {line_no}: {e}");
+    match req {
+        None => {
+            let mut header: serde_json::Value = serde_json::from_slice(line).map_err(error)?;
+            if let Some(fields) = header.as_object_mut() {
+                fields.entry("cuts").or_insert(serde_json::json!([]));
             }
-            let rotation = RotationConstraint::from_grain(stock_grain, c.grain, req.allow_rotate)
-                .with_cut_direction(req.cut_direction, c.rect);
-            let fits = match rotation {
-                RotationConstraint::NoRotate => c.rect.fits_in(&stock),
-                RotationConstraint::ForceRotate => c.rect.rotated().fits_in(&stock),
-                RotationConstraint::Free => {
-                    c.rect.fits_in(&stock) || c.rect.rotated().fits_in(&stock)
-                }
+            *req = Some(serde_json::from_value(header).map_err(error)?);
+        }
+        Some(req) => req.cuts.push(serde_json::from_slice(line).map_err(error)?),
+    }
+    Ok(())
+}
+
+/// Status for a solve the validation let through but the solver refused.
+fn solve_error_status(e: &SolveError) -> StatusCode {
+    match e {
+        SolveError::TooManyPieces { .. } => StatusCode::BAD_REQUEST,
+        SolveError::InvalidStripWidth { .. }
+        | SolveError::GrainConflict { .. }
+        | SolveError::PieceTooLarge { .. }
+        | SolveError::KerfTooLarge { .. }
+        | SolveError::FaceGrainConflict { .. }
+        | SolveError::InvalidGridPitch { .. }
+        | SolveError::OffGrid { .. }
+        | SolveError::CutOutsideStock { .. } => StatusCode::UNPROCESSABLE_ENTITY,
+        // The ladder falls back to an estimate; only a bug gets here
+        SolveError::DeadlineExceeded => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+/// Validate and solve a request, then format the response.
+async fn solve_request(
+    budgets: SolveBudgets,
+    query: OptimizeQuery,
+    mut req: OptimizeRequest,
+) -> Result<Response, Response> {
+    METRICS.lock().unwrap().requests += 1;
+
+    validate(&req).map_err(IntoResponse::into_response)?;
+
+    let stock = req.stock();
+    let report_options = req.report_options();
+    let offcut_buckets = req.offcut_buckets.take();
+    let (demands, rejected) = req.take_demands();
+
+    let requested_area = requested_area(&demands, &req.area_cuts);
+    let make_solver = move || req.solver(demands.clone());
+    let _permit = SOLVE_PERMITS.acquire().await.map_err(|_| {
+        (
+            StatusCode::SERVICE_UNAVAILABLE,
+            "server is shutting down".to_string(),
+        )
+            .into_response()
+    })?;
+    let started = Instant::now();
+    let outcome = tokio::task::spawn_blocking(move || solve_ladder(make_solver, budgets))
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response())?
+        .map_err(|e| (solve_error_status(&e), e.to_string()).into_response())?;
+    METRICS
+        .lock()
+        .unwrap()
+        .solve_seconds
+        .observe(started.elapsed().as_secs_f64());
+
+    let (solution, quality) = match outcome {
+        LadderOutcome::Solved(solution, quality) => {
+            let mut metrics = METRICS.lock().unwrap();
+            metrics.sheet_count.observe(solution.sheet_count() as f64);
+            metrics
+                .waste_percent
+                .observe(solution.total_waste_percent());
+            (solution, quality)
+        }
+        LadderOutcome::Estimate(sheet_count) if query.format == ResponseFormat::Text => {
+            return Ok(format!(
+                "Estimate: at least {} sheet{} needed, no layout found in time\n",
+                sheet_count,
+                if sheet_count == 1 { "" } else { "s" },
+            )
+            .into_response());
+        }
+        LadderOutcome::Estimate(sheet_count) => {
+            let total = stock.area() as u128 * sheet_count as u128;
+            let waste_percent = if total == 0 {
+                0.0
+            } else {
+                total.saturating_sub(requested_area) as f64 / total as f64 * 100.0
             };
-            if !fits {
-                return Err(format!(
-                    "piece {}x{} does not fit in stock {}x{}",
-                    c.rect.length, c.rect.width, stock.length, stock.width
-                ));
-            }
-            Ok(Demand {
-                rect: c.rect,
-                qty: c.qty,
-                allow_rotate: req.allow_rotate,
-                grain: c.grain,
+            return Ok(Json(OptimizeResponse {
+                sheets: vec![],
+                stock,
+                sheet_count,
+                stock_usage: vec![StockUsage {
+                    stock,
+                    count: sheet_count,
+                }],
+                waste_percent,
+                offcut_histogram: None,
+                bill_of_materials: vec![],
+                warnings: vec![],
+                quality: Quality::Estimate,
+                rejected,
             })
-        })
-        .collect::<Result<Vec<_>, _>>()
-        .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
-
-    let solver = Solver::new(stock, req.kerf, req.cut_direction, stock_grain, demands);
-    let solution: Solution = solver.solve();
+            .into_response());
+        }
+    };
+    if query.format == ResponseFormat::Text {
+        let mut report = solution.to_report(&report_options);
+        if !rejected.is_empty() {
+            report.push_str(&rejected_report(&rejected));
+        }
+        return Ok(report.into_response());
+    }
 
-    let response = OptimizeResponse {
-        sheets: solution
+    let sheets = if query.summary {
+        Vec::new()
+    } else {
+        solution
             .sheets
             .iter()
-            .map(|s| SheetResponse {
-                placements: s.placements.clone(),
-                waste_area: s.waste_area,
+            .enumerate()
+            .map(|(i, s)| {
+                let (x, y, rect) = s.used_bounds();
+                SheetResponse {
+                    label: sheet_label(
+                        report_options.sheet_label_prefix.as_deref(),
+                        report_options.sheet_offset,
+                        i,
+                    ),
+                    placements: s
+                        .placements
+                        .iter()
+                        .map(|p| PlacementResponse {
+                            placement: p.clone(),
+                            original_size: p.original_size(),
+                        })
+                        .collect(),
+                    waste_area: s.waste_area,
+                    material: s.material.clone(),
+                    cut_direction: s.cut_direction,
+                    used_bounds: UsedBounds { x, y, rect },
+                    available_regions: s.offcuts.clone(),
+                }
             })
-            .collect(),
+            .collect()
+    };
+    let response = OptimizeResponse {
+        sheets,
         stock: solution.stock,
         sheet_count: solution.sheet_count(),
+        stock_usage: solution
+            .stock_usage()
+            .into_iter()
+            .map(|(stock, count)| StockUsage { stock, count })
+            .collect(),
         waste_percent: solution.total_waste_percent(),
+        offcut_histogram: offcut_buckets.map(|buckets| solution.offcut_histogram(&buckets)),
+        bill_of_materials: solution.bill_of_materials(),
+        warnings: solution.warnings,
+        quality,
+        rejected,
     };
 
-    Ok(Json(response))
+    Ok(Json(response).into_response())
 }
 
 #[tokio::main]
@@ -154,16 +609,657 @@ async fn main() {
     let port = std::env::var("PORT").unwrap_or_else(|_| "3001".to_string());
     let addr = format!("0.0.0.0:{port}");
 
-    let app = Router::new()
+    let listener = tokio::net::TcpListener::bind(&addr).await.unwrap();
+    eprintln!("Listening on {addr}");
+    serve(listener, shutdown_signal()).await;
+}
+
+/// Serve until `shutdown` resolves, then stop accepting connections and
+/// wait for in-flight requests to finish.
+async fn serve(
+    listener: tokio::net::TcpListener,
+    shutdown: impl Future<Output = ()> + Send + 'static,
+) {
+    axum::serve(listener, app())
+        .with_graceful_shutdown(shutdown)
+        .await
+        .unwrap();
+}
+
+/// Resolves on SIGINT or SIGTERM.
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        tokio::signal::ctrl_c()
+            .await
+            .expect("failed to install Ctrl+C handler");
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+            .expect("failed to install SIGTERM handler")
+            .recv()
+            .await;
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {},
+        _ = terminate => {},
+    }
+    eprintln!("Shutting down, draining in-flight requests");
+}
+
+fn app() -> Router {
+    app_with_budgets(SolveBudgets::from_env())
+}
+
+fn app_with_budgets(budgets: SolveBudgets) -> Router {
+    Router::new()
         .route("/up", get(|| async { "ok" }))
+        .route("/capabilities", get(capabilities))
         .route("/optimize", post(optimize))
+        .route("/optimize-stream", post(optimize_stream))
+        .route("/estimate", post(estimate))
+        .route("/metrics", get(metrics))
         .layer(
             TraceLayer::new_for_http()
                 .make_span_with(DefaultMakeSpan::new().level(Level::INFO))
                 .on_response(DefaultOnResponse::new().level(Level::INFO)),
+        )
+        .with_state(budgets)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::Request;
+    use tower::ServiceExt;
+
+    async fn get_json(uri: &str) -> (StatusCode, serde_json::Value) {
+        let response = app()
+            .oneshot(Request::get(uri).body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let status = response.status();
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        (status, serde_json::from_slice(&bytes).unwrap())
+    }
+
+    async fn post_json(uri: &str, body: serde_json::Value) -> (StatusCode, serde_json::Value) {
+        let request = Request::post(uri)
+            .header("content-type", "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap();
+        let response = app().oneshot(request).await.unwrap();
+        let status = response.status();
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        (status, serde_json::from_slice(&bytes).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_graceful_shutdown_drains_in_flight() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (tx, rx) = tokio::sync::oneshot::channel::<()>();
+        let server = tokio::spawn(serve(listener, async {
+            rx.await.ok();
+        }));
+
+        // Start a solve heavy enough to still be running at shutdown
+        let body = serde_json::json!({
+            "stock": { "length": 2440, "width": 1220 },
+            "cuts": [
+                { "rect": { "length": 800, "width": 600 }, "qty": 40 },
+                { "rect": { "length": 400, "width": 300 }, "qty": 80 },
+                { "rect": { "length": 300, "width": 170 }, "qty": 80 }
+            ],
+            "kerf": 3
+        })
+        .to_string();
+        let mut stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+        let request = format!(
+            "POST /optimize HTTP/1.1\r\nHost: {addr}\r\nContent-Type: application/json\r\n\
+             Content-Length: {}\r\nConnection: close\r\n\r\n{body}",
+            body.len()
         );
+        stream.write_all(request.as_bytes()).await.unwrap();
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
 
-    let listener = tokio::net::TcpListener::bind(&addr).await.unwrap();
-    eprintln!("Listening on {addr}");
-    axum::serve(listener, app).await.unwrap();
+        tx.send(()).unwrap();
+
+        // The in-flight request still completes
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        assert!(response.starts_with("HTTP/1.1 200"), "got: {response}");
+        assert!(response.contains("sheet_count"));
+
+        // Once drained the server exits and new connections are refused
+        server.await.unwrap();
+        assert!(tokio::net::TcpStream::connect(addr).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_capabilities() {
+        let (status, body) = get_json("/capabilities").await;
+        assert_eq!(status, StatusCode::OK);
+
+        let directions = body["options"]["cut_direction"].as_array().unwrap();
+        for expected in ["auto", "along_length", "along_width", "mixed"] {
+            assert!(
+                directions.iter().any(|d| d == expected),
+                "missing cut_direction {expected}: {directions:?}"
+            );
+        }
+        assert_eq!(body["options"]["strategy"].as_array().unwrap().len(), 3);
+        assert_eq!(body["defaults"]["cut_direction"], "auto");
+        assert_eq!(body["defaults"]["stock_grain"], "none");
+        assert_eq!(body["defaults"]["allow_rotate"], true);
+    }
+
+    #[tokio::test]
+    async fn test_optimize_summary_omits_placements() {
+        let body = serde_json::json!({
+            "stock": { "length": 2440, "width": 1220 },
+            "cuts": [
+                { "rect": { "length": 800, "width": 600 }, "qty": 5 },
+                { "rect": { "length": 400, "width": 300 }, "qty": 7 }
+            ],
+            "kerf": 3
+        });
+        let (status, full) = post_json("/optimize", body.clone()).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(!full["sheets"].as_array().unwrap().is_empty());
+
+        let (status, summary) = post_json("/optimize?summary=true", body).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(summary["sheets"].as_array().unwrap().is_empty());
+        assert_eq!(summary["sheet_count"], full["sheet_count"]);
+        assert_eq!(summary["waste_percent"], full["waste_percent"]);
+    }
+
+    #[tokio::test]
+    async fn test_optimize_stream() {
+        let post_ndjson = |body: &'static str| async move {
+            let request = Request::post("/optimize-stream")
+                .header("content-type", "application/x-ndjson")
+                .body(Body::from(body))
+                .unwrap();
+            let response = app().oneshot(request).await.unwrap();
+            let status = response.status();
+            let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            (status, String::from_utf8_lossy(&bytes).into_owned())
+        };
+
+        let (status, streamed) = post_ndjson(
+            "{\"stock\": {\"length\": 2440, \"width\": 1220}, \"kerf\": 3}\n\
+             {\"rect\": {\"length\": 800, \"width\": 600}, \"qty\": 5}\n\
+             \n\
+             {\"rect\": {\"length\": 400, \"width\": 300}, \"qty\": 7}",
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        let streamed: serde_json::Value = serde_json::from_str(&streamed).unwrap();
+        let (_, whole) = post_json(
+            "/optimize",
+            serde_json::json!({
+                "stock": { "length": 2440, "width": 1220 },
+                "cuts": [
+                    { "rect": { "length": 800, "width": 600 }, "qty": 5 },
+                    { "rect": { "length": 400, "width": 300 }, "qty": 7 }
+                ],
+                "kerf": 3
+            }),
+        )
+        .await;
+        assert_eq!(streamed, whole);
+
+        let (status, message) =
+            post_ndjson("{\"stock\": {\"length\": 1000, \"width\": 500}}\n{\"rect\": 12}\n").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(message.starts_with("line 2: "), "{message}");
+
+        let (status, message) = post_ndjson("\n").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(message, "empty body");
+    }
+
+    #[tokio::test]
+    async fn test_optimize_rejects_huge_quantity() {
+        let body = serde_json::json!({
+            "stock": { "length": 2440, "width": 1220 },
+            "cuts": [{ "rect": { "length": 10, "width": 10 }, "qty": 4_000_000_000u32 }]
+        });
+        let request = Request::post("/optimize")
+            .header("content-type", "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap();
+        let response = app().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert!(String::from_utf8_lossy(&bytes).contains("too many pieces"));
+    }
+
+    #[tokio::test]
+    async fn test_optimize_unsolvable_is_unprocessable() {
+        // Passes validation, but no 2000 mm strip fits a 1000x500 sheet
+        let body = serde_json::json!({
+            "stock": { "length": 1000, "width": 500 },
+            "cuts": [],
+            "area_cuts": [{ "width": 2000, "total_area": 1_000_000 }]
+        });
+        let request = Request::post("/optimize")
+            .header("content-type", "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap();
+        let response = app().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(
+            String::from_utf8_lossy(&bytes),
+            "strip width 2000 does not fit in stock"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_metrics() {
+        let body = serde_json::json!({
+            "stock": { "length": 1000, "width": 500 },
+            "cuts": [{ "rect": { "length": 400, "width": 300 }, "qty": 2 }]
+        });
+        let (status, _) = post_json("/optimize", body).await;
+        assert_eq!(status, StatusCode::OK);
+
+        let response = app()
+            .oneshot(Request::get("/metrics").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let text = String::from_utf8(bytes.to_vec()).unwrap();
+        let value = |name: &str| -> f64 {
+            let line = text
+                .lines()
+                .find(|l| l.split(' ').next() == Some(name))
+                .unwrap();
+            line.rsplit(' ').next().unwrap().parse().unwrap()
+        };
+        assert!(value("cut_optimizer_optimize_requests_total") > 0.0);
+        assert!(value("cut_optimizer_solve_duration_seconds_count") > 0.0);
+        assert!(value("cut_optimizer_sheet_count_bucket{le=\"+Inf\"}") > 0.0);
+        assert!(text.contains("# TYPE cut_optimizer_waste_percent histogram\n"));
+    }
+
+    #[tokio::test]
+    async fn test_optimize_text_report() {
+        let body = serde_json::json!({
+            "stock": { "length": 1000, "width": 500 },
+            "cuts": [{ "rect": { "length": 400, "width": 300 }, "qty": 2 }]
+        });
+        let request = Request::post("/optimize?format=text")
+            .header("content-type", "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap();
+        let response = app().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(
+            response.headers()["content-type"]
+                .to_str()
+                .unwrap()
+                .starts_with("text/plain")
+        );
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let text = String::from_utf8(bytes.to_vec()).unwrap();
+        assert!(text.starts_with("Sheet 1:\n"));
+        assert!(text.contains("Utilization: 48.0%"));
+        assert!(text.ends_with("Summary: 1 sheet used (1x 1000x500), 52.0% waste\n"));
+    }
+
+    #[tokio::test]
+    async fn test_optimize_sheet_labels() {
+        let body = serde_json::json!({
+            "stock": { "length": 1000, "width": 500 },
+            "cuts": [{ "rect": { "length": 800, "width": 400 }, "qty": 2 }],
+            "sheet_label_prefix": "JOB42",
+            "sheet_offset": 6
+        });
+        let (status, json) = post_json("/optimize", body.clone()).await;
+        assert_eq!(status, StatusCode::OK);
+        let labels: Vec<&str> = json["sheets"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|s| s["label"].as_str().unwrap())
+            .collect();
+        assert_eq!(labels, ["JOB42-Sheet-7", "JOB42-Sheet-8"]);
+
+        let request = Request::post("/optimize?format=text")
+            .header("content-type", "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap();
+        let response = app().oneshot(request).await.unwrap();
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let text = String::from_utf8(bytes.to_vec()).unwrap();
+        assert!(text.starts_with("JOB42-Sheet-7:\n"));
+        assert!(text.contains("\nJOB42-Sheet-8:\n"));
+    }
+
+    #[tokio::test]
+    async fn test_optimize_offcut_histogram() {
+        // One 600x500 piece leaves a single 400x500 offcut
+        let body = |buckets: serde_json::Value| {
+            serde_json::json!({
+                "stock": { "length": 1000, "width": 500 },
+                "cuts": [{ "rect": { "length": 600, "width": 500 }, "qty": 1 }],
+                "offcut_buckets": buckets
+            })
+        };
+        let (status, json) =
+            post_json("/optimize", body(serde_json::json!([100_000, 1_000_000]))).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(json["offcut_histogram"], serde_json::json!([0, 1, 0]));
+        assert_eq!(
+            json["sheets"][0]["available_regions"],
+            serde_json::json!([{ "x": 600, "y": 0, "rect": { "length": 400, "width": 500 } }])
+        );
+        assert_eq!(
+            json["bill_of_materials"],
+            serde_json::json!([{
+                "size": { "length": 600, "width": 500 },
+                "label": null,
+                "count": 1,
+                "total_area": 300_000
+            }])
+        );
+
+        let (status, json) = post_json("/optimize", body(serde_json::Value::Null)).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(json["offcut_histogram"].is_null());
+
+        let (status, json) = post_json("/optimize", body(serde_json::json!([1000, 1000]))).await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(json["errors"][0]["field"], "offcut_buckets");
+    }
+
+    #[tokio::test]
+    async fn test_optimize_relax_grain() {
+        let body = |relax: bool| {
+            serde_json::json!({
+                "stock": { "length": 2440, "width": 1220, "grain": "along_length" },
+                "cuts": [{ "rect": { "length": 800, "width": 1300 }, "qty": 1, "grain": "length" }],
+                "relax_grain_on_infeasible": relax
+            })
+        };
+        let (status, json) = post_json("/optimize", body(false)).await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(json["errors"][0]["field"], "cuts[0].rect");
+
+        let (status, json) = post_json("/optimize", body(true)).await;
+        assert_eq!(status, StatusCode::OK);
+        let placement = &json["sheets"][0]["placements"][0];
+        assert_eq!(placement["rotated"], true);
+        assert_eq!(
+            placement["rect"],
+            serde_json::json!({ "length": 1300, "width": 800 })
+        );
+        assert_eq!(
+            placement["original_size"],
+            serde_json::json!({ "length": 800, "width": 1300 })
+        );
+        assert_eq!(json["warnings"][0]["grain_relaxed"]["demand"], 0);
+    }
+
+    #[tokio::test]
+    async fn test_optimize_skip_infeasible() {
+        let body = |on_infeasible: &str| {
+            serde_json::json!({
+                "stock": { "length": 1000, "width": 500 },
+                "cuts": [
+                    { "rect": { "length": 400, "width": 300 }, "qty": 2 },
+                    { "rect": { "length": 1200, "width": 100 }, "qty": 3, "label": "rail" },
+                    { "rect": { "length": 200, "width": 100 }, "qty": 1 }
+                ],
+                "on_infeasible": on_infeasible
+            })
+        };
+        let (status, _) = post_json("/optimize", body("reject")).await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+
+        let (status, body) = post_json("/optimize", body("skip")).await;
+        assert_eq!(status, StatusCode::OK);
+        let placed: usize = body["sheets"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|s| s["placements"].as_array().unwrap().len())
+            .sum();
+        assert_eq!(placed, 3);
+        assert_eq!(
+            body["rejected"],
+            serde_json::json!([{
+                "index": 1,
+                "rect": { "length": 1200, "width": 100 },
+                "qty": 3,
+                "label": "rail",
+                "reason": "piece 1200x100 does not fit in stock 1000x500"
+            }])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_optimize_skip_zero_qty() {
+        let body = |skip: bool| {
+            serde_json::json!({
+                "stock": { "length": 1000, "width": 500 },
+                "cuts": [
+                    { "rect": { "length": 400, "width": 300 }, "qty": 2 },
+                    { "rect": { "length": 200, "width": 100 }, "qty": 0, "label": "spare" },
+                    { "rect": { "length": 200, "width": 100 }, "qty": 1 }
+                ],
+                "skip_zero_qty": skip
+            })
+        };
+        let (status, body_strict) = post_json("/optimize", body(false)).await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(body_strict["errors"][0]["field"], "cuts[1].qty");
+
+        let (status, body) = post_json("/optimize", body(true)).await;
+        assert_eq!(status, StatusCode::OK);
+        let placed: usize = body["sheets"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|s| s["placements"].as_array().unwrap().len())
+            .sum();
+        assert_eq!(placed, 3);
+        assert_eq!(
+            body["rejected"],
+            serde_json::json!([{
+                "index": 1,
+                "rect": { "length": 200, "width": 100 },
+                "qty": 0,
+                "label": "spare",
+                "reason": "quantity is zero"
+            }])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_optimize_field_errors() {
+        let body = serde_json::json!({
+            "stock": { "length": 1000, "width": 0 },
+            "cuts": [
+                { "rect": { "length": 100, "width": 100 }, "qty": 1 },
+                { "rect": { "length": 0, "width": 100 }, "qty": 0 },
+                { "rect": { "length": 100, "width": 0 }, "qty": 1 }
+            ]
+        });
+        let (status, body) = post_json("/optimize", body).await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        let errors = body["errors"].as_array().unwrap();
+        let fields: Vec<&str> = errors
+            .iter()
+            .map(|e| e["field"].as_str().unwrap())
+            .collect();
+        assert_eq!(
+            fields,
+            [
+                "stock.width",
+                "cuts[1].rect.length",
+                "cuts[1].qty",
+                "cuts[2].rect.width"
+            ]
+        );
+        assert!(errors.iter().all(|e| e["message"] == "must be non-zero"));
+
+        // A kerf that would eat the whole sheet
+        let body = serde_json::json!({
+            "stock": { "length": 100, "width": 100 },
+            "cuts": [{ "rect": { "length": 10, "width": 10 }, "qty": 1 }],
+            "kerf": 200
+        });
+        let (status, body) = post_json("/optimize", body).await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(body["errors"][0]["field"], "kerf");
+        assert_eq!(
+            body["errors"][0]["message"],
+            "kerf 200 mm must be smaller than the shorter side of stock 100x100 (100 mm)"
+        );
+
+        // A piece larger than the stock is reported on its rect
+        let body = serde_json::json!({
+            "stock": { "length": 1000, "width": 500 },
+            "cuts": [
+                { "rect": { "length": 100, "width": 100 }, "qty": 1 },
+                { "rect": { "length": 1200, "width": 100 }, "qty": 1 }
+            ]
+        });
+        let (status, body) = post_json("/optimize", body).await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(body["errors"][0]["field"], "cuts[1].rect");
+        assert_eq!(
+            body["errors"][0]["message"],
+            "piece 1200x100 does not fit in stock 1000x500"
+        );
+
+        // Grain forcing a rotation while rotation is disabled
+        let body = serde_json::json!({
+            "stock": { "length": 1000, "width": 500, "grain": "along_length" },
+            "cuts": [
+                { "rect": { "length": 400, "width": 300 }, "qty": 1, "grain": "width" }
+            ],
+            "allow_rotate": false
+        });
+        let (status, body) = post_json("/optimize", body).await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(body["errors"][0]["field"], "cuts[0].grain");
+        assert_eq!(
+            body["errors"][0]["message"],
+            "piece grain Width needs a 90° rotation on AlongLength stock, but rotation is disabled"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_estimate() {
+        // Five 600x600 pieces need five sheets of 1000x1000; their area
+        // alone needs two
+        let body = serde_json::json!({
+            "stock": { "length": 1000, "width": 1000 },
+            "cuts": [{ "rect": { "length": 600, "width": 600 }, "qty": 5 }]
+        });
+        let (status, body) = post_json("/estimate", body).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(
+            body,
+            serde_json::json!({
+                "lower_bound_sheets": 2,
+                "total_piece_area": 1_800_000,
+                "stock_area": 1_000_000
+            })
+        );
+
+        // A job far too large to solve in time still answers at once
+        let body = serde_json::json!({
+            "stock": { "length": 2440, "width": 1220 },
+            "cuts": [{ "rect": { "length": 300, "width": 170 }, "qty": 100_000 }],
+            "kerf": 3
+        });
+        let started = Instant::now();
+        let (status, body) = post_json("/estimate", body).await;
+        assert!(started.elapsed() < Duration::from_secs(1));
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["lower_bound_sheets"], 1714);
+
+        // Same validation as /optimize
+        let body = serde_json::json!({
+            "stock": { "length": 1000, "width": 0 },
+            "cuts": [{ "rect": { "length": 100, "width": 100 }, "qty": 1 }]
+        });
+        let (status, body) = post_json("/estimate", body).await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(body["errors"][0]["field"], "stock.width");
+    }
+
+    #[tokio::test]
+    async fn test_solve_ladder_tiers() {
+        let body = serde_json::json!({
+            "stock": { "length": 2440, "width": 1220 },
+            "cuts": [
+                { "rect": { "length": 800, "width": 600 }, "qty": 5 },
+                { "rect": { "length": 400, "width": 300 }, "qty": 7 }
+            ],
+            "kerf": 3
+        });
+        let post = |full_ms: u64, greedy_ms: u64| {
+            let budgets = SolveBudgets {
+                full: Duration::from_millis(full_ms),
+                greedy: Duration::from_millis(greedy_ms),
+            };
+            let request = Request::post("/optimize")
+                .header("content-type", "application/json")
+                .body(Body::from(body.to_string()))
+                .unwrap();
+            async move {
+                let response = app_with_budgets(budgets).oneshot(request).await.unwrap();
+                assert_eq!(response.status(), StatusCode::OK);
+                let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+                    .await
+                    .unwrap();
+                serde_json::from_slice::<serde_json::Value>(&bytes).unwrap()
+            }
+        };
+
+        let optimal = post(60_000, 60_000).await;
+        assert_eq!(optimal["quality"], "optimal");
+        assert!(!optimal["sheets"].as_array().unwrap().is_empty());
+
+        let greedy = post(0, 60_000).await;
+        assert_eq!(greedy["quality"], "greedy");
+        assert!(!greedy["sheets"].as_array().unwrap().is_empty());
+
+        let estimate = post(0, 0).await;
+        assert_eq!(estimate["quality"], "estimate");
+        assert!(estimate["sheets"].as_array().unwrap().is_empty());
+        assert_eq!(estimate["sheet_count"], 2);
+        assert!(estimate["sheet_count"].as_u64() <= optimal["sheet_count"].as_u64());
+    }
 }
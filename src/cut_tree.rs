@@ -0,0 +1,697 @@
+use crate::types::{Axis, Placement, Rect, SheetResult};
+use serde::Serialize;
+
+/// Hierarchical guillotine cut plan of a sheet.
+///
+/// Each `Split` is a single edge-to-edge cut through its region: `first` is
+/// the part before the cut, `second` the part after it (the kerf sits between
+/// them, starting at `position`). Leaves are either placed pieces or waste.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CutNode {
+    Piece(Placement),
+    Waste {
+        x: u32,
+        y: u32,
+        rect: Rect,
+    },
+    Split {
+        axis: Axis,
+        position: u32,
+        first: Box<CutNode>,
+        second: Box<CutNode>,
+    },
+}
+
+impl CutNode {
+    /// All placements at the leaves, in tree order.
+    pub fn pieces(&self) -> Vec<Placement> {
+        match self {
+            CutNode::Piece(p) => vec![p.clone()],
+            CutNode::Waste { .. } => vec![],
+            CutNode::Split { first, second, .. } => {
+                let mut pieces = first.pieces();
+                pieces.extend(second.pieces());
+                pieces
+            }
+        }
+    }
+
+    /// Number of cuts (split nodes) in the tree.
+    pub fn cut_count(&self) -> usize {
+        match self {
+            CutNode::Piece(_) | CutNode::Waste { .. } => 0,
+            CutNode::Split { first, second, .. } => 1 + first.cut_count() + second.cut_count(),
+        }
+    }
+}
+
+/// Micro-tabs: short uncut bridges left in each cut so pieces don't shift
+/// while a CNC router finishes the sheet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Tabs {
+    pub tab_length: u32,
+    pub tabs_per_edge: u32,
+}
+
+impl Tabs {
+    /// Tab spans along a cut from `from` to `to`, spaced so the cut
+    /// stretches between them are equal. Cuts too short for every stretch
+    /// to be at least a tab long get none.
+    fn along(&self, from: u32, to: u32) -> Vec<(u32, u32)> {
+        let (n, t) = (self.tabs_per_edge, self.tab_length);
+        let uncut = n as u64 * t as u64;
+        let Some(cut) = ((to - from) as u64).checked_sub(uncut) else {
+            return vec![];
+        };
+        let stretch = cut / (n as u64 + 1);
+        if n == 0 || t == 0 || stretch < t as u64 {
+            return vec![];
+        }
+        (1..=n as u64)
+            .map(|i| {
+                let start = from + (i * stretch + (i - 1) * t as u64) as u32;
+                (start, start + t)
+            })
+            .collect()
+    }
+}
+
+/// One edge-to-edge cut of a sheet's cut plan.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Cut {
+    pub axis: Axis,
+    /// Where the kerf starts: an x for [`Axis::X`], a y for [`Axis::Y`].
+    pub position: u32,
+    /// Extent of the cut along the other axis.
+    pub from: u32,
+    pub to: u32,
+    /// Stretches `(from, to)` of the cut left uncut, see [`Tabs`].
+    pub tabs: Vec<(u32, u32)>,
+}
+
+/// A piece positioned within the sub-panel it is cut from, for machines
+/// that work panel by panel, see [`SheetResult::relative_placements`].
+#[derive(Debug, Clone, Serialize)]
+pub struct RelativePlacement {
+    pub placement: Placement,
+    /// Sheet position of the sub-panel: the region whose cut frees the
+    /// piece, or the sheet itself for a piece covering it.
+    pub parent_x: u32,
+    pub parent_y: u32,
+    pub parent: Rect,
+    /// Piece position from the sub-panel's origin.
+    pub x: u32,
+    pub y: u32,
+}
+
+/// A rectangular region of the sheet being decomposed.
+#[derive(Debug, Clone, Copy)]
+struct Region {
+    x: u32,
+    y: u32,
+    rect: Rect,
+}
+
+impl SheetResult {
+    /// Reconstruct the guillotine cut tree of this sheet from its placements.
+    ///
+    /// Returns `None` if the layout cannot be produced by edge-to-edge cuts
+    /// of width `kerf`.
+    pub fn cut_tree(&self, stock: Rect, kerf: u32) -> Option<CutNode> {
+        let region = Region {
+            x: 0,
+            y: 0,
+            rect: stock,
+        };
+        let placements: Vec<&Placement> = self.placements.iter().collect();
+        decompose(region, &placements, kerf)
+    }
+
+    /// The cuts of [`cut_tree`](Self::cut_tree) in cutting order (each
+    /// before the cuts of the parts it separates), with micro-tabs if
+    /// `tabs` is given.
+    pub fn cut_list(&self, stock: Rect, kerf: u32, tabs: Option<Tabs>) -> Option<Vec<Cut>> {
+        let tree = self.cut_tree(stock, kerf)?;
+        let region = Region {
+            x: 0,
+            y: 0,
+            rect: stock,
+        };
+        let mut cuts = Vec::new();
+        collect_cuts(&tree, region, kerf, tabs, &mut cuts);
+        Some(cuts)
+    }
+
+    /// The cuts of [`cut_list`](Self::cut_list) as numbered steps in plain
+    /// English, e.g. `2. Rip the right part of step 1 (397x500) at 40 mm
+    /// from the top edge.` Positions are measured from the part being cut;
+    /// edges and sides are named as the layout is drawn, y = 0 at the top
+    /// (see [`Anchor`](crate::types::Anchor)).
+    pub fn instructions(&self, stock: Rect, kerf: u32) -> Option<Vec<String>> {
+        let tree = self.cut_tree(stock, kerf)?;
+        let region = Region {
+            x: 0,
+            y: 0,
+            rect: stock,
+        };
+        let mut steps = Vec::new();
+        describe_cuts(&tree, region, "the sheet", kerf, &mut steps);
+        Some(steps)
+    }
+
+    /// The placements of [`cut_tree`](Self::cut_tree), in tree order, each
+    /// relative to the sub-panel it is cut from.
+    pub fn relative_placements(&self, stock: Rect, kerf: u32) -> Option<Vec<RelativePlacement>> {
+        let tree = self.cut_tree(stock, kerf)?;
+        let region = Region {
+            x: 0,
+            y: 0,
+            rect: stock,
+        };
+        let mut placements = Vec::new();
+        collect_relative(&tree, region, region, kerf, &mut placements);
+        Some(placements)
+    }
+}
+
+/// Pieces under `node`, which covers `region` and was cut from `parent`.
+fn collect_relative(
+    node: &CutNode,
+    region: Region,
+    parent: Region,
+    kerf: u32,
+    placements: &mut Vec<RelativePlacement>,
+) {
+    match node {
+        CutNode::Piece(p) => placements.push(RelativePlacement {
+            placement: p.clone(),
+            parent_x: parent.x,
+            parent_y: parent.y,
+            parent: parent.rect,
+            x: p.x - parent.x,
+            y: p.y - parent.y,
+        }),
+        CutNode::Waste { .. } => {}
+        CutNode::Split {
+            axis,
+            position,
+            first,
+            second,
+        } => {
+            let (first_region, second_region) = split_region(region, *axis, *position, kerf);
+            collect_relative(first, first_region, region, kerf, placements);
+            collect_relative(second, second_region, region, kerf, placements);
+        }
+    }
+}
+
+fn collect_cuts(
+    node: &CutNode,
+    region: Region,
+    kerf: u32,
+    tabs: Option<Tabs>,
+    cuts: &mut Vec<Cut>,
+) {
+    let CutNode::Split {
+        axis,
+        position,
+        first,
+        second,
+    } = node
+    else {
+        return;
+    };
+    let (from, to) = match axis {
+        Axis::X => (region.y, region.y + region.rect.width),
+        Axis::Y => (region.x, region.x + region.rect.length),
+    };
+    cuts.push(Cut {
+        axis: *axis,
+        position: *position,
+        from,
+        to,
+        tabs: tabs.map(|t| t.along(from, to)).unwrap_or_default(),
+    });
+    let (first_region, second_region) = split_region(region, *axis, *position, kerf);
+    collect_cuts(first, first_region, kerf, tabs, cuts);
+    collect_cuts(second, second_region, kerf, tabs, cuts);
+}
+
+/// Steps for the cuts under `node`, which covers `region`, called `name`.
+fn describe_cuts(node: &CutNode, region: Region, name: &str, kerf: u32, steps: &mut Vec<String>) {
+    let CutNode::Split {
+        axis,
+        position,
+        first,
+        second,
+    } = node
+    else {
+        return;
+    };
+    let step = steps.len() + 1;
+    let (verb, offset, edge, sides) = match axis {
+        Axis::X => ("Crosscut", position - region.x, "left", ["left", "right"]),
+        Axis::Y => ("Rip", position - region.y, "top", ["top", "bottom"]),
+    };
+    steps.push(format!(
+        "{}. {} {} at {} mm from the {} edge.",
+        step, verb, name, offset, edge
+    ));
+    let (first_region, second_region) = split_region(region, *axis, *position, kerf);
+    for (node, region, side) in [
+        (first, first_region, sides[0]),
+        (second, second_region, sides[1]),
+    ] {
+        let name = format!("the {} part of step {} ({})", side, step, region.rect);
+        describe_cuts(node, region, &name, kerf, steps);
+    }
+}
+
+/// Whether `placements` can all be separated by edge-to-edge cuts of width
+/// `kerf` on `stock`.
+pub(crate) fn is_guillotine(stock: Rect, placements: &[&Placement], kerf: u32) -> bool {
+    let region = Region {
+        x: 0,
+        y: 0,
+        rect: stock,
+    };
+    decompose(region, placements, kerf).is_some()
+}
+
+/// Number of cuts needed to free `placements` from `stock`, or `None` if
+/// they can't be separated by edge-to-edge cuts of width `kerf`.
+pub(crate) fn cut_count(stock: Rect, placements: &[Placement], kerf: u32) -> Option<usize> {
+    let region = Region {
+        x: 0,
+        y: 0,
+        rect: stock,
+    };
+    let placements: Vec<&Placement> = placements.iter().collect();
+    decompose(region, &placements, kerf).map(|tree| tree.cut_count())
+}
+
+fn decompose(region: Region, placements: &[&Placement], kerf: u32) -> Option<CutNode> {
+    if placements.is_empty() {
+        return Some(CutNode::Waste {
+            x: region.x,
+            y: region.y,
+            rect: region.rect,
+        });
+    }
+    if let [p] = placements
+        && p.x == region.x
+        && p.y == region.y
+        && p.rect == region.rect
+    {
+        return Some(CutNode::Piece((*p).clone()));
+    }
+
+    // Rip cuts (across Y) first, then crosscuts (across X)
+    for axis in [Axis::Y, Axis::X] {
+        let Some(position) = find_cut(region, placements, axis, kerf) else {
+            continue;
+        };
+        let (first_region, second_region) = split_region(region, axis, position, kerf);
+        let (first, second): (Vec<&Placement>, Vec<&Placement>) = placements
+            .iter()
+            .copied()
+            .partition(|p| span(p, axis).1 <= position);
+        return Some(CutNode::Split {
+            axis,
+            position,
+            first: Box::new(decompose(first_region, &first, kerf)?),
+            second: Box::new(decompose(second_region, &second, kerf)?),
+        });
+    }
+    None
+}
+
+/// Start and end of a placement along the axis crossed by the cut.
+fn span(p: &Placement, axis: Axis) -> (u32, u32) {
+    match axis {
+        Axis::X => (p.x, p.x + p.rect.length),
+        Axis::Y => (p.y, p.y + p.rect.width),
+    }
+}
+
+/// Smallest cut position that separates the placements without crossing any.
+fn find_cut(region: Region, placements: &[&Placement], axis: Axis, kerf: u32) -> Option<u32> {
+    let (start, end) = match axis {
+        Axis::X => (region.x, region.x + region.rect.length),
+        Axis::Y => (region.y, region.y + region.rect.width),
+    };
+
+    // A cut can start where a piece ends, or end (after kerf) where one starts
+    let mut candidates: Vec<u32> = placements
+        .iter()
+        .flat_map(|p| {
+            let (s, e) = span(p, axis);
+            [Some(e), s.checked_sub(kerf)]
+        })
+        .flatten()
+        .filter(|&c| c > start && c < end)
+        .collect();
+    candidates.sort_unstable();
+    candidates.dedup();
+
+    candidates.into_iter().find(|&c| {
+        placements.iter().all(|p| {
+            let (s, e) = span(p, axis);
+            e <= c || s >= c + kerf
+        })
+    })
+}
+
+fn split_region(region: Region, axis: Axis, position: u32, kerf: u32) -> (Region, Region) {
+    match axis {
+        Axis::X => {
+            let end = region.x + region.rect.length;
+            let second_x = (position + kerf).min(end);
+            (
+                Region {
+                    rect: Rect::new(position - region.x, region.rect.width),
+                    ..region
+                },
+                Region {
+                    x: second_x,
+                    y: region.y,
+                    rect: Rect::new(end - second_x, region.rect.width),
+                },
+            )
+        }
+        Axis::Y => {
+            let end = region.y + region.rect.width;
+            let second_y = (position + kerf).min(end);
+            (
+                Region {
+                    rect: Rect::new(region.rect.length, position - region.y),
+                    ..region
+                },
+                Region {
+                    x: region.x,
+                    y: second_y,
+                    rect: Rect::new(region.rect.length, end - second_y),
+                },
+            )
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::solver::Solver;
+    use crate::types::{Anchor, CutDirection, Demand, PieceGrain, StockGrain};
+
+    fn placement(l: u32, w: u32, x: u32, y: u32) -> Placement {
+        Placement::new(Rect::new(l, w), x, y)
+    }
+
+    /// Checks that every node lies within its region and every split cuts
+    /// its region edge to edge, returning the number of piece leaves.
+    fn assert_valid(node: &CutNode, region: Region, kerf: u32) -> usize {
+        match node {
+            CutNode::Piece(p) => {
+                assert_eq!((p.x, p.y, p.rect), (region.x, region.y, region.rect));
+                1
+            }
+            CutNode::Waste { x, y, rect } => {
+                assert_eq!((*x, *y, *rect), (region.x, region.y, region.rect));
+                0
+            }
+            CutNode::Split {
+                axis,
+                position,
+                first,
+                second,
+            } => {
+                let (lo, hi) = match axis {
+                    Axis::X => (region.x, region.x + region.rect.length),
+                    Axis::Y => (region.y, region.y + region.rect.width),
+                };
+                assert!(*position > lo && *position < hi);
+                let (a, b) = split_region(region, *axis, *position, kerf);
+                assert_valid(first, a, kerf) + assert_valid(second, b, kerf)
+            }
+        }
+    }
+
+    #[test]
+    fn test_cut_tree_simple() {
+        let stock = Rect::new(100, 100);
+        let sheet = SheetResult {
+            stock,
+            stock_rotated: false,
+            placements: vec![placement(50, 30, 0, 0), placement(45, 30, 55, 0)],
+            waste_area: 0,
+            material: None,
+            offcuts: vec![],
+            reserved: None,
+            cut_direction: CutDirection::Auto,
+        };
+        let tree = sheet.cut_tree(stock, 5).unwrap();
+        let root = Region {
+            x: 0,
+            y: 0,
+            rect: stock,
+        };
+        assert_eq!(assert_valid(&tree, root, 5), 2);
+
+        // First cut rips the 30-wide strip off the sheet
+        let CutNode::Split { axis, position, .. } = &tree else {
+            panic!("expected a split at the root");
+        };
+        assert_eq!((*axis, *position), (Axis::Y, 30));
+    }
+
+    #[test]
+    fn test_cut_tree_non_guillotine() {
+        // Pinwheel layout: no edge-to-edge cut separates the pieces
+        let sheet = SheetResult {
+            stock: Rect::new(3, 3),
+            stock_rotated: false,
+            placements: vec![
+                placement(2, 1, 0, 0),
+                placement(1, 2, 2, 0),
+                placement(2, 1, 1, 2),
+                placement(1, 2, 0, 1),
+            ],
+            waste_area: 0,
+            material: None,
+            offcuts: vec![],
+            reserved: None,
+            cut_direction: CutDirection::Auto,
+        };
+        assert!(sheet.cut_tree(Rect::new(3, 3), 0).is_none());
+    }
+
+    #[test]
+    fn test_cut_tree_leaves_match_placements() {
+        let stock = Rect::new(2440, 1220);
+        let demands = [(800, 600, 3), (400, 300, 6), (1200, 400, 2), (300, 200, 5)]
+            .iter()
+            .map(|&(l, w, qty)| Demand {
+                rect: Rect::new(l, w),
+                qty,
+                allow_rotate: true,
+                grain: PieceGrain::Auto,
+                ..Default::default()
+            })
+            .collect();
+        let kerf = 3;
+        let sol = Solver::new(stock, kerf, CutDirection::Auto, StockGrain::None, demands).solve();
+
+        for sheet in &sol.sheets {
+            let tree = sheet.cut_tree(stock, kerf).expect("layout is guillotine");
+            let root = Region {
+                x: 0,
+                y: 0,
+                rect: stock,
+            };
+            assert_eq!(assert_valid(&tree, root, kerf), sheet.placements.len());
+
+            let key = |p: &Placement| (p.x, p.y, p.rect.length, p.rect.width);
+            let mut leaves: Vec<_> = tree.pieces().iter().map(key).collect();
+            let mut expected: Vec<_> = sheet.placements.iter().map(key).collect();
+            leaves.sort_unstable();
+            expected.sort_unstable();
+            assert_eq!(leaves, expected);
+        }
+    }
+
+    #[test]
+    fn test_relative_placements() {
+        let stock = Rect::new(1000, 500);
+        let sheet = SheetResult {
+            stock,
+            stock_rotated: false,
+            placements: vec![
+                placement(600, 500, 0, 0),
+                placement(397, 40, 603, 0),
+                placement(397, 457, 603, 43),
+            ],
+            waste_area: 0,
+            material: None,
+            offcuts: vec![],
+            reserved: None,
+            cut_direction: CutDirection::Auto,
+        };
+        let relative = sheet.relative_placements(stock, 3).unwrap();
+        let spots: Vec<_> = relative
+            .iter()
+            .map(|r| (r.parent_x, r.parent_y, r.parent, r.x, r.y))
+            .collect();
+        assert_eq!(
+            spots,
+            [
+                (0, 0, stock, 0, 0),
+                (603, 0, Rect::new(397, 500), 0, 0),
+                // Cut off the same sub-panel, past the 40 mm rip
+                (603, 0, Rect::new(397, 500), 0, 43),
+            ]
+        );
+
+        // Relative position plus sub-panel origin gives back the sheet position
+        let demands = [(800, 600, 3), (400, 300, 6), (1200, 400, 2), (300, 200, 5)]
+            .iter()
+            .map(|&(l, w, qty)| Demand::new(Rect::new(l, w), qty))
+            .collect();
+        let stock = Rect::new(2440, 1220);
+        let sol = Solver::new(stock, 3, CutDirection::Auto, StockGrain::None, demands).solve();
+        for sheet in &sol.sheets {
+            let relative = sheet.relative_placements(stock, 3).unwrap();
+            assert_eq!(relative.len(), sheet.placements.len());
+            for r in &relative {
+                let p = &r.placement;
+                assert_eq!((r.parent_x + r.x, r.parent_y + r.y), (p.x, p.y));
+                assert!(r.x + p.rect.length <= r.parent.length);
+                assert!(r.y + p.rect.width <= r.parent.width);
+            }
+        }
+    }
+
+    #[test]
+    fn test_instructions() {
+        let stock = Rect::new(1000, 500);
+        let sheet = SheetResult {
+            stock,
+            stock_rotated: false,
+            placements: vec![
+                placement(600, 500, 0, 0),
+                placement(397, 40, 603, 0),
+                placement(397, 457, 603, 43),
+            ],
+            waste_area: 0,
+            material: None,
+            offcuts: vec![],
+            reserved: None,
+            cut_direction: CutDirection::Auto,
+        };
+        assert_eq!(
+            sheet.instructions(stock, 3).unwrap(),
+            [
+                "1. Crosscut the sheet at 600 mm from the left edge.",
+                "2. Rip the right part of step 1 (397x500) at 40 mm from the top edge.",
+            ]
+        );
+
+        let demands = [(800, 600, 3), (400, 300, 6), (1200, 400, 2), (300, 200, 5)]
+            .iter()
+            .map(|&(l, w, qty)| Demand::new(Rect::new(l, w), qty))
+            .collect();
+        let stock = Rect::new(2440, 1220);
+        let sol = Solver::new(stock, 3, CutDirection::Auto, StockGrain::None, demands).solve();
+        for sheet in &sol.sheets {
+            let steps = sheet.instructions(stock, 3).unwrap();
+            let cuts = sheet.cut_list(stock, 3, None).unwrap();
+            assert_eq!(steps.len(), cuts.len());
+            for (i, step) in steps.iter().enumerate() {
+                assert!(step.starts_with(&format!("{}. ", i + 1)), "{step}");
+            }
+        }
+
+        // Sides are named as the anchors: the top part is the one at y = 0
+        let demands = vec![
+            Demand::new(Rect::new(600, 200), 1),
+            Demand::new(Rect::new(300, 400), 1),
+        ];
+        let stock = Rect::new(1000, 500);
+        for (anchor, first_steps) in [
+            (
+                Anchor::TopRight,
+                [
+                    "1. Rip the sheet at 400 mm from the top edge.",
+                    "2. Crosscut the top part of step 1 (1000x400) at 94 mm from the left edge.",
+                ],
+            ),
+            (
+                Anchor::BottomRight,
+                [
+                    "1. Rip the sheet at 97 mm from the top edge.",
+                    "2. Crosscut the bottom part of step 1 (1000x400) at 94 mm from the left edge.",
+                ],
+            ),
+        ] {
+            let sol = Solver::new(
+                stock,
+                3,
+                CutDirection::Auto,
+                StockGrain::None,
+                demands.clone(),
+            )
+            .with_anchor(Some(anchor))
+            .solve();
+            let steps = sol.sheets[0].instructions(stock, 3).unwrap();
+            assert_eq!(steps[..2], first_steps, "{:?}", anchor);
+        }
+    }
+
+    #[test]
+    fn test_cut_list_tabs() {
+        let stock = Rect::new(1000, 500);
+        let sheet = SheetResult {
+            stock,
+            stock_rotated: false,
+            placements: vec![placement(600, 500, 0, 0), placement(397, 40, 603, 0)],
+            waste_area: 0,
+            material: None,
+            offcuts: vec![],
+            reserved: None,
+            cut_direction: CutDirection::Auto,
+        };
+        let tabs = Tabs {
+            tab_length: 100,
+            tabs_per_edge: 2,
+        };
+        let cuts = sheet.cut_list(stock, 3, Some(tabs)).unwrap();
+        assert_eq!(
+            cuts,
+            [
+                // 500 long: three 100 stretches of cut around two 100 tabs
+                Cut {
+                    axis: Axis::X,
+                    position: 600,
+                    from: 0,
+                    to: 500,
+                    tabs: vec![(100, 200), (300, 400)],
+                },
+                // Too short to keep 100 of cut between the tabs
+                Cut {
+                    axis: Axis::Y,
+                    position: 40,
+                    from: 603,
+                    to: 1000,
+                    tabs: vec![],
+                },
+            ]
+        );
+        assert!(
+            sheet
+                .cut_list(stock, 3, None)
+                .unwrap()
+                .iter()
+                .all(|c| c.tabs.is_empty())
+        );
+    }
+}
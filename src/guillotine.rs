@@ -1,23 +1,53 @@
-use crate::types::{CutDirection, Placement, Rect, RotationConstraint};
+use crate::cut_tree;
+use crate::types::{Axis, CutDirection, Placement, Rect, RotationConstraint};
+use serde::Serialize;
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct FreeRect {
     pub x: u32,
     pub y: u32,
     pub rect: Rect,
 }
 
+/// What [`GuillotineBin::unplace`] needs to take the last placement back,
+/// returned by [`GuillotineBin::place_undoable`].
+#[derive(Debug, Clone)]
+pub struct PlaceUndo {
+    free_rects: Vec<FreeRect>,
+    scrap_len: usize,
+}
+
 #[derive(Debug, Clone)]
 pub struct GuillotineBin {
-    #[allow(dead_code)]
     stock: Rect,
     kerf: u32,
     cut_direction: CutDirection,
+    /// Free rects with a side shorter than this can't hold any piece and are dropped.
+    min_useful: u32,
+    /// Break score ties in favour of free rects closer to (0, 0).
+    prefer_origin: bool,
+    /// Break score ties between a piece's two orientations in favour of the
+    /// one with its longer side along this axis.
+    long_side_along: Option<Axis>,
     pub free_rects: Vec<FreeRect>,
+    /// Free rects dropped for being smaller than `min_useful`.
+    pub scrap: Vec<FreeRect>,
+    /// Space set aside by [`reserve`](Self::reserve); never placed into.
+    pub reserved: Option<FreeRect>,
+    /// Zones removed by [`block_out`](Self::block_out).
+    blocked: Vec<FreeRect>,
+    /// Cuts set by [`set_first_cut`](Self::set_first_cut) and
+    /// [`set_strip_boundaries`](Self::set_strip_boundaries), which free
+    /// rects are never merged across.
+    fixed_cuts: Vec<(Axis, u32)>,
+    /// Grid set by [`set_grid_pitch`](Self::set_grid_pitch), as
+    /// `(along x, along y)`.
+    grid_pitch: Option<(u32, u32)>,
     pub placements: Vec<Placement>,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
 #[allow(clippy::enum_variant_names)]
 pub enum ScoreStrategy {
     BestAreaFit,
@@ -25,11 +55,55 @@ pub enum ScoreStrategy {
     BestLongSideFit,
 }
 
+impl ScoreStrategy {
+    pub const ALL: [Self; 3] = [
+        Self::BestAreaFit,
+        Self::BestShortSideFit,
+        Self::BestLongSideFit,
+    ];
+}
+
+/// Ranks a free rect for a piece that fits in it, lower being better.
+/// Implement it for placement heuristics beyond [`ScoreStrategy`] and pass
+/// it to [`GuillotineBin::find_best_scored`].
+pub trait PlacementScorer {
+    fn score(&self, piece: Rect, free: Rect) -> (u64, u64);
+}
+
+impl PlacementScorer for ScoreStrategy {
+    fn score(&self, piece: Rect, free: Rect) -> (u64, u64) {
+        match self {
+            ScoreStrategy::BestAreaFit => {
+                let area_diff = free.area() - piece.area();
+                let short_side =
+                    std::cmp::min(free.length - piece.length, free.width - piece.width) as u64;
+                (area_diff, short_side)
+            }
+            ScoreStrategy::BestShortSideFit => {
+                let short =
+                    std::cmp::min(free.length - piece.length, free.width - piece.width) as u64;
+                let long =
+                    std::cmp::max(free.length - piece.length, free.width - piece.width) as u64;
+                (short, long)
+            }
+            ScoreStrategy::BestLongSideFit => {
+                let long =
+                    std::cmp::max(free.length - piece.length, free.width - piece.width) as u64;
+                let short =
+                    std::cmp::min(free.length - piece.length, free.width - piece.width) as u64;
+                (long, short)
+            }
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy)]
 pub struct ScoredPlacement {
     pub free_idx: usize,
     pub rotated: bool,
-    pub score: (u64, u64),
+    /// Strategy score, then distance to the origin when biased toward it
+    /// (lower is better).
+    pub score: (u64, u64, u64),
 }
 
 impl GuillotineBin {
@@ -38,15 +112,234 @@ impl GuillotineBin {
             stock,
             kerf,
             cut_direction,
+            min_useful: 0,
+            prefer_origin: false,
+            long_side_along: None,
             free_rects: vec![FreeRect {
                 x: 0,
                 y: 0,
                 rect: stock,
             }],
+            scrap: Vec::new(),
+            reserved: None,
+            blocked: Vec::new(),
+            fixed_cuts: Vec::new(),
+            grid_pitch: None,
             placements: Vec::new(),
         }
     }
 
+    /// Set `rect` aside (either orientation) as if it were a placed piece,
+    /// so later placements can't use it. Returns `false`, leaving the bin
+    /// unchanged, if it doesn't fit.
+    pub fn reserve(&mut self, rect: Rect) -> bool {
+        let Some(scored) =
+            self.find_best(rect, RotationConstraint::Free, ScoreStrategy::BestAreaFit)
+        else {
+            return false;
+        };
+        let placed = self.place(scored, rect);
+        self.placements.pop();
+        self.reserved = Some(FreeRect {
+            x: placed.x,
+            y: placed.y,
+            rect: placed.rect,
+        });
+        true
+    }
+
+    /// Limit placement to the `rect` window at `(x, y)`, clipped to the stock.
+    /// Must be called before any piece is placed.
+    pub fn restrict_to(&mut self, x: u32, y: u32, rect: Rect) {
+        let length = rect.length.min(self.stock.length.saturating_sub(x));
+        let width = rect.width.min(self.stock.width.saturating_sub(y));
+        self.free_rects.clear();
+        if length > 0 && width > 0 {
+            self.free_rects.push(FreeRect {
+                x,
+                y,
+                rect: Rect::new(length, width),
+            });
+        }
+    }
+
+    /// Remove the `rect` area at `(x, y)` from the free space, e.g. an
+    /// unusable zone of the sheet. Free rects it crosses keep full-length
+    /// bands below and above it, and the parts beside it in between, each
+    /// a kerf away from the zone and from one another. Must be called
+    /// before any piece is placed.
+    pub fn block_out(&mut self, x: u32, y: u32, rect: Rect) {
+        let kerf = self.kerf;
+        let (end_x, end_y) = (x + rect.length, y + rect.width);
+        self.blocked.push(FreeRect { x, y, rect });
+        let mut free_rects = Vec::with_capacity(self.free_rects.len());
+        for f in self.free_rects.drain(..) {
+            let (f_end_x, f_end_y) = (f.x + f.rect.length, f.y + f.rect.width);
+            if x >= f_end_x || end_x <= f.x || y >= f_end_y || end_y <= f.y {
+                free_rects.push(f);
+                continue;
+            }
+            let (lo_y, hi_y) = (y.max(f.y), end_y.min(f_end_y));
+            let parts = [
+                (f.x, f.y, f.rect.length, (lo_y - f.y).saturating_sub(kerf)),
+                (
+                    f.x,
+                    hi_y + kerf,
+                    f.rect.length,
+                    (f_end_y - hi_y).saturating_sub(kerf),
+                ),
+                (
+                    f.x,
+                    lo_y,
+                    x.saturating_sub(f.x).saturating_sub(kerf),
+                    hi_y - lo_y,
+                ),
+                (
+                    end_x + kerf,
+                    lo_y,
+                    f_end_x.saturating_sub(end_x).saturating_sub(kerf),
+                    hi_y - lo_y,
+                ),
+            ];
+            free_rects.extend(
+                parts
+                    .into_iter()
+                    .filter(|&(_, _, l, w)| l > 0 && w > 0)
+                    .map(|(x, y, l, w)| FreeRect {
+                        x,
+                        y,
+                        rect: Rect::new(l, w),
+                    }),
+            );
+        }
+        self.free_rects = free_rects;
+    }
+
+    /// Make the first cut of the sheet an edge-to-edge cut at `position`
+    /// (where its kerf starts) across `axis`: free rects it crosses are split
+    /// in two on either side of the kerf, and never merged back. Must be
+    /// called before any piece is placed.
+    pub fn set_first_cut(&mut self, axis: Axis, position: u32) {
+        self.add_fixed_cut(axis, position);
+    }
+
+    /// Planned strip cuts, edge to edge across `axis` at each of `positions`
+    /// (where the kerf starts), made before any crosscut: like
+    /// [`set_first_cut`](Self::set_first_cut), free space is split along
+    /// them and never merged across, so pieces stay within their strip.
+    pub fn set_strip_boundaries(&mut self, axis: Axis, positions: &[u32]) {
+        for &position in positions {
+            self.add_fixed_cut(axis, position);
+        }
+    }
+
+    fn add_fixed_cut(&mut self, axis: Axis, position: u32) {
+        let kerf = self.kerf;
+        let mut free_rects = Vec::with_capacity(self.free_rects.len() + 1);
+        for f in self.free_rects.drain(..) {
+            let (start, len) = match axis {
+                Axis::X => (f.x, f.rect.length),
+                Axis::Y => (f.y, f.rect.width),
+            };
+            if position < start || position >= start + len {
+                free_rects.push(f);
+                continue;
+            }
+            let before = position - start;
+            let after = (start + len).saturating_sub(position + kerf);
+            let part = |offset: u32, len: u32| match axis {
+                Axis::X => FreeRect {
+                    x: f.x + offset,
+                    y: f.y,
+                    rect: Rect::new(len, f.rect.width),
+                },
+                Axis::Y => FreeRect {
+                    x: f.x,
+                    y: f.y + offset,
+                    rect: Rect::new(f.rect.length, len),
+                },
+            };
+            if before > 0 {
+                free_rects.push(part(0, before));
+            }
+            if after > 0 {
+                free_rects.push(part(before + kerf, after));
+            }
+        }
+        self.free_rects = free_rects;
+        self.fixed_cuts.push((axis, position));
+    }
+
+    /// Drop free rects whose shorter side is below `min_useful` after each
+    /// placement, typically the smallest dimension of any piece to place.
+    pub fn set_min_useful(&mut self, min_useful: u32) {
+        self.min_useful = min_useful;
+    }
+
+    /// Snap every placement to a grid: free rects start on multiples of
+    /// `pitch` (`(along x, along y)`), cuts moving the next free rect past
+    /// the kerf to the following grid line, and only orientations whose
+    /// sides are multiples of the pitch are tried. Existing free rects are
+    /// shrunk onto the grid.
+    pub fn set_grid_pitch(&mut self, pitch: (u32, u32)) {
+        self.grid_pitch = Some(pitch);
+        let free_rects = std::mem::take(&mut self.free_rects);
+        for f in free_rects {
+            let (x, y) = (align_up(f.x, pitch.0), align_up(f.y, pitch.1));
+            let (end_x, end_y) = (f.x + f.rect.length, f.y + f.rect.width);
+            if x < end_x && y < end_y {
+                self.free_rects.push(FreeRect {
+                    x,
+                    y,
+                    rect: Rect::new(end_x - x, end_y - y),
+                });
+            }
+        }
+    }
+
+    /// Whether `piece` sits on the grid, if there is one.
+    fn on_grid(&self, piece: Rect) -> bool {
+        self.grid_pitch.is_none_or(|(px, py)| {
+            piece.length.is_multiple_of(px) && piece.width.is_multiple_of(py)
+        })
+    }
+
+    /// Among equally scored free rects, prefer the one nearest (0, 0).
+    pub fn set_prefer_origin(&mut self, prefer_origin: bool) {
+        self.prefer_origin = prefer_origin;
+    }
+
+    /// Among equally scored placements, prefer a piece's orientation with
+    /// its longer side along `axis`, e.g. the stock grain.
+    pub fn set_long_side_along(&mut self, axis: Option<Axis>) {
+        self.long_side_along = axis;
+    }
+
+    /// Change how free rects are split by subsequent placements.
+    pub fn set_cut_direction(&mut self, cut_direction: CutDirection) {
+        self.cut_direction = cut_direction;
+    }
+
+    pub fn stock(&self) -> Rect {
+        self.stock
+    }
+
+    pub fn cut_direction(&self) -> CutDirection {
+        self.cut_direction
+    }
+
+    /// Whether any piece placed in `self` could be placed the same way in
+    /// `other`, and vice versa (same free space and splitting rules).
+    pub fn same_state(&self, other: &GuillotineBin) -> bool {
+        self.stock == other.stock
+            && self.kerf == other.kerf
+            && self.cut_direction == other.cut_direction
+            && self.min_useful == other.min_useful
+            && self.free_rects == other.free_rects
+            && self.blocked == other.blocked
+            && self.fixed_cuts == other.fixed_cuts
+    }
+
     pub fn used_area(&self) -> u64 {
         self.placements.iter().map(|p| p.rect.area()).sum()
     }
@@ -57,16 +350,114 @@ impl GuillotineBin {
         rotation: RotationConstraint,
         score_strategy: ScoreStrategy,
     ) -> Option<ScoredPlacement> {
-        let try_normal = rotation != RotationConstraint::ForceRotate;
-        let try_rotated = rotation != RotationConstraint::NoRotate;
+        self.find_best_penalized(piece, rotation, score_strategy, [0, 0])
+    }
+
+    /// Like [`find_best`](Self::find_best), with `penalty[0]` added to the
+    /// primary score of unrotated placements and `penalty[1]` to rotated ones.
+    pub fn find_best_penalized(
+        &self,
+        piece: Rect,
+        rotation: RotationConstraint,
+        score_strategy: ScoreStrategy,
+        penalty: [u64; 2],
+    ) -> Option<ScoredPlacement> {
+        self.find_best_scored(piece, rotation, &score_strategy, penalty)
+    }
+
+    /// Best free rect and orientation for `piece` as ranked by `scorer`,
+    /// with `penalty` added as in
+    /// [`find_best_penalized`](Self::find_best_penalized).
+    pub fn find_best_scored(
+        &self,
+        piece: Rect,
+        rotation: RotationConstraint,
+        scorer: &dyn PlacementScorer,
+        penalty: [u64; 2],
+    ) -> Option<ScoredPlacement> {
+        self.find_best_ranked(piece, rotation, scorer, penalty, false, &|_| true)
+    }
+
+    /// Like [`find_best_penalized`](Self::find_best_penalized), but ranks
+    /// placements first by how much of the piece's outline would border
+    /// free space rather than placed pieces or the sheet edge. Keeps thin,
+    /// fragile pieces away from large open areas where they get knocked.
+    pub fn find_sheltered(
+        &self,
+        piece: Rect,
+        rotation: RotationConstraint,
+        score_strategy: ScoreStrategy,
+        penalty: [u64; 2],
+    ) -> Option<ScoredPlacement> {
+        self.find_best_ranked(piece, rotation, &score_strategy, penalty, true, &|_| true)
+    }
+
+    /// Like [`find_best_penalized`](Self::find_best_penalized), among the
+    /// free rects `keep` accepts only.
+    pub fn find_best_where(
+        &self,
+        piece: Rect,
+        rotation: RotationConstraint,
+        score_strategy: ScoreStrategy,
+        penalty: [u64; 2],
+        keep: &dyn Fn(&FreeRect) -> bool,
+    ) -> Option<ScoredPlacement> {
+        self.find_best_ranked(piece, rotation, &score_strategy, penalty, false, keep)
+    }
+
+    fn find_best_ranked(
+        &self,
+        piece: Rect,
+        rotation: RotationConstraint,
+        scorer: &dyn PlacementScorer,
+        penalty: [u64; 2],
+        sheltered: bool,
+        keep: &dyn Fn(&FreeRect) -> bool,
+    ) -> Option<ScoredPlacement> {
+        let try_normal = rotation != RotationConstraint::ForceRotate && self.on_grid(piece);
+        let try_rotated = rotation != RotationConstraint::NoRotate && self.on_grid(piece.rotated());
+        let preferred = |rotated: bool| {
+            let placed = if rotated { piece.rotated() } else { piece };
+            match self.long_side_along {
+                Some(Axis::X) => placed.length > placed.width,
+                Some(Axis::Y) => placed.width > placed.length,
+                None => false,
+            }
+        };
+        let beats = |score, rotated, best: Option<ScoredPlacement>| {
+            best.is_none_or(|best| {
+                score < best.score
+                    || (score == best.score && preferred(rotated) && !preferred(best.rotated))
+            })
+        };
 
         let mut best: Option<ScoredPlacement> = None;
 
         for (idx, free) in self.free_rects.iter().enumerate() {
+            if !keep(free) {
+                continue;
+            }
             // Try normal orientation
+            let origin_distance = if self.prefer_origin {
+                free.x as u64 + free.y as u64
+            } else {
+                0
+            };
+            let rank = |placed: Rect, penalty: u64| {
+                let (a, b) = scorer.score(placed, free.rect);
+                if sheltered {
+                    (
+                        self.exposed_outline(idx, placed),
+                        a.saturating_add(penalty),
+                        b,
+                    )
+                } else {
+                    (a.saturating_add(penalty), b, origin_distance)
+                }
+            };
             if try_normal && piece.fits_in(&free.rect) {
-                let score = Self::score(piece, free.rect, score_strategy);
-                if best.is_none() || score < best.unwrap().score {
+                let score = rank(piece, penalty[0]);
+                if beats(score, false, best) {
                     best = Some(ScoredPlacement {
                         free_idx: idx,
                         rotated: false,
@@ -78,8 +469,8 @@ impl GuillotineBin {
             if try_rotated {
                 let rotated = piece.rotated();
                 if rotated.fits_in(&free.rect) {
-                    let score = Self::score(rotated, free.rect, score_strategy);
-                    if best.is_none() || score < best.unwrap().score {
+                    let score = rank(rotated, penalty[1]);
+                    if beats(score, true, best) {
                         best = Some(ScoredPlacement {
                             free_idx: idx,
                             rotated: true,
@@ -93,64 +484,158 @@ impl GuillotineBin {
         best
     }
 
-    fn score(piece: Rect, free: Rect, strategy: ScoreStrategy) -> (u64, u64) {
-        match strategy {
-            ScoreStrategy::BestAreaFit => {
-                let area_diff = free.area() - piece.area();
-                let short_side =
-                    std::cmp::min(free.length - piece.length, free.width - piece.width) as u64;
-                (area_diff, short_side)
+    /// Length of the outline of `piece`, placed at the top-left corner of
+    /// free rect `idx`, that would border free space: the rest of that
+    /// free rect, or another one across a kerf at most.
+    fn exposed_outline(&self, idx: usize, piece: Rect) -> u64 {
+        let free = self.free_rects[idx];
+        let (x0, y0) = (free.x, free.y);
+        let (x1, y1) = (x0 + piece.length, y0 + piece.width);
+        let mut exposed = 0;
+        if free.rect.length > piece.length {
+            exposed += piece.width as u64;
+        }
+        if free.rect.width > piece.width {
+            exposed += piece.length as u64;
+        }
+        let overlap = |a0: u32, a1: u32, b0: u32, b1: u32| a1.min(b1).saturating_sub(a0.max(b0));
+        let touches = |lo: u32, hi: u32, near: u32, far: u32| {
+            (hi <= near && hi + self.kerf >= near) || (lo >= far && lo <= far + self.kerf)
+        };
+        for (i, other) in self.free_rects.iter().enumerate() {
+            if i == idx {
+                continue;
             }
-            ScoreStrategy::BestShortSideFit => {
-                let short =
-                    std::cmp::min(free.length - piece.length, free.width - piece.width) as u64;
-                let long =
-                    std::cmp::max(free.length - piece.length, free.width - piece.width) as u64;
-                (short, long)
+            let (ox1, oy1) = (other.x + other.rect.length, other.y + other.rect.width);
+            if touches(other.x, ox1, x0, x1) {
+                exposed += overlap(y0, y1, other.y, oy1) as u64;
             }
-            ScoreStrategy::BestLongSideFit => {
-                let long =
-                    std::cmp::max(free.length - piece.length, free.width - piece.width) as u64;
-                let short =
-                    std::cmp::min(free.length - piece.length, free.width - piece.width) as u64;
-                (long, short)
+            if touches(other.y, oy1, y0, y1) {
+                exposed += overlap(x0, x1, other.x, ox1) as u64;
             }
         }
+        exposed
     }
 
+    /// Place `piece` as scored by [`find_best`](Self::find_best), at the
+    /// top-left corner of its free rect.
+    ///
+    /// Kerf is only taken where a remainder is cut off: a piece spanning
+    /// its free rect along an axis needs no cut there. A full-sheet piece
+    /// therefore uses the whole sheet whatever the kerf, and a remainder no
+    /// wider than the kerf is lost to the cut.
     pub fn place(&mut self, scored: ScoredPlacement, piece: Rect) -> Placement {
+        self.place_with_clearance(scored, piece, [0; 4])
+    }
+
+    /// Like [`place`](Self::place), but reserves `clearance`
+    /// (`[left, right, top, bottom]`) around the piece. `scored` must come
+    /// from `find_best` on `piece.with_clearance(clearance)`; the placement
+    /// keeps the bare piece size, offset by the leading clearance.
+    pub fn place_with_clearance(
+        &mut self,
+        scored: ScoredPlacement,
+        piece: Rect,
+        clearance: [u32; 4],
+    ) -> Placement {
         let free = self.free_rects[scored.free_idx];
-        let placed = if scored.rotated {
-            piece.rotated()
+        let (placed, clearance) = if scored.rotated {
+            (piece.rotated(), rotate_clearance(clearance))
         } else {
-            piece
+            (piece, clearance)
         };
+        let footprint = placed.with_clearance(clearance);
 
         let placement = Placement {
             rect: placed,
-            x: free.x,
-            y: free.y,
+            x: free.x + clearance[0],
+            y: free.y + clearance[2],
             rotated: scored.rotated,
+            requested: piece,
+            orientation_deg: if scored.rotated { 90 } else { 0 },
+            label: None,
+            is_filler: false,
+            directional: false,
+            group_hint: None,
         };
 
         // Remove the used free rect and split
         self.free_rects.swap_remove(scored.free_idx);
-        self.split(free, placed);
-        self.placements.push(placement);
+        self.split(free, footprint);
+        self.placements.push(placement.clone());
         self.merge_free_rects();
+        let min_useful = self.min_useful;
+        let (useful, scrap): (Vec<FreeRect>, Vec<FreeRect>) = self
+            .free_rects
+            .iter()
+            .partition(|f| f.rect.length.min(f.rect.width) >= min_useful);
+        self.free_rects = useful;
+        self.scrap.extend(scrap);
 
         placement
     }
 
+    /// Like [`place_with_clearance`](Self::place_with_clearance), also
+    /// returning what [`unplace`](Self::unplace) needs to undo it. Cheaper
+    /// than cloning the bin to try a placement: only the free rects are
+    /// saved.
+    pub fn place_undoable(
+        &mut self,
+        scored: ScoredPlacement,
+        piece: Rect,
+        clearance: [u32; 4],
+    ) -> PlaceUndo {
+        let undo = PlaceUndo {
+            free_rects: self.free_rects.clone(),
+            scrap_len: self.scrap.len(),
+        };
+        self.place_with_clearance(scored, piece, clearance);
+        undo
+    }
+
+    /// Number of cuts needed to free the pieces placed so far, see
+    /// [`CutNode::cut_count`](cut_tree::CutNode::cut_count).
+    pub fn cut_count(&self) -> Option<usize> {
+        cut_tree::cut_count(self.stock, &self.placements, self.kerf)
+    }
+
+    /// Take back the last placement, restoring the bin exactly as it was
+    /// before the [`place_undoable`](Self::place_undoable) that returned
+    /// `undo`. Placements made since must be undone first.
+    pub fn unplace(&mut self, undo: PlaceUndo) {
+        self.placements.pop();
+        self.free_rects = undo.free_rects;
+        self.scrap.truncate(undo.scrap_len);
+    }
+
+    /// Replace `free` by what is left around `placed` at its corner, one
+    /// kerf away from it (up to the next grid line with a grid pitch).
+    /// Sides where nothing is left past the kerf yield no free rect.
     fn split(&mut self, free: FreeRect, placed: Rect) {
-        let right_l = free.rect.length.saturating_sub(placed.length + self.kerf);
-        let bottom_w = free.rect.width.saturating_sub(placed.width + self.kerf);
+        let (kerf_x, kerf_y) = match self.grid_pitch {
+            None => (self.kerf, self.kerf),
+            Some((px, py)) => {
+                let (end_x, end_y) = (free.x + placed.length, free.y + placed.width);
+                (
+                    align_up(end_x + self.kerf, px) - end_x,
+                    align_up(end_y + self.kerf, py) - end_y,
+                )
+            }
+        };
+        let right_l = free
+            .rect
+            .length
+            .saturating_sub(placed.length.saturating_add(kerf_x));
+        let bottom_w = free
+            .rect
+            .width
+            .saturating_sub(placed.width.saturating_add(kerf_y));
 
         // Use shorter leftover axis split
         if right_l > 0 && bottom_w > 0 {
             // Decide split direction based on cut_direction preference
             let split_horizontally = match self.cut_direction {
-                CutDirection::Auto => {
+                CutDirection::Auto | CutDirection::Mixed => {
                     free.rect.length - placed.length < free.rect.width - placed.width
                 }
                 CutDirection::AlongLength => true,
@@ -160,54 +645,65 @@ impl GuillotineBin {
                 // Split horizontally: right rect is narrow, bottom rect spans full length
                 // Right remainder
                 self.free_rects.push(FreeRect {
-                    x: free.x + placed.length + self.kerf,
+                    x: free.x + placed.length + kerf_x,
                     y: free.y,
                     rect: Rect::new(right_l, placed.width),
                 });
                 // Bottom remainder
                 self.free_rects.push(FreeRect {
                     x: free.x,
-                    y: free.y + placed.width + self.kerf,
+                    y: free.y + placed.width + kerf_y,
                     rect: Rect::new(free.rect.length, bottom_w),
                 });
             } else {
                 // Split vertically: bottom rect is narrow, right rect spans full width
                 // Right remainder
                 self.free_rects.push(FreeRect {
-                    x: free.x + placed.length + self.kerf,
+                    x: free.x + placed.length + kerf_x,
                     y: free.y,
                     rect: Rect::new(right_l, free.rect.width),
                 });
                 // Bottom remainder
                 self.free_rects.push(FreeRect {
                     x: free.x,
-                    y: free.y + placed.width + self.kerf,
+                    y: free.y + placed.width + kerf_y,
                     rect: Rect::new(placed.length, bottom_w),
                 });
             }
         } else if right_l > 0 {
             self.free_rects.push(FreeRect {
-                x: free.x + placed.length + self.kerf,
+                x: free.x + placed.length + kerf_x,
                 y: free.y,
                 rect: Rect::new(right_l, free.rect.width),
             });
         } else if bottom_w > 0 {
             self.free_rects.push(FreeRect {
                 x: free.x,
-                y: free.y + placed.width + self.kerf,
+                y: free.y + placed.width + kerf_y,
                 rect: Rect::new(free.rect.length, bottom_w),
             });
         }
     }
 
+    /// Merge free rects that are adjacent, or only a kerf apart when the
+    /// cut between them can wait until after the merged rect is cut out
+    /// (see [`spans_kerf_safely`](Self::spans_kerf_safely)).
     fn merge_free_rects(&mut self) {
         let mut merged = true;
         while merged {
             merged = false;
             'outer: for i in 0..self.free_rects.len() {
                 for j in (i + 1)..self.free_rects.len() {
-                    if let Some(m) =
-                        Self::try_merge(self.free_rects[i], self.free_rects[j], self.cut_direction)
+                    let (a, b) = (self.free_rects[i], self.free_rects[j]);
+                    let m = Self::try_merge(a, b, self.cut_direction, 0).or_else(|| {
+                        if self.kerf == 0 {
+                            return None;
+                        }
+                        Self::try_merge(a, b, self.cut_direction, self.kerf)
+                            .filter(|&m| self.spans_kerf_safely(i, j, m))
+                    });
+                    if let Some(m) = m
+                        && !self.crosses_fixed_cut(m)
                     {
                         self.free_rects[i] = m;
                         self.free_rects.swap_remove(j);
@@ -219,22 +715,62 @@ impl GuillotineBin {
         }
     }
 
-    fn try_merge(a: FreeRect, b: FreeRect, cut_direction: CutDirection) -> Option<FreeRect> {
+    /// Whether `f` lies across the kerf of a fixed cut.
+    fn crosses_fixed_cut(&self, f: FreeRect) -> bool {
+        self.fixed_cuts.iter().any(|&(axis, position)| match axis {
+            Axis::X => f.x < position + self.kerf && f.x + f.rect.length > position,
+            Axis::Y => f.y < position + self.kerf && f.y + f.rect.width > position,
+        })
+    }
+
+    /// Whether free rects `i` and `j` can be replaced by `merged`, which
+    /// also covers the kerf between them: the layout must still come apart
+    /// with edge-to-edge cuts if a piece fills `merged` and every other free
+    /// rect. Otherwise the kerf is a real cut that a piece would straddle.
+    fn spans_kerf_safely(&self, i: usize, j: usize, merged: FreeRect) -> bool {
+        let solid = |f: &FreeRect| Placement::new(f.rect, f.x, f.y);
+        let solids: Vec<Placement> = self
+            .reserved
+            .iter()
+            .chain(&self.blocked)
+            .chain(
+                self.free_rects
+                    .iter()
+                    .enumerate()
+                    .filter(|&(k, _)| k != i && k != j)
+                    .map(|(_, f)| f),
+            )
+            .chain([&merged])
+            .map(solid)
+            .collect();
+        let layout: Vec<&Placement> = self.placements.iter().chain(&solids).collect();
+        cut_tree::is_guillotine(self.stock, &layout, self.kerf)
+    }
+
+    /// Merge `a` and `b` if they line up and are exactly `gap` apart; the
+    /// result covers the gap.
+    fn try_merge(
+        a: FreeRect,
+        b: FreeRect,
+        cut_direction: CutDirection,
+        gap: u32,
+    ) -> Option<FreeRect> {
         // Merge horizontally: same y, same width, adjacent x
         // Disabled for AlongWidth to preserve column boundaries
         if cut_direction != CutDirection::AlongWidth && a.y == b.y && a.rect.width == b.rect.width {
-            if a.x + a.rect.length == b.x {
+            let length = a.rect.length + gap + b.rect.length;
+            if a.x + a.rect.length + gap == b.x {
                 return Some(FreeRect {
                     x: a.x,
                     y: a.y,
-                    rect: Rect::new(a.rect.length + b.rect.length, a.rect.width),
+                    rect: Rect::new(length, a.rect.width),
                 });
             }
-            if b.x + b.rect.length == a.x {
+            if b.x + b.rect.length + gap == a.x {
                 return Some(FreeRect {
                     x: b.x,
                     y: b.y,
-                    rect: Rect::new(a.rect.length + b.rect.length, a.rect.width),
+                    rect: Rect::new(length, a.rect.width),
                 });
             }
         }
@@ -244,18 +780,19 @@ impl GuillotineBin {
             && a.x == b.x
             && a.rect.length == b.rect.length
         {
-            if a.y + a.rect.width == b.y {
+            let width = a.rect.width + gap + b.rect.width;
+            if a.y + a.rect.width + gap == b.y {
                 return Some(FreeRect {
                     x: a.x,
                     y: a.y,
-                    rect: Rect::new(a.rect.length, a.rect.width + b.rect.width),
+                    rect: Rect::new(a.rect.length, width),
                 });
             }
-            if b.y + b.rect.width == a.y {
+            if b.y + b.rect.width + gap == a.y {
                 return Some(FreeRect {
                     x: b.x,
                     y: b.y,
-                    rect: Rect::new(a.rect.length, a.rect.width + b.rect.width),
+                    rect: Rect::new(a.rect.length, width),
                 });
             }
         }
@@ -263,6 +800,18 @@ impl GuillotineBin {
     }
 }
 
+/// Clearance of a piece turned 90° clockwise: the left gap ends up on top,
+/// the top gap on the right, and so on.
+fn rotate_clearance(clearance: [u32; 4]) -> [u32; 4] {
+    let [left, right, top, bottom] = clearance;
+    [bottom, top, left, right]
+}
+
+/// `value` rounded up to a multiple of `pitch`.
+fn align_up(value: u32, pitch: u32) -> u32 {
+    value.div_ceil(pitch) * pitch
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -352,6 +901,199 @@ mod tests {
         assert!(bin.free_rects.is_empty());
     }
 
+    #[test]
+    fn test_first_cut() {
+        let mut bin = GuillotineBin::new(Rect::new(100, 50), 5, CutDirection::Auto);
+        bin.set_first_cut(Axis::Y, 20);
+        assert_eq!(
+            bin.free_rects,
+            [
+                FreeRect {
+                    x: 0,
+                    y: 0,
+                    rect: Rect::new(100, 20),
+                },
+                FreeRect {
+                    x: 0,
+                    y: 25,
+                    rect: Rect::new(100, 25),
+                },
+            ]
+        );
+        // Too tall for either side, though it fits the sheet
+        let piece = Rect::new(60, 40);
+        assert!(
+            bin.find_best(
+                piece,
+                RotationConstraint::NoRotate,
+                ScoreStrategy::BestAreaFit
+            )
+            .is_none()
+        );
+        // Merging the free space back never bridges the cut
+        let piece = Rect::new(100, 20);
+        let scored = bin
+            .find_best(
+                piece,
+                RotationConstraint::NoRotate,
+                ScoreStrategy::BestAreaFit,
+            )
+            .unwrap();
+        bin.place(scored, piece);
+        assert!(bin.free_rects.iter().all(|f| f.y >= 25));
+    }
+
+    #[test]
+    fn test_unplace() {
+        let mut bin = GuillotineBin::new(Rect::new(100, 80), 3, CutDirection::Auto);
+        bin.set_min_useful(15);
+        let first = Rect::new(60, 50);
+        let scored = bin
+            .find_best(
+                first,
+                RotationConstraint::NoRotate,
+                ScoreStrategy::BestAreaFit,
+            )
+            .unwrap();
+        bin.place(scored, first);
+        let before = bin.clone();
+
+        // Leaves a 12 mm sliver, dropped as scrap
+        let piece = Rect::new(25, 50);
+        let scored = bin
+            .find_best(
+                piece,
+                RotationConstraint::NoRotate,
+                ScoreStrategy::BestAreaFit,
+            )
+            .unwrap();
+        let undo = bin.place_undoable(scored, piece, [0; 4]);
+        assert_eq!(bin.placements.len(), 2);
+        assert!(bin.scrap.len() > before.scrap.len());
+
+        bin.unplace(undo);
+        assert_eq!(bin.free_rects, before.free_rects);
+        let spots = |b: &GuillotineBin| -> Vec<(u32, u32, Rect)> {
+            b.placements.iter().map(|p| (p.x, p.y, p.rect)).collect()
+        };
+        assert_eq!(spots(&bin), spots(&before));
+        assert_eq!(bin.scrap, before.scrap);
+    }
+
+    #[test]
+    fn test_strip_boundaries() {
+        let mut bin = GuillotineBin::new(Rect::new(100, 40), 0, CutDirection::Auto);
+        bin.set_strip_boundaries(Axis::Y, &[20]);
+        assert_eq!(bin.free_rects.len(), 2);
+        // One piece per strip leaves two stacked 40x20 rects on the right
+        let piece = Rect::new(60, 20);
+        for _ in 0..2 {
+            let scored = bin
+                .find_best(
+                    piece,
+                    RotationConstraint::NoRotate,
+                    ScoreStrategy::BestAreaFit,
+                )
+                .unwrap();
+            bin.place(scored, piece);
+        }
+        let mut free = bin.free_rects.clone();
+        free.sort_by_key(|f| f.y);
+        assert_eq!(
+            free,
+            [
+                FreeRect {
+                    x: 60,
+                    y: 0,
+                    rect: Rect::new(40, 20),
+                },
+                FreeRect {
+                    x: 60,
+                    y: 20,
+                    rect: Rect::new(40, 20),
+                },
+            ]
+        );
+        // They line up, but merging would bridge the strip boundary
+        assert!(GuillotineBin::try_merge(free[0], free[1], CutDirection::Auto, 0).is_some());
+    }
+
+    #[test]
+    fn test_grid_pitch() {
+        let mut bin = GuillotineBin::new(Rect::new(200, 100), 3, CutDirection::AlongLength);
+        bin.set_grid_pitch((20, 25));
+        // Not a multiple of the pitch in either orientation
+        let odd = Rect::new(30, 25);
+        assert!(
+            bin.find_best(odd, RotationConstraint::Free, ScoreStrategy::BestAreaFit)
+                .is_none()
+        );
+        // The next free rects start on the grid line past the kerf
+        let piece = Rect::new(40, 50);
+        let scored = bin
+            .find_best(
+                piece,
+                RotationConstraint::NoRotate,
+                ScoreStrategy::BestAreaFit,
+            )
+            .unwrap();
+        bin.place(scored, piece);
+        let mut corners: Vec<(u32, u32)> = bin.free_rects.iter().map(|f| (f.x, f.y)).collect();
+        corners.sort();
+        assert_eq!(corners, [(0, 75), (60, 0)]);
+    }
+
+    #[test]
+    fn test_full_sheet_with_kerf() {
+        let stock = Rect::new(100, 100);
+        let mut bin = GuillotineBin::new(stock, 5, CutDirection::Auto);
+        let scored = bin
+            .find_best(
+                stock,
+                RotationConstraint::NoRotate,
+                ScoreStrategy::BestAreaFit,
+            )
+            .unwrap();
+        let placed = bin.place(scored, stock);
+        assert_eq!((placed.x, placed.y, placed.rect), (0, 0, stock));
+        assert_eq!(bin.placements.len(), 1);
+        assert!(bin.free_rects.is_empty());
+
+        // Up to a kerf short of the sheet, the rest goes to the cut
+        for length in [95, 96] {
+            let mut bin = GuillotineBin::new(stock, 5, CutDirection::Auto);
+            let piece = Rect::new(length, 100);
+            let scored = bin
+                .find_best(
+                    piece,
+                    RotationConstraint::NoRotate,
+                    ScoreStrategy::BestAreaFit,
+                )
+                .unwrap();
+            bin.place(scored, piece);
+            assert!(bin.free_rects.is_empty());
+        }
+        // Beyond that, the remainder past the kerf stays free
+        let mut bin = GuillotineBin::new(stock, 5, CutDirection::Auto);
+        let piece = Rect::new(94, 100);
+        let scored = bin
+            .find_best(
+                piece,
+                RotationConstraint::NoRotate,
+                ScoreStrategy::BestAreaFit,
+            )
+            .unwrap();
+        bin.place(scored, piece);
+        assert_eq!(
+            bin.free_rects,
+            [FreeRect {
+                x: 99,
+                y: 0,
+                rect: Rect::new(1, 100),
+            }]
+        );
+    }
+
     /// Place a 40x30 piece in a 100x100 stock. The leftover is asymmetric (60 vs 70),
     /// so AlongLength and AlongWidth must produce different free rects.
     ///
@@ -466,6 +1208,177 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_restrict_to_window() {
+        let mut bin = GuillotineBin::new(Rect::new(100, 100), 0, CutDirection::Auto);
+        bin.restrict_to(20, 10, Rect::new(200, 50));
+        // Window is clipped to the stock
+        assert_eq!(bin.free_rects.len(), 1);
+        assert_eq!((bin.free_rects[0].x, bin.free_rects[0].y), (20, 10));
+        assert_eq!(bin.free_rects[0].rect, Rect::new(80, 50));
+
+        let piece = Rect::new(60, 60);
+        assert!(
+            bin.find_best(piece, RotationConstraint::Free, ScoreStrategy::BestAreaFit)
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_min_useful_prunes_slivers() {
+        let stock = Rect::new(100, 100);
+        let pieces = [Rect::new(98, 30), Rect::new(60, 69), Rect::new(30, 30)];
+
+        let mut plain = GuillotineBin::new(stock, 0, CutDirection::AlongLength);
+        let mut pruned = GuillotineBin::new(stock, 0, CutDirection::AlongLength);
+        pruned.set_min_useful(30);
+
+        for piece in pieces {
+            let a = plain
+                .find_best(piece, RotationConstraint::Free, ScoreStrategy::BestAreaFit)
+                .unwrap();
+            let b = pruned
+                .find_best(piece, RotationConstraint::Free, ScoreStrategy::BestAreaFit)
+                .unwrap();
+            let pa = plain.place(a, piece);
+            let pb = pruned.place(b, piece);
+            // Same packing with or without pruning
+            assert_eq!((pa.x, pa.y, pa.rect), (pb.x, pb.y, pb.rect));
+        }
+
+        // The 2-wide and 1-tall slivers remain in the plain bin only
+        assert!(
+            plain
+                .free_rects
+                .iter()
+                .any(|f| f.rect.length.min(f.rect.width) < 30)
+        );
+        assert!(
+            pruned
+                .free_rects
+                .iter()
+                .all(|f| f.rect.length.min(f.rect.width) >= 30)
+        );
+    }
+
+    #[test]
+    fn test_clearance_reserves_gap_on_one_side() {
+        let mut bin = GuillotineBin::new(Rect::new(100, 50), 3, CutDirection::AlongWidth);
+        let piece = Rect::new(20, 50);
+        let mut place = |clearance: [u32; 4]| {
+            let scored = bin
+                .find_best(
+                    piece.with_clearance(clearance),
+                    RotationConstraint::NoRotate,
+                    ScoreStrategy::BestAreaFit,
+                )
+                .unwrap();
+            bin.place_with_clearance(scored, piece, clearance)
+        };
+        let left = place([0; 4]);
+        let middle = place([0, 10, 0, 0]);
+        let right = place([0; 4]);
+
+        // Reported size is the bare piece
+        assert_eq!(middle.rect, piece);
+        let gap_left = middle.x - (left.x + left.rect.length);
+        let gap_right = right.x - (middle.x + middle.rect.length);
+        assert_eq!(gap_left, 3);
+        assert_eq!(gap_right, 13);
+    }
+
+    #[test]
+    fn test_rotate_clearance() {
+        // Turning clockwise moves the left gap to the top
+        assert_eq!(rotate_clearance([1, 2, 3, 4]), [4, 3, 1, 2]);
+        assert_eq!(
+            Rect::new(20, 10).with_clearance([1, 2, 3, 4]).rotated(),
+            Rect::new(10, 20).with_clearance(rotate_clearance([1, 2, 3, 4]))
+        );
+    }
+
+    #[test]
+    fn test_prefer_origin_breaks_ties() {
+        let mut bin = GuillotineBin::new(Rect::new(100, 100), 0, CutDirection::Auto);
+        bin.free_rects = vec![
+            FreeRect {
+                x: 50,
+                y: 50,
+                rect: Rect::new(50, 50),
+            },
+            FreeRect {
+                x: 0,
+                y: 50,
+                rect: Rect::new(50, 50),
+            },
+        ];
+        let piece = Rect::new(40, 40);
+        let best = |bin: &GuillotineBin| {
+            bin.find_best(
+                piece,
+                RotationConstraint::NoRotate,
+                ScoreStrategy::BestAreaFit,
+            )
+            .unwrap()
+            .free_idx
+        };
+        assert_eq!(best(&bin), 0);
+        bin.set_prefer_origin(true);
+        assert_eq!(best(&bin), 1);
+    }
+
+    #[test]
+    fn test_long_side_breaks_orientation_ties() {
+        let mut bin = GuillotineBin::new(Rect::new(100, 100), 0, CutDirection::Auto);
+        // 48x50 and 50x48 both leave a 50 short side
+        let piece = Rect::new(48, 50);
+        let rotated = |bin: &GuillotineBin| {
+            bin.find_best(piece, RotationConstraint::Free, ScoreStrategy::BestAreaFit)
+                .unwrap()
+                .rotated
+        };
+        assert!(!rotated(&bin));
+        bin.set_long_side_along(Some(Axis::X));
+        assert!(rotated(&bin));
+        bin.set_long_side_along(Some(Axis::Y));
+        assert!(!rotated(&bin));
+    }
+
+    #[test]
+    fn test_sheltered_placement() {
+        let mut bin = GuillotineBin::new(Rect::new(1000, 1000), 0, CutDirection::Auto);
+        // An exact slot next to a large open area, and a slightly larger one
+        // walled in by pieces with only a small drop below it
+        bin.free_rects = vec![
+            FreeRect {
+                x: 500,
+                y: 500,
+                rect: Rect::new(50, 300),
+            },
+            FreeRect {
+                x: 550,
+                y: 500,
+                rect: Rect::new(450, 500),
+            },
+            FreeRect {
+                x: 0,
+                y: 0,
+                rect: Rect::new(50, 320),
+            },
+        ];
+        let piece = Rect::new(50, 300);
+        let rotation = RotationConstraint::NoRotate;
+        let strategy = ScoreStrategy::BestAreaFit;
+        let open = bin.find_best(piece, rotation, strategy).unwrap();
+        assert_eq!(open.free_idx, 0);
+        let sheltered = bin
+            .find_sheltered(piece, rotation, strategy, [0, 0])
+            .unwrap();
+        assert_eq!(sheltered.free_idx, 2);
+        // Only the 50 mm bottom edge borders the drop
+        assert_eq!(sheltered.score.0, 50);
+    }
+
     #[test]
     fn test_force_rotate() {
         let bin = GuillotineBin::new(Rect::new(100, 50), 0, CutDirection::Auto);
@@ -490,4 +1403,169 @@ mod tests {
             .unwrap();
         assert!(scored.rotated);
     }
+
+    #[test]
+    fn test_penalty_shifts_orientation() {
+        let bin = GuillotineBin::new(Rect::new(100, 100), 0, CutDirection::Auto);
+        // Unrotated 60x40 leaves a 40 short side, rotated 40x60 the same:
+        // the tie goes to the unpenalized orientation
+        let piece = Rect::new(60, 40);
+        let strategy = ScoreStrategy::BestAreaFit;
+        let free = RotationConstraint::Free;
+        let scored = bin
+            .find_best_penalized(piece, free, strategy, [10, 0])
+            .unwrap();
+        assert!(scored.rotated);
+        assert_eq!(scored.score.0, 100 * 100 - 60 * 40);
+        let scored = bin
+            .find_best_penalized(piece, free, strategy, [0, 10])
+            .unwrap();
+        assert!(!scored.rotated);
+        assert_eq!(
+            scored.score,
+            bin.find_best(piece, free, strategy).unwrap().score
+        );
+    }
+
+    #[test]
+    fn test_reserve_keeps_space_free() {
+        let mut bin = GuillotineBin::new(Rect::new(100, 50), 2, CutDirection::Auto);
+        assert!(!bin.reserve(Rect::new(120, 10)));
+        assert!(bin.free_rects.len() == 1 && bin.reserved.is_none());
+
+        assert!(bin.reserve(Rect::new(40, 50)));
+        let reserved = bin.reserved.unwrap();
+        assert_eq!(
+            (reserved.x, reserved.y, reserved.rect),
+            (0, 0, Rect::new(40, 50))
+        );
+        assert!(bin.placements.is_empty());
+        assert_eq!(bin.used_area(), 0);
+
+        // Only the 58x50 beyond the reservation and its kerf is left
+        let piece = Rect::new(60, 50);
+        let strategy = ScoreStrategy::BestAreaFit;
+        assert!(
+            bin.find_best(piece, RotationConstraint::Free, strategy)
+                .is_none()
+        );
+        let scored = bin
+            .find_best(Rect::new(58, 50), RotationConstraint::Free, strategy)
+            .unwrap();
+        assert_eq!(bin.free_rects[scored.free_idx].x, 42);
+    }
+
+    #[test]
+    fn test_block_out_corners() {
+        let mut bin = GuillotineBin::new(Rect::new(100, 50), 0, CutDirection::Auto);
+        for (x, y) in [(0, 0), (90, 0), (0, 40), (90, 40)] {
+            bin.block_out(x, y, Rect::new(10, 10));
+        }
+        let mut free: Vec<_> = bin.free_rects.iter().map(|f| (f.x, f.y, f.rect)).collect();
+        free.sort_by_key(|&(x, y, _)| (y, x));
+        assert_eq!(
+            free,
+            [
+                (10, 0, Rect::new(80, 10)),
+                (0, 10, Rect::new(100, 30)),
+                (10, 40, Rect::new(80, 10)),
+            ]
+        );
+        let area: u64 = bin.free_rects.iter().map(|f| f.rect.area()).sum();
+        assert_eq!(area, 100 * 50 - 4 * 10 * 10);
+    }
+
+    #[test]
+    fn test_merge_across_kerf() {
+        // A 30x60 piece splits the sheet into a full-width column on the right
+        // and a 30x35 strip below it. Filling the column's top leaves a 65x35
+        // strip level with the first one, a kerf apart: the rip below both
+        // pieces can be cut first, so the strips become one 100x35 rect.
+        let mut bin = GuillotineBin::new(Rect::new(100, 100), 5, CutDirection::Auto);
+        for piece in [Rect::new(30, 60), Rect::new(65, 60)] {
+            let scored = bin
+                .find_best(
+                    piece,
+                    RotationConstraint::NoRotate,
+                    ScoreStrategy::BestAreaFit,
+                )
+                .unwrap();
+            bin.place(scored, piece);
+        }
+        assert_eq!(
+            bin.free_rects,
+            [FreeRect {
+                x: 0,
+                y: 65,
+                rect: Rect::new(100, 35),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_no_merge_across_real_cut() {
+        // The strips are a kerf apart, but the rips above them are blocked
+        // by the tall pieces on either side: the cut between the strips has
+        // to run the full height of the sheet first.
+        let mut bin = GuillotineBin::new(Rect::new(100, 100), 5, CutDirection::Auto);
+        let piece = |l, w, x, y| Placement::new(Rect::new(l, w), x, y);
+        bin.placements = vec![
+            piece(45, 25, 0, 0),
+            piece(15, 70, 0, 30),
+            piece(50, 60, 50, 0),
+        ];
+        let strips = [
+            FreeRect {
+                x: 20,
+                y: 65,
+                rect: Rect::new(25, 35),
+            },
+            FreeRect {
+                x: 50,
+                y: 65,
+                rect: Rect::new(50, 35),
+            },
+        ];
+        bin.free_rects = strips.to_vec();
+        bin.merge_free_rects();
+        assert_eq!(bin.free_rects, strips);
+    }
+
+    #[test]
+    fn test_custom_scorer() {
+        /// Prefers the free rect whose leftover bounding box has the
+        /// smallest perimeter.
+        struct LeftoverPerimeter;
+        impl PlacementScorer for LeftoverPerimeter {
+            fn score(&self, piece: Rect, free: Rect) -> (u64, u64) {
+                let perimeter = 2 * (free.length - piece.length + free.width - piece.width);
+                (perimeter as u64, 0)
+            }
+        }
+
+        let mut bin = GuillotineBin::new(Rect::new(200, 100), 0, CutDirection::Auto);
+        bin.free_rects = vec![
+            FreeRect {
+                x: 0,
+                y: 0,
+                rect: Rect::new(100, 12),
+            },
+            FreeRect {
+                x: 0,
+                y: 50,
+                rect: Rect::new(40, 40),
+            },
+        ];
+        let piece = Rect::new(10, 10);
+        let rotation = RotationConstraint::NoRotate;
+        let by_area = bin
+            .find_best(piece, rotation, ScoreStrategy::BestAreaFit)
+            .unwrap();
+        assert_eq!(by_area.free_idx, 0);
+        let by_perimeter = bin
+            .find_best_scored(piece, rotation, &LeftoverPerimeter, [0, 0])
+            .unwrap();
+        assert_eq!(by_perimeter.free_idx, 1);
+        assert_eq!(by_perimeter.score, (120, 0, 0));
+    }
 }
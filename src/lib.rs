@@ -1,4 +1,13 @@
+pub mod api;
+pub mod cut_tree;
 pub mod guillotine;
+pub mod parse;
 pub mod render;
+pub mod report;
 pub mod solver;
 pub mod types;
+pub mod units;
+
+/// Why a solve was refused: the library's error type, see
+/// [`Solver::try_solve`](solver::Solver::try_solve).
+pub use solver::SolveError as Error;
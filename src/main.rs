@@ -1,9 +1,16 @@
 use clap::Parser;
-use cut_optimizer::render;
-use cut_optimizer::solver::Solver;
+use cut_optimizer::api::{OptimizeRequest, RejectedCut, rejected_report};
+use cut_optimizer::parse::{ParseError, parse_cut, parse_dimensions};
+use cut_optimizer::render::LabelFormat;
+use cut_optimizer::report::{ReportOptions, sheet_label};
+use cut_optimizer::solver::{Checkpoint, Infeasibility, Solver, check_kerf};
 use cut_optimizer::types::{
-    CutDirection, Demand, PieceGrain, Rect, RotationConstraint, StockGrain,
+    Anchor, BomRow, CutDirection, Demand, DirectionTieBreak, Rect, RotationConstraint, Solution,
+    StockGrain,
 };
+use cut_optimizer::units::{self, Units};
+use std::io::Write;
+use std::path::{Path, PathBuf};
 
 #[derive(Parser)]
 #[command(
@@ -12,8 +19,13 @@ use cut_optimizer::types::{
 )]
 struct Cli {
     /// Stock sheet dimensions (LxW, e.g. 2400x1200)
-    #[arg(long)]
-    stock: String,
+    #[arg(long, required_unless_present = "stdin")]
+    stock: Option<String>,
+
+    /// Unit of --stock and --cuts: mm, or inch (decimals allowed, rounded to 1/16 in).
+    /// The solution is reported in mm either way
+    #[arg(long, default_value = "mm", value_parser = parse_units)]
+    units: Units,
 
     /// Cut pieces as LxW:qty (e.g. 800x600:3 400x300:5)
     #[arg(long = "cuts", num_args = 1..)]
@@ -27,13 +39,134 @@ struct Cli {
     #[arg(long)]
     no_rotate: bool,
 
-    /// Cut direction: auto, along-length, or along-width
+    /// Drop cuts with quantity 0 (listed as rejected) instead of failing
+    #[arg(long)]
+    skip_zero_qty: bool,
+
+    /// Cut direction: auto, along-length, along-width, or mixed (chosen per sheet)
     #[arg(long, default_value = "auto", value_parser = parse_cut_direction)]
     cut_direction: CutDirection,
 
+    /// Break ties between layouts with as many sheets: prefer-fewer-cuts, prefer-length,
+    /// prefer-width, or prefer-lower-waste
+    #[arg(long, value_parser = parse_direction_tie_break)]
+    direction_tie_break: Option<DirectionTieBreak>,
+
     /// Show ASCII layout of each sheet
     #[arg(long)]
     layout: bool,
+
+    /// Pull pieces toward a sheet corner: bottom-left, bottom-right, top-left, or top-right
+    #[arg(long, value_parser = parse_anchor)]
+    anchor: Option<Anchor>,
+
+    /// Fill unused space with a hatch pattern in the ASCII layout
+    #[arg(long)]
+    hatch_waste: bool,
+
+    /// Piece labels in the ASCII layout, with {label}, {length}, {width} and {index} placeholders
+    #[arg(long, value_parser = parse_label_format)]
+    label_format: Option<String>,
+
+    /// Also try packing pieces thinner than N mm first, as full-length strips
+    #[arg(long)]
+    strip_threshold: Option<u32>,
+
+    /// Warn about pieces ending less than N mm from a sheet edge
+    #[arg(long, default_value_t = 0)]
+    tight_fit_margin: u32,
+
+    /// Lay out sheets holding the same pieces exactly alike, for jigs
+    #[arg(long)]
+    template_sheet: bool,
+
+    /// Name sheets PREFIX-Sheet-N instead of Sheet N (e.g. a job number)
+    #[arg(long)]
+    sheet_prefix: Option<String>,
+
+    /// Start sheet numbering after N, to continue from a previous job (default: 0)
+    #[arg(long)]
+    sheet_offset: Option<usize>,
+
+    /// Print identical sheets once, with their count
+    #[arg(long)]
+    group_sheets: bool,
+
+    /// Read the job from stdin as JSON, in the server's /optimize request format,
+    /// instead of from --stock, --cuts and the other job options
+    #[arg(long, conflicts_with_all = ["stock", "cuts", "units", "kerf", "no_rotate", "cut_direction"])]
+    stdin: bool,
+
+    /// Only parse and validate the job, print the piece count and a sheet estimate, and exit
+    #[arg(long)]
+    dry_run: bool,
+
+    /// Only list the cuts that can't be placed, and why (too large, grain, sheet caps), and exit
+    #[arg(long)]
+    diagnose: bool,
+
+    /// Count offcuts per area bucket: increasing bounds in mm², comma-separated (e.g. 10000,100000)
+    #[arg(long, value_delimiter = ',')]
+    histogram: Option<Vec<u64>>,
+
+    /// After the report, list the placed pieces per size and label, with their total area
+    #[arg(long)]
+    bom: bool,
+
+    /// Output format: text (report), ndjson (one JSON object per sheet, then a summary),
+    /// points (corner coordinates of the stock and each piece, per sheet), instructions
+    /// (numbered cut steps), or tsv (a tab-separated table per sheet, then a summary)
+    #[arg(long, default_value = "text", value_parser = parse_format)]
+    format: OutputFormat,
+
+    /// Re-check the solution for overlaps and out-of-bounds pieces; exit non-zero on failure
+    #[arg(long)]
+    verify: bool,
+
+    /// Save the best solution so far to FILE each time the search improves it,
+    /// and the final one at the end, to resume an interrupted solve with --resume
+    #[arg(long, value_name = "FILE")]
+    checkpoint: Option<PathBuf>,
+
+    /// Resume from a --checkpoint FILE of the same job: the result is never worse than it
+    #[arg(long, value_name = "FILE")]
+    resume: Option<PathBuf>,
+}
+
+#[derive(Clone, Copy)]
+enum OutputFormat {
+    Text,
+    Ndjson,
+    Points,
+    Instructions,
+    Tsv,
+}
+
+fn parse_format(s: &str) -> Result<OutputFormat, String> {
+    match s {
+        "text" => Ok(OutputFormat::Text),
+        "ndjson" => Ok(OutputFormat::Ndjson),
+        "points" => Ok(OutputFormat::Points),
+        "instructions" => Ok(OutputFormat::Instructions),
+        "tsv" => Ok(OutputFormat::Tsv),
+        _ => Err(format!(
+            "invalid format '{}', expected: text, ndjson, points, instructions, or tsv",
+            s
+        )),
+    }
+}
+
+fn parse_units(s: &str) -> Result<Units, String> {
+    match s {
+        "mm" => Ok(Units::Mm),
+        "inch" => Ok(Units::Inch),
+        _ => Err(format!("invalid units '{}', expected: mm or inch", s)),
+    }
+}
+
+fn parse_label_format(s: &str) -> Result<String, String> {
+    LabelFormat::parse(s)?;
+    Ok(s.to_string())
 }
 
 fn parse_cut_direction(s: &str) -> Result<CutDirection, String> {
@@ -41,109 +174,548 @@ fn parse_cut_direction(s: &str) -> Result<CutDirection, String> {
         "auto" => Ok(CutDirection::Auto),
         "along-length" => Ok(CutDirection::AlongLength),
         "along-width" => Ok(CutDirection::AlongWidth),
+        "mixed" => Ok(CutDirection::Mixed),
         _ => Err(format!(
-            "invalid cut direction '{}', expected: auto, along-length, or along-width",
+            "invalid cut direction '{}', expected: auto, along-length, along-width, or mixed",
             s
         )),
     }
 }
 
-fn parse_dimensions(s: &str) -> Result<Rect, String> {
-    let parts: Vec<&str> = s.split('x').collect();
-    if parts.len() != 2 {
-        return Err(format!("invalid dimensions '{}', expected LxW", s));
+fn parse_direction_tie_break(s: &str) -> Result<DirectionTieBreak, String> {
+    match s {
+        "prefer-fewer-cuts" => Ok(DirectionTieBreak::PreferFewerCuts),
+        "prefer-length" => Ok(DirectionTieBreak::PreferLength),
+        "prefer-width" => Ok(DirectionTieBreak::PreferWidth),
+        "prefer-lower-waste" => Ok(DirectionTieBreak::PreferLowerWaste),
+        _ => Err(format!(
+            "invalid tie-break '{}', expected: prefer-fewer-cuts, prefer-length, prefer-width, or prefer-lower-waste",
+            s
+        )),
     }
-    let length = parts[0]
-        .parse::<u32>()
-        .map_err(|_| format!("invalid length in '{}'", s))?;
-    let width = parts[1]
-        .parse::<u32>()
-        .map_err(|_| format!("invalid width in '{}'", s))?;
-    if length == 0 || width == 0 {
-        return Err(format!("dimensions must be non-zero in '{}'", s));
+}
+
+fn parse_anchor(s: &str) -> Result<Anchor, String> {
+    match s {
+        "bottom-left" => Ok(Anchor::BottomLeft),
+        "bottom-right" => Ok(Anchor::BottomRight),
+        "top-left" => Ok(Anchor::TopLeft),
+        "top-right" => Ok(Anchor::TopRight),
+        _ => Err(format!(
+            "invalid anchor '{}', expected: bottom-left, bottom-right, top-left, or top-right",
+            s
+        )),
     }
-    Ok(Rect::new(length, width))
 }
 
-fn parse_cut(s: &str, allow_rotate: bool) -> Result<Demand, String> {
-    let parts: Vec<&str> = s.split(':').collect();
-    if parts.len() != 2 {
-        return Err(format!("invalid cut '{}', expected LxW:qty", s));
+/// The parsed stock and demands, converted to mm for the solver.
+fn job_in_mm(stock: Rect, demands: Vec<Demand>, units: Units) -> (Rect, Vec<Demand>) {
+    match units {
+        Units::Mm => (stock, demands),
+        Units::Inch => (units::rect_to_mm(stock), units::inch_to_mm(&demands)),
     }
-    let rect = parse_dimensions(parts[0])?;
-    let qty = parts[1]
-        .parse::<u32>()
-        .map_err(|_| format!("invalid quantity in '{}'", s))?;
-    if qty == 0 {
-        return Err(format!("quantity must be non-zero in '{}'", s));
+}
+
+/// The `--cuts` as demands in `units`. With `skip_zero_qty`, cuts of
+/// quantity 0 are left out and returned as rejected, sized in mm.
+fn parse_cuts(
+    cuts: &[String],
+    allow_rotate: bool,
+    units: Units,
+    skip_zero_qty: bool,
+) -> Result<(Vec<Demand>, Vec<RejectedCut>), ParseError> {
+    let mut demands = Vec::new();
+    let mut rejected = Vec::new();
+    for (index, c) in cuts.iter().enumerate() {
+        match parse_cut(c, allow_rotate, units) {
+            Ok(demand) => demands.push(demand),
+            Err(ParseError::ZeroQuantity(_)) if skip_zero_qty => {
+                let (dimensions, _) = c.split_once(':').unwrap_or_default();
+                let rect = parse_dimensions(dimensions, units)?;
+                rejected.push(RejectedCut {
+                    index,
+                    rect: match units {
+                        Units::Mm => rect,
+                        Units::Inch => units::rect_to_mm(rect),
+                    },
+                    qty: 0,
+                    label: None,
+                    reason: "quantity is zero".to_string(),
+                });
+            }
+            Err(e) => return Err(e),
+        }
     }
-    Ok(Demand {
-        rect,
-        qty,
-        allow_rotate,
-        grain: PieceGrain::Auto,
-    })
+    Ok((demands, rejected))
 }
 
-fn main() {
-    let cli = Cli::parse();
+/// A `--stdin` job: the validated request, its demands, and the cuts left
+/// out with `on_infeasible: "skip"` or `skip_zero_qty` (also turned on by
+/// `skip_zero_qty` here).
+fn read_json_job(
+    input: impl std::io::Read,
+    skip_zero_qty: bool,
+) -> Result<(OptimizeRequest, Vec<Demand>, Vec<RejectedCut>), String> {
+    let mut req: OptimizeRequest =
+        serde_json::from_reader(input).map_err(|e| format!("invalid JSON job: {}", e))?;
+    req.skip_zero_qty |= skip_zero_qty;
+    let errors: Vec<String> = req
+        .validate()
+        .into_iter()
+        .map(|e| format!("{}: {}", e.field, e.message))
+        .collect();
+    if !errors.is_empty() {
+        return Err(errors.join("; "));
+    }
+    let (demands, rejected) = req.take_demands();
+    Ok((req, demands, rejected))
+}
 
-    let stock = parse_dimensions(&cli.stock).unwrap_or_else(|e| {
-        eprintln!("Error: {}", e);
-        std::process::exit(1);
-    });
+fn read_checkpoint(path: &Path) -> Result<Checkpoint, String> {
+    let file = std::fs::File::open(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    serde_json::from_reader(std::io::BufReader::new(file))
+        .map_err(|e| format!("{}: invalid checkpoint: {}", path.display(), e))
+}
 
-    let demands: Vec<Demand> = cli
-        .cuts
-        .iter()
-        .map(|c| parse_cut(c, !cli.no_rotate))
-        .collect::<Result<Vec<_>, _>>()
-        .unwrap_or_else(|e| {
-            eprintln!("Error: {}", e);
-            std::process::exit(1);
-        });
+/// Write `checkpoint` to `path` through a temporary file, so an interrupted
+/// write never leaves a truncated checkpoint.
+fn write_checkpoint(path: &Path, checkpoint: &Checkpoint) -> std::io::Result<()> {
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    std::fs::write(&tmp, serde_json::to_vec(checkpoint)?)?;
+    std::fs::rename(&tmp, path)
+}
 
-    // Validate all pieces fit in stock (considering rotation and cut direction)
-    for d in &demands {
-        let rotation = RotationConstraint::from_grain(StockGrain::None, d.grain, d.allow_rotate)
-            .with_cut_direction(cli.cut_direction, d.rect);
+/// Check every piece fits the stock (considering rotation and cut direction).
+fn check_fits(stock: Rect, demands: &[Demand], cut_direction: CutDirection) -> Result<(), String> {
+    for d in demands {
+        let rotation = RotationConstraint::from_grain(StockGrain::None, d.grain, d.can_rotate())
+            .map_err(|e| format!("piece {}: {}", d.rect, e))?
+            .with_cut_direction(cut_direction, d.rect);
         let fits = match rotation {
             RotationConstraint::NoRotate => d.rect.fits_in(&stock),
             RotationConstraint::ForceRotate => d.rect.rotated().fits_in(&stock),
             RotationConstraint::Free => d.rect.fits_in(&stock) || d.rect.rotated().fits_in(&stock),
         };
         if !fits {
-            eprintln!("Error: piece {} does not fit in stock {}", d.rect, stock);
-            std::process::exit(1);
+            return Err(format!("piece {} does not fit in stock {}", d.rect, stock));
         }
     }
+    Ok(())
+}
+
+/// Offcut counts per `--histogram` bucket, one line each.
+fn histogram_lines(buckets: &[u64], counts: &[usize]) -> String {
+    let mut out = String::from("Offcut histogram:\n");
+    for (i, count) in counts.iter().enumerate() {
+        let bucket = match buckets.get(i) {
+            Some(bound) => format!("<= {} mm²", bound),
+            None => format!("> {} mm²", buckets.last().copied().unwrap_or(0)),
+        };
+        out.push_str(&format!("  {}: {}\n", bucket, count));
+    }
+    out
+}
 
-    let solver = Solver::new(
-        stock,
-        cli.kerf,
-        cli.cut_direction,
-        StockGrain::None,
-        demands,
-    );
-    let solution = solver.solve();
+/// `--bom` section: count and total area per size and label.
+fn bom_lines(rows: &[BomRow]) -> String {
+    let mut out = String::from("Bill of materials:\n");
+    for row in rows {
+        let label = match &row.label {
+            Some(label) => format!(" {}", label),
+            None => String::new(),
+        };
+        out.push_str(&format!(
+            "  {}x {}{}: {} mm²\n",
+            row.count, row.size, label, row.total_area
+        ));
+    }
+    out
+}
 
-    // Output results
+/// `--format instructions`: each sheet's cuts as numbered plain-English
+/// steps.
+fn instruction_lines(
+    solution: &Solution,
+    kerf: u32,
+    sheet_prefix: Option<&str>,
+    sheet_offset: usize,
+) -> String {
+    let mut out = String::new();
     for (i, sheet) in solution.sheets.iter().enumerate() {
-        println!("Sheet {}:", i + 1);
-        for p in &sheet.placements {
-            let rot = if p.rotated { " [rotated]" } else { "" };
-            println!("  {} @ ({}, {}){}", p.rect, p.x, p.y, rot);
+        out.push_str(&format!(
+            "{}:\n",
+            sheet_label(sheet_prefix, sheet_offset, i)
+        ));
+        match sheet.instructions(sheet.stock, kerf) {
+            Some(steps) if steps.is_empty() => out.push_str("  No cuts.\n"),
+            Some(steps) => {
+                for step in steps {
+                    out.push_str(&format!("  {}\n", step));
+                }
+            }
+            None => out.push_str("  Not cuttable edge to edge.\n"),
         }
-        if cli.layout {
-            print!("{}", render::render_sheet(stock, &sheet.placements));
+        out.push('\n');
+    }
+    out
+}
+
+/// What `--dry-run` prints: the expanded piece count and area, and the
+/// lower-bound sheet estimate.
+fn dry_run_plan(stock: Rect, demands: &[Demand], lower_bound: usize) -> String {
+    let pieces: u64 = demands.iter().map(|d| d.qty as u64).sum();
+    let area: u64 = demands.iter().map(|d| d.rect.area() * d.qty as u64).sum();
+    format!(
+        "Pieces: {} ({} mm² total)\nEstimate: at least {} sheet{} of {}\n",
+        pieces,
+        area,
+        lower_bound,
+        if lower_bound == 1 { "" } else { "s" },
+        stock
+    )
+}
+
+/// What `--diagnose` prints: one line per cut that can't be placed.
+fn diagnose_lines(found: &[Infeasibility]) -> String {
+    if found.is_empty() {
+        return "Every cut can be placed\n".to_string();
+    }
+    found.iter().map(|f| format!("{}\n", f)).collect()
+}
+
+fn main() {
+    let cli = Cli::parse();
+
+    let mut sheet_prefix = cli.sheet_prefix;
+    let mut sheet_offset = cli.sheet_offset;
+    let mut histogram = cli.histogram;
+    let (stock, kerf, demands, solver, rejected) = if cli.stdin {
+        let (req, demands, skipped) = read_json_job(std::io::stdin().lock(), cli.skip_zero_qty)
+            .unwrap_or_else(|e| {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            });
+        // Options given on the command line win over the job's
+        sheet_prefix = sheet_prefix.or(req.sheet_label_prefix.clone());
+        sheet_offset = sheet_offset.or(Some(req.sheet_offset));
+        histogram = histogram.or(req.offcut_buckets.clone());
+        (
+            req.stock(),
+            req.kerf,
+            demands.clone(),
+            req.solver(demands),
+            skipped,
+        )
+    } else {
+        let stock = parse_dimensions(cli.stock.as_deref().unwrap_or_default(), cli.units)
+            .unwrap_or_else(|e| {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            });
+
+        let (demands, rejected) =
+            parse_cuts(&cli.cuts, !cli.no_rotate, cli.units, cli.skip_zero_qty).unwrap_or_else(
+                |e| {
+                    eprintln!("Error: {}", e);
+                    std::process::exit(1);
+                },
+            );
+        let (stock, demands) = job_in_mm(stock, demands, cli.units);
+
+        if let Err(e) = check_kerf(stock, cli.kerf) {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+        // Oversized pieces are for --diagnose to explain
+        if !cli.diagnose
+            && let Err(e) = check_fits(stock, &demands, cli.cut_direction)
+        {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
         }
-        println!();
+        let solver = Solver::new(
+            stock,
+            cli.kerf,
+            cli.cut_direction,
+            StockGrain::None,
+            demands.clone(),
+        );
+        (stock, cli.kerf, demands, solver, rejected)
+    };
+    if let Some(buckets) = &histogram
+        && buckets.windows(2).any(|w| w[0] >= w[1])
+    {
+        eprintln!("Error: histogram buckets must be in increasing order");
+        std::process::exit(1);
     }
 
-    println!(
-        "Summary: {} sheet{} used, {:.1}% waste",
-        solution.sheet_count(),
-        if solution.sheet_count() == 1 { "" } else { "s" },
-        solution.total_waste_percent(),
-    );
+    let solver = solver
+        .with_strip_threshold(cli.strip_threshold)
+        .with_anchor(cli.anchor)
+        .with_direction_tie_break(cli.direction_tie_break)
+        .with_tight_fit_margin(cli.tight_fit_margin)
+        .with_template_sheet(cli.template_sheet);
+    let solver = match &cli.resume {
+        Some(path) => solver.with_resume(Some(read_checkpoint(path).unwrap_or_else(|e| {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }))),
+        None => solver,
+    };
+    let solver = match cli.checkpoint.clone() {
+        Some(path) => {
+            let job_hash = solver.job_hash();
+            solver.with_on_improvement(move |solution| {
+                let checkpoint = Checkpoint {
+                    job_hash,
+                    solution: solution.clone(),
+                };
+                if let Err(e) = write_checkpoint(&path, &checkpoint) {
+                    eprintln!("Warning: checkpoint not saved: {}", e);
+                }
+            })
+        }
+        None => solver,
+    };
+    if cli.diagnose {
+        print!("{}", diagnose_lines(&solver.diagnose()));
+        return;
+    }
+    if cli.dry_run {
+        print!(
+            "{}",
+            dry_run_plan(stock, &demands, solver.lower_bound_sheets())
+        );
+        return;
+    }
+    let solution = solver.try_solve().unwrap_or_else(|e| {
+        eprintln!("Error: {}", e);
+        std::process::exit(1);
+    });
+    if let Some(path) = &cli.checkpoint
+        && let Err(e) = write_checkpoint(path, &solver.checkpoint(&solution))
+    {
+        eprintln!("Warning: checkpoint not saved: {}", e);
+    }
+
+    if cli.verify
+        && let Err(violations) = solution.validate()
+    {
+        for v in &violations {
+            eprintln!("Error: {}", v);
+        }
+        std::process::exit(2);
+    }
+
+    match cli.format {
+        OutputFormat::Text => {
+            if cli.units == Units::Inch {
+                println!("Dimensions in mm, converted from inches\n");
+            }
+            print!(
+                "{}",
+                solution.to_report(&ReportOptions {
+                    layout: cli.layout,
+                    hatch_waste: cli.hatch_waste,
+                    sheet_label_prefix: sheet_prefix,
+                    sheet_offset: sheet_offset.unwrap_or(0),
+                    label_format: cli.label_format,
+                    group_identical: cli.group_sheets,
+                })
+            );
+            if !rejected.is_empty() {
+                print!("{}", rejected_report(&rejected));
+            }
+            if let Some(buckets) = &histogram {
+                let counts = solution.offcut_histogram(buckets);
+                print!("\n{}", histogram_lines(buckets, &counts));
+            }
+            if cli.bom {
+                print!("\n{}", bom_lines(&solution.bill_of_materials()));
+            }
+        }
+        OutputFormat::Instructions => {
+            for r in &rejected {
+                eprintln!("Rejected: cuts[{}]: {}", r.index, r.reason);
+            }
+            print!(
+                "{}",
+                instruction_lines(
+                    &solution,
+                    kerf,
+                    sheet_prefix.as_deref(),
+                    sheet_offset.unwrap_or(0)
+                )
+            );
+        }
+        OutputFormat::Ndjson | OutputFormat::Points | OutputFormat::Tsv => {
+            for r in &rejected {
+                eprintln!("Rejected: cuts[{}]: {}", r.index, r.reason);
+            }
+            let mut out = std::io::BufWriter::new(std::io::stdout().lock());
+            let written = match cli.format {
+                OutputFormat::Points => solution.write_points(&mut out),
+                OutputFormat::Tsv => {
+                    solution.write_tsv(&mut out, sheet_prefix.as_deref(), sheet_offset.unwrap_or(0))
+                }
+                _ => solution.write_ndjson(&mut out),
+            };
+            if let Err(e) = written.and_then(|_| out.flush()) {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cut_optimizer::units::INCH_GRID;
+
+    #[test]
+    fn test_dry_run_rejects_oversized_piece() {
+        let stock = Rect::new(1000, 500);
+        let demands = [parse_cut("1200x100:2", false, Units::Mm).unwrap()];
+        assert_eq!(
+            check_fits(stock, &demands, CutDirection::Auto),
+            Err("piece 1200x100 does not fit in stock 1000x500".to_string())
+        );
+    }
+
+    #[test]
+    fn test_parse_cuts_skip_zero_qty() {
+        let cuts: Vec<String> = ["600x400:3", "300x200:0", "100x50:1"]
+            .iter()
+            .map(|c| c.to_string())
+            .collect();
+        assert_eq!(
+            parse_cuts(&cuts, true, Units::Mm, false).err(),
+            Some(ParseError::ZeroQuantity("300x200:0".to_string()))
+        );
+        let (demands, rejected) = parse_cuts(&cuts, true, Units::Mm, true).unwrap();
+        let sizes: Vec<Rect> = demands.iter().map(|d| d.rect).collect();
+        assert_eq!(sizes, [Rect::new(600, 400), Rect::new(100, 50)]);
+        assert_eq!(rejected.len(), 1);
+        assert_eq!(
+            (rejected[0].index, rejected[0].rect, rejected[0].qty),
+            (1, Rect::new(300, 200), 0)
+        );
+        assert_eq!(
+            rejected_report(&rejected),
+            "\nRejected:\n  cuts[1]: quantity is zero\n"
+        );
+    }
+
+    #[test]
+    fn test_dry_run_plan() {
+        let stock = Rect::new(1000, 500);
+        let demands: Vec<Demand> = ["600x400:3", "300x200:4"]
+            .iter()
+            .map(|c| parse_cut(c, true, Units::Mm).unwrap())
+            .collect();
+        assert_eq!(check_fits(stock, &demands, CutDirection::Auto), Ok(()));
+        let solver = Solver::new(
+            stock,
+            3,
+            CutDirection::Auto,
+            StockGrain::None,
+            demands.clone(),
+        );
+        assert_eq!(
+            dry_run_plan(stock, &demands, solver.lower_bound_sheets()),
+            "\
+Pieces: 7 (960000 mm² total)
+Estimate: at least 2 sheets of 1000x500
+"
+        );
+    }
+
+    #[test]
+    fn test_diagnose_lines() {
+        let stock = Rect::new(1000, 500);
+        let demands: Vec<Demand> = ["600x400:3", "400x800:1", "1200x600:1"]
+            .iter()
+            .map(|c| parse_cut(c, false, Units::Mm).unwrap())
+            .collect();
+        let solver = Solver::new(stock, 0, CutDirection::Auto, StockGrain::None, demands);
+        assert_eq!(
+            diagnose_lines(&solver.diagnose()),
+            "\
+demand 1 (400x800): fits only in an orientation its grain or rotation settings forbid
+demand 2 (1200x600): does not fit an empty sheet in either orientation
+"
+        );
+        assert_eq!(diagnose_lines(&[]), "Every cut can be placed\n");
+    }
+
+    #[test]
+    fn test_inch_units() {
+        let stock = parse_dimensions("96x48", Units::Inch).unwrap();
+        assert_eq!(stock, Rect::new(96 * INCH_GRID, 48 * INCH_GRID));
+        let demands = vec![parse_cut("23.5x11.75:2", true, Units::Inch).unwrap()];
+        let (stock, demands) = job_in_mm(stock, demands, Units::Inch);
+        assert_eq!(stock, Rect::new(2438, 1219));
+        assert_eq!(demands[0].rect, Rect::new(597, 298));
+        assert_eq!(demands[0].qty, 2);
+
+        assert!(parse_dimensions("23.5x12", Units::Mm).is_err());
+        assert!(parse_dimensions("-1x12", Units::Inch).is_err());
+    }
+
+    #[test]
+    fn test_json_job() {
+        let input = r#"{
+            "stock": { "length": 1000, "width": 500 },
+            "cuts": [
+                { "rect": { "length": 500, "width": 500 }, "qty": 2, "label": "door" },
+                { "rect": { "length": 1200, "width": 100 }, "qty": 1 }
+            ],
+            "kerf": 0,
+            "on_infeasible": "skip",
+            "sheet_label_prefix": "JOB7"
+        }"#;
+        let (req, demands, rejected) = read_json_job(input.as_bytes(), false).unwrap();
+        assert_eq!(demands.len(), 1);
+        assert_eq!(rejected[0].index, 1);
+        let solution = req.solver(demands).solve();
+        assert_eq!(
+            solution.to_report(&req.report_options()),
+            "\
+JOB7-Sheet-1:
+  500x500 @ (0, 0)
+  500x500 @ (500, 0)
+  Used area: 1000x500 @ (0, 0)
+  Utilization: 100.0%
+
+Summary: 1 sheet used (1x 1000x500), 0.0% waste
+"
+        );
+
+        let invalid = r#"{ "stock": { "length": 0, "width": 500 }, "cuts": [] }"#;
+        assert_eq!(
+            read_json_job(invalid.as_bytes(), false).err().unwrap(),
+            "stock.length: must be non-zero"
+        );
+        assert!(
+            read_json_job("not json".as_bytes(), false)
+                .err()
+                .unwrap()
+                .starts_with("invalid JSON job:")
+        );
+    }
+
+    #[test]
+    fn test_histogram_lines() {
+        assert_eq!(
+            histogram_lines(&[1000, 50000], &[3, 0, 1]),
+            "\
+Offcut histogram:
+  <= 1000 mm²: 3
+  <= 50000 mm²: 0
+  > 50000 mm²: 1
+"
+        );
+    }
 }
@@ -0,0 +1,163 @@
+//! Parsing of the `LxW` and `LxW:qty` notation used on the command line.
+
+use crate::types::{Demand, Rect};
+use crate::units::{INCH_GRID, Units};
+use std::fmt;
+use std::str::FromStr;
+
+/// Why a dimension or cut string was rejected. Each variant holds the
+/// whole string as given.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseError {
+    /// Not of the form `LxW`.
+    Dimensions(String),
+    /// The length is not a number in the expected units.
+    Length(String),
+    /// The width is not a number in the expected units.
+    Width(String),
+    /// A side is zero.
+    ZeroDimension(String),
+    /// Not of the form `LxW:qty`.
+    Cut(String),
+    /// The quantity is not a whole number.
+    Quantity(String),
+    /// The quantity is zero.
+    ZeroQuantity(String),
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::Dimensions(s) => write!(f, "invalid dimensions '{}', expected LxW", s),
+            ParseError::Length(s) => write!(f, "invalid length in '{}'", s),
+            ParseError::Width(s) => write!(f, "invalid width in '{}'", s),
+            ParseError::ZeroDimension(s) => write!(f, "dimensions must be non-zero in '{}'", s),
+            ParseError::Cut(s) => write!(f, "invalid cut '{}', expected LxW:qty", s),
+            ParseError::Quantity(s) => write!(f, "invalid quantity in '{}'", s),
+            ParseError::ZeroQuantity(s) => write!(f, "quantity must be non-zero in '{}'", s),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// One side in `units`: whole mm, or inches (decimals allowed) rounded to
+/// the inch grid.
+pub fn parse_side(s: &str, units: Units) -> Option<u32> {
+    match units {
+        Units::Mm => s.parse().ok(),
+        Units::Inch => {
+            let steps = s.parse::<f64>().ok()? * INCH_GRID as f64;
+            (steps.is_finite() && steps >= 0.0 && steps <= u32::MAX as f64)
+                .then(|| steps.round() as u32)
+        }
+    }
+}
+
+/// `LxW` in `units`, e.g. `2400x1200`. Inch dimensions come out on the
+/// inch grid, see [`crate::units`].
+pub fn parse_dimensions(s: &str, units: Units) -> Result<Rect, ParseError> {
+    let parts: Vec<&str> = s.split('x').collect();
+    if parts.len() != 2 {
+        return Err(ParseError::Dimensions(s.to_string()));
+    }
+    let length = parse_side(parts[0], units).ok_or_else(|| ParseError::Length(s.to_string()))?;
+    let width = parse_side(parts[1], units).ok_or_else(|| ParseError::Width(s.to_string()))?;
+    if length == 0 || width == 0 {
+        return Err(ParseError::ZeroDimension(s.to_string()));
+    }
+    Ok(Rect::new(length, width))
+}
+
+/// `LxW:qty` in `units`, e.g. `800x600:3`.
+pub fn parse_cut(s: &str, allow_rotate: bool, units: Units) -> Result<Demand, ParseError> {
+    let parts: Vec<&str> = s.split(':').collect();
+    if parts.len() != 2 {
+        return Err(ParseError::Cut(s.to_string()));
+    }
+    let rect = parse_dimensions(parts[0], units)?;
+    let qty = parts[1]
+        .parse::<u32>()
+        .map_err(|_| ParseError::Quantity(s.to_string()))?;
+    if qty == 0 {
+        return Err(ParseError::ZeroQuantity(s.to_string()));
+    }
+    Ok(Demand::new(rect, qty).with_allow_rotate(allow_rotate))
+}
+
+/// `LxW` in mm.
+impl FromStr for Rect {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_dimensions(s, Units::Mm)
+    }
+}
+
+impl Demand {
+    /// Demand from `LxW:qty` in mm, e.g. `800x600:3`.
+    pub fn from_cut_str(s: &str, allow_rotate: bool) -> Result<Demand, ParseError> {
+        parse_cut(s, allow_rotate, Units::Mm)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_rect() {
+        assert_eq!("2400x1200".parse(), Ok(Rect::new(2400, 1200)));
+        let err = |s: &str| s.parse::<Rect>().unwrap_err();
+        assert_eq!(err("2400"), ParseError::Dimensions("2400".to_string()));
+        assert_eq!(err("1x2x3"), ParseError::Dimensions("1x2x3".to_string()));
+        assert_eq!(err("ax1200"), ParseError::Length("ax1200".to_string()));
+        assert_eq!(err("2400x-1"), ParseError::Width("2400x-1".to_string()));
+        assert_eq!(err("23.5x12"), ParseError::Length("23.5x12".to_string()));
+        assert_eq!(
+            err("0x1200"),
+            ParseError::ZeroDimension("0x1200".to_string())
+        );
+    }
+
+    #[test]
+    fn test_parse_cut() {
+        let demand = Demand::from_cut_str("800x600:3", false).unwrap();
+        assert_eq!((demand.rect, demand.qty), (Rect::new(800, 600), 3));
+        assert!(!demand.can_rotate());
+
+        let err = |s: &str| Demand::from_cut_str(s, true).unwrap_err();
+        assert_eq!(err("800x600"), ParseError::Cut("800x600".to_string()));
+        assert_eq!(
+            err("800x600:1:2"),
+            ParseError::Cut("800x600:1:2".to_string())
+        );
+        assert_eq!(err("800:3"), ParseError::Dimensions("800".to_string()));
+        assert_eq!(
+            err("800x600:x"),
+            ParseError::Quantity("800x600:x".to_string())
+        );
+        assert_eq!(
+            err("800x600:0"),
+            ParseError::ZeroQuantity("800x600:0".to_string())
+        );
+        assert_eq!(
+            err("800x600:0").to_string(),
+            "quantity must be non-zero in '800x600:0'"
+        );
+    }
+
+    #[test]
+    fn test_parse_inches() {
+        assert_eq!(
+            parse_dimensions("96x48", Units::Inch),
+            Ok(Rect::new(96 * INCH_GRID, 48 * INCH_GRID))
+        );
+        let demand = parse_cut("23.5x11.75:2", true, Units::Inch).unwrap();
+        assert_eq!(demand.rect, Rect::new(376, 188));
+        assert_eq!(
+            parse_dimensions("-1x12", Units::Inch),
+            Err(ParseError::Length("-1x12".to_string()))
+        );
+    }
+}
@@ -1,9 +1,177 @@
-use crate::types::{Placement, Rect};
+use crate::types::{Offcut, Placement, Rect};
 
 const MAX_WIDTH: f64 = 80.0;
 const MAX_HEIGHT: f64 = 40.0;
+const HATCH: char = '.';
+const DIRECTION_MARKER: char = '^';
 
+/// Template for the piece labels of the ASCII layout, e.g.
+/// `{label} ({width}×{length})`. Placeholders are `{label}` (empty for
+/// unlabelled pieces), `{length}` and `{width}` (as placed) and `{index}`
+/// (zero-based, in placement order). The default is `{length}x{width}`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LabelFormat {
+    segments: Vec<Segment>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Segment {
+    Text(String),
+    Label,
+    Length,
+    Width,
+    Index,
+}
+
+impl LabelFormat {
+    /// Parse `template`, rejecting unknown placeholders and unmatched
+    /// braces.
+    pub fn parse(template: &str) -> Result<Self, String> {
+        let mut segments = Vec::new();
+        let mut rest = template;
+        while !rest.is_empty() {
+            let Some(open) = rest.find(['{', '}']) else {
+                segments.push(Segment::Text(rest.to_string()));
+                break;
+            };
+            if open > 0 {
+                segments.push(Segment::Text(rest[..open].to_string()));
+            }
+            rest = &rest[open..];
+            let close = match rest.find('}') {
+                Some(close) if rest.starts_with('{') => close,
+                _ => return Err(format!("unmatched brace in label format '{}'", template)),
+            };
+            segments.push(match &rest[1..close] {
+                "label" => Segment::Label,
+                "length" => Segment::Length,
+                "width" => Segment::Width,
+                "index" => Segment::Index,
+                other => {
+                    return Err(format!(
+                        "unknown placeholder '{{{}}}' in label format, expected: {{label}}, {{length}}, {{width}} or {{index}}",
+                        other
+                    ));
+                }
+            });
+            rest = &rest[close + 1..];
+        }
+        Ok(Self { segments })
+    }
+
+    /// Label of `p`, the `index`-th placement of its sheet.
+    pub fn format(&self, p: &Placement, index: usize) -> String {
+        self.segments
+            .iter()
+            .map(|s| match s {
+                Segment::Text(text) => text.clone(),
+                Segment::Label => p.label.clone().unwrap_or_default(),
+                Segment::Length => p.rect.length.to_string(),
+                Segment::Width => p.rect.width.to_string(),
+                Segment::Index => index.to_string(),
+            })
+            .collect()
+    }
+}
+
+impl Default for LabelFormat {
+    fn default() -> Self {
+        Self {
+            segments: vec![
+                Segment::Length,
+                Segment::Text("x".to_string()),
+                Segment::Width,
+            ],
+        }
+    }
+}
+
+/// Render a sheet as ASCII art, preceded by a header line (stock size and
+/// utilization) and a legend mapping label abbreviations to piece sizes.
+///
+/// Pieces too small to hold their full `LxW` label are marked with their
+/// legend letter instead.
 pub fn render_sheet(stock: Rect, placements: &[Placement]) -> String {
+    render_sheet_hatched(stock, placements, &[])
+}
+
+/// Like [`render_sheet`], with the inside of each offcut filled with `.` to
+/// set waste apart from pieces.
+pub fn render_sheet_hatched(stock: Rect, placements: &[Placement], offcuts: &[Offcut]) -> String {
+    render_sheet_formatted(stock, placements, offcuts, &LabelFormat::default())
+}
+
+/// Like [`render_sheet_hatched`], with pieces labelled by `label_format`
+/// (legend letters still stand in for labels that don't fit).
+pub fn render_sheet_formatted(
+    stock: Rect,
+    placements: &[Placement],
+    offcuts: &[Offcut],
+    label_format: &LabelFormat,
+) -> String {
+    let legend = build_legend(placements);
+    let mut result = render_header(stock, placements, &legend);
+    result.push_str(&render_grid(
+        stock,
+        placements,
+        offcuts,
+        &legend,
+        label_format,
+    ));
+    result
+}
+
+/// Distinct placed sizes in order of first appearance, with their count.
+fn build_legend(placements: &[Placement]) -> Vec<(Rect, usize)> {
+    let mut legend: Vec<(Rect, usize)> = Vec::new();
+    for p in placements {
+        match legend.iter_mut().find(|(r, _)| *r == p.rect) {
+            Some((_, count)) => *count += 1,
+            None => legend.push((p.rect, 1)),
+        }
+    }
+    legend
+}
+
+/// Abbreviation for the n-th legend entry: A..Z, then AA, AB, ...
+fn legend_key(mut n: usize) -> String {
+    let mut key = Vec::new();
+    loop {
+        key.push((b'A' + (n % 26) as u8) as char);
+        if n < 26 {
+            break;
+        }
+        n = n / 26 - 1;
+    }
+    key.iter().rev().collect()
+}
+
+fn render_header(stock: Rect, placements: &[Placement], legend: &[(Rect, usize)]) -> String {
+    let used: u64 = placements.iter().map(|p| p.rect.area()).sum();
+    let utilization = if stock.area() == 0 {
+        0.0
+    } else {
+        used as f64 / stock.area() as f64 * 100.0
+    };
+    let mut header = format!("Sheet {}, {:.1}% used\n", stock, utilization);
+    if !legend.is_empty() {
+        let entries: Vec<String> = legend
+            .iter()
+            .enumerate()
+            .map(|(i, (rect, count))| format!("{}={} (x{})", legend_key(i), rect, count))
+            .collect();
+        header.push_str(&format!("Legend: {}\n", entries.join(", ")));
+    }
+    header
+}
+
+fn render_grid(
+    stock: Rect,
+    placements: &[Placement],
+    offcuts: &[Offcut],
+    legend: &[(Rect, usize)],
+    label_format: &LabelFormat,
+) -> String {
     let scale = f64::min(
         MAX_WIDTH / stock.length as f64,
         MAX_HEIGHT / stock.width as f64,
@@ -21,7 +189,7 @@ pub fn render_sheet(stock: Rect, placements: &[Placement]) -> String {
     draw_rect(&mut grid, 0, 0, grid_w, grid_h);
 
     // Draw each placement
-    for p in placements {
+    for (index, p) in placements.iter().enumerate() {
         let sx = (p.x as f64 * scale).round() as usize;
         let sy = (p.y as f64 * scale).round() as usize;
         let sw = (p.rect.length as f64 * scale).round() as usize;
@@ -33,8 +201,12 @@ pub fn render_sheet(stock: Rect, placements: &[Placement]) -> String {
 
         draw_rect(&mut grid, sx, sy, sw, sh);
 
-        // Label
-        let label = format!("{}x{}", p.rect.length, p.rect.width);
+        // Label: formatted if it fits inside the box, legend key otherwise
+        let mut label = label_format.format(p, index);
+        if label.chars().count() + 1 >= sw {
+            let idx = legend.iter().position(|(r, _)| *r == p.rect).unwrap_or(0);
+            label = legend_key(idx);
+        }
         let label_chars: Vec<char> = label.chars().collect();
 
         if sw > 2 && sh > 0 {
@@ -50,6 +222,26 @@ pub fn render_sheet(stock: Rect, placements: &[Placement]) -> String {
                 }
             }
         }
+
+        // Directional pieces: an arrow under the top edge, above the label
+        if p.directional && sw > 2 && sy + sh / 2 > sy + 1 {
+            grid[sy + 1][sx + sw / 2] = DIRECTION_MARKER;
+        }
+    }
+
+    // Hatch offcuts; blank cells only, so borders and labels stay
+    for o in offcuts {
+        let sx = (o.x as f64 * scale).round() as usize;
+        let sy = (o.y as f64 * scale).round() as usize;
+        let ex = ((o.x + o.rect.length) as f64 * scale).round() as usize;
+        let ey = ((o.y + o.rect.width) as f64 * scale).round() as usize;
+        for row in grid.iter_mut().take(ey + 1).skip(sy) {
+            for cell in row.iter_mut().take(ex + 1).skip(sx) {
+                if *cell == ' ' {
+                    *cell = HATCH;
+                }
+            }
+        }
     }
 
     let mut result = String::new();
@@ -61,6 +253,32 @@ pub fn render_sheet(stock: Rect, placements: &[Placement]) -> String {
     result
 }
 
+/// Which placement covers each cell of a `cols` x `rows` grid laid over
+/// the stock (row-major, row 0 at y = 0), sampled at the cell centres:
+/// the index into `placements`, or `None` for free space. A structured
+/// counterpart of [`render_sheet`], e.g. to look for overlaps.
+pub fn render_index_map(
+    stock: Rect,
+    placements: &[Placement],
+    cols: usize,
+    rows: usize,
+) -> Vec<Vec<Option<usize>>> {
+    // Centre of cell `i` out of `n` along a side of `len`
+    let centre =
+        |i: usize, n: usize, len: u32| ((2 * i as u64 + 1) * len as u64 / (2 * n as u64)) as u32;
+    (0..rows)
+        .map(|r| {
+            let y = centre(r, rows, stock.width);
+            (0..cols)
+                .map(|c| {
+                    let x = centre(c, cols, stock.length);
+                    placements.iter().position(|p| p.contains_point(x, y))
+                })
+                .collect()
+        })
+        .collect()
+}
+
 #[allow(clippy::needless_range_loop)]
 fn draw_rect(grid: &mut [Vec<char>], x: usize, y: usize, w: usize, h: usize) {
     let rows = grid.len();
@@ -119,6 +337,61 @@ fn draw_rect(grid: &mut [Vec<char>], x: usize, y: usize, w: usize, h: usize) {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::solver::Solver;
+    use crate::types::{Anchor, CutDirection, Demand, StockGrain};
+
+    #[test]
+    fn test_render_index_map() {
+        let piece = |l, w, x, y| Placement::new(Rect::new(l, w), x, y);
+        let placements = [piece(50, 100, 0, 0), piece(25, 50, 50, 50)];
+        let map = render_index_map(Rect::new(100, 100), &placements, 4, 2);
+        assert_eq!(
+            map,
+            [
+                [Some(0), Some(0), None, None],
+                [Some(0), Some(0), Some(1), None],
+            ]
+        );
+        assert!(
+            render_index_map(Rect::new(100, 100), &[], 3, 3)
+                .iter()
+                .flatten()
+                .all(Option::is_none)
+        );
+    }
+
+    #[test]
+    fn test_label_format() {
+        let stock = Rect::new(100, 50);
+        let placements = vec![Placement {
+            rect: Rect::new(100, 50),
+            x: 0,
+            y: 0,
+            rotated: false,
+            requested: Rect::new(100, 50),
+            orientation_deg: 0,
+            label: Some("P-17".to_string()),
+            is_filler: false,
+            directional: false,
+            group_hint: None,
+        }];
+        let format = LabelFormat::parse("#{index} {label} ({width}×{length})").unwrap();
+        let output = render_sheet_formatted(stock, &placements, &[], &format);
+        let row = output
+            .lines()
+            .find(|l| l.contains("P-17"))
+            .expect("label drawn in the grid");
+        assert!(row.contains("#0 P-17 (50×100)"), "{row}");
+        assert!(!row.contains("100x50"));
+
+        assert_eq!(
+            LabelFormat::parse("{length}x{width}"),
+            Ok(LabelFormat::default())
+        );
+        assert!(LabelFormat::parse("{name}").unwrap_err().contains("{name}"));
+        assert!(LabelFormat::parse("{label").is_err());
+        assert!(LabelFormat::parse("label}").is_err());
+    }
 
     #[test]
     fn test_render_single_piece() {
@@ -128,6 +401,12 @@ mod tests {
             x: 0,
             y: 0,
             rotated: false,
+            requested: Rect::new(100, 50),
+            orientation_deg: 0,
+            label: None,
+            is_filler: false,
+            directional: false,
+            group_hint: None,
         }];
         let output = render_sheet(stock, &placements);
         assert!(output.contains('+'));
@@ -145,18 +424,109 @@ mod tests {
                 x: 0,
                 y: 0,
                 rotated: false,
+                requested: Rect::new(50, 100),
+                orientation_deg: 0,
+                label: None,
+                is_filler: false,
+                directional: false,
+                group_hint: None,
             },
             Placement {
                 rect: Rect::new(50, 100),
                 x: 50,
                 y: 0,
                 rotated: false,
+                requested: Rect::new(50, 100),
+                orientation_deg: 0,
+                label: None,
+                is_filler: false,
+                directional: false,
+                group_hint: None,
             },
         ];
         let output = render_sheet(stock, &placements);
         assert!(output.contains("50x100"));
     }
 
+    #[test]
+    fn test_render_header_and_legend() {
+        let stock = Rect::new(200, 100);
+        let placements = vec![
+            Placement {
+                rect: Rect::new(100, 100),
+                x: 0,
+                y: 0,
+                rotated: false,
+                requested: Rect::new(100, 100),
+                orientation_deg: 0,
+                label: None,
+                is_filler: false,
+                directional: false,
+                group_hint: None,
+            },
+            Placement {
+                rect: Rect::new(10, 50),
+                x: 100,
+                y: 0,
+                rotated: false,
+                requested: Rect::new(10, 50),
+                orientation_deg: 0,
+                label: None,
+                is_filler: false,
+                directional: false,
+                group_hint: None,
+            },
+        ];
+        let output = render_sheet(stock, &placements);
+        let lines: Vec<&str> = output.lines().collect();
+        assert!(lines[0].contains("200x100"));
+        assert!(lines[0].contains("52.5% used"));
+        assert_eq!(lines[1], "Legend: A=100x100 (x1), B=10x50 (x1)");
+        // Grid follows the header, starting with the stock border
+        assert!(lines[2].starts_with('+'));
+        assert!(output.contains("100x100"));
+    }
+
+    #[test]
+    fn test_render_anchored_layout() {
+        // Anchors are named as drawn, with y = 0 on the top row
+        let stock = Rect::new(1000, 500);
+        let demands = vec![
+            Demand::new(Rect::new(600, 300), 1),
+            Demand::new(Rect::new(300, 200), 1),
+        ];
+        for (anchor, top, right) in [
+            (Anchor::TopLeft, true, false),
+            (Anchor::TopRight, true, true),
+            (Anchor::BottomLeft, false, false),
+            (Anchor::BottomRight, false, true),
+        ] {
+            let solution = Solver::new(
+                stock,
+                0,
+                CutDirection::Auto,
+                StockGrain::None,
+                demands.clone(),
+            )
+            .with_anchor(Some(anchor))
+            .solve();
+            let output = render_sheet(stock, &solution.sheets[0].placements);
+            let grid: Vec<&str> = output.lines().skip(2).collect();
+            let row = grid.iter().position(|l| l.contains("600x300")).unwrap();
+            let col = grid[row].find("600x300").unwrap();
+            assert_eq!(row < grid.len() / 2, top, "{:?}\n{}", anchor, output);
+            assert_eq!(col > grid[row].len() / 2, right, "{:?}\n{}", anchor, output);
+        }
+    }
+
+    #[test]
+    fn test_legend_key() {
+        assert_eq!(legend_key(0), "A");
+        assert_eq!(legend_key(25), "Z");
+        assert_eq!(legend_key(26), "AA");
+        assert_eq!(legend_key(27), "AB");
+    }
+
     #[test]
     fn test_render_empty() {
         let stock = Rect::new(100, 100);
@@ -164,4 +534,49 @@ mod tests {
         // Should still draw the stock border
         assert!(output.contains('+'));
     }
+
+    #[test]
+    fn test_render_hatched_waste() {
+        let stock = Rect::new(100, 50);
+        let placements = vec![Placement {
+            rect: Rect::new(60, 50),
+            x: 0,
+            y: 0,
+            rotated: false,
+            requested: Rect::new(60, 50),
+            orientation_deg: 0,
+            label: None,
+            is_filler: false,
+            directional: false,
+            group_hint: None,
+        }];
+        let offcuts = vec![Offcut {
+            x: 60,
+            y: 0,
+            rect: Rect::new(40, 50),
+        }];
+        let plain = render_sheet(stock, &placements);
+        let hatched = render_sheet_hatched(stock, &placements, &offcuts);
+        assert!(hatched.contains(HATCH));
+
+        let plain_lines: Vec<&str> = plain.lines().collect();
+        let hatched_lines: Vec<&str> = hatched.lines().collect();
+        assert_eq!(plain_lines.len(), hatched_lines.len());
+        for (p, h) in plain_lines.iter().zip(&hatched_lines) {
+            let p: Vec<char> = p.chars().collect();
+            let h: Vec<char> = h.chars().collect();
+            for (i, &hc) in h.iter().enumerate() {
+                match p.get(i) {
+                    // Only blank cells (or trimmed trailing blanks) change
+                    Some(&pc) if pc != hc => assert_eq!((pc, hc), (' ', HATCH)),
+                    None => assert_eq!(hc, HATCH),
+                    _ => {}
+                }
+            }
+        }
+        // The piece (left 60% of the grid) is untouched
+        for h in &hatched_lines[2..] {
+            assert!(!h.chars().take(48).any(|c| c == HATCH), "{h}");
+        }
+    }
 }
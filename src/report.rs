@@ -0,0 +1,546 @@
+use crate::render::{self, LabelFormat};
+use crate::types::{Rect, SheetResult, Solution, Warning};
+use serde::Serialize;
+use std::fmt::Write;
+use std::io;
+
+/// What [`Solution::to_report`] includes besides the placement list.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ReportOptions {
+    /// ASCII layout of each sheet.
+    pub layout: bool,
+    /// Hatch offcuts in the layouts.
+    pub hatch_waste: bool,
+    /// Job name prepended to sheet names, see [`sheet_label`].
+    pub sheet_label_prefix: Option<String>,
+    /// Added to every sheet number, to carry numbering over from earlier
+    /// jobs in a batch.
+    pub sheet_offset: usize,
+    /// Piece label template for the layouts, see [`LabelFormat`]. Invalid
+    /// templates fall back to the default.
+    pub label_format: Option<String>,
+    /// Print identical sheets once, as e.g. `Sheets 3-9 (×7)`, see
+    /// [`Solution::identical_sheet_groups`].
+    pub group_identical: bool,
+}
+
+/// Name of the `index`-th (zero-based) sheet: `Sheet 3`, or `JOB42-Sheet-3`
+/// with a prefix. Numbering starts at `offset + 1`.
+pub fn sheet_label(prefix: Option<&str>, offset: usize, index: usize) -> String {
+    let number = offset + index + 1;
+    match prefix {
+        Some(prefix) => format!("{}-Sheet-{}", prefix, number),
+        None => format!("Sheet {}", number),
+    }
+}
+
+/// Name of a group of identical sheets, given their zero-based indices:
+/// `Sheets 3-9 (×7)` for a run, `Sheets 1, 4 (×2)` otherwise, or
+/// `JOB42-Sheets-3-9 (×7)` with a prefix. A single sheet is named by
+/// [`sheet_label`].
+pub fn sheet_group_label(prefix: Option<&str>, offset: usize, indices: &[usize]) -> String {
+    let [first, .., last] = indices else {
+        return sheet_label(prefix, offset, indices.first().copied().unwrap_or(0));
+    };
+    let numbers = if last - first + 1 == indices.len() {
+        format!("{}-{}", offset + first + 1, offset + last + 1)
+    } else {
+        let numbers: Vec<String> = indices
+            .iter()
+            .map(|i| (offset + i + 1).to_string())
+            .collect();
+        numbers.join(", ")
+    };
+    match prefix {
+        Some(prefix) => format!("{}-Sheets-{} (×{})", prefix, numbers, indices.len()),
+        None => format!("Sheets {} (×{})", numbers, indices.len()),
+    }
+}
+
+/// One line of [`Solution::write_ndjson`].
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum NdjsonLine<'a> {
+    Sheet {
+        index: usize,
+        #[serde(flatten)]
+        sheet: &'a SheetResult,
+    },
+    Summary {
+        sheet_count: usize,
+        stock_usage: Vec<StockCount>,
+        waste_percent: f64,
+        warnings: &'a [Warning],
+    },
+}
+
+#[derive(Serialize)]
+struct StockCount {
+    stock: Rect,
+    count: usize,
+}
+
+/// Corners of the `rect` at `(x, y)`, from `(x, y)`: counter-clockwise in
+/// tools with the y axis pointing up (OpenSCAD, plotting tools), clockwise
+/// as the layout is drawn with y = 0 at the top.
+pub fn corners(x: u32, y: u32, rect: Rect) -> [(u32, u32); 4] {
+    let (x1, y1) = (x + rect.length, y + rect.width);
+    [(x, y), (x1, y), (x1, y1), (x, y1)]
+}
+
+fn write_polygon(out: &mut impl io::Write, kind: &str, corners: [(u32, u32); 4]) -> io::Result<()> {
+    write!(out, "{}", kind)?;
+    for (x, y) in corners {
+        write!(out, " {},{}", x, y)?;
+    }
+    writeln!(out)
+}
+
+impl Solution {
+    /// Plain coordinate list for previews in other tools: for each sheet a
+    /// `sheet N` line, then the stock outline and every placement as a
+    /// polygon of its four [`corners`], e.g. `piece 0,0 800,0 800,600 0,600`.
+    pub fn write_points(&self, out: &mut impl io::Write) -> io::Result<()> {
+        for (index, sheet) in self.sheets.iter().enumerate() {
+            writeln!(out, "sheet {}", index)?;
+            write_polygon(out, "stock", corners(0, 0, sheet.stock))?;
+            for p in &sheet.placements {
+                write_polygon(out, "piece", corners(p.x, p.y, p.rect))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Newline-delimited JSON: one `"type": "sheet"` object per sheet, then
+    /// a `"type": "summary"` one. Each line is serialized and written on its
+    /// own, so large solutions are never held in memory as a single document.
+    pub fn write_ndjson(&self, out: &mut impl io::Write) -> io::Result<()> {
+        for (index, sheet) in self.sheets.iter().enumerate() {
+            serde_json::to_writer(&mut *out, &NdjsonLine::Sheet { index, sheet })?;
+            out.write_all(b"\n")?;
+        }
+        let summary = NdjsonLine::Summary {
+            sheet_count: self.sheet_count(),
+            stock_usage: self
+                .stock_usage()
+                .into_iter()
+                .map(|(stock, count)| StockCount { stock, count })
+                .collect(),
+            waste_percent: self.total_waste_percent(),
+            warnings: &self.warnings,
+        };
+        serde_json::to_writer(&mut *out, &summary)?;
+        out.write_all(b"\n")
+    }
+
+    /// Tab-separated tables for spreadsheets, one block per sheet and a
+    /// blank line between blocks: the sheet's [`sheet_label`], stock and
+    /// material, then a header row and one row per placement (sizes in
+    /// mm). A final `Summary` block has a row per stock size with its
+    /// sheet count and waste, and a `total` row.
+    pub fn write_tsv(
+        &self,
+        out: &mut impl io::Write,
+        prefix: Option<&str>,
+        offset: usize,
+    ) -> io::Result<()> {
+        // Labels are free text: keep them on their cell
+        let cell = |s: &str| s.replace(['\t', '\n', '\r'], " ");
+        let yes_no = |b: bool| if b { "yes" } else { "no" };
+        for (index, sheet) in self.sheets.iter().enumerate() {
+            writeln!(
+                out,
+                "{}\t{}\t{}",
+                cell(&sheet_label(prefix, offset, index)),
+                sheet.stock,
+                cell(sheet.material.as_deref().unwrap_or(""))
+            )?;
+            writeln!(out, "piece\tx\ty\tlength\twidth\trotated\tlabel\tfiller")?;
+            for (i, p) in sheet.placements.iter().enumerate() {
+                writeln!(
+                    out,
+                    "{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}",
+                    i + 1,
+                    p.x,
+                    p.y,
+                    p.rect.length,
+                    p.rect.width,
+                    yes_no(p.rotated),
+                    cell(p.label.as_deref().unwrap_or("")),
+                    yes_no(p.is_filler)
+                )?;
+            }
+            writeln!(out)?;
+        }
+
+        writeln!(out, "Summary")?;
+        writeln!(out, "stock\tsheets\twaste %")?;
+        for (stock, count) in self.stock_usage() {
+            let sheets = self.sheets.iter().filter(|s| s.stock == stock);
+            let waste: u64 = sheets.map(|s| s.waste_area).sum();
+            let area = stock.area() as f64 * count as f64;
+            writeln!(
+                out,
+                "{}\t{}\t{:.1}",
+                stock,
+                count,
+                waste as f64 / area * 100.0
+            )?;
+        }
+        writeln!(
+            out,
+            "total\t{}\t{:.1}",
+            self.sheet_count(),
+            self.total_waste_percent()
+        )
+    }
+
+    /// Human-readable report: each sheet with its placements, used area and
+    /// utilization, then a summary line and any warnings.
+    pub fn to_report(&self, options: &ReportOptions) -> String {
+        let label_format = options
+            .label_format
+            .as_deref()
+            .and_then(|t| LabelFormat::parse(t).ok())
+            .unwrap_or_default();
+        let groups = if options.group_identical {
+            self.identical_sheet_groups()
+        } else {
+            (0..self.sheets.len()).map(|i| vec![i]).collect()
+        };
+        let mut out = String::new();
+        for indices in &groups {
+            let sheet = &self.sheets[indices[0]];
+            let label = sheet_group_label(
+                options.sheet_label_prefix.as_deref(),
+                options.sheet_offset,
+                indices,
+            );
+            writeln!(out, "{}:", label).unwrap();
+            for p in &sheet.placements {
+                let rot = if p.rotated { " [rotated]" } else { "" };
+                let filler = if p.is_filler { " [filler]" } else { "" };
+                writeln!(out, "  {} @ ({}, {}){}{}", p.rect, p.x, p.y, rot, filler).unwrap();
+            }
+            let (bx, by, bounds) = sheet.used_bounds();
+            writeln!(out, "  Used area: {} @ ({}, {})", bounds, bx, by).unwrap();
+            let used: u64 = sheet.placements.iter().map(|p| p.rect.area()).sum();
+            let utilization = if sheet.stock.area() == 0 {
+                0.0
+            } else {
+                used as f64 / sheet.stock.area() as f64 * 100.0
+            };
+            writeln!(out, "  Utilization: {:.1}%", utilization).unwrap();
+            if options.layout {
+                let offcuts = if options.hatch_waste {
+                    &sheet.offcuts[..]
+                } else {
+                    &[]
+                };
+                out.push_str(&render::render_sheet_formatted(
+                    sheet.stock,
+                    &sheet.placements,
+                    offcuts,
+                    &label_format,
+                ));
+            }
+            out.push('\n');
+        }
+
+        let usage: Vec<String> = self
+            .stock_usage()
+            .iter()
+            .map(|(stock, count)| format!("{}x {}", count, stock))
+            .collect();
+        writeln!(
+            out,
+            "Summary: {} sheet{} used ({}), {:.1}% waste",
+            self.sheet_count(),
+            if self.sheet_count() == 1 { "" } else { "s" },
+            usage.join(", "),
+            self.total_waste_percent(),
+        )
+        .unwrap();
+        if !self.warnings.is_empty() {
+            out.push_str("\nWarnings:\n");
+            for w in &self.warnings {
+                writeln!(out, "  {}", w).unwrap();
+            }
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{CutDirection, Placement, Rect, SheetResult, Warning};
+
+    fn placed(l: u32, w: u32, x: u32, y: u32, rotated: bool) -> Placement {
+        Placement {
+            rotated,
+            requested: if rotated {
+                Rect::new(w, l)
+            } else {
+                Rect::new(l, w)
+            },
+            orientation_deg: if rotated { 90 } else { 0 },
+            ..Placement::new(Rect::new(l, w), x, y)
+        }
+    }
+
+    fn sample() -> Solution {
+        let stock = Rect::new(100, 50);
+        let sheet = |placements: Vec<Placement>| {
+            let used: u64 = placements.iter().map(|p| p.rect.area()).sum();
+            SheetResult {
+                stock,
+                stock_rotated: false,
+                placements,
+                waste_area: stock.area() - used,
+                material: None,
+                offcuts: vec![],
+                reserved: None,
+                cut_direction: CutDirection::Auto,
+            }
+        };
+        Solution {
+            sheets: vec![
+                sheet(vec![
+                    placed(60, 50, 0, 0, false),
+                    placed(40, 20, 60, 0, true),
+                ]),
+                sheet(vec![placed(30, 30, 0, 0, false)]),
+            ],
+            stock,
+            warnings: vec![],
+            unplaced: vec![],
+        }
+    }
+
+    #[test]
+    fn test_report_text() {
+        let report = sample().to_report(&ReportOptions::default());
+        assert_eq!(
+            report,
+            "\
+Sheet 1:
+  60x50 @ (0, 0)
+  40x20 @ (60, 0) [rotated]
+  Used area: 100x50 @ (0, 0)
+  Utilization: 76.0%
+
+Sheet 2:
+  30x30 @ (0, 0)
+  Used area: 30x30 @ (0, 0)
+  Utilization: 18.0%
+
+Summary: 2 sheets used (2x 100x50), 53.0% waste
+"
+        );
+    }
+
+    #[test]
+    fn test_report_with_layout() {
+        let solution = sample();
+        let report = solution.to_report(&ReportOptions {
+            layout: true,
+            ..Default::default()
+        });
+        let sheet = &solution.sheets[0];
+        let layout = render::render_sheet(sheet.stock, &sheet.placements);
+        assert!(report.contains(&layout));
+        assert!(report.ends_with("53.0% waste\n"));
+    }
+
+    #[test]
+    fn test_report_label_format() {
+        let solution = sample();
+        let report = |label_format: &str| {
+            solution.to_report(&ReportOptions {
+                layout: true,
+                label_format: Some(label_format.to_string()),
+                ..Default::default()
+            })
+        };
+        assert!(report("#{index}").contains("#0"));
+        // Invalid templates fall back to the default labels
+        let default = solution.to_report(&ReportOptions {
+            layout: true,
+            ..Default::default()
+        });
+        assert_eq!(report("{bogus}"), default);
+    }
+
+    #[test]
+    fn test_report_group_identical() {
+        let mut solution = sample();
+        let first = solution.sheets[0].clone();
+        solution.sheets.insert(1, first.clone());
+        solution.sheets.push(first);
+        let report = |group_identical: bool| {
+            solution.to_report(&ReportOptions {
+                group_identical,
+                ..Default::default()
+            })
+        };
+        let headings = |report: &str| -> Vec<String> {
+            report
+                .lines()
+                .filter(|l| l.ends_with(':'))
+                .map(String::from)
+                .collect()
+        };
+        assert_eq!(
+            headings(&report(false)),
+            ["Sheet 1:", "Sheet 2:", "Sheet 3:", "Sheet 4:"]
+        );
+        assert_eq!(
+            headings(&report(true)),
+            ["Sheets 1, 2, 4 (×3):", "Sheet 3:"]
+        );
+        assert!(report(true).contains("Summary: 4 sheets used"));
+
+        assert_eq!(
+            sheet_group_label(Some("JOB42"), 2, &[2, 3, 4, 5, 6, 7, 8]),
+            "JOB42-Sheets-5-11 (×7)"
+        );
+    }
+
+    #[test]
+    fn test_report_warnings() {
+        let mut solution = sample();
+        solution.warnings.push(Warning::TightFit {
+            sheet: 0,
+            piece: 1,
+            spare: 0,
+        });
+        let report = solution.to_report(&ReportOptions::default());
+        assert!(report.ends_with(
+            "53.0% waste\n\nWarnings:\n  sheet 0: piece 1 fits with only 0 mm to spare\n"
+        ));
+    }
+
+    #[test]
+    fn test_report_sheet_labels() {
+        let report = sample().to_report(&ReportOptions {
+            sheet_label_prefix: Some("JOB42".to_string()),
+            sheet_offset: 5,
+            ..Default::default()
+        });
+        let headings: Vec<&str> = report.lines().filter(|l| l.ends_with(':')).collect();
+        assert_eq!(headings, ["JOB42-Sheet-6:", "JOB42-Sheet-7:"]);
+
+        let report = sample().to_report(&ReportOptions {
+            sheet_offset: 2,
+            ..Default::default()
+        });
+        assert!(report.starts_with("Sheet 3:\n"));
+    }
+
+    #[test]
+    fn test_points() {
+        let solution = sample();
+        let mut out = Vec::new();
+        solution.write_points(&mut out).unwrap();
+        let text = String::from_utf8(out).unwrap();
+        assert!(text.starts_with("sheet 0\nstock 0,0 100,0 100,50 0,50\n"));
+
+        let polygons: Vec<Vec<(i64, i64)>> = text
+            .lines()
+            .filter_map(|l| l.strip_prefix("piece "))
+            .map(|l| {
+                l.split(' ')
+                    .map(|c| {
+                        let (x, y) = c.split_once(',').unwrap();
+                        (x.parse().unwrap(), y.parse().unwrap())
+                    })
+                    .collect()
+            })
+            .collect();
+        let placements: Vec<&Placement> =
+            solution.sheets.iter().flat_map(|s| &s.placements).collect();
+        assert_eq!(polygons.len(), placements.len());
+        for (corners, p) in polygons.iter().zip(placements) {
+            assert_eq!(corners.len(), 4);
+            let xs: Vec<i64> = corners.iter().map(|c| c.0).collect();
+            let ys: Vec<i64> = corners.iter().map(|c| c.1).collect();
+            assert_eq!(*xs.iter().min().unwrap(), p.x as i64);
+            assert_eq!(*ys.iter().min().unwrap(), p.y as i64);
+            assert_eq!(*xs.iter().max().unwrap(), (p.x + p.rect.length) as i64);
+            assert_eq!(*ys.iter().max().unwrap(), (p.y + p.rect.width) as i64);
+            // Shoelace: positive twice-area means counter-clockwise
+            let twice_area: i64 = (0..4)
+                .map(|i| {
+                    let (a, b) = (corners[i], corners[(i + 1) % 4]);
+                    a.0 * b.1 - b.0 * a.1
+                })
+                .sum();
+            assert_eq!(twice_area, 2 * p.rect.area() as i64);
+        }
+    }
+
+    #[test]
+    fn test_tsv() {
+        let mut solution = sample();
+        solution.sheets[0].placements[1].label = Some("shelf\tleft".to_string());
+        let mut out = Vec::new();
+        solution.write_tsv(&mut out, Some("JOB7"), 0).unwrap();
+        let text = String::from_utf8(out).unwrap();
+
+        let blocks: Vec<Vec<Vec<&str>>> = text
+            .trim_end()
+            .split("\n\n")
+            .map(|b| b.lines().map(|l| l.split('\t').collect()).collect())
+            .collect();
+        assert_eq!(blocks.len(), solution.sheet_count() + 1);
+        for (block, sheet) in blocks.iter().zip(&solution.sheets) {
+            assert_eq!(block[0][1], "100x50");
+            assert_eq!(block[1][..3], ["piece", "x", "y"]);
+            assert_eq!(block.len(), sheet.placements.len() + 2);
+            for (row, p) in block[2..].iter().zip(&sheet.placements) {
+                assert_eq!(row.len(), block[1].len());
+                let num = |i: usize| row[i].parse::<u32>().unwrap();
+                assert_eq!((num(1), num(2)), (p.x, p.y));
+                assert_eq!((num(3), num(4)), (p.rect.length, p.rect.width));
+            }
+        }
+        assert_eq!(blocks[0][0][0], "JOB7-Sheet-1");
+        assert_eq!(
+            blocks[0][3],
+            ["2", "60", "0", "40", "20", "yes", "shelf left", "no"]
+        );
+        assert_eq!(
+            blocks[2],
+            [
+                vec!["Summary"],
+                vec!["stock", "sheets", "waste %"],
+                vec!["100x50", "2", "53.0"],
+                vec!["total", "2", "53.0"],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_ndjson() {
+        let mut out = Vec::new();
+        sample().write_ndjson(&mut out).unwrap();
+        let text = String::from_utf8(out).unwrap();
+        let lines: Vec<serde_json::Value> = text
+            .lines()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 3);
+        for (i, line) in lines[..2].iter().enumerate() {
+            assert_eq!(line["type"], "sheet");
+            assert_eq!(line["index"], i);
+            assert!(line["placements"].is_array());
+        }
+        assert_eq!(lines[1]["placements"][0]["rect"]["length"], 30);
+        let summary = &lines[2];
+        assert_eq!(summary["type"], "summary");
+        assert_eq!(summary["sheet_count"], 2);
+        assert_eq!(summary["stock_usage"][0]["count"], 2);
+        assert_eq!(summary["waste_percent"], 53.0);
+    }
+}
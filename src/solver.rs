@@ -1,7 +1,184 @@
-use crate::guillotine::{GuillotineBin, ScoreStrategy};
+use crate::guillotine::{FreeRect, GuillotineBin, PlaceUndo, ScoreStrategy, ScoredPlacement};
 use crate::types::{
-    CutDirection, Demand, Rect, RotationConstraint, SheetResult, Solution, StockGrain,
+    Anchor, AreaDemand, Axis, CutDirection, Demand, DirectionTieBreak, GrainConflict, Offcut,
+    PieceGrain, Placement, Rect, RotationConstraint, SheetResult, Solution, StockGrain, Unplaced,
+    Warning,
 };
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap};
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::time::Instant;
+
+/// Default for [`Solver::with_max_expanded_pieces`].
+pub const DEFAULT_MAX_EXPANDED_PIECES: u64 = 1_000_000;
+
+/// Why a solve was refused.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SolveError {
+    /// The demands add up to more pieces than the configured cap.
+    TooManyPieces { requested: u64, max: u64 },
+    /// An area demand's strip width is zero or fits the stock in neither
+    /// orientation.
+    InvalidStripWidth { width: u32 },
+    /// A demand's grain needs a rotation its `allow_rotate = false` forbids.
+    GrainConflict {
+        demand: usize,
+        conflict: GrainConflict,
+    },
+    /// A piece fits nowhere on an empty sheet (usable window, rounded
+    /// corners and clearance included).
+    PieceTooLarge { rect: Rect },
+    /// The deadline passed before the search finished.
+    DeadlineExceeded,
+    /// The kerf is as wide as the stock's shorter side (or wider): no cut
+    /// could leave anything on both sides of it.
+    KerfTooLarge { kerf: u32, stock: Rect },
+    /// The front and back grain of a demand need different orientations.
+    FaceGrainConflict { demand: usize },
+    /// A grid pitch of zero.
+    InvalidGridPitch { pitch: (u32, u32) },
+    /// A piece (or its clearance) is not a multiple of the grid pitch.
+    OffGrid { rect: Rect, pitch: (u32, u32) },
+    /// A first cut or strip boundary whose kerf runs past the far edge of
+    /// the stock.
+    CutOutsideStock { axis: Axis, position: u32 },
+}
+
+impl std::fmt::Display for SolveError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SolveError::TooManyPieces { requested, max } => write!(
+                f,
+                "too many pieces: {} requested, at most {} allowed",
+                requested, max
+            ),
+            SolveError::InvalidStripWidth { width } => {
+                write!(f, "strip width {} does not fit in stock", width)
+            }
+            SolveError::GrainConflict { demand, conflict } => {
+                write!(f, "demand {}: {}", demand, conflict)
+            }
+            SolveError::PieceTooLarge { rect } => {
+                write!(f, "piece {} does not fit in the usable sheet area", rect)
+            }
+            SolveError::DeadlineExceeded => write!(f, "solve deadline exceeded"),
+            SolveError::KerfTooLarge { kerf, stock } => write!(
+                f,
+                "kerf {} mm must be smaller than the shorter side of stock {} ({} mm)",
+                kerf,
+                stock,
+                stock.length.min(stock.width)
+            ),
+            SolveError::FaceGrainConflict { demand } => write!(
+                f,
+                "demand {}: front and back grain need different orientations",
+                demand
+            ),
+            SolveError::InvalidGridPitch { pitch } => {
+                write!(f, "grid pitch {}x{} must be non-zero", pitch.0, pitch.1)
+            }
+            SolveError::OffGrid { rect, pitch } => write!(
+                f,
+                "piece {} is not a multiple of the grid pitch {}x{}",
+                rect, pitch.0, pitch.1
+            ),
+            SolveError::CutOutsideStock { axis, position } => write!(
+                f,
+                "cut at {} mm across {:?} leaves no room for the kerf before the stock edge",
+                position, axis
+            ),
+        }
+    }
+}
+
+impl std::error::Error for SolveError {}
+
+/// Why a demand can't be placed, or not in full, found by
+/// [`Solver::diagnose`]. `demand` indexes the solver's demands.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Infeasibility {
+    /// Its grain (on either face) and `allow_rotate` agree on no
+    /// orientation.
+    GrainConflict { demand: usize, rect: Rect },
+    /// Too large for an empty sheet in either orientation (usable window,
+    /// rounded corners and clearance included).
+    Oversize { demand: usize, rect: Rect },
+    /// Fits an empty sheet only in an orientation its grain,
+    /// `allow_rotate` or the cut direction forbids.
+    GrainLocked { demand: usize, rect: Rect },
+    /// Fits, but going by area the `sheets` the caps allow its material
+    /// can't hold all its pieces once those picked first (by value, then
+    /// size) are in: some are likely left unplaced.
+    ExcludedByCap {
+        demand: usize,
+        rect: Rect,
+        sheets: usize,
+    },
+}
+
+impl Infeasibility {
+    pub fn demand(&self) -> usize {
+        match *self {
+            Infeasibility::GrainConflict { demand, .. }
+            | Infeasibility::Oversize { demand, .. }
+            | Infeasibility::GrainLocked { demand, .. }
+            | Infeasibility::ExcludedByCap { demand, .. } => demand,
+        }
+    }
+}
+
+impl std::fmt::Display for Infeasibility {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Infeasibility::GrainConflict { demand, rect } => write!(
+                f,
+                "demand {} ({}): grain and rotation settings allow no orientation",
+                demand, rect
+            ),
+            Infeasibility::Oversize { demand, rect } => write!(
+                f,
+                "demand {} ({}): does not fit an empty sheet in either orientation",
+                demand, rect
+            ),
+            Infeasibility::GrainLocked { demand, rect } => write!(
+                f,
+                "demand {} ({}): fits only in an orientation its grain or rotation settings forbid",
+                demand, rect
+            ),
+            Infeasibility::ExcludedByCap {
+                demand,
+                rect,
+                sheets,
+            } => write!(
+                f,
+                "demand {} ({}): not all pieces fit on the {} sheet(s) the caps allow",
+                demand, rect, sheets
+            ),
+        }
+    }
+}
+
+/// Rejects a kerf at least as wide as the stock's shorter side, which
+/// splits would otherwise silently clamp into a bogus layout.
+pub fn check_kerf(stock: Rect, kerf: u32) -> Result<(), SolveError> {
+    if kerf > 0 && kerf >= stock.length.min(stock.width) {
+        return Err(SolveError::KerfTooLarge { kerf, stock });
+    }
+    Ok(())
+}
+
+/// Callback invoked with each improved solution found during the search.
+pub type ImprovementCallback = Box<dyn Fn(&Solution) + Send + Sync>;
+
+/// Best solution found so far for a job, saved to resume a long solve with
+/// [`Solver::with_resume`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Checkpoint {
+    /// [`Solver::job_hash`] of the job it was found for.
+    pub job_hash: u64,
+    pub solution: Solution,
+}
 
 pub struct Solver {
     stock: Rect,
@@ -9,6 +186,116 @@ pub struct Solver {
     cut_direction: CutDirection,
     stock_grain: StockGrain,
     demands: Vec<Demand>,
+    parallel: bool,
+    size_tolerance: u32,
+    far_edge_waste: bool,
+    usable_window: Option<(u32, u32, Rect)>,
+    strip_threshold: Option<u32>,
+    on_improvement: Option<ImprovementCallback>,
+    symmetry_breaking: bool,
+    cluster_labels: bool,
+    max_expanded_pieces: u64,
+    area_demands: Vec<AreaDemand>,
+    anchor: Option<Anchor>,
+    deadline: Option<Instant>,
+    branch_and_bound: bool,
+    grain_penalty: Option<u64>,
+    reserve_offcut: Option<Rect>,
+    tight_fit_margin: u32,
+    corner_radius: u32,
+    fillers: Vec<Rect>,
+    first_cut: Option<(Axis, u32)>,
+    relax_grain_on_infeasible: bool,
+    grid_pitch: Option<(u32, u32)>,
+    back_grain: StockGrain,
+    defects: Vec<(u32, u32, Rect)>,
+    target_sheets: Option<usize>,
+    strategy_weights: Vec<(CutDirection, ScoreStrategy, u32)>,
+    strip_boundaries: Vec<u32>,
+    max_sheets: Option<usize>,
+    max_total_area: Option<u64>,
+    piece_values: HashMap<String, f64>,
+    max_cuts_per_sheet: Option<usize>,
+    allow_stock_rotation: bool,
+    template_sheet: bool,
+    square_offcuts: bool,
+    direction_tie_break: Option<DirectionTieBreak>,
+    single_orientation_per_sheet: bool,
+    resume: Option<Checkpoint>,
+}
+
+/// Branch and bound is skipped when [`Solver::bb_search_size`] is above
+/// this: about 22 different pieces, each free to turn.
+const BB_SEARCH_BUDGET: f64 = 2e13;
+/// Sheets each piece is counted as choosing between in
+/// [`Solver::bb_search_size`]; the lower bound prunes the rest.
+const BB_SEARCH_SHEETS: f64 = 2.0;
+
+/// Best solution found so far by branch and bound.
+struct BbSearch {
+    best_bins: Option<Vec<GuillotineBin>>,
+    best_count: usize,
+    /// Search nodes visited.
+    nodes: u64,
+    /// Drop set aside on every new bin.
+    reserve: Option<Rect>,
+}
+
+/// A single piece to place, expanded from a `Demand`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Piece {
+    rect: Rect,
+    rotation: RotationConstraint,
+    /// Size as requested by the demand, before tolerance snapping.
+    requested: Rect,
+    /// Extra gap reserved around the piece, `[left, right, top, bottom]`.
+    clearance: [u32; 4],
+    label: Option<String>,
+    directional: bool,
+    group_hint: Option<u32>,
+    fragile: bool,
+    /// Score penalty for placing the piece unrotated and rotated.
+    penalty: [u64; 2],
+}
+
+impl Piece {
+    /// Space the piece takes up in a bin, clearance included.
+    fn footprint(&self) -> Rect {
+        self.rect.with_clearance(self.clearance)
+    }
+
+    /// Best placement of the piece in `bin`, off-grain penalty included.
+    fn find_best_in(
+        &self,
+        bin: &GuillotineBin,
+        strategy: ScoreStrategy,
+    ) -> Option<ScoredPlacement> {
+        self.find_oriented(bin, self.rotation, strategy)
+    }
+
+    /// Best placement in `bin` within `rotation`, sheltered spots first if
+    /// the piece is fragile.
+    fn find_oriented(
+        &self,
+        bin: &GuillotineBin,
+        rotation: RotationConstraint,
+        strategy: ScoreStrategy,
+    ) -> Option<ScoredPlacement> {
+        if self.fragile {
+            bin.find_sheltered(self.footprint(), rotation, strategy, self.penalty)
+        } else {
+            bin.find_best_penalized(self.footprint(), rotation, strategy, self.penalty)
+        }
+    }
+
+    /// Whether `bin` holds a piece with the same group hint.
+    fn shares_hint_with(&self, bin: &GuillotineBin) -> bool {
+        self.group_hint.is_some()
+            && bin
+                .placements
+                .iter()
+                .any(|p| p.group_hint == self.group_hint)
+    }
 }
 
 impl Solver {
@@ -25,1332 +312,6474 @@ impl Solver {
             cut_direction,
             stock_grain,
             demands,
+            parallel: true,
+            size_tolerance: 0,
+            far_edge_waste: false,
+            usable_window: None,
+            strip_threshold: None,
+            on_improvement: None,
+            symmetry_breaking: true,
+            cluster_labels: false,
+            max_expanded_pieces: DEFAULT_MAX_EXPANDED_PIECES,
+            area_demands: Vec::new(),
+            anchor: None,
+            deadline: None,
+            branch_and_bound: true,
+            grain_penalty: None,
+            reserve_offcut: None,
+            tight_fit_margin: 0,
+            corner_radius: 0,
+            fillers: Vec::new(),
+            first_cut: None,
+            relax_grain_on_infeasible: false,
+            grid_pitch: None,
+            back_grain: StockGrain::None,
+            defects: Vec::new(),
+            target_sheets: None,
+            strategy_weights: Vec::new(),
+            strip_boundaries: Vec::new(),
+            max_sheets: None,
+            max_total_area: None,
+            piece_values: HashMap::new(),
+            max_cuts_per_sheet: None,
+            allow_stock_rotation: false,
+            template_sheet: false,
+            square_offcuts: false,
+            direction_tie_break: None,
+            single_orientation_per_sheet: false,
+            resume: None,
         }
     }
 
-    pub fn solve(&self) -> Solution {
-        let pieces = self.expand_demands();
-        if pieces.is_empty() {
-            return Solution {
-                sheets: vec![],
-                stock: self.stock,
-            };
-        }
+    /// Catalog of small standard parts (shims, spacers, ...) that can always
+    /// be used. Once every demand is placed, as many fillers as fit are cut
+    /// from each sheet's offcuts, largest first, flagged
+    /// [`Placement::is_filler`]. Fillers never change where demands go.
+    pub fn with_fillers(mut self, fillers: Vec<Rect>) -> Self {
+        self.fillers = fillers;
+        self
+    }
 
-        // Greedy phase: try multiple strategies, keep best
-        let greedy = self.greedy_best(&pieces);
+    /// Force the first cut of every sheet: an edge-to-edge cut across `axis`
+    /// whose kerf starts at `position` (from the stock's origin), e.g. to
+    /// break sheets down to fit a panel saw. No piece straddles it.
+    pub fn with_first_cut(mut self, first_cut: Option<(Axis, u32)>) -> Self {
+        self.first_cut = first_cut;
+        self
+    }
 
-        // B&B phase: try to improve on greedy
-        let bb = self.branch_and_bound(&pieces, greedy.sheets.len());
+    /// Strip cuts the shop makes on every sheet before crosscutting, as
+    /// positions where each kerf starts: y positions (strips along the
+    /// length), or x positions when the cut direction is `AlongWidth`.
+    /// Pieces never straddle them.
+    pub fn with_strip_boundaries(mut self, positions: Vec<u32>) -> Self {
+        self.strip_boundaries = positions;
+        self
+    }
 
-        if !bb.sheets.is_empty() && bb.sheets.len() < greedy.sheets.len() {
-            bb
-        } else {
-            greedy
-        }
+    /// Sheets with rounded corners: a `radius` square at each corner of the
+    /// stock is left unused. 0 (the default) means square corners.
+    pub fn with_corner_radius(mut self, radius: u32) -> Self {
+        self.corner_radius = radius;
+        self
     }
 
-    fn expand_demands(&self) -> Vec<(Rect, RotationConstraint)> {
-        let mut pieces = Vec::new();
-        for d in &self.demands {
-            let rotation =
-                RotationConstraint::from_grain(self.stock_grain, d.grain, d.allow_rotate)
-                    .with_cut_direction(self.cut_direction, d.rect);
-            for _ in 0..d.qty {
-                pieces.push((d.rect, rotation));
-            }
-        }
-        // Sort by area descending for better packing
-        pieces.sort_by(|a, b| b.0.area().cmp(&a.0.area()));
-        pieces
+    /// Grain of the back face, for sheets laminated on both sides: pieces
+    /// must then also match their [`Demand::back_grain`] against it. A piece
+    /// whose two faces need different orientations is a
+    /// [`SolveError::FaceGrainConflict`].
+    pub fn with_back_grain(mut self, back_grain: StockGrain) -> Self {
+        self.back_grain = back_grain;
+        self
     }
 
-    fn greedy_best(&self, pieces: &[(Rect, RotationConstraint)]) -> Solution {
-        let strategies = [
-            ScoreStrategy::BestAreaFit,
-            ScoreStrategy::BestShortSideFit,
-            ScoreStrategy::BestLongSideFit,
-        ];
+    /// Flawed zones, as `(x, y, size)` from the stock's origin, that no
+    /// piece may cover. A defect on either face rules out the zone through
+    /// the sheet, so back-face defects go here too, in front-face
+    /// coordinates.
+    pub fn with_defects(mut self, defects: Vec<(u32, u32, Rect)>) -> Self {
+        self.defects = defects;
+        self
+    }
 
-        // In Auto mode, try both directions and keep the best result
-        let directions = match self.cut_direction {
-            CutDirection::Auto => vec![CutDirection::AlongLength, CutDirection::AlongWidth],
-            dir => vec![dir],
-        };
+    /// Align every piece to a grid of `(along length, along width)` pitch,
+    /// e.g. for pegboard drilling: placements start on multiples of the
+    /// pitch (from the anchor corner), each cut leaving the kerf and the
+    /// rest of the grid cell behind. Piece sides (in an orientation they
+    /// may take) and clearances must be multiples of the pitch, see
+    /// [`SolveError::OffGrid`]. Area strips and fillers are not aligned.
+    pub fn with_grid_pitch(mut self, pitch: Option<(u32, u32)>) -> Self {
+        self.grid_pitch = pitch;
+        self
+    }
 
-        let mut best: Option<Solution> = None;
-        for &dir in &directions {
-            for &strategy in &strategies {
-                let sol = self.greedy_solve(pieces, strategy, dir);
-                let dominated = match &best {
-                    None => false,
-                    Some(prev) => {
-                        let prev_n = prev.sheets.len();
-                        let sol_n = sol.sheets.len();
-                        if sol_n < prev_n {
-                            false
-                        } else if sol_n > prev_n {
-                            true
-                        } else {
-                            // Same sheet count: prefer more compact last sheet
-                            Self::last_sheet_bounding_area(&sol)
-                                >= Self::last_sheet_bounding_area(prev)
-                        }
-                    }
-                };
-                if !dominated {
-                    best = Some(sol);
-                }
-            }
-        }
-        best.unwrap()
+    /// Rather than fail when a demand's grain leaves it fitting nowhere on
+    /// the sheet, drop its grain constraint (rotation then only follows
+    /// `allow_rotate`) and report it as [`Warning::GrainRelaxed`]. Demands
+    /// that fit with their grain are left alone.
+    pub fn with_relax_grain_on_infeasible(mut self, relax: bool) -> Self {
+        self.relax_grain_on_infeasible = relax;
+        self
     }
 
-    fn greedy_solve(
-        &self,
-        pieces: &[(Rect, RotationConstraint)],
-        strategy: ScoreStrategy,
-        direction: CutDirection,
-    ) -> Solution {
-        let mut bins: Vec<GuillotineBin> = Vec::new();
+    /// Warn about pieces that end less than `margin` away from the far edge
+    /// of the sheet (exact fits included) with no other piece in between,
+    /// as [`Warning::TightFit`] on [`Solution::warnings`]. Purely advisory;
+    /// 0 (the default) disables the check.
+    pub fn with_tight_fit_margin(mut self, margin: u32) -> Self {
+        self.tight_fit_margin = margin;
+        self
+    }
+
+    /// Keep a `drop`-sized offcut (either orientation) intact on each sheet,
+    /// reported in [`SheetResult::reserved`]. The drop is set aside before
+    /// packing; when that costs extra sheets, it is only carved out of the
+    /// leftover space of the sheets where it still fits.
+    pub fn with_reserve_offcut(mut self, drop: Option<Rect>) -> Self {
+        self.reserve_offcut = drop;
+        self
+    }
 
-        for &(piece, rotation) in pieces {
-            // Try to fit in existing bins
-            let mut best_bin = None;
-            let mut best_score = None;
+    /// Soft grain mode: instead of forbidding or forcing rotation, grain
+    /// only adds `penalty` to the fit score of off-grain placements, so the
+    /// solver can turn a piece against the grain when that saves material.
+    /// `Some(0)` ignores grain entirely; `None` (the default) keeps grain a
+    /// hard constraint.
+    pub fn with_grain_penalty(mut self, penalty: Option<u64>) -> Self {
+        self.grain_penalty = penalty;
+        self
+    }
 
-            for (bi, bin) in bins.iter().enumerate() {
-                if let Some(scored) = bin.find_best(piece, rotation, strategy)
-                    && (best_score.is_none() || scored.score < best_score.unwrap())
-                {
-                    best_bin = Some(bi);
-                    best_score = Some(scored.score);
-                }
-            }
+    /// Give up with [`SolveError::DeadlineExceeded`] once `deadline` passes.
+    /// Checked between greedy runs and at every branch-and-bound node.
+    pub fn with_deadline(mut self, deadline: Option<Instant>) -> Self {
+        self.deadline = deadline;
+        self
+    }
 
-            if let Some(bi) = best_bin {
-                let scored = bins[bi].find_best(piece, rotation, strategy).unwrap();
-                bins[bi].place(scored, piece);
-            } else {
-                // Open new bin
-                let mut bin = GuillotineBin::new(self.stock, self.kerf, direction);
-                let scored = bin
-                    .find_best(piece, rotation, strategy)
-                    .expect("piece larger than stock");
-                bin.place(scored, piece);
-                bins.push(bin);
-            }
-        }
+    /// Good enough is enough, e.g. for quoting: branch and bound stops at
+    /// the first solution with at most `target` sheets (per material), and
+    /// is skipped when the greedy phase already meets it. The result may
+    /// then use more sheets than the optimum.
+    pub fn with_target_sheets(mut self, target: Option<usize>) -> Self {
+        self.target_sheets = target;
+        self
+    }
 
-        self.bins_to_solution(bins)
+    /// Use at most `max` sheets (per material). When the job needs more,
+    /// pieces are picked by value (see [`with_piece_values`](Self::with_piece_values)),
+    /// then by size, and those that don't fit are listed in
+    /// [`Solution::unplaced`].
+    pub fn with_max_sheets(mut self, max: Option<usize>) -> Self {
+        self.max_sheets = max;
+        self
     }
 
-    fn bb_directions(&self) -> Vec<CutDirection> {
-        match self.cut_direction {
-            CutDirection::Auto => vec![CutDirection::AlongLength, CutDirection::AlongWidth],
-            dir => vec![dir],
-        }
+    /// Budget on the stock area of all sheets together, e.g. when material
+    /// is bought by the square metre: no sheet is opened past it, and the
+    /// pieces left out are listed in [`Solution::unplaced`] as with
+    /// [`with_max_sheets`](Self::with_max_sheets). Unlike that cap, the
+    /// budget is shared by all materials, which draw from it in order.
+    /// Strips of area demands are not counted against it.
+    pub fn with_max_total_area(mut self, area: Option<u64>) -> Self {
+        self.max_total_area = area;
+        self
     }
 
-    fn branch_and_bound(
-        &self,
-        pieces: &[(Rect, RotationConstraint)],
-        upper_bound: usize,
-    ) -> Solution {
-        // Skip B&B for large inputs (too slow)
-        if pieces.len() > 20 {
-            return Solution {
-                sheets: vec![],
-                stock: self.stock,
-            };
+    /// Most sheets a material may use once `used` sheets are taken.
+    fn sheet_cap(&self, used: usize) -> Option<usize> {
+        let budget = self.max_total_area.map(|area| {
+            let sheets = area.checked_div(self.stock.area()).unwrap_or(0);
+            (sheets as usize).saturating_sub(used)
+        });
+        match (self.max_sheets, budget) {
+            (Some(max), Some(budget)) => Some(max.min(budget)),
+            (max, budget) => max.or(budget),
         }
+    }
 
-        let mut best_bins: Option<Vec<GuillotineBin>> = None;
-        let mut best_count = upper_bound;
+    /// Most cuts a sheet's cut plan may have (see [`CutNode::cut_count`]),
+    /// for saws that can only store so many. Pieces that would take a sheet
+    /// past it go to another sheet instead, so the job may need more sheets.
+    /// A sheet always takes its first piece, even if that alone needs more
+    /// cuts. Not applied by [`solve_fixed`](Self::solve_fixed).
+    ///
+    /// [`CutNode::cut_count`]: crate::cut_tree::CutNode::cut_count
+    pub fn with_max_cuts_per_sheet(mut self, max: Option<usize>) -> Self {
+        self.max_cuts_per_sheet = max;
+        self
+    }
 
-        let bins: Vec<GuillotineBin> = vec![];
-        self.bb_recurse(pieces, 0, bins, &mut best_bins, &mut best_count);
+    /// Let each new sheet be fed turned a quarter, stock length along y,
+    /// when that places its first piece better or is the only way it fits.
+    /// Only for plain sheets: ignored with a stock grain, a back grain, a
+    /// reserved offcut, a usable window, rounded corners, defects, a first
+    /// cut, strip boundaries or a grid pitch, which are all given in the
+    /// stock's own frame. Sheets fed this way have
+    /// [`SheetResult::stock_rotated`] set and the turned size as `stock`.
+    pub fn with_allow_stock_rotation(mut self, allow: bool) -> Self {
+        self.allow_stock_rotation = allow;
+        self
+    }
 
-        match best_bins {
-            Some(bins) => self.bins_to_solution(bins),
-            None => Solution {
-                sheets: vec![],
-                stock: self.stock,
-            },
-        }
+    /// Lay out every sheet holding the same pieces as an earlier one exactly
+    /// like that first sheet, so jigs set up for it fit them all. Pieces are
+    /// the same when they have the same size, label and options; sheets must
+    /// also share stock and material. Only changes sheets the packer laid
+    /// out differently, never the sheet count.
+    pub fn with_template_sheet(mut self, template: bool) -> Self {
+        self.template_sheet = template;
+        self
     }
 
-    fn bb_recurse(
-        &self,
-        pieces: &[(Rect, RotationConstraint)],
-        idx: usize,
-        bins: Vec<GuillotineBin>,
-        best_bins: &mut Option<Vec<GuillotineBin>>,
-        best_count: &mut usize,
-    ) {
-        if idx == pieces.len() {
-            if bins.len() < *best_count {
-                *best_count = bins.len();
-                *best_bins = Some(bins);
+    /// Keep every piece of a sheet either unrotated or rotated, not both,
+    /// to make grain matching on the sheet easier. A sheet takes the
+    /// orientation of its first piece; pieces that can't be cut that way go
+    /// to another sheet, so the job may need more sheets. Fillers follow the
+    /// sheet's orientation too.
+    pub fn with_single_orientation_per_sheet(mut self, single: bool) -> Self {
+        self.single_orientation_per_sheet = single;
+        self
+    }
+
+    /// Orientation every further piece on a sheet holding `placements` must
+    /// have: that of the first one with
+    /// [`with_single_orientation_per_sheet`](Self::with_single_orientation_per_sheet),
+    /// `Free` otherwise.
+    fn sheet_orientation(&self, placements: &[Placement]) -> RotationConstraint {
+        match placements.first() {
+            Some(first) if self.single_orientation_per_sheet => {
+                if first.rotated {
+                    RotationConstraint::ForceRotate
+                } else {
+                    RotationConstraint::NoRotate
+                }
             }
-            return;
+            _ => RotationConstraint::Free,
         }
+    }
 
-        // Pruning: if current bins already >= best, no point continuing
-        if bins.len() >= *best_count {
-            return;
+    /// Best placement of `piece` in `bin`, in the sheet's orientation.
+    fn find_best_on_sheet(
+        &self,
+        piece: &Piece,
+        bin: &GuillotineBin,
+        strategy: ScoreStrategy,
+    ) -> Option<ScoredPlacement> {
+        let rotation = piece
+            .rotation
+            .intersect(self.sheet_orientation(&bin.placements))?;
+        piece.find_oriented(bin, rotation, strategy)
+    }
+
+    /// Whether placing `piece` at `scored` keeps `bin` within the cut limit.
+    /// The bin is left as it was.
+    fn fits_cut_limit(
+        &self,
+        bin: &mut GuillotineBin,
+        scored: ScoredPlacement,
+        piece: &Piece,
+    ) -> bool {
+        let Some(max) = self.max_cuts_per_sheet else {
+            return true;
+        };
+        if bin.placements.is_empty() {
+            return true;
         }
+        let undo = Self::place_piece_undoable(bin, scored, piece);
+        let fits = bin.cut_count().is_none_or(|cuts| cuts <= max);
+        bin.unplace(undo);
+        fits
+    }
 
-        let (piece, rotation) = pieces[idx];
+    /// Value of each piece by label, for choosing what to cut under
+    /// [`with_max_sheets`](Self::with_max_sheets): the placed value is
+    /// maximized rather than the piece count. Unlisted pieces are worth 0.
+    pub fn with_piece_values(mut self, values: HashMap<String, f64>) -> Self {
+        self.piece_values = values;
+        self
+    }
 
-        // Lower bound: remaining area / stock area
-        let remaining_area: u64 = pieces[idx..].iter().map(|(r, _)| r.area()).sum();
-        let stock_area = self.stock.area();
-        let min_extra_bins = if remaining_area > 0 {
-            remaining_area.div_ceil(stock_area) as usize
-        } else {
-            0
-        };
-        let open_free_area: u64 = bins
+    fn piece_value(&self, piece: &Piece) -> f64 {
+        self.label_value(piece.label.as_ref())
+    }
+
+    fn label_value(&self, label: Option<&String>) -> f64 {
+        label
+            .and_then(|label| self.piece_values.get(label))
+            .copied()
+            .unwrap_or(0.0)
+    }
+
+    fn meets_target(&self, sheets: usize) -> bool {
+        self.target_sheets.is_some_and(|t| sheets <= t)
+    }
+
+    /// How often each greedy combination won on past, similar jobs. When
+    /// set, the greedy phase runs sequentially, heaviest combination first
+    /// (unlisted ones weigh 0), and stops as soon as one reaches the area
+    /// lower bound, since nothing can beat it.
+    pub fn with_strategy_weights(
+        mut self,
+        weights: Vec<(CutDirection, ScoreStrategy, u32)>,
+    ) -> Self {
+        self.strategy_weights = weights;
+        self
+    }
+
+    fn strategy_weight(&self, direction: CutDirection, strategy: ScoreStrategy) -> u32 {
+        self.strategy_weights
             .iter()
-            .flat_map(|b| &b.free_rects)
-            .map(|f| f.rect.area())
-            .sum();
-        let needed = if remaining_area > open_free_area {
-            bins.len() + (remaining_area - open_free_area).div_ceil(stock_area) as usize
-        } else {
-            bins.len()
-        };
-        let lower_bound = std::cmp::max(
-            needed,
-            bins.len()
-                .saturating_add(min_extra_bins.saturating_sub(bins.len())),
-        );
+            .find(|&&(d, s, _)| d == direction && s == strategy)
+            .map_or(0, |&(_, _, weight)| weight)
+    }
 
-        if lower_bound >= *best_count {
-            return;
-        }
+    /// Run the branch-and-bound improvement phase after the greedy one
+    /// (enabled by default). Disable for a faster, greedy-only answer.
+    pub fn with_branch_and_bound(mut self, branch_and_bound: bool) -> Self {
+        self.branch_and_bound = branch_and_bound;
+        self
+    }
 
-        // Try placing in each existing bin
-        for bi in 0..bins.len() {
-            let orientations: &[bool] = match rotation {
-                RotationConstraint::Free if piece.length != piece.width => &[false, true],
-                RotationConstraint::ForceRotate => &[true],
-                _ => &[false],
-            };
+    fn expired(&self) -> bool {
+        self.deadline.is_some_and(|d| Instant::now() >= d)
+    }
 
-            for &rotated in orientations {
-                let try_piece = if rotated { piece.rotated() } else { piece };
-                let strategy = ScoreStrategy::BestAreaFit;
+    /// Every demand that can't be placed, or not in full, with the reason,
+    /// in demand order: grain conflicts, pieces too large for the sheet
+    /// with or without their grain, and pieces the sheet caps
+    /// ([`with_max_sheets`](Self::with_max_sheets),
+    /// [`with_max_total_area`](Self::with_max_total_area)) leave no room
+    /// for. Needs no search, so the cap check goes by area only, and a
+    /// shared area budget is assumed to be drawn down by the earlier
+    /// materials' area lower bounds.
+    pub fn diagnose(&self) -> Vec<Infeasibility> {
+        let (demands, _) = self.relax_infeasible_grain();
+        let empty = self.new_bin(CutDirection::Auto, 0, None);
+        let turned = self.new_turned_bin(CutDirection::Auto, 0, None);
+        let fits = |piece: &Piece| {
+            std::iter::once(&empty).chain(&turned).any(|bin| {
+                piece
+                    .find_best_in(bin, ScoreStrategy::BestAreaFit)
+                    .is_some()
+            })
+        };
 
-                if let Some(scored) =
-                    bins[bi].find_best(try_piece, RotationConstraint::NoRotate, strategy)
-                {
-                    let mut new_bins = bins.clone();
-                    new_bins[bi].place(scored, try_piece);
-                    self.bb_recurse(pieces, idx + 1, new_bins, best_bins, best_count);
-                }
+        let mut found = Vec::new();
+        for (demand, d) in demands.iter().enumerate() {
+            let rect = d.rect;
+            // Soft grain never restricts rotation
+            if self.grain_penalty.is_none() && self.strict_rotation(d).is_none() {
+                found.push(Infeasibility::GrainConflict { demand, rect });
+                continue;
+            }
+            let one = Demand {
+                qty: 1,
+                ..d.clone()
+            };
+            let Some(piece) = self.expand_demands(&[one]).pop() else {
+                continue;
+            };
+            if fits(&piece) {
+                continue;
             }
+            let free = Piece {
+                rotation: RotationConstraint::Free,
+                ..piece
+            };
+            found.push(if fits(&free) {
+                Infeasibility::GrainLocked { demand, rect }
+            } else {
+                Infeasibility::Oversize { demand, rect }
+            });
         }
 
-        // Try opening a new bin (only if it wouldn't exceed best)
-        if bins.len() + 1 < *best_count {
-            for &dir in &self.bb_directions() {
-                let mut new_bins = bins.clone();
-                let mut new_bin = GuillotineBin::new(self.stock, self.kerf, dir);
-                let scored = new_bin.find_best(piece, rotation, ScoreStrategy::BestAreaFit);
-                if let Some(scored) = scored {
-                    new_bin.place(scored, piece);
-                    new_bins.push(new_bin);
-                    self.bb_recurse(pieces, idx + 1, new_bins, best_bins, best_count);
+        let usable = self.usable_rect().area() as u128;
+        let mut used = 0;
+        for (material, _) in Self::material_partitions(&demands) {
+            let mut members: Vec<usize> = (0..demands.len())
+                .filter(|&i| demands[i].material == material)
+                .filter(|&i| !found.iter().any(|f| f.demand() == i))
+                .collect();
+            let area = |i: usize| demands[i].rect.area() as u128;
+            let total: u128 = members
+                .iter()
+                .map(|&i| area(i) * demands[i].qty as u128)
+                .sum();
+            let cap = self.sheet_cap(used);
+            if usable > 0 {
+                used += total.div_ceil(usable) as usize;
+            }
+            let Some(sheets) = cap else {
+                continue;
+            };
+            // The capped solve's pick order: by value, then largest first
+            let value = |i: usize| self.label_value(demands[i].label.as_ref());
+            members.sort_by(|&a, &b| value(b).total_cmp(&value(a)).then(area(b).cmp(&area(a))));
+            let capacity = usable * sheets as u128;
+            let mut taken = 0;
+            for i in members {
+                taken += area(i) * demands[i].qty as u128;
+                if taken > capacity {
+                    found.push(Infeasibility::ExcludedByCap {
+                        demand: i,
+                        rect: demands[i].rect,
+                        sheets,
+                    });
                 }
             }
         }
+        found.sort_by_key(Infeasibility::demand);
+        found
     }
 
-    /// Bounding box area of the last sheet's placements.
-    /// Used as tiebreaker: smaller means more compact layout.
-    fn last_sheet_bounding_area(sol: &Solution) -> u64 {
-        sol.sheets.last().map_or(0, |sheet| {
-            let max_x = sheet
-                .placements
-                .iter()
-                .map(|p| p.x + p.rect.length)
-                .max()
-                .unwrap_or(0);
-            let max_y = sheet
-                .placements
-                .iter()
-                .map(|p| p.y + p.rect.width)
-                .max()
-                .unwrap_or(0);
-            max_x as u64 * max_y as u64
-        })
-    }
-
-    fn bins_to_solution(&self, bins: Vec<GuillotineBin>) -> Solution {
-        let stock_area = self.stock.area();
-        let sheets = bins
-            .into_iter()
-            .map(|bin| {
-                let used = bin.used_area();
-                SheetResult {
-                    placements: bin.placements,
-                    waste_area: stock_area - used,
+    /// Quick lower bound on the sheet count: total piece area over usable
+    /// sheet area, per material. Needs no search.
+    pub fn lower_bound_sheets(&self) -> usize {
+        let usable = self.usable_rect();
+        if usable.area() == 0 {
+            return 0;
+        }
+        let mut partitions = Self::material_partitions(&self.demands);
+        let strips_area: u128 = self.area_demands.iter().map(|a| a.total_area as u128).sum();
+        if strips_area > 0 && !partitions.iter().any(|(m, _)| m.is_none()) {
+            partitions.push((None, vec![]));
+        }
+        partitions
+            .iter()
+            .map(|(material, demands)| {
+                let mut area: u128 = demands
+                    .iter()
+                    .map(|d| d.rect.area() as u128 * d.qty as u128)
+                    .sum();
+                if material.is_none() {
+                    area += strips_area;
                 }
+                area.div_ceil(usable.area() as u128) as usize
             })
-            .collect();
+            .sum()
+    }
 
-        Solution {
-            sheets,
-            stock: self.stock,
+    /// Part of the stock pieces may go on: the usable window, clipped to
+    /// the sheet, or the whole sheet.
+    fn usable_rect(&self) -> Rect {
+        match self.usable_window {
+            Some((x, y, window)) => Rect::new(
+                window.length.min(self.stock.length.saturating_sub(x)),
+                window.width.min(self.stock.width.saturating_sub(y)),
+            ),
+            None => self.stock,
         }
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::types::{Demand, PieceGrain, Placement, StockGrain};
+    /// Area lower bound on the sheets `pieces` need.
+    fn pieces_lower_bound(&self, pieces: &[Piece]) -> usize {
+        let usable = self.usable_rect().area() as u128;
+        if usable == 0 {
+            return 0;
+        }
+        let area: u128 = pieces.iter().map(|p| p.rect.area() as u128).sum();
+        area.div_ceil(usable) as usize
+    }
 
-    /// Validates a complete solution:
-    /// 1. Every placement fits within the stock dimensions
-    /// 2. No two placements on the same sheet overlap
-    /// 3. The total number of placed pieces matches expectations
-    fn assert_solution_valid(sol: &Solution, expected_pieces: usize) {
-        let stock = sol.stock;
-        let total_placed: usize = sol.sheets.iter().map(|s| s.placements.len()).sum();
-        assert_eq!(
-            total_placed, expected_pieces,
-            "expected {} pieces placed, got {}",
-            expected_pieces, total_placed
-        );
+    /// Smallest stock (by area, then squarest) on which the greedy packer
+    /// fits every demand on a single sheet, or `None` without pieces.
+    ///
+    /// Candidate sides are the lengths a row of pieces can add up to
+    /// (kerfs included). For each length, the smallest width that works is
+    /// found by bisection, each step being a full greedy solve, so this is
+    /// meant for small jobs.
+    pub fn minimal_single_sheet(demands: &[Demand], kerf: u32) -> Option<Rect> {
+        let footprints: Vec<(Rect, bool, u32)> = demands
+            .iter()
+            .filter(|d| d.qty > 0)
+            .map(|d| (d.rect.with_clearance(d.clearance), d.can_rotate(), d.qty))
+            .collect();
+        if footprints.is_empty() {
+            return None;
+        }
+        let area: u64 = footprints
+            .iter()
+            .map(|(r, _, qty)| r.area() * *qty as u64)
+            .sum();
+        // Each piece must fit on its own, turned if allowed
+        let min_side = |side: fn(&Rect) -> u32| {
+            footprints
+                .iter()
+                .map(|(r, rotate, _)| {
+                    if *rotate {
+                        r.length.min(r.width)
+                    } else {
+                        side(r)
+                    }
+                })
+                .max()
+                .unwrap_or(0)
+        };
+        let (min_length, min_width) = (min_side(|r| r.length), min_side(|r| r.width));
+        let sides = row_lengths(&footprints, kerf);
 
-        for (si, sheet) in sol.sheets.iter().enumerate() {
-            for (pi, p) in sheet.placements.iter().enumerate() {
-                // Check bounds
-                assert!(
-                    p.x + p.rect.length <= stock.length,
-                    "sheet {si}, piece {pi} ({}) exceeds stock length: x={} + length={} > {}",
-                    p.rect,
-                    p.x,
-                    p.rect.length,
-                    stock.length
-                );
-                assert!(
-                    p.y + p.rect.width <= stock.width,
-                    "sheet {si}, piece {pi} ({}) exceeds stock width: y={} + width={} > {}",
-                    p.rect,
-                    p.y,
-                    p.rect.width,
-                    stock.width
-                );
+        let fits = |length: u32, width: u32| {
+            Solver::new(
+                Rect::new(length, width),
+                kerf,
+                CutDirection::Auto,
+                StockGrain::None,
+                demands.to_vec(),
+            )
+            .with_branch_and_bound(false)
+            .try_solve()
+            .is_ok_and(|sol| sol.sheets.len() == 1)
+        };
+
+        let mut best: Option<Rect> = None;
+        for &length in sides.iter().filter(|&&l| l >= min_length) {
+            let floor = area.div_ceil(length as u64).max(min_width as u64);
+            if best.is_some_and(|b| length as u64 * floor > b.area()) {
+                break;
+            }
+            let widths: Vec<u32> = sides
+                .iter()
+                .copied()
+                .filter(|&w| w as u64 >= floor)
+                .collect();
+            // First width that fits, assuming fitting is monotonic in width
+            let (mut lo, mut hi) = (0, widths.len());
+            while lo < hi {
+                let mid = (lo + hi) / 2;
+                if fits(length, widths[mid]) {
+                    hi = mid;
+                } else {
+                    lo = mid + 1;
+                }
+            }
+            let Some(&width) = widths.get(lo) else {
+                continue;
+            };
+            let candidate = Rect::new(length, width);
+            let better = match best {
+                None => true,
+                Some(b) => {
+                    (candidate.area(), length.max(width)) < (b.area(), b.length.max(b.width))
+                }
+            };
+            if better {
+                best = Some(candidate);
             }
+        }
+        best
+    }
 
-            // Check no overlaps between any pair of placements
-            assert_no_overlaps(si, &sheet.placements);
+    /// Hash of the job, for caching solutions: the stock, the demands and
+    /// every option that can change the result. Demand order doesn't
+    /// matter, and demands differing only in quantity are merged, so
+    /// logically identical jobs hash the same. Stable within one build only.
+    pub fn job_hash(&self) -> u64 {
+        // Destructured so a new option can't be forgotten here
+        let Self {
+            stock,
+            kerf,
+            cut_direction,
+            stock_grain,
+            demands,
+            parallel: _,
+            size_tolerance,
+            far_edge_waste,
+            usable_window,
+            strip_threshold,
+            on_improvement: _,
+            symmetry_breaking,
+            cluster_labels,
+            max_expanded_pieces,
+            area_demands,
+            anchor,
+            deadline: _,
+            branch_and_bound,
+            grain_penalty,
+            reserve_offcut,
+            tight_fit_margin,
+            corner_radius,
+            fillers,
+            first_cut,
+            relax_grain_on_infeasible,
+            grid_pitch,
+            back_grain,
+            defects,
+            target_sheets,
+            strategy_weights,
+            strip_boundaries,
+            max_sheets,
+            max_total_area,
+            piece_values,
+            max_cuts_per_sheet,
+            allow_stock_rotation,
+            template_sheet,
+            square_offcuts,
+            direction_tie_break,
+            single_orientation_per_sheet,
+            resume: _,
+        } = self;
+
+        // Total quantity per demand, keyed by the hash of everything else
+        let mut quantities: BTreeMap<u64, u64> = BTreeMap::new();
+        for d in demands {
+            let mut hasher = DefaultHasher::new();
+            Demand {
+                qty: 0,
+                ..d.clone()
+            }
+            .hash(&mut hasher);
+            *quantities.entry(hasher.finish()).or_default() += d.qty as u64;
+        }
+        let mut strip_areas: BTreeMap<u32, u64> = BTreeMap::new();
+        for ad in area_demands {
+            let area = strip_areas.entry(ad.width).or_default();
+            *area = area.saturating_add(ad.total_area);
         }
+
+        let mut hasher = DefaultHasher::new();
+        (stock, kerf, cut_direction, stock_grain).hash(&mut hasher);
+        quantities.hash(&mut hasher);
+        strip_areas.hash(&mut hasher);
+        (
+            size_tolerance,
+            far_edge_waste,
+            square_offcuts,
+            usable_window,
+            strip_threshold,
+        )
+            .hash(&mut hasher);
+        (
+            symmetry_breaking,
+            cluster_labels,
+            max_expanded_pieces,
+            anchor,
+        )
+            .hash(&mut hasher);
+        (
+            branch_and_bound,
+            grain_penalty,
+            reserve_offcut,
+            tight_fit_margin,
+        )
+            .hash(&mut hasher);
+        (
+            corner_radius,
+            fillers,
+            first_cut,
+            relax_grain_on_infeasible,
+            grid_pitch,
+        )
+            .hash(&mut hasher);
+        (
+            back_grain,
+            defects,
+            target_sheets,
+            strategy_weights,
+            strip_boundaries,
+        )
+            .hash(&mut hasher);
+        let mut values: Vec<(&String, u64)> =
+            piece_values.iter().map(|(k, v)| (k, v.to_bits())).collect();
+        values.sort_unstable();
+        (
+            max_sheets,
+            max_total_area,
+            values,
+            max_cuts_per_sheet,
+            allow_stock_rotation,
+            template_sheet,
+            single_orientation_per_sheet,
+            direction_tie_break,
+        )
+            .hash(&mut hasher);
+        hasher.finish()
     }
 
-    fn assert_no_overlaps(sheet_idx: usize, placements: &[Placement]) {
-        for i in 0..placements.len() {
-            for j in (i + 1)..placements.len() {
-                let a = &placements[i];
-                let b = &placements[j];
+    /// Pull pieces toward `anchor`: on top of any strategy, ties go to the
+    /// free space nearest that corner, so the first (largest) piece of each
+    /// sheet sits in it.
+    pub fn with_anchor(mut self, anchor: Option<Anchor>) -> Self {
+        self.anchor = anchor;
+        self
+    }
 
-                let a_x_end = a.x + a.rect.length;
-                let a_y_end = a.y + a.rect.width;
-                let b_x_end = b.x + b.rect.length;
-                let b_y_end = b.y + b.rect.width;
+    /// Strips to cut by total area once the regular demands are placed.
+    /// They fill the leftover space of sheets without a material first,
+    /// then extra sheets as needed.
+    pub fn with_area_demands(mut self, area_demands: Vec<AreaDemand>) -> Self {
+        self.area_demands = area_demands;
+        self
+    }
 
-                let overlaps = a.x < b_x_end && b.x < a_x_end && a.y < b_y_end && b.y < a_y_end;
+    /// Refuse to solve when the demand quantities add up to more than
+    /// `max` pieces, instead of expanding them all in memory.
+    pub fn with_max_expanded_pieces(mut self, max: u64) -> Self {
+        self.max_expanded_pieces = max;
+        self
+    }
 
-                assert!(
-                    !overlaps,
-                    "sheet {sheet_idx}: piece {i} ({} @ ({},{})) overlaps piece {j} ({} @ ({},{}))",
-                    a.rect, a.x, a.y, b.rect, b.x, b.y
-                );
+    /// After solving, swap same-size pieces within each sheet so pieces
+    /// sharing a label sit close together and can be picked up as a stack.
+    /// Only the labels move: the layout and sheet count are unchanged.
+    pub fn with_cluster_labels(mut self, cluster_labels: bool) -> Self {
+        self.cluster_labels = cluster_labels;
+        self
+    }
+
+    /// Prune branch-and-bound branches that only differ by swapping
+    /// identical pieces or identical bins (enabled by default). The optimal
+    /// sheet count is unchanged; the search just visits far fewer nodes.
+    pub fn with_symmetry_breaking(mut self, symmetry_breaking: bool) -> Self {
+        self.symmetry_breaking = symmetry_breaking;
+        self
+    }
+
+    /// Call `on_improvement` each time branch and bound finds a solution
+    /// using fewer sheets than the best so far, so callers can show
+    /// intermediate results. Every reported solution has all pieces placed
+    /// (per material partition when demands specify several materials),
+    /// with fillers and the anchor applied as in the final layout.
+    pub fn with_on_improvement(
+        mut self,
+        on_improvement: impl Fn(&Solution) + Send + Sync + 'static,
+    ) -> Self {
+        self.on_improvement = Some(Box::new(on_improvement));
+        self
+    }
+
+    /// Start from a [`Checkpoint`] saved by an earlier, interrupted solve of
+    /// the same job, e.g. from [`with_on_improvement`](Self::with_on_improvement):
+    /// the job is solved again, but the checkpointed solution is returned
+    /// instead when it places at least as many pieces on fewer sheets, or
+    /// when the deadline passes, so resuming never does worse. Checkpoints
+    /// of another job, or from another build (see
+    /// [`job_hash`](Self::job_hash)), are ignored.
+    pub fn with_resume(mut self, checkpoint: Option<Checkpoint>) -> Self {
+        self.resume = checkpoint;
+        self
+    }
+
+    /// Checkpoint of `solution` for this job.
+    pub fn checkpoint(&self, solution: &Solution) -> Checkpoint {
+        Checkpoint {
+            job_hash: self.job_hash(),
+            solution: solution.clone(),
+        }
+    }
+
+    /// The checkpoint resumed from, if it is for this job.
+    fn own_checkpoint(&self) -> Option<&Checkpoint> {
+        self.resume
+            .as_ref()
+            .filter(|checkpoint| checkpoint.job_hash == self.job_hash())
+    }
+
+    /// `solution`, or the checkpoint resumed from if it's better.
+    fn resumed(&self, solution: Solution) -> Solution {
+        let placed = |s: &Solution| {
+            s.sheets
+                .iter()
+                .flat_map(|sheet| &sheet.placements)
+                .filter(|p| !p.is_filler)
+                .count()
+        };
+        match self.own_checkpoint() {
+            Some(checkpoint)
+                if placed(&checkpoint.solution) >= placed(&solution)
+                    && checkpoint.solution.sheet_count() < solution.sheet_count() =>
+            {
+                checkpoint.solution.clone()
             }
+            _ => solution,
         }
     }
 
-    #[test]
-    fn test_single_piece() {
-        let solver = Solver::new(
-            Rect::new(100, 100),
-            0,
-            CutDirection::Auto,
-            StockGrain::None,
-            vec![Demand {
-                rect: Rect::new(50, 50),
-                qty: 1,
-                allow_rotate: true,
-                grain: PieceGrain::Auto,
-            }],
-        );
-        let sol = solver.solve();
-        assert_solution_valid(&sol, 1);
-        assert_eq!(sol.sheet_count(), 1);
+    /// Also try packing pieces whose smaller dimension is below `threshold`
+    /// first, as full-length strips along the top of each sheet, before the
+    /// others; the end of each row is filled with the strips that fit it
+    /// best. The greedy phase keeps whichever variant is best.
+    pub fn with_strip_threshold(mut self, threshold: Option<u32>) -> Self {
+        self.strip_threshold = threshold;
+        self
     }
 
-    #[test]
-    fn test_exact_fit_four_pieces() {
-        let solver = Solver::new(
-            Rect::new(100, 100),
-            0,
-            CutDirection::Auto,
-            StockGrain::None,
-            vec![Demand {
-                rect: Rect::new(50, 50),
-                qty: 4,
-                allow_rotate: false,
-                grain: PieceGrain::Auto,
-            }],
-        );
-        let sol = solver.solve();
-        assert_solution_valid(&sol, 4);
-        assert_eq!(sol.sheet_count(), 1);
+    /// Only place pieces inside the `window` sub-rectangle at `(x, y)` of each
+    /// sheet (e.g. a machine travel limit). Waste is still reported against
+    /// the full stock.
+    pub fn with_usable_window(mut self, x: u32, y: u32, window: Rect) -> Self {
+        self.usable_window = Some((x, y, window));
+        self
     }
 
-    #[test]
-    fn test_needs_two_sheets() {
-        let solver = Solver::new(
-            Rect::new(100, 100),
-            0,
-            CutDirection::Auto,
-            StockGrain::None,
-            vec![Demand {
-                rect: Rect::new(60, 60),
-                qty: 4,
-                allow_rotate: false,
-                grain: PieceGrain::Auto,
-            }],
-        );
-        let sol = solver.solve();
-        assert_solution_valid(&sol, 4);
-        // Each sheet can fit at most 1 piece (60x60 leaves 40x100 and 60x40 — no room for another 60x60)
-        assert!(sol.sheet_count() >= 4);
+    /// Among solutions with the same sheet count, prefer the one whose waste
+    /// is concentrated toward the far corner (high x, high y) of the sheets,
+    /// where offcuts are easiest to re-rack.
+    pub fn with_far_edge_waste(mut self, far_edge_waste: bool) -> Self {
+        self.far_edge_waste = far_edge_waste;
+        self
     }
 
-    #[test]
-    fn test_rotation_helps() {
-        // Stock 100x50, piece 50x100 — only fits if rotated
-        let solver = Solver::new(
-            Rect::new(100, 50),
-            0,
-            CutDirection::Auto,
-            StockGrain::None,
-            vec![Demand {
-                rect: Rect::new(50, 100),
-                qty: 1,
-                allow_rotate: true,
-                grain: PieceGrain::Auto,
-            }],
-        );
-        let sol = solver.solve();
-        assert_solution_valid(&sol, 1);
-        assert_eq!(sol.sheet_count(), 1);
-        assert!(sol.sheets[0].placements[0].rotated);
+    /// Among solutions with the same sheet count, prefer chunky offcuts to
+    /// long thin ones, the larger the offcut the more it counts: a 700x600
+    /// drop beats a 2000x50 sliver. Checked after
+    /// [`with_far_edge_waste`](Self::with_far_edge_waste).
+    pub fn with_square_offcuts(mut self, square_offcuts: bool) -> Self {
+        self.square_offcuts = square_offcuts;
+        self
     }
 
-    #[test]
-    fn test_no_demands() {
-        let solver = Solver::new(
-            Rect::new(100, 100),
-            0,
-            CutDirection::Auto,
-            StockGrain::None,
-            vec![],
-        );
-        let sol = solver.solve();
-        assert_solution_valid(&sol, 0);
+    /// Among solutions with the same sheet count, first prefer the one
+    /// `tie_break` ranks best, so `Auto` picks its direction on purpose
+    /// rather than by trial order. Checked before
+    /// [`with_far_edge_waste`](Self::with_far_edge_waste); `None` goes
+    /// straight to the other tiebreaks.
+    pub fn with_direction_tie_break(mut self, tie_break: Option<DirectionTieBreak>) -> Self {
+        self.direction_tie_break = tie_break;
+        self
     }
 
-    #[test]
-    fn test_kerf_reduces_capacity() {
-        // Without kerf: 2 pieces of 50x100 fit in 100x100
-        let solver_no_kerf = Solver::new(
-            Rect::new(100, 100),
-            0,
-            CutDirection::Auto,
-            StockGrain::None,
-            vec![Demand {
-                rect: Rect::new(50, 100),
-                qty: 2,
-                allow_rotate: false,
-                grain: PieceGrain::Auto,
-            }],
-        );
-        let sol_no_kerf = solver_no_kerf.solve();
-        assert_solution_valid(&sol_no_kerf, 2);
-        assert_eq!(sol_no_kerf.sheet_count(), 1);
+    /// Treat demand sizes within `tolerance` of each other (on both
+    /// dimensions) as one size class, packed at the class's largest
+    /// dimensions. Placements keep the originally requested size.
+    pub fn with_size_tolerance(mut self, tolerance: u32) -> Self {
+        self.size_tolerance = tolerance;
+        self
+    }
 
-        // With kerf of 5: 50 + 5 + 50 = 105 > 100, needs 2 sheets
-        let solver_kerf = Solver::new(
-            Rect::new(100, 100),
-            5,
-            CutDirection::Auto,
-            StockGrain::None,
-            vec![Demand {
-                rect: Rect::new(50, 100),
-                qty: 2,
-                allow_rotate: false,
-                grain: PieceGrain::Auto,
-            }],
-        );
-        let sol_kerf = solver_kerf.solve();
-        assert_solution_valid(&sol_kerf, 2);
-        assert_eq!(sol_kerf.sheet_count(), 2);
+    /// Evaluate greedy strategy/direction combinations, and material
+    /// partitions, on separate threads (enabled by default). Results are
+    /// identical to the serial path.
+    pub fn with_parallel(mut self, parallel: bool) -> Self {
+        self.parallel = parallel;
+        self
     }
 
-    #[test]
-    fn test_waste_percent() {
-        let solver = Solver::new(
-            Rect::new(100, 100),
-            0,
-            CutDirection::Auto,
-            StockGrain::None,
-            vec![Demand {
-                rect: Rect::new(100, 100),
-                qty: 1,
-                allow_rotate: false,
-                grain: PieceGrain::Auto,
-            }],
-        );
-        let sol = solver.solve();
-        assert_solution_valid(&sol, 1);
-        assert!((sol.total_waste_percent() - 0.0).abs() < 0.01);
+    /// Solve, panicking on invalid input.
+    ///
+    /// # Panics
+    ///
+    /// If [`try_solve`](Self::try_solve) returns an error.
+    pub fn solve(&self) -> Solution {
+        self.try_solve().unwrap_or_else(|e| panic!("{}", e))
     }
 
-    /// 30 pieces, 6 different sizes, standard plywood sheet 2440x1220, no kerf.
-    /// Verifies all pieces are placed and no placement overlaps or exceeds the stock.
-    #[test]
-    fn test_complex_mixed_sizes_no_kerf() {
-        let stock = Rect::new(2440, 1220);
-        let demands = vec![
-            Demand {
-                rect: Rect::new(800, 600),
-                qty: 5,
-                allow_rotate: true,
-                grain: PieceGrain::Auto,
-            },
-            Demand {
-                rect: Rect::new(400, 300),
-                qty: 8,
-                allow_rotate: true,
-                grain: PieceGrain::Auto,
-            },
-            Demand {
-                rect: Rect::new(600, 400),
-                qty: 4,
-                allow_rotate: true,
-                grain: PieceGrain::Auto,
-            },
-            Demand {
-                rect: Rect::new(1200, 600),
-                qty: 3,
-                allow_rotate: true,
-                grain: PieceGrain::Auto,
-            },
-            Demand {
-                rect: Rect::new(300, 200),
-                qty: 6,
+    pub fn try_solve(&self) -> Result<Solution, SolveError> {
+        match self.solve_job() {
+            Ok(solution) => Ok(self.resumed(solution)),
+            // The checkpoint is the best known, even if this run found nothing
+            Err(SolveError::DeadlineExceeded) if let Some(checkpoint) = self.own_checkpoint() => {
+                Ok(checkpoint.solution.clone())
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    fn solve_job(&self) -> Result<Solution, SolveError> {
+        check_kerf(self.stock, self.kerf)?;
+        if let Some(pitch) = self.grid_pitch {
+            self.check_grid(pitch)?;
+        }
+        self.check_fixed_cuts()?;
+        let longest_side = self.stock.length.max(self.stock.width);
+        let mut requested: u64 = self.demands.iter().map(|d| d.qty as u64).sum();
+        for ad in &self.area_demands {
+            if ad.width == 0 || ad.width > longest_side {
+                return Err(SolveError::InvalidStripWidth { width: ad.width });
+            }
+            // At least this many strips, each at most a stock side long
+            let strip_area = ad.width as u64 * longest_side as u64;
+            requested = requested.saturating_add(ad.total_area.div_ceil(strip_area));
+        }
+        if requested > self.max_expanded_pieces {
+            return Err(SolveError::TooManyPieces {
+                requested,
+                max: self.max_expanded_pieces,
+            });
+        }
+        let (demands, warnings) = self.relax_infeasible_grain();
+        // Soft grain never forces a rotation, so it can't conflict
+        if self.grain_penalty.is_none() {
+            for (demand, d) in demands.iter().enumerate() {
+                let front =
+                    RotationConstraint::from_grain(self.stock_grain, d.grain, d.can_rotate())
+                        .map_err(|conflict| SolveError::GrainConflict { demand, conflict })?;
+                let back =
+                    RotationConstraint::from_grain(self.back_grain, d.back_grain, d.can_rotate())
+                        .map_err(|conflict| SolveError::GrainConflict { demand, conflict })?;
+                front
+                    .intersect(back)
+                    .ok_or(SolveError::FaceGrainConflict { demand })?;
+            }
+        }
+
+        // Materials never share a sheet: solve each partition on its own,
+        // concurrently, then merge in partition order
+        let partitions = Self::material_partitions(&demands);
+        let solutions: Vec<Result<Solution, SolveError>> = if self.max_total_area.is_some() {
+            // The area budget is shared: each material takes what the
+            // previous ones left
+            let mut used = 0;
+            partitions
+                .iter()
+                .map(|(_, demands)| {
+                    let solution = self.solve_demands(demands, self.sheet_cap(used));
+                    if let Ok(solution) = &solution {
+                        used += solution.sheets.len();
+                    }
+                    solution
+                })
+                .collect()
+        } else if self.parallel && partitions.len() > 1 {
+            std::thread::scope(|scope| {
+                let handles: Vec<_> = partitions
+                    .iter()
+                    .map(|(_, demands)| {
+                        scope.spawn(move || self.solve_demands(demands, self.max_sheets))
+                    })
+                    .collect();
+                handles
+                    .into_iter()
+                    .map(|h| h.join().expect("partition worker panicked"))
+                    .collect()
+            })
+        } else {
+            partitions
+                .iter()
+                .map(|(_, demands)| self.solve_demands(demands, self.max_sheets))
+                .collect()
+        };
+        let mut sheets = Vec::new();
+        let mut unplaced = Vec::new();
+        for ((material, _), solution) in partitions.into_iter().zip(solutions) {
+            let solution = solution?;
+            unplaced.extend(solution.unplaced);
+            sheets.extend(solution.sheets.into_iter().map(|mut sheet| {
+                sheet.material = material.clone();
+                if self.cluster_labels {
+                    self.cluster_sheet_labels(&mut sheet.placements);
+                }
+                sheet
+            }));
+        }
+        for ad in &self.area_demands {
+            self.fill_area_demand(&mut sheets, ad);
+        }
+        Ok(Solution {
+            unplaced,
+            ..self.finish_sheets(sheets, warnings)
+        })
+    }
+
+    /// Best layout on exactly `n` sheets, when the sheet count is fixed
+    /// anyway: instead of filling sheets one after the other, each piece
+    /// (largest first) goes on the emptiest sheet that holds it, so the
+    /// worst sheet wastes as little as possible. `None` if the job can't
+    /// be solved or needs more than `n` sheets.
+    ///
+    /// Jobs with several materials, area demands or a reserved offcut, and
+    /// jobs no balanced layout is found for, get the minimal layout.
+    pub fn solve_fixed(&self, n: usize) -> Option<Solution> {
+        let minimal = self.try_solve().ok()?;
+        if minimal.sheet_count() > n {
+            return None;
+        }
+        let (demands, warnings) = self.relax_infeasible_grain();
+        let partitions = Self::material_partitions(&demands);
+        if partitions.len() != 1 || !self.area_demands.is_empty() || self.reserve_offcut.is_some() {
+            return Some(minimal);
+        }
+        let pieces = self.expand_demands(&partitions[0].1);
+        let directions = match self.cut_direction {
+            CutDirection::Auto | CutDirection::Mixed => {
+                vec![CutDirection::AlongLength, CutDirection::AlongWidth]
+            }
+            dir => vec![dir],
+        };
+        let mut best: Option<Vec<GuillotineBin>> = None;
+        for &dir in &directions {
+            for strategy in ScoreStrategy::ALL {
+                let Some(bins) = self.balanced_bins(&pieces, n, strategy, dir) else {
+                    continue;
+                };
+                let least_used = |bins: &[GuillotineBin]| bins.iter().map(|b| b.used_area()).min();
+                if best
+                    .as_ref()
+                    .is_none_or(|b| least_used(&bins) > least_used(b))
+                {
+                    best = Some(bins);
+                }
+            }
+        }
+        let Some(bins) = best else {
+            return Some(minimal);
+        };
+        let mut sheets = self.bins_to_solution(bins).sheets;
+        for sheet in &mut sheets {
+            sheet.material = partitions[0].0.clone();
+            if self.cluster_labels {
+                self.cluster_sheet_labels(&mut sheet.placements);
+            }
+        }
+        Some(self.finish_sheets(sheets, warnings))
+    }
+
+    /// `pieces` spread over `n` sheets, each on the least used sheet it
+    /// fits, or `None` if one fits nowhere.
+    fn balanced_bins(
+        &self,
+        pieces: &[Piece],
+        n: usize,
+        strategy: ScoreStrategy,
+        direction: CutDirection,
+    ) -> Option<Vec<GuillotineBin>> {
+        let min_useful = Self::min_piece_dimension(pieces);
+        let mut bins: Vec<GuillotineBin> = (0..n)
+            .map(|_| self.new_bin(direction, min_useful, None))
+            .collect();
+        let mut order: Vec<usize> = (0..n).collect();
+        for piece in pieces {
+            order.sort_by_key(|&i| bins[i].used_area());
+            let (bi, scored) = order.iter().find_map(|&i| {
+                self.find_best_on_sheet(piece, &bins[i], strategy)
+                    .map(|s| (i, s))
+            })?;
+            Self::place_piece(&mut bins[bi], scored, piece);
+        }
+        Some(bins)
+    }
+
+    /// Warnings, fillers and anchor mirroring, shared by every way of
+    /// laying out the sheets.
+    fn finish_sheets(&self, mut sheets: Vec<SheetResult>, mut warnings: Vec<Warning>) -> Solution {
+        if self.template_sheet {
+            Self::apply_template_sheets(&mut sheets);
+        }
+        // Checked before mirroring, while the far edges are at high x and y
+        warnings.extend(self.tight_fit_warnings(&sheets));
+        self.place_overflow(&mut sheets);
+        if !self.fillers.is_empty() {
+            for sheet in &mut sheets {
+                self.place_fillers(sheet);
+            }
+        }
+        // Packing always works toward (0, 0); mirror into the anchor corner
+        let (flip_x, flip_y) = self.anchor_flips();
+        if flip_x || flip_y {
+            for sheet in &mut sheets {
+                self.mirror_sheet(sheet, flip_x, flip_y);
+            }
+        }
+        Solution {
+            sheets,
+            stock: self.stock,
+            warnings,
+            unplaced: vec![],
+        }
+    }
+
+    /// Replace each sheet by the first sheet holding the same pieces.
+    fn apply_template_sheets(sheets: &mut [SheetResult]) {
+        let keys: Vec<PieceSet> = sheets.iter().map(piece_set).collect();
+        for i in 1..sheets.len() {
+            let first = keys.iter().position(|k| *k == keys[i]).unwrap_or(i);
+            if first < i {
+                sheets[i] = sheets[first].clone();
+            }
+        }
+    }
+
+    /// Pieces closer than `tight_fit_margin` to the far sheet edge along
+    /// either axis, with no other piece in between.
+    fn tight_fit_warnings(&self, sheets: &[SheetResult]) -> Vec<Warning> {
+        let mut warnings = Vec::new();
+        if self.tight_fit_margin == 0 {
+            return warnings;
+        }
+        for (si, sheet) in sheets.iter().enumerate() {
+            for (pi, p) in sheet.placements.iter().enumerate() {
+                let (end_x, end_y) = (p.x + p.rect.length, p.y + p.rect.width);
+                let beyond_x = sheet
+                    .placements
+                    .iter()
+                    .any(|q| q.x >= end_x && q.y < end_y && p.y < q.y + q.rect.width);
+                let beyond_y = sheet
+                    .placements
+                    .iter()
+                    .any(|q| q.y >= end_y && q.x < end_x && p.x < q.x + q.rect.length);
+                let spare = [
+                    (!beyond_x).then(|| sheet.stock.length.saturating_sub(end_x)),
+                    (!beyond_y).then(|| sheet.stock.width.saturating_sub(end_y)),
+                ]
+                .into_iter()
+                .flatten()
+                .min();
+                if let Some(spare) = spare
+                    && spare < self.tight_fit_margin
+                {
+                    warnings.push(Warning::TightFit {
+                        sheet: si,
+                        piece: pi,
+                        spare,
+                    });
+                }
+            }
+        }
+        warnings
+    }
+
+    /// Cut the [`Demand::optional_overflow`] pieces out of the offcuts of
+    /// sheets of their material, largest first, as long as they fit.
+    fn place_overflow(&self, sheets: &mut [SheetResult]) {
+        let mut pieces: Vec<(Option<String>, Piece)> = Vec::new();
+        for d in self.demands.iter().filter(|d| d.optional_overflow > 0) {
+            let extra = Demand {
+                qty: d.optional_overflow,
+                ..d.clone()
+            };
+            for piece in self.expand_demands(&[extra]) {
+                pieces.push((d.material.clone(), piece));
+            }
+        }
+        pieces.sort_by_key(|(_, p)| std::cmp::Reverse(p.rect.area()));
+        for sheet in sheets.iter_mut() {
+            if pieces.is_empty() {
+                return;
+            }
+            let orientation = self.sheet_orientation(&sheet.placements);
+            for o in std::mem::take(&mut sheet.offcuts) {
+                let mut bin = GuillotineBin::new(sheet.stock, self.kerf, sheet.cut_direction);
+                bin.restrict_to(o.x, o.y, o.rect);
+                while let Some((i, scored)) =
+                    pieces
+                        .iter()
+                        .enumerate()
+                        .find_map(|(i, (material, piece))| {
+                            let rotation = piece.rotation.intersect(orientation)?;
+                            (*material == sheet.material)
+                                .then(|| {
+                                    piece.find_oriented(&bin, rotation, ScoreStrategy::BestAreaFit)
+                                })
+                                .flatten()
+                                .map(|scored| (i, scored))
+                        })
+                {
+                    let (_, piece) = pieces.remove(i);
+                    Self::place_piece(&mut bin, scored, &piece);
+                }
+                sheet.waste_area -= bin.used_area();
+                sheet.placements.extend(bin.placements);
+                sheet
+                    .offcuts
+                    .extend(bin.free_rects.iter().chain(&bin.scrap).map(|f| Offcut {
+                        x: f.x,
+                        y: f.y,
+                        rect: f.rect,
+                    }));
+            }
+        }
+    }
+
+    /// Cut as many catalog fillers as fit out of each offcut of `sheet`,
+    /// trying the largest first at every step.
+    fn place_fillers(&self, sheet: &mut SheetResult) {
+        let mut fillers = self.fillers.clone();
+        fillers.sort_by_key(|f| std::cmp::Reverse(f.area()));
+        let orientation = self.sheet_orientation(&sheet.placements);
+        for o in std::mem::take(&mut sheet.offcuts) {
+            let mut bin = GuillotineBin::new(sheet.stock, self.kerf, sheet.cut_direction);
+            bin.restrict_to(o.x, o.y, o.rect);
+            while let Some((filler, scored)) = fillers.iter().find_map(|&filler| {
+                bin.find_best(filler, orientation, ScoreStrategy::BestAreaFit)
+                    .map(|scored| (filler, scored))
+            }) {
+                bin.place(scored, filler);
+            }
+            sheet.waste_area -= bin.used_area();
+            sheet
+                .placements
+                .extend(bin.placements.into_iter().map(|p| Placement {
+                    is_filler: true,
+                    ..p
+                }));
+            sheet
+                .offcuts
+                .extend(bin.free_rects.iter().chain(&bin.scrap).map(|f| Offcut {
+                    x: f.x,
+                    y: f.y,
+                    rect: f.rect,
+                }));
+        }
+    }
+
+    /// Whether the anchor corner is across the x and y axes from (0, 0).
+    fn anchor_flips(&self) -> (bool, bool) {
+        match self.anchor {
+            None | Some(Anchor::TopLeft) => (false, false),
+            Some(Anchor::TopRight) => (true, false),
+            Some(Anchor::BottomLeft) => (false, true),
+            Some(Anchor::BottomRight) => (true, true),
+        }
+    }
+
+    fn mirror_sheet(&self, sheet: &mut SheetResult, flip_x: bool, flip_y: bool) {
+        let stock = sheet.stock;
+        let mirror = |x: &mut u32, y: &mut u32, rect: Rect| {
+            if flip_x {
+                *x = stock.length - *x - rect.length;
+            }
+            if flip_y {
+                *y = stock.width - *y - rect.width;
+            }
+        };
+        for p in &mut sheet.placements {
+            mirror(&mut p.x, &mut p.y, p.rect);
+        }
+        for o in sheet.offcuts.iter_mut().chain(&mut sheet.reserved) {
+            mirror(&mut o.x, &mut o.y, o.rect);
+        }
+    }
+
+    /// Cut strips for `ad` out of the offcuts of material-less sheets, then
+    /// out of new sheets, until its area is covered.
+    fn fill_area_demand(&self, sheets: &mut Vec<SheetResult>, ad: &AreaDemand) {
+        let mut remaining = ad.total_area;
+        for sheet in sheets.iter_mut().filter(|s| s.material.is_none()) {
+            if remaining == 0 {
+                return;
+            }
+            remaining = self.fill_strips(sheet, ad.width, remaining);
+        }
+        while remaining > 0 {
+            let bin = self.new_bin(self.cut_direction, 0, None);
+            let mut sheet = SheetResult {
+                stock: self.stock,
+                stock_rotated: false,
+                placements: vec![],
+                waste_area: self.stock.area(),
+                material: None,
+                offcuts: bin
+                    .free_rects
+                    .iter()
+                    .map(|f| Offcut {
+                        x: f.x,
+                        y: f.y,
+                        rect: f.rect,
+                    })
+                    .collect(),
+                reserved: None,
+                cut_direction: CutDirection::Auto,
+            };
+            let left = self.fill_strips(&mut sheet, ad.width, remaining);
+            if left == remaining {
+                // Strip too wide for the usable window
+                return;
+            }
+            remaining = left;
+            sheets.push(sheet);
+        }
+    }
+
+    /// Fill the sheet's offcuts with rows of `width`-wide strips, each as long
+    /// as the offcut (the last one only as long as needed). Returns the area
+    /// still to cut.
+    fn fill_strips(&self, sheet: &mut SheetResult, width: u32, mut remaining: u64) -> u64 {
+        let kerf = self.kerf;
+        let mut i = 0;
+        while i < sheet.offcuts.len() && remaining > 0 {
+            let o = sheet.offcuts[i];
+            // Strips along x (rows) or along y (columns), whichever covers
+            // more, unless the sheet's orientation is set
+            let rows = (o.rect.width as u64 + kerf as u64) / (width as u64 + kerf as u64);
+            let cols = (o.rect.length as u64 + kerf as u64) / (width as u64 + kerf as u64);
+            let along_x = match self.sheet_orientation(&sheet.placements) {
+                RotationConstraint::NoRotate => true,
+                RotationConstraint::ForceRotate => false,
+                RotationConstraint::Free => {
+                    rows * o.rect.length as u64 >= cols * o.rect.width as u64
+                }
+            };
+            let (count, full_len, across) = if along_x {
+                (rows, o.rect.length, o.rect.width)
+            } else {
+                (cols, o.rect.width, o.rect.length)
+            };
+            if count == 0 {
+                i += 1;
+                continue;
+            }
+
+            sheet.offcuts.swap_remove(i);
+            let mut used_across = 0;
+            for _ in 0..count {
+                if remaining == 0 {
+                    break;
+                }
+                let len = remaining.div_ceil(width as u64).min(full_len as u64) as u32;
+                let (x, y, rect) = if along_x {
+                    (o.x, o.y + used_across, Rect::new(len, width))
+                } else {
+                    (o.x + used_across, o.y, Rect::new(width, len))
+                };
+                sheet.placements.push(Placement {
+                    rect,
+                    x,
+                    y,
+                    rotated: !along_x,
+                    requested: Rect::new(len, width),
+                    orientation_deg: if along_x { 0 } else { 90 },
+                    label: None,
+                    is_filler: false,
+                    directional: false,
+                    group_hint: None,
+                });
+                remaining = remaining.saturating_sub(len as u64 * width as u64);
+
+                // Short last strip: the rest of its row stays free
+                let rest = full_len.saturating_sub(len + kerf);
+                if rest > 0 && len < full_len {
+                    let offcut = if along_x {
+                        Offcut {
+                            x: x + len + kerf,
+                            y,
+                            rect: Rect::new(rest, width),
+                        }
+                    } else {
+                        Offcut {
+                            x,
+                            y: y + len + kerf,
+                            rect: Rect::new(width, rest),
+                        }
+                    };
+                    sheet.offcuts.push(offcut);
+                }
+                used_across = (used_across + width + kerf).min(across);
+            }
+            // Whatever is left beyond the last row (or column)
+            if used_across < across {
+                let rest = across - used_across;
+                sheet.offcuts.push(if along_x {
+                    Offcut {
+                        x: o.x,
+                        y: o.y + used_across,
+                        rect: Rect::new(o.rect.length, rest),
+                    }
+                } else {
+                    Offcut {
+                        x: o.x + used_across,
+                        y: o.y,
+                        rect: Rect::new(rest, o.rect.width),
+                    }
+                });
+            }
+        }
+        let used: u64 = sheet.placements.iter().map(|p| p.rect.area()).sum();
+        sheet.waste_area = sheet.stock.area() - used;
+        remaining
+    }
+
+    /// Local search over label swaps: repeatedly exchange the labels of two
+    /// interchangeable placements when that lowers the summed distance
+    /// between same-label centroids, until no swap helps.
+    fn cluster_sheet_labels(&self, placements: &mut [Placement]) {
+        let centroid = |p: &Placement| {
+            (
+                p.x as f64 + p.rect.length as f64 / 2.0,
+                p.y as f64 + p.rect.width as f64 / 2.0,
+            )
+        };
+        let centroids: Vec<(f64, f64)> = placements.iter().map(centroid).collect();
+        let dist = |a: usize, b: usize| {
+            let (dx, dy) = (
+                centroids[a].0 - centroids[b].0,
+                centroids[a].1 - centroids[b].1,
+            );
+            (dx * dx + dy * dy).sqrt()
+        };
+
+        const MAX_PASSES: usize = 100;
+        for _ in 0..MAX_PASSES {
+            let mut improved = false;
+            for i in 0..placements.len() {
+                for j in (i + 1)..placements.len() {
+                    if placements[i].label == placements[j].label
+                        || !self.interchangeable(&placements[i], &placements[j])
+                    {
+                        continue;
+                    }
+                    // Distance from `slot` to the other pieces labelled `label`
+                    let cost = |label: &Option<String>, slot: usize| -> f64 {
+                        if label.is_none() {
+                            return 0.0;
+                        }
+                        (0..placements.len())
+                            .filter(|&k| k != i && k != j && placements[k].label == *label)
+                            .map(|k| dist(slot, k))
+                            .sum()
+                    };
+                    let (li, lj) = (&placements[i].label, &placements[j].label);
+                    let before = cost(li, i) + cost(lj, j);
+                    let after = cost(li, j) + cost(lj, i);
+                    if after + 1e-9 < before {
+                        let label = placements[i].label.take();
+                        placements[i].label = std::mem::replace(&mut placements[j].label, label);
+                        improved = true;
+                    }
+                }
+            }
+            if !improved {
+                break;
+            }
+        }
+    }
+
+    /// Whether two placements can trade places: same placed size and
+    /// orientation, from demands with the same clearance.
+    fn interchangeable(&self, a: &Placement, b: &Placement) -> bool {
+        let clearance = |p: &Placement| {
+            self.demands
+                .iter()
+                .find(|d| d.label == p.label && d.rect == p.requested)
+                .map(|d| d.clearance)
+        };
+        a.rect == b.rect
+            && a.rotated == b.rotated
+            && a.directional == b.directional
+            && a.group_hint == b.group_hint
+            && a.requested == b.requested
+            && clearance(a) == clearance(b)
+    }
+
+    /// Rejects a first cut or strip boundary whose kerf doesn't end within
+    /// the stock: mirrored for an anchor, it would start before the origin.
+    fn check_fixed_cuts(&self) -> Result<(), SolveError> {
+        let strip_axis = match self.cut_direction {
+            CutDirection::AlongWidth => Axis::X,
+            _ => Axis::Y,
+        };
+        let cuts = self
+            .first_cut
+            .into_iter()
+            .chain(self.strip_boundaries.iter().map(|&p| (strip_axis, p)));
+        for (axis, position) in cuts {
+            let extent = match axis {
+                Axis::X => self.stock.length,
+                Axis::Y => self.stock.width,
+            };
+            if position as u64 + self.kerf as u64 > extent as u64 {
+                return Err(SolveError::CutOutsideStock { axis, position });
+            }
+        }
+        Ok(())
+    }
+
+    /// Every demand must fit the grid in an orientation it may take, with
+    /// its clearance.
+    fn check_grid(&self, pitch: (u32, u32)) -> Result<(), SolveError> {
+        let (px, py) = pitch;
+        if px == 0 || py == 0 {
+            return Err(SolveError::InvalidGridPitch { pitch });
+        }
+        let on_grid = |r: Rect| r.length.is_multiple_of(px) && r.width.is_multiple_of(py);
+        for d in &self.demands {
+            let clearance_on_grid = d
+                .clearance
+                .iter()
+                .all(|c| c.is_multiple_of(px) && c.is_multiple_of(py));
+            let fits = on_grid(d.rect) || (d.can_rotate() && on_grid(d.rect.rotated()));
+            if !fits || !clearance_on_grid {
+                return Err(SolveError::OffGrid {
+                    rect: d.rect,
+                    pitch,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// The demands to solve. With
+    /// [`with_relax_grain_on_infeasible`](Self::with_relax_grain_on_infeasible),
+    /// those that fit an empty sheet only against their grain lose it.
+    fn relax_infeasible_grain(&self) -> (Cow<'_, [Demand]>, Vec<Warning>) {
+        if !self.relax_grain_on_infeasible || self.grain_penalty.is_some() {
+            return (Cow::Borrowed(&self.demands), vec![]);
+        }
+        let empty = self.new_bin(CutDirection::Auto, 0, None);
+        let fits = |d: &Demand| {
+            self.expand_demands(&[Demand {
+                qty: 1,
+                ..d.clone()
+            }])
+            .iter()
+            .all(|p| p.find_best_in(&empty, ScoreStrategy::BestAreaFit).is_some())
+        };
+        let mut demands = self.demands.clone();
+        let mut warnings = Vec::new();
+        for (demand, d) in demands.iter_mut().enumerate() {
+            // A grain conflict can't be expanded, let alone placed
+            let unconstrained = d.grain == PieceGrain::Auto && d.back_grain == PieceGrain::Auto;
+            if unconstrained || (self.strict_rotation(d).is_some() && fits(d)) {
+                continue;
+            }
+            let relaxed = Demand {
+                grain: PieceGrain::Auto,
+                back_grain: PieceGrain::Auto,
+                ..d.clone()
+            };
+            if fits(&relaxed) {
+                *d = relaxed;
+                warnings.push(Warning::GrainRelaxed {
+                    demand,
+                    rect: d.rect,
+                });
+            }
+        }
+        (Cow::Owned(demands), warnings)
+    }
+
+    /// Rotation allowed by the grain of both faces and `allow_rotate`, if
+    /// they agree.
+    fn strict_rotation(&self, d: &Demand) -> Option<RotationConstraint> {
+        let front = RotationConstraint::from_grain(self.stock_grain, d.grain, d.can_rotate());
+        let back = RotationConstraint::from_grain(self.back_grain, d.back_grain, d.can_rotate());
+        front.ok()?.intersect(back.ok()?)
+    }
+
+    /// Demands grouped by material, in order of first appearance.
+    fn material_partitions(demands: &[Demand]) -> Vec<(Option<String>, Vec<Demand>)> {
+        let mut partitions: Vec<(Option<String>, Vec<Demand>)> = Vec::new();
+        for d in demands {
+            match partitions.iter_mut().find(|(m, _)| *m == d.material) {
+                Some((_, demands)) => demands.push(d.clone()),
+                None => partitions.push((d.material.clone(), vec![d.clone()])),
+            }
+        }
+        partitions
+    }
+
+    /// Layout of `demands` on at most `cap` sheets.
+    fn solve_demands(
+        &self,
+        demands: &[Demand],
+        cap: Option<usize>,
+    ) -> Result<Solution, SolveError> {
+        let pieces = self.expand_demands(demands);
+        let empty = self.new_bin(CutDirection::Auto, 0, None);
+        let turned = self.new_turned_bin(CutDirection::Auto, 0, None);
+        for (i, piece) in pieces.iter().enumerate() {
+            let checked = i > 0 && pieces[i - 1] == *piece;
+            if !checked
+                && std::iter::once(&empty).chain(&turned).all(|bin| {
+                    piece
+                        .find_best_in(bin, ScoreStrategy::BestAreaFit)
+                        .is_none()
+                })
+            {
+                return Err(SolveError::PieceTooLarge {
+                    rect: piece.requested,
+                });
+            }
+        }
+        if pieces.is_empty() {
+            return Ok(Solution {
+                sheets: vec![],
+                stock: self.stock,
+                warnings: vec![],
+                unplaced: vec![],
+            });
+        }
+
+        let Some(drop) = self.reserve_offcut else {
+            return self.solve_pieces(&pieces, None, cap);
+        };
+        let reserved = self.solve_pieces(&pieces, Some(drop), cap)?;
+        let mut plain = self.solve_pieces(&pieces, None, cap)?;
+        if reserved.sheets.len() <= plain.sheets.len() {
+            return Ok(reserved);
+        }
+        for sheet in &mut plain.sheets {
+            self.carve_reserved(sheet, drop);
+        }
+        Ok(plain)
+    }
+
+    /// Set `drop` aside in the first offcut of `sheet` that holds it.
+    fn carve_reserved(&self, sheet: &mut SheetResult, drop: Rect) {
+        let kerf = self.kerf;
+        let Some((i, rect)) = sheet.offcuts.iter().enumerate().find_map(|(i, o)| {
+            [drop, drop.rotated()]
+                .into_iter()
+                .find(|r| r.fits_in(&o.rect))
+                .map(|r| (i, r))
+        }) else {
+            return;
+        };
+        let o = sheet.offcuts.swap_remove(i);
+        sheet.reserved = Some(Offcut {
+            x: o.x,
+            y: o.y,
+            rect,
+        });
+        // Cut off the full-width remainder past the drop, then the rest
+        // of the drop's column
+        let right = o
+            .rect
+            .length
+            .saturating_sub(rect.length.saturating_add(kerf));
+        let top = o.rect.width.saturating_sub(rect.width.saturating_add(kerf));
+        if right > 0 {
+            sheet.offcuts.push(Offcut {
+                x: o.x + rect.length + kerf,
+                y: o.y,
+                rect: Rect::new(right, o.rect.width),
+            });
+        }
+        if top > 0 {
+            sheet.offcuts.push(Offcut {
+                x: o.x,
+                y: o.y + rect.width + kerf,
+                rect: Rect::new(rect.length, top),
+            });
+        }
+    }
+
+    /// Best layout of `pieces` with `reserve` set aside on each sheet,
+    /// on at most `cap` sheets.
+    fn solve_pieces(
+        &self,
+        pieces: &[Piece],
+        reserve: Option<Rect>,
+        cap: Option<usize>,
+    ) -> Result<Solution, SolveError> {
+        let solution = self.solve_all_pieces(pieces, reserve)?;
+        match cap {
+            Some(max) if solution.sheets.len() > max => {
+                Ok(self.capped_greedy(pieces, max, reserve))
+            }
+            _ => Ok(solution),
+        }
+    }
+
+    /// Greedy with at most `max` sheets: pieces in decreasing value, each
+    /// in the best open bin, or a new one while under the cap, or left out.
+    /// The strategy placing the most value (then area) wins.
+    fn capped_greedy(&self, pieces: &[Piece], max: usize, reserve: Option<Rect>) -> Solution {
+        let mut order: Vec<&Piece> = pieces.iter().collect();
+        // Stable: equal values keep the largest-first order
+        order.sort_by(|a, b| self.piece_value(b).total_cmp(&self.piece_value(a)));
+        let min_useful = Self::min_piece_dimension(pieces);
+        let directions = match self.cut_direction {
+            CutDirection::Auto | CutDirection::Mixed => {
+                vec![CutDirection::AlongLength, CutDirection::AlongWidth]
+            }
+            dir => vec![dir],
+        };
+
+        let mut best: Option<(f64, u64, Vec<GuillotineBin>, Vec<&Piece>)> = None;
+        for &dir in &directions {
+            for strategy in ScoreStrategy::ALL {
+                let mut bins: Vec<GuillotineBin> = Vec::new();
+                let mut left_out = Vec::new();
+                let (mut value, mut area) = (0.0, 0);
+                for &piece in &order {
+                    let mut open: Option<(usize, ScoredPlacement)> = None;
+                    for (bi, bin) in bins.iter_mut().enumerate() {
+                        if let Some(scored) = self.find_best_on_sheet(piece, bin, strategy)
+                            && open.is_none_or(|(_, best)| scored.score < best.score)
+                            && self.fits_cut_limit(bin, scored, piece)
+                        {
+                            open = Some((bi, scored));
+                        }
+                    }
+                    let placed = match open {
+                        Some((bi, scored)) => {
+                            Self::place_piece(&mut bins[bi], scored, piece);
+                            true
+                        }
+                        None if bins.len() < max => {
+                            match self.fresh_bin(piece, strategy, dir, min_useful, reserve) {
+                                Some((mut bin, scored)) => {
+                                    Self::place_piece(&mut bin, scored, piece);
+                                    bins.push(bin);
+                                    true
+                                }
+                                None => false,
+                            }
+                        }
+                        None => false,
+                    };
+                    if placed {
+                        value += self.piece_value(piece);
+                        area += piece.rect.area();
+                    } else {
+                        left_out.push(piece);
+                    }
+                }
+                if best
+                    .as_ref()
+                    .is_none_or(|&(v, a, ..)| (value, area) > (v, a))
+                {
+                    best = Some((value, area, bins, left_out));
+                }
+            }
+        }
+        let (_, _, bins, left_out) = best.expect("at least one strategy tried");
+
+        let mut unplaced: Vec<Unplaced> = Vec::new();
+        for piece in left_out {
+            match unplaced
+                .iter_mut()
+                .find(|u| u.rect == piece.requested && u.label == piece.label)
+            {
+                Some(u) => u.qty += 1,
+                None => unplaced.push(Unplaced {
+                    rect: piece.requested,
+                    label: piece.label.clone(),
+                    qty: 1,
+                }),
+            }
+        }
+        Solution {
+            unplaced,
+            ..self.bins_to_solution(bins)
+        }
+    }
+
+    /// Greedy, then branch and bound, with `reserve` set aside on each sheet.
+    fn solve_all_pieces(
+        &self,
+        pieces: &[Piece],
+        reserve: Option<Rect>,
+    ) -> Result<Solution, SolveError> {
+        if let Some(tiling) = self.exact_tiling(pieces, reserve) {
+            return Ok(tiling);
+        }
+
+        // Greedy phase: try multiple strategies, keep best
+        let greedy = self.greedy_best(pieces, reserve);
+        if self.expired() {
+            return Err(SolveError::DeadlineExceeded);
+        }
+        if !self.branch_and_bound || self.meets_target(greedy.sheets.len()) {
+            return Ok(greedy);
+        }
+
+        // B&B phase: try to improve on greedy
+        let bb = self.branch_and_bound(pieces, greedy.sheets.len(), reserve);
+        // An interrupted search proves nothing
+        if self.expired() {
+            return Err(SolveError::DeadlineExceeded);
+        }
+
+        if !bb.sheets.is_empty() && bb.sheets.len() < greedy.sheets.len() {
+            Ok(bb)
+        } else {
+            Ok(greedy)
+        }
+    }
+
+    /// Grid layout for pieces that are all the same and tile the stock
+    /// exactly, kerfs included, in an allowed orientation.
+    ///
+    /// Each piece then takes up `(l + kerf) x (w + kerf)` of a stock grown
+    /// by one kerf on both axes, so no sheet can hold more than the grid:
+    /// filling sheets one after the other is optimal and needs no search.
+    fn exact_tiling(&self, pieces: &[Piece], reserve: Option<Rect>) -> Option<Solution> {
+        let first = pieces.first()?;
+        let simple_sheet = self.plain_sheet(reserve) && self.max_cuts_per_sheet.is_none();
+        let identical = pieces.iter().all(|p| {
+            p.rect == first.rect
+                && p.rotation == first.rotation
+                && p.clearance == [0; 4]
+                && p.penalty == [0, 0]
+        });
+        if !simple_sheet || !identical {
+            return None;
+        }
+
+        let orientations: &[bool] = match first.rotation {
+            RotationConstraint::NoRotate => &[false],
+            RotationConstraint::ForceRotate => &[true],
+            RotationConstraint::Free => &[false, true],
+        };
+        let kerf = self.kerf;
+        let grid = |side: u32, stock_side: u32| {
+            let (pitch, span) = (side as u64 + kerf as u64, stock_side as u64 + kerf as u64);
+            (span % pitch == 0).then(|| (span / pitch) as u32)
+        };
+        let (rotated, rect, cols, rows) = orientations.iter().find_map(|&rotated| {
+            let rect = if rotated {
+                first.rect.rotated()
+            } else {
+                first.rect
+            };
+            let cols = grid(rect.length, self.stock.length)?;
+            let rows = grid(rect.width, self.stock.width)?;
+            Some((rotated, rect, cols, rows))
+        })?;
+
+        let (pitch_x, pitch_y) = (rect.length + kerf, rect.width + kerf);
+        let per_sheet = cols as usize * rows as usize;
+        let sheets = pieces
+            .chunks(per_sheet)
+            .map(|chunk| {
+                let placements: Vec<Placement> = chunk
+                    .iter()
+                    .enumerate()
+                    .map(|(i, piece)| Placement {
+                        rect,
+                        x: (i as u32 % cols) * pitch_x,
+                        y: (i as u32 / cols) * pitch_y,
+                        rotated,
+                        requested: piece.requested,
+                        orientation_deg: if rotated { 90 } else { 0 },
+                        label: piece.label.clone(),
+                        is_filler: false,
+                        directional: piece.directional,
+                        group_hint: piece.group_hint,
+                    })
+                    .collect();
+                // A partly filled last sheet: the rest of the last row, then
+                // everything below it
+                let (full_rows, partial) = (chunk.len() as u32 / cols, chunk.len() as u32 % cols);
+                let mut offcuts = Vec::new();
+                if partial > 0 {
+                    offcuts.push(Offcut {
+                        x: partial * pitch_x,
+                        y: full_rows * pitch_y,
+                        rect: Rect::new(self.stock.length - partial * pitch_x, rect.width),
+                    });
+                }
+                let used_rows = full_rows + u32::from(partial > 0);
+                if used_rows < rows {
+                    offcuts.push(Offcut {
+                        x: 0,
+                        y: used_rows * pitch_y,
+                        rect: Rect::new(self.stock.length, self.stock.width - used_rows * pitch_y),
+                    });
+                }
+                SheetResult {
+                    stock: self.stock,
+                    stock_rotated: false,
+                    waste_area: self.stock.area() - rect.area() * chunk.len() as u64,
+                    placements,
+                    material: None,
+                    offcuts,
+                    reserved: None,
+                    cut_direction: CutDirection::Auto,
+                }
+            })
+            .collect();
+        Some(Solution {
+            sheets,
+            stock: self.stock,
+            warnings: vec![],
+            unplaced: vec![],
+        })
+    }
+
+    fn expand_demands(&self, demands: &[Demand]) -> Vec<Piece> {
+        let sizes = self.snapped_sizes(demands);
+        let mut rotations = Vec::new();
+        for (d, &rect) in demands.iter().zip(&sizes) {
+            let (rotation, penalty) = match self.grain_penalty {
+                None => (
+                    self.strict_rotation(d)
+                        .expect("grain conflicts are rejected by try_solve"),
+                    [0, 0],
+                ),
+                Some(w) => {
+                    // Off-grain on both faces costs twice
+                    let penalty = |stock_grain, piece_grain| match RotationConstraint::from_grain(
+                        stock_grain,
+                        piece_grain,
+                        true,
+                    )
+                    .expect("rotation is allowed")
+                    {
+                        RotationConstraint::Free => [0, 0],
+                        RotationConstraint::NoRotate => [0, w],
+                        RotationConstraint::ForceRotate => [w, 0],
+                    };
+                    let front = penalty(self.stock_grain, d.grain);
+                    let back = penalty(self.back_grain, d.back_grain);
+                    (
+                        RotationConstraint::from_grain(StockGrain::None, d.grain, d.can_rotate())
+                            .expect("no stock grain"),
+                        [
+                            front[0].saturating_add(back[0]),
+                            front[1].saturating_add(back[1]),
+                        ],
+                    )
+                }
+            };
+            rotations.push((
+                rotation.with_cut_direction(self.cut_direction, rect),
+                penalty,
+            ));
+        }
+        self.align_grain_groups(demands, &sizes, &mut rotations);
+
+        let mut pieces = Vec::new();
+        for ((d, &rect), &(rotation, penalty)) in demands.iter().zip(&sizes).zip(&rotations) {
+            for _ in 0..d.qty {
+                pieces.push(Piece {
+                    rect,
+                    rotation,
+                    requested: d.rect,
+                    clearance: d.clearance,
+                    label: d.label.clone(),
+                    directional: d.directional,
+                    group_hint: d.group_hint,
+                    fragile: d.fragile,
+                    penalty,
+                });
+            }
+        }
+        // Sort by area descending for better packing; same-hint pieces of
+        // equal area next to each other so they fill sheets together
+        pieces.sort_by(|a, b| {
+            b.rect
+                .area()
+                .cmp(&a.rect.area())
+                .then(a.group_hint.cmp(&b.group_hint))
+        });
+        pieces
+    }
+
+    /// Turn every demand of a [`Demand::grain_group`] the same way: the
+    /// orientation all its members allow that fits the most pieces on an
+    /// empty sheet, unrotated on a tie. Groups with no orientation
+    /// allowed by all members keep their own constraints.
+    fn align_grain_groups(
+        &self,
+        demands: &[Demand],
+        sizes: &[Rect],
+        rotations: &mut [(RotationConstraint, [u64; 2])],
+    ) {
+        let empty = self.new_bin(CutDirection::Auto, 0, None);
+        let mut groups: Vec<u32> = demands.iter().filter_map(|d| d.grain_group).collect();
+        groups.sort_unstable();
+        groups.dedup();
+        for group in groups {
+            let members: Vec<usize> = (0..demands.len())
+                .filter(|&i| demands[i].grain_group == Some(group))
+                .collect();
+            let fitting = |orientation: RotationConstraint| -> Option<u64> {
+                let mut count = 0;
+                for &i in &members {
+                    // A member forbids this orientation
+                    rotations[i].0.intersect(orientation)?;
+                    let footprint = sizes[i].with_clearance(demands[i].clearance);
+                    if empty
+                        .find_best(footprint, orientation, ScoreStrategy::BestAreaFit)
+                        .is_some()
+                    {
+                        count += demands[i].qty as u64;
+                    }
+                }
+                Some(count)
+            };
+            let orientation = match (
+                fitting(RotationConstraint::NoRotate),
+                fitting(RotationConstraint::ForceRotate),
+            ) {
+                (Some(plain), Some(turned)) if turned > plain => RotationConstraint::ForceRotate,
+                (Some(_), _) => RotationConstraint::NoRotate,
+                (None, Some(_)) => RotationConstraint::ForceRotate,
+                (None, None) => continue,
+            };
+            for i in members {
+                rotations[i].0 = orientation;
+            }
+        }
+    }
+
+    /// Packing size of each demand after merging near-duplicate sizes.
+    ///
+    /// A demand joins the first size class whose seed is within
+    /// `size_tolerance` on both dimensions; the class is packed at the
+    /// maximum length and width of its members so every original still fits.
+    fn snapped_sizes(&self, demands: &[Demand]) -> Vec<Rect> {
+        if self.size_tolerance == 0 {
+            return demands.iter().map(|d| d.rect).collect();
+        }
+
+        // (seed, representative) per size class
+        let mut classes: Vec<(Rect, Rect)> = Vec::new();
+        let mut class_of = Vec::with_capacity(demands.len());
+        for d in demands {
+            let joined = classes.iter_mut().position(|(seed, rep)| {
+                let close = d.rect.length.abs_diff(seed.length) <= self.size_tolerance
+                    && d.rect.width.abs_diff(seed.width) <= self.size_tolerance;
+                let merged = Rect::new(rep.length.max(d.rect.length), rep.width.max(d.rect.width));
+                close && (merged.fits_in(&self.stock) || merged.rotated().fits_in(&self.stock))
+            });
+            match joined {
+                Some(ci) => {
+                    let rep = &mut classes[ci].1;
+                    *rep = Rect::new(rep.length.max(d.rect.length), rep.width.max(d.rect.width));
+                    class_of.push(ci);
+                }
+                None => {
+                    classes.push((d.rect, d.rect));
+                    class_of.push(classes.len() - 1);
+                }
+            }
+        }
+        class_of.into_iter().map(|ci| classes[ci].1).collect()
+    }
+
+    /// Smallest side of any piece: free rects narrower than this are useless.
+    fn min_piece_dimension(pieces: &[Piece]) -> u32 {
+        pieces
+            .iter()
+            .map(|p| {
+                let footprint = p.footprint();
+                footprint.length.min(footprint.width)
+            })
+            .min()
+            .unwrap_or(0)
+    }
+
+    /// Whether sheets are the bare stock: nothing set aside, blocked out or
+    /// pre-cut.
+    fn plain_sheet(&self, reserve: Option<Rect>) -> bool {
+        reserve.is_none()
+            && self.usable_window.is_none()
+            && self.corner_radius == 0
+            && self.first_cut.is_none()
+            && self.strip_boundaries.is_empty()
+            && self.grid_pitch.is_none()
+            && self.defects.is_empty()
+    }
+
+    /// Empty sheet fed turned a quarter, see
+    /// [`with_allow_stock_rotation`](Self::with_allow_stock_rotation).
+    /// `None` when the stock can't be turned for this job.
+    fn new_turned_bin(
+        &self,
+        direction: CutDirection,
+        min_useful: u32,
+        reserve: Option<Rect>,
+    ) -> Option<GuillotineBin> {
+        let turnable = self.allow_stock_rotation
+            && self.stock_grain == StockGrain::None
+            && self.back_grain == StockGrain::None
+            && self.stock != self.stock.rotated()
+            && self.plain_sheet(reserve);
+        if !turnable {
+            return None;
+        }
+        let mut bin = GuillotineBin::new(self.stock.rotated(), self.kerf, direction);
+        bin.set_min_useful(min_useful);
+        bin.set_prefer_origin(self.anchor.is_some());
+        bin.set_long_side_along(self.preferred_long_axis());
+        Some(bin)
+    }
+
+    /// Empty sheet for `piece`, fed whichever way places it with the better
+    /// score. The `reserve` drop is left out when the piece doesn't fit
+    /// beside it. `None` if it fits neither way.
+    fn fresh_bin(
+        &self,
+        piece: &Piece,
+        strategy: ScoreStrategy,
+        direction: CutDirection,
+        min_useful: u32,
+        reserve: Option<Rect>,
+    ) -> Option<(GuillotineBin, ScoredPlacement)> {
+        let bin = self.new_bin(direction, min_useful, reserve);
+        let mut best = piece.find_best_in(&bin, strategy).map(|s| (bin, s));
+        if let Some(turned) = self.new_turned_bin(direction, min_useful, reserve)
+            && let Some(scored) = piece.find_best_in(&turned, strategy)
+            && best.as_ref().is_none_or(|(_, s)| scored.score < s.score)
+        {
+            best = Some((turned, scored));
+        }
+        if best.is_none() && reserve.is_some() {
+            return self.fresh_bin(piece, strategy, direction, min_useful, None);
+        }
+        best
+    }
+
+    /// Axis a piece's longer side should run along when both orientations
+    /// score the same: the stock grain's, else the cut direction's.
+    fn preferred_long_axis(&self) -> Option<Axis> {
+        match (self.stock_grain, self.cut_direction) {
+            (StockGrain::AlongLength, _) | (StockGrain::None, CutDirection::AlongLength) => {
+                Some(Axis::X)
+            }
+            (StockGrain::AlongWidth, _) | (StockGrain::None, CutDirection::AlongWidth) => {
+                Some(Axis::Y)
+            }
+            _ => None,
+        }
+    }
+
+    /// Empty sheet ready for packing.
+    fn new_bin(
+        &self,
+        direction: CutDirection,
+        min_useful: u32,
+        reserve: Option<Rect>,
+    ) -> GuillotineBin {
+        let mut bin = GuillotineBin::new(self.stock, self.kerf, direction);
+        bin.set_min_useful(min_useful);
+        bin.set_prefer_origin(self.anchor.is_some());
+        bin.set_long_side_along(self.preferred_long_axis());
+        if let Some((mut x, mut y, window)) = self.usable_window {
+            // Packed in the mirrored frame when anchored away from (0, 0)
+            let (flip_x, flip_y) = self.anchor_flips();
+            let clipped = Rect::new(
+                window.length.min(self.stock.length.saturating_sub(x)),
+                window.width.min(self.stock.width.saturating_sub(y)),
+            );
+            if flip_x {
+                x = self.stock.length.saturating_sub(x + clipped.length);
+            }
+            if flip_y {
+                y = self.stock.width.saturating_sub(y + clipped.width);
+            }
+            bin.restrict_to(x, y, clipped);
+        }
+        let r = self
+            .corner_radius
+            .min(self.stock.length / 2)
+            .min(self.stock.width / 2);
+        if r > 0 {
+            let (far_x, far_y) = (self.stock.length - r, self.stock.width - r);
+            for (x, y) in [(0, 0), (far_x, 0), (0, far_y), (far_x, far_y)] {
+                bin.block_out(x, y, Rect::new(r, r));
+            }
+        }
+        let (flip_x, flip_y) = self.anchor_flips();
+        for &(mut x, mut y, defect) in &self.defects {
+            if flip_x {
+                x = self.stock.length.saturating_sub(x + defect.length);
+            }
+            if flip_y {
+                y = self.stock.width.saturating_sub(y + defect.width);
+            }
+            bin.block_out(x, y, defect);
+        }
+        if let Some(drop) = reserve {
+            bin.reserve(drop);
+        }
+        let mirrored = |axis: Axis, position: u32| {
+            let (flip, extent) = match axis {
+                Axis::X => (flip_x, self.stock.length),
+                Axis::Y => (flip_y, self.stock.width),
+            };
+            if flip {
+                // Within the stock, see check_fixed_cuts
+                extent - (position + self.kerf)
+            } else {
+                position
+            }
+        };
+        if let Some((axis, position)) = self.first_cut {
+            bin.set_first_cut(axis, mirrored(axis, position));
+        }
+        if !self.strip_boundaries.is_empty() {
+            let axis = match self.cut_direction {
+                CutDirection::AlongWidth => Axis::X,
+                _ => Axis::Y,
+            };
+            let positions: Vec<u32> = self
+                .strip_boundaries
+                .iter()
+                .map(|&p| mirrored(axis, p))
+                .collect();
+            bin.set_strip_boundaries(axis, &positions);
+        }
+        if let Some(pitch) = self.grid_pitch {
+            bin.set_grid_pitch(pitch);
+        }
+        bin
+    }
+
+    /// Place `piece` in `bin` and tag the placement with its requested size.
+    fn place_piece(bin: &mut GuillotineBin, scored: ScoredPlacement, piece: &Piece) {
+        bin.place_with_clearance(scored, piece.rect, piece.clearance);
+        Self::tag_placement(bin, piece);
+    }
+
+    /// [`place_piece`](Self::place_piece), to be taken back with
+    /// [`GuillotineBin::unplace`].
+    fn place_piece_undoable(
+        bin: &mut GuillotineBin,
+        scored: ScoredPlacement,
+        piece: &Piece,
+    ) -> PlaceUndo {
+        let undo = bin.place_undoable(scored, piece.rect, piece.clearance);
+        Self::tag_placement(bin, piece);
+        undo
+    }
+
+    fn tag_placement(bin: &mut GuillotineBin, piece: &Piece) {
+        if let Some(p) = bin.placements.last_mut() {
+            p.requested = piece.requested;
+            p.label = piece.label.clone();
+            p.directional = piece.directional;
+            p.group_hint = piece.group_hint;
+        }
+    }
+
+    fn greedy_best(&self, pieces: &[Piece], reserve: Option<Rect>) -> Solution {
+        self.greedy_best_counted(pieces, reserve).0
+    }
+
+    /// Greedy phase, also returning the number of combinations run.
+    fn greedy_best_counted(&self, pieces: &[Piece], reserve: Option<Rect>) -> (Solution, usize) {
+        let strategies = ScoreStrategy::ALL;
+
+        // In Auto mode, try both directions and keep the best result;
+        // Mixed also competes with sheets choosing theirs one by one
+        let directions = match self.cut_direction {
+            CutDirection::Auto => vec![CutDirection::AlongLength, CutDirection::AlongWidth],
+            CutDirection::Mixed => vec![
+                CutDirection::AlongLength,
+                CutDirection::AlongWidth,
+                CutDirection::Mixed,
+            ],
+            dir => vec![dir],
+        };
+
+        // With a strip threshold, the strip pre-pass variants compete with
+        // the plain ones so enabling it never makes the result worse.
+        let strip_modes = match self.strip_threshold {
+            Some(threshold) => vec![None, Some(threshold)],
+            None => vec![None],
+        };
+        let mut combos: Vec<(CutDirection, ScoreStrategy, Option<u32>)> = Vec::new();
+        for &strips in &strip_modes {
+            for &dir in &directions {
+                for &strategy in &strategies {
+                    combos.push((dir, strategy, strips));
+                }
+            }
+        }
+
+        // Each combination is independent; solutions are collected in combo
+        // order so the selection below is the same on both paths.
+        // Past the deadline, remaining combinations are skipped.
+        let run = |(dir, strategy, strips): (CutDirection, ScoreStrategy, Option<u32>)| {
+            if self.expired() {
+                None
+            } else {
+                Some(self.greedy_solve(pieces, strategy, dir, strips, reserve))
+            }
+        };
+        let solutions: Vec<Option<Solution>> = if !self.strategy_weights.is_empty() {
+            // Likeliest winner first; reaching the bound ends the search
+            combos.sort_by_key(|&(dir, strategy, _)| {
+                std::cmp::Reverse(self.strategy_weight(dir, strategy))
+            });
+            let bound = self.pieces_lower_bound(pieces);
+            let mut solutions = Vec::new();
+            for &combo in &combos {
+                let sol = run(combo);
+                let optimal = sol.as_ref().is_some_and(|s| s.sheets.len() <= bound);
+                solutions.push(sol);
+                if optimal {
+                    break;
+                }
+            }
+            solutions
+        } else if self.parallel && combos.len() > 1 {
+            std::thread::scope(|scope| {
+                let handles: Vec<_> = combos
+                    .iter()
+                    .map(|&combo| scope.spawn(move || run(combo)))
+                    .collect();
+                handles
+                    .into_iter()
+                    .map(|h| h.join().expect("greedy worker panicked"))
+                    .collect()
+            })
+        } else {
+            combos.iter().map(|&combo| run(combo)).collect()
+        };
+
+        let runs = solutions.iter().flatten().count();
+        let mut best: Option<Solution> = None;
+        for sol in solutions.into_iter().flatten() {
+            let dominated = match &best {
+                None => false,
+                Some(prev) => self.is_dominated(&sol, prev),
+            };
+            if !dominated {
+                best = Some(sol);
+            }
+        }
+        let best = best.unwrap_or(Solution {
+            sheets: vec![],
+            stock: self.stock,
+            warnings: vec![],
+            unplaced: vec![],
+        });
+        (best, runs)
+    }
+
+    /// Whether `sol` is no better than `prev` (ties keep `prev`).
+    fn is_dominated(&self, sol: &Solution, prev: &Solution) -> bool {
+        let prev_n = prev.sheets.len();
+        let sol_n = sol.sheets.len();
+        if sol_n != prev_n {
+            return sol_n > prev_n;
+        }
+        if let Some(tie_break) = self.direction_tie_break {
+            // Lower is better
+            let rank = |s: &Solution| self.tie_break_rank(tie_break, s);
+            let (sol_r, prev_r) = (rank(sol), rank(prev));
+            if sol_r != prev_r {
+                return sol_r > prev_r;
+            }
+        }
+        if self.far_edge_waste {
+            // Same sheet count: prefer waste gathered toward the far corner
+            let sol_d = Self::waste_far_corner_distance(sol);
+            let prev_d = Self::waste_far_corner_distance(prev);
+            if sol_d != prev_d {
+                return sol_d > prev_d;
+            }
+        }
+        if self.square_offcuts {
+            let sol_s = Self::offcut_squares_area(sol);
+            let prev_s = Self::offcut_squares_area(prev);
+            if sol_s != prev_s {
+                return sol_s < prev_s;
+            }
+        }
+        // Same sheet count: prefer more compact last sheet
+        Self::last_sheet_bounding_area(sol) >= Self::last_sheet_bounding_area(prev)
+    }
+
+    fn greedy_solve(
+        &self,
+        pieces: &[Piece],
+        strategy: ScoreStrategy,
+        direction: CutDirection,
+        strip_threshold: Option<u32>,
+        reserve: Option<Rect>,
+    ) -> Solution {
+        let mut bins: Vec<GuillotineBin> = Vec::new();
+        let min_useful = Self::min_piece_dimension(pieces);
+
+        let rest: Vec<Piece> = match strip_threshold {
+            Some(threshold) => {
+                let (strips, rest): (Vec<Piece>, Vec<Piece>) = pieces
+                    .iter()
+                    .cloned()
+                    .partition(|p| p.rect.length.min(p.rect.width) < threshold);
+                // Thin pieces first, laid end to end in full-length rows
+                // from the top edge; rows are separated by full-length cuts.
+                let mut strips = self.orient_strips(&strips);
+                while !strips.is_empty() {
+                    let piece = strips.remove(0);
+                    let bi = self.greedy_place(
+                        &mut bins,
+                        &piece,
+                        ScoreStrategy::BestShortSideFit,
+                        CutDirection::AlongLength,
+                        min_useful,
+                        reserve,
+                    );
+                    self.fill_row_end(&mut bins[bi], &mut strips);
+                }
+                for bin in &mut bins {
+                    bin.set_cut_direction(match direction {
+                        CutDirection::Mixed => CutDirection::AlongLength,
+                        dir => dir,
+                    });
+                }
+                rest
+            }
+            None => pieces.to_vec(),
+        };
+
+        if direction == CutDirection::Mixed {
+            self.greedy_fill_sheets(&mut bins, &rest, strategy, min_useful, reserve);
+        } else {
+            for piece in &rest {
+                self.greedy_place(&mut bins, piece, strategy, direction, min_useful, reserve);
+            }
+        }
+
+        self.bins_to_solution(bins)
+    }
+
+    /// Sheet-by-sheet greedy for `CutDirection::Mixed`: top up the open
+    /// bins, then fill one new sheet at a time with whatever still fits,
+    /// packing it both ways and keeping the direction that places more area.
+    fn greedy_fill_sheets(
+        &self,
+        bins: &mut Vec<GuillotineBin>,
+        pieces: &[Piece],
+        strategy: ScoreStrategy,
+        min_useful: u32,
+        reserve: Option<Rect>,
+    ) {
+        let mut left: Vec<&Piece> = pieces.iter().collect();
+        for bin in bins.iter_mut() {
+            left = self.fill_bin(bin, left, strategy);
+        }
+        while !left.is_empty() {
+            let fill_fresh = |reserve: Option<Rect>| {
+                let mut best: Option<(GuillotineBin, Vec<&Piece>)> = None;
+                let fresh = [CutDirection::AlongLength, CutDirection::AlongWidth]
+                    .into_iter()
+                    .flat_map(|dir| {
+                        std::iter::once(self.new_bin(dir, min_useful, reserve))
+                            .chain(self.new_turned_bin(dir, min_useful, reserve))
+                    });
+                for mut bin in fresh {
+                    let rest = self.fill_bin(&mut bin, left.clone(), strategy);
+                    if best
+                        .as_ref()
+                        .is_none_or(|(b, _)| bin.used_area() > b.used_area())
+                    {
+                        best = Some((bin, rest));
+                    }
+                }
+                best.expect("both directions tried")
+            };
+            let (mut bin, mut rest) = fill_fresh(reserve);
+            if bin.placements.is_empty() && reserve.is_some() {
+                // Nothing fits beside the drop: this sheet goes without it
+                (bin, rest) = fill_fresh(None);
+            }
+            if bin.placements.is_empty() {
+                // Nothing fits a fresh sheet; rejected before solving
+                break;
+            }
+            bins.push(bin);
+            left = rest;
+        }
+    }
+
+    /// Place each of `pieces` in `bin` where it fits, in order. Returns the
+    /// pieces that didn't fit.
+    fn fill_bin<'a>(
+        &self,
+        bin: &mut GuillotineBin,
+        pieces: Vec<&'a Piece>,
+        strategy: ScoreStrategy,
+    ) -> Vec<&'a Piece> {
+        pieces
+            .into_iter()
+            .filter(
+                |piece| match self.find_best_on_sheet(piece, bin, strategy) {
+                    Some(scored) if self.fits_cut_limit(bin, scored, piece) => {
+                        Self::place_piece(bin, scored, piece);
+                        false
+                    }
+                    _ => true,
+                },
+            )
+            .collect()
+    }
+
+    /// Place `piece` in the best-scoring open bin, or in a new bin. Returns
+    /// the index of the bin it went into.
+    fn greedy_place(
+        &self,
+        bins: &mut Vec<GuillotineBin>,
+        piece: &Piece,
+        strategy: ScoreStrategy,
+        direction: CutDirection,
+        min_useful: u32,
+        reserve: Option<Rect>,
+    ) -> usize {
+        // Try to fit in existing bins, those already holding pieces of the
+        // same group hint first
+        let mut best_bin = None;
+        let mut best_score = None;
+
+        for (bi, bin) in bins.iter_mut().enumerate() {
+            if let Some(scored) = self.find_best_on_sheet(piece, bin, strategy) {
+                let score = (!piece.shares_hint_with(bin), scored.score);
+                if (best_score.is_none() || score < best_score.unwrap())
+                    && self.fits_cut_limit(bin, scored, piece)
+                {
+                    best_bin = Some(bi);
+                    best_score = Some(score);
+                }
+            }
+        }
+
+        // An off-grain fit only beats a fresh sheet while its penalty is
+        // smaller than the fresh sheet's own score
+        if let Some(bi) = best_bin {
+            let scored = self.find_best_on_sheet(piece, &bins[bi], strategy).unwrap();
+            if piece.penalty[scored.rotated as usize] > 0
+                && let Some((mut bin, fresh)) =
+                    self.fresh_bin(piece, strategy, direction, min_useful, reserve)
+                && fresh.score < scored.score
+            {
+                Self::place_piece(&mut bin, fresh, piece);
+                bins.push(bin);
+                return bins.len() - 1;
+            }
+            Self::place_piece(&mut bins[bi], scored, piece);
+            bi
+        } else {
+            // Open new bin
+            let (mut bin, scored) = self
+                .fresh_bin(piece, strategy, direction, min_useful, reserve)
+                .expect("piece larger than stock");
+            Self::place_piece(&mut bin, scored, piece);
+            bins.push(bin);
+            bins.len() - 1
+        }
+    }
+
+    /// Fill the rest of the row of the last piece placed in `bin`, to its
+    /// right, with the `strips` that fit it best, largest first. Placing
+    /// strips in width order alone lets a narrow short one take a row end
+    /// that a longer one would have filled.
+    fn fill_row_end(&self, bin: &mut GuillotineBin, strips: &mut Vec<Piece>) {
+        let Some(last) = bin.placements.last() else {
+            return;
+        };
+        let (x, y, width) = (last.x, last.y, last.rect.width);
+        let in_row = |f: &FreeRect| f.x > x && f.y >= y && f.y + f.rect.width <= y + width;
+        loop {
+            let orientation = self.sheet_orientation(&bin.placements);
+            let best = strips
+                .iter()
+                .enumerate()
+                .filter_map(|(i, piece)| {
+                    let rotation = piece.rotation.intersect(orientation)?;
+                    let scored = bin.find_best_where(
+                        piece.footprint(),
+                        rotation,
+                        ScoreStrategy::BestAreaFit,
+                        piece.penalty,
+                        &in_row,
+                    )?;
+                    Some((i, scored))
+                })
+                .min_by_key(|(_, scored)| scored.score);
+            let Some((i, scored)) = best else {
+                return;
+            };
+            if !self.fits_cut_limit(bin, scored, &strips[i]) {
+                return;
+            }
+            let piece = strips.remove(i);
+            Self::place_piece(bin, scored, &piece);
+        }
+    }
+
+    /// Orient thin pieces lengthwise (when rotation allows) and order them
+    /// widest first, then longest, so rows fill with same-width strips.
+    fn orient_strips(&self, strips: &[Piece]) -> Vec<Piece> {
+        let mut oriented: Vec<Piece> = strips
+            .iter()
+            .map(|p| {
+                let mut p = p.clone();
+                if p.rotation == RotationConstraint::Free {
+                    let lengthwise = if p.rect.length >= p.rect.width {
+                        p.rect
+                    } else {
+                        p.rect.rotated()
+                    };
+                    if lengthwise.fits_in(&self.stock) {
+                        p.rotation = if lengthwise == p.rect {
+                            RotationConstraint::NoRotate
+                        } else {
+                            RotationConstraint::ForceRotate
+                        };
+                    }
+                }
+                p
+            })
+            .collect();
+        let placed = |p: &Piece| match p.rotation {
+            RotationConstraint::ForceRotate => p.rect.rotated(),
+            _ => p.rect,
+        };
+        oriented.sort_by_key(|p| {
+            let r = placed(p);
+            std::cmp::Reverse((r.width, r.length))
+        });
+        oriented
+    }
+
+    fn bb_directions(&self) -> Vec<CutDirection> {
+        // Every new bin tries both, so Auto already mixes directions here
+        match self.cut_direction {
+            CutDirection::Auto | CutDirection::Mixed => {
+                vec![CutDirection::AlongLength, CutDirection::AlongWidth]
+            }
+            dir => vec![dir],
+        }
+    }
+
+    fn branch_and_bound(
+        &self,
+        pieces: &[Piece],
+        upper_bound: usize,
+        reserve: Option<Rect>,
+    ) -> Solution {
+        self.branch_and_bound_counted(pieces, upper_bound, reserve)
+            .0
+    }
+
+    /// Branch and bound, also returning the number of search nodes visited.
+    fn branch_and_bound_counted(
+        &self,
+        pieces: &[Piece],
+        upper_bound: usize,
+        reserve: Option<Rect>,
+    ) -> (Solution, u64) {
+        // Skip B&B for large or varied inputs (too slow)
+        if self.bb_search_size(pieces) > BB_SEARCH_BUDGET {
+            return (
+                Solution {
+                    sheets: vec![],
+                    stock: self.stock,
+                    warnings: vec![],
+                    unplaced: vec![],
+                },
+                0,
+            );
+        }
+
+        let mut search = BbSearch {
+            best_bins: None,
+            best_count: upper_bound,
+            nodes: 0,
+            reserve,
+        };
+
+        self.bb_recurse(pieces, 0, &mut Vec::new(), 0, &mut search);
+
+        let solution = match search.best_bins {
+            Some(bins) => self.bins_to_solution(bins),
+            None => Solution {
+                sheets: vec![],
+                stock: self.stock,
+                warnings: vec![],
+                unplaced: vec![],
+            },
+        };
+        (solution, search.nodes)
+    }
+
+    /// Rough number of layouts branch and bound has to try for `pieces`:
+    /// each piece goes on one of a few sheets, in each orientation it may
+    /// take. With symmetry breaking, a run of identical pieces only counts
+    /// its distinct spreads over the sheets, so many copies of few sizes
+    /// stay cheap where as many different sizes don't.
+    fn bb_search_size(&self, pieces: &[Piece]) -> f64 {
+        let sheets = BB_SEARCH_SHEETS;
+        let mut size = 1.0;
+        for run in pieces.chunk_by(|a, b| a == b) {
+            let footprint = run[0].footprint();
+            let orientations: f64 = match run[0].rotation {
+                RotationConstraint::Free if footprint != footprint.rotated() => 2.0,
+                _ => 1.0,
+            };
+            let k = run.len() as f64;
+            size *= if self.symmetry_breaking {
+                // Multisets of k sheets: C(sheets + k - 1, k)
+                (1..run.len()).fold(sheets, |c, i| c * (sheets + i as f64) / (i + 1) as f64)
+                    * orientations.powf(k)
+            } else {
+                (sheets * orientations).powf(k)
+            };
+        }
+        size
+    }
+
+    /// Placements are made in `bins` and taken back on the way out, so
+    /// the whole search works on a single set of bins.
+    ///
+    /// `min_bin` is the lowest bin index the piece may go to: with symmetry
+    /// breaking, a piece identical to the previous one never goes to an
+    /// earlier bin, since swapping the two would give the same layout.
+    fn bb_recurse(
+        &self,
+        pieces: &[Piece],
+        idx: usize,
+        bins: &mut Vec<GuillotineBin>,
+        min_bin: usize,
+        search: &mut BbSearch,
+    ) {
+        search.nodes += 1;
+        if self.expired() || self.meets_target(search.best_count) {
+            return;
+        }
+
+        if idx == pieces.len() {
+            if bins.len() < search.best_count {
+                search.best_count = bins.len();
+                if let Some(on_improvement) = &self.on_improvement {
+                    let sheets = self.bins_to_solution(bins.clone()).sheets;
+                    on_improvement(&self.finish_sheets(sheets, vec![]));
+                }
+                search.best_bins = Some(bins.clone());
+            }
+            return;
+        }
+
+        // Pruning: if current bins already >= best, no point continuing
+        if bins.len() >= search.best_count {
+            return;
+        }
+
+        let piece = &pieces[idx];
+
+        // Lower bound: remaining area / stock area
+        // (u128: sums of areas near u64::MAX would overflow)
+        let remaining_area: u128 = pieces[idx..].iter().map(|p| p.rect.area() as u128).sum();
+        let stock_area = self.stock.area() as u128;
+        let min_extra_bins = if remaining_area > 0 {
+            remaining_area.div_ceil(stock_area) as usize
+        } else {
+            0
+        };
+        let open_free_area: u128 = bins
+            .iter()
+            .flat_map(|b| &b.free_rects)
+            .map(|f| f.rect.area() as u128)
+            .sum();
+        let needed = if remaining_area > open_free_area {
+            bins.len() + (remaining_area - open_free_area).div_ceil(stock_area) as usize
+        } else {
+            bins.len()
+        };
+        let lower_bound = std::cmp::max(
+            needed,
+            bins.len()
+                .saturating_add(min_extra_bins.saturating_sub(bins.len())),
+        );
+
+        if lower_bound >= search.best_count {
+            return;
+        }
+
+        // Bin the next piece may start from, if it is identical to this one
+        let next_min_bin = |bi: usize| {
+            let same_next = self.symmetry_breaking && pieces.get(idx + 1) == Some(piece);
+            if same_next { bi } else { 0 }
+        };
+
+        // Try placing in each existing bin
+        for bi in min_bin..bins.len() {
+            // Bins in the same state lead to the same subtrees
+            if self.symmetry_breaking && bins[min_bin..bi].iter().any(|b| b.same_state(&bins[bi])) {
+                continue;
+            }
+
+            // Preferred (unpenalized) orientation first
+            let orientations: &[bool] = match piece.rotation {
+                RotationConstraint::Free if piece.footprint() != piece.footprint().rotated() => {
+                    if piece.penalty[0] > piece.penalty[1] {
+                        &[true, false]
+                    } else {
+                        &[false, true]
+                    }
+                }
+                RotationConstraint::ForceRotate => &[true],
+                _ => &[false],
+            };
+
+            for &rotated in orientations {
+                // Costs more than the sheet it could save
+                if piece.penalty[rotated as usize] >= self.stock.area() {
+                    continue;
+                }
+                let orientation = if rotated {
+                    RotationConstraint::ForceRotate
+                } else {
+                    RotationConstraint::NoRotate
+                };
+                if self
+                    .sheet_orientation(&bins[bi].placements)
+                    .intersect(orientation)
+                    .is_none()
+                {
+                    continue;
+                }
+                let strategy = ScoreStrategy::BestAreaFit;
+
+                if let Some(scored) = piece.find_oriented(&bins[bi], orientation, strategy) {
+                    if !self.fits_cut_limit(&mut bins[bi], scored, piece) {
+                        continue;
+                    }
+                    let undo = Self::place_piece_undoable(&mut bins[bi], scored, piece);
+                    self.bb_recurse(pieces, idx + 1, bins, next_min_bin(bi), search);
+                    bins[bi].unplace(undo);
+                }
+            }
+        }
+
+        // Try opening a new bin (only if it wouldn't exceed best)
+        if bins.len() + 1 < search.best_count {
+            let (min_useful, reserve) = (Self::min_piece_dimension(pieces), search.reserve);
+            let fresh = self.bb_directions().into_iter().flat_map(|dir| {
+                std::iter::once(self.new_bin(dir, min_useful, reserve))
+                    .chain(self.new_turned_bin(dir, min_useful, reserve))
+            });
+            for mut new_bin in fresh {
+                let scored = piece.find_best_in(&new_bin, ScoreStrategy::BestAreaFit);
+                if let Some(scored) = scored {
+                    Self::place_piece(&mut new_bin, scored, piece);
+                    bins.push(new_bin);
+                    let bi = bins.len() - 1;
+                    self.bb_recurse(pieces, idx + 1, bins, next_min_bin(bi), search);
+                    bins.pop();
+                }
+            }
+        }
+    }
+
+    /// Normalized distance between the waste centroid and the far corner
+    /// (max x, max y) of the stock, summed over sheets.
+    /// Used as tiebreaker: smaller means offcuts sit at the trailing edges.
+    fn waste_far_corner_distance(sol: &Solution) -> f64 {
+        sol.sheets
+            .iter()
+            .map(|sheet| {
+                let stock = sheet.stock;
+                let stock_area = stock.area() as f64;
+                // Waste moment = stock moment - sum of piece moments
+                let mut waste_area = stock_area;
+                let mut mx = stock_area * stock.length as f64 / 2.0;
+                let mut my = stock_area * stock.width as f64 / 2.0;
+                for p in &sheet.placements {
+                    let area = p.rect.area() as f64;
+                    waste_area -= area;
+                    mx -= area * (p.x as f64 + p.rect.length as f64 / 2.0);
+                    my -= area * (p.y as f64 + p.rect.width as f64 / 2.0);
+                }
+                if waste_area <= 0.0 {
+                    return 0.0;
+                }
+                let (cx, cy) = (mx / waste_area, my / waste_area);
+                (stock.length as f64 - cx) / stock.length as f64
+                    + (stock.width as f64 - cy) / stock.width as f64
+            })
+            .sum()
+    }
+
+    /// Area of the largest square in each offcut (its shorter side squared,
+    /// i.e. its area times its short/long aspect ratio), summed over sheets.
+    /// Used as tiebreaker: larger means chunkier drops.
+    fn offcut_squares_area(sol: &Solution) -> u64 {
+        sol.sheets
+            .iter()
+            .flat_map(|sheet| &sheet.offcuts)
+            .map(|o| {
+                let side = o.rect.length.min(o.rect.width) as u64;
+                side * side
+            })
+            .sum()
+    }
+
+    /// Score of `sol` under `tie_break`, lower being better: cuts, sheets
+    /// not cut in the preferred direction, or waste within the pieces'
+    /// bounds.
+    fn tie_break_rank(&self, tie_break: DirectionTieBreak, sol: &Solution) -> u64 {
+        let against =
+            |dir: CutDirection| sol.sheets.iter().filter(|s| s.cut_direction != dir).count() as u64;
+        match tie_break {
+            DirectionTieBreak::PreferFewerCuts => sol
+                .sheets
+                .iter()
+                .map(|s| {
+                    crate::cut_tree::cut_count(s.stock, &s.placements, self.kerf)
+                        .map_or(u64::MAX / 2, |n| n as u64)
+                })
+                .fold(0, u64::saturating_add),
+            DirectionTieBreak::PreferLength => against(CutDirection::AlongLength),
+            DirectionTieBreak::PreferWidth => against(CutDirection::AlongWidth),
+            DirectionTieBreak::PreferLowerWaste => sol
+                .sheets
+                .iter()
+                .map(|s| {
+                    let used: u64 = s.placements.iter().map(|p| p.rect.area()).sum();
+                    s.used_bounds().2.area() - used
+                })
+                .sum(),
+        }
+    }
+
+    /// Bounding box area of the last sheet's placements.
+    /// Used as tiebreaker: smaller means more compact layout.
+    fn last_sheet_bounding_area(sol: &Solution) -> u64 {
+        sol.sheets.last().map_or(0, |sheet| {
+            let max_x = sheet
+                .placements
+                .iter()
+                .map(|p| p.x + p.rect.length)
+                .max()
+                .unwrap_or(0);
+            let max_y = sheet
+                .placements
+                .iter()
+                .map(|p| p.y + p.rect.width)
+                .max()
+                .unwrap_or(0);
+            max_x as u64 * max_y as u64
+        })
+    }
+
+    fn bins_to_solution(&self, bins: Vec<GuillotineBin>) -> Solution {
+        let stock_area = self.stock.area();
+        let sheets = bins
+            .into_iter()
+            .map(|bin| {
+                let used = bin.used_area();
+                let cut_direction = bin.cut_direction();
+                let stock = bin.stock();
+                SheetResult {
+                    stock,
+                    stock_rotated: stock != self.stock,
+                    placements: bin.placements,
+                    waste_area: stock_area - used,
+                    material: None,
+                    offcuts: bin
+                        .free_rects
+                        .iter()
+                        .chain(&bin.scrap)
+                        .map(|f| Offcut {
+                            x: f.x,
+                            y: f.y,
+                            rect: f.rect,
+                        })
+                        .collect(),
+                    reserved: bin.reserved.map(|f| Offcut {
+                        x: f.x,
+                        y: f.y,
+                        rect: f.rect,
+                    }),
+                    cut_direction,
+                }
+            })
+            .collect();
+
+        Solution {
+            sheets,
+            stock: self.stock,
+            warnings: vec![],
+            unplaced: vec![],
+        }
+    }
+}
+
+/// What a sheet holds regardless of layout: stock, material, and each
+/// piece's unturned size, requested size, label and options, sorted.
+type PieceSet = (
+    (u32, u32),
+    Option<String>,
+    Vec<(u32, u32, u32, u32, Option<String>, bool, bool, Option<u32>)>,
+);
+
+fn piece_set(sheet: &SheetResult) -> PieceSet {
+    let mut pieces: Vec<_> = sheet
+        .placements
+        .iter()
+        .map(|p| {
+            let size = p.original_size();
+            (
+                size.length,
+                size.width,
+                p.requested.length,
+                p.requested.width,
+                p.label.clone(),
+                p.is_filler,
+                p.directional,
+                p.group_hint,
+            )
+        })
+        .collect();
+    pieces.sort_unstable();
+    (
+        (sheet.stock.length, sheet.stock.width),
+        sheet.material.clone(),
+        pieces,
+    )
+}
+
+/// Every length a single row of pieces can add up to, kerfs between
+/// pieces included, in increasing order.
+fn row_lengths(footprints: &[(Rect, bool, u32)], kerf: u32) -> Vec<u32> {
+    // Bitset of reachable sums, each piece adding its side plus one kerf
+    let max: usize = footprints
+        .iter()
+        .map(|(r, _, qty)| (r.length.max(r.width) as usize + kerf as usize) * *qty as usize)
+        .sum();
+    let mut reachable = vec![0u64; max / 64 + 1];
+    reachable[0] = 1;
+    for (r, rotate, qty) in footprints {
+        let sides: &[u32] = if *rotate && r.length != r.width {
+            &[r.length, r.width]
+        } else {
+            &[r.length]
+        };
+        for _ in 0..*qty {
+            let before = reachable.clone();
+            for &side in sides {
+                let shift = side as usize + kerf as usize;
+                let (words, bits) = (shift / 64, shift % 64);
+                for i in (words..reachable.len()).rev() {
+                    let mut moved = before[i - words] << bits;
+                    if bits > 0 && i > words {
+                        moved |= before[i - words - 1] >> (64 - bits);
+                    }
+                    reachable[i] |= moved;
+                }
+            }
+        }
+    }
+    (1..=max)
+        .filter(|&s| reachable[s / 64] >> (s % 64) & 1 == 1)
+        .filter_map(|s| (s as u32).checked_sub(kerf).filter(|&l| l > 0))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{Demand, PieceGrain, Placement, StockGrain};
+
+    /// Validates a complete solution:
+    /// 1. Every placement fits within the stock dimensions
+    /// 2. No two placements on the same sheet overlap
+    /// 3. The total number of placed pieces matches expectations
+    fn assert_solution_valid(sol: &Solution, expected_pieces: usize) {
+        let total_placed: usize = sol.sheets.iter().map(|s| s.placements.len()).sum();
+        assert_eq!(
+            total_placed, expected_pieces,
+            "expected {} pieces placed, got {}",
+            expected_pieces, total_placed
+        );
+
+        for (si, sheet) in sol.sheets.iter().enumerate() {
+            let stock = sheet.stock;
+            for (pi, p) in sheet.placements.iter().enumerate() {
+                // Check bounds
+                assert!(
+                    p.x + p.rect.length <= stock.length,
+                    "sheet {si}, piece {pi} ({}) exceeds stock length: x={} + length={} > {}",
+                    p.rect,
+                    p.x,
+                    p.rect.length,
+                    stock.length
+                );
+                assert!(
+                    p.y + p.rect.width <= stock.width,
+                    "sheet {si}, piece {pi} ({}) exceeds stock width: y={} + width={} > {}",
+                    p.rect,
+                    p.y,
+                    p.rect.width,
+                    stock.width
+                );
+            }
+
+            // Check no overlaps between any pair of placements
+            assert_no_overlaps(si, &sheet.placements);
+        }
+    }
+
+    fn assert_no_overlaps(sheet_idx: usize, placements: &[Placement]) {
+        for i in 0..placements.len() {
+            for j in (i + 1)..placements.len() {
+                let a = &placements[i];
+                let b = &placements[j];
+                assert!(
+                    !a.overlaps(b, 0),
+                    "sheet {sheet_idx}: piece {i} ({} @ ({},{})) overlaps piece {j} ({} @ ({},{}))",
+                    a.rect,
+                    a.x,
+                    a.y,
+                    b.rect,
+                    b.x,
+                    b.y
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_single_piece() {
+        let solver = Solver::new(
+            Rect::new(100, 100),
+            0,
+            CutDirection::Auto,
+            StockGrain::None,
+            vec![Demand {
+                rect: Rect::new(50, 50),
+                qty: 1,
+                allow_rotate: true,
+                grain: PieceGrain::Auto,
+                ..Default::default()
+            }],
+        );
+        let sol = solver.solve();
+        assert_solution_valid(&sol, 1);
+        assert_eq!(sol.sheet_count(), 1);
+    }
+
+    #[test]
+    fn test_exact_fit_four_pieces() {
+        let solver = Solver::new(
+            Rect::new(100, 100),
+            0,
+            CutDirection::Auto,
+            StockGrain::None,
+            vec![Demand {
+                rect: Rect::new(50, 50),
+                qty: 4,
+                allow_rotate: false,
+                grain: PieceGrain::Auto,
+                ..Default::default()
+            }],
+        );
+        let sol = solver.solve();
+        assert_solution_valid(&sol, 4);
+        assert_eq!(sol.sheet_count(), 1);
+    }
+
+    #[test]
+    fn test_needs_two_sheets() {
+        let solver = Solver::new(
+            Rect::new(100, 100),
+            0,
+            CutDirection::Auto,
+            StockGrain::None,
+            vec![Demand {
+                rect: Rect::new(60, 60),
+                qty: 4,
+                allow_rotate: false,
+                grain: PieceGrain::Auto,
+                ..Default::default()
+            }],
+        );
+        let sol = solver.solve();
+        assert_solution_valid(&sol, 4);
+        // Each sheet can fit at most 1 piece (60x60 leaves 40x100 and 60x40 — no room for another 60x60)
+        assert!(sol.sheet_count() >= 4);
+    }
+
+    #[test]
+    fn test_rotation_helps() {
+        // Stock 100x50, piece 50x100 — only fits if rotated
+        let solver = Solver::new(
+            Rect::new(100, 50),
+            0,
+            CutDirection::Auto,
+            StockGrain::None,
+            vec![Demand {
+                rect: Rect::new(50, 100),
+                qty: 1,
+                allow_rotate: true,
+                grain: PieceGrain::Auto,
+                ..Default::default()
+            }],
+        );
+        let sol = solver.solve();
+        assert_solution_valid(&sol, 1);
+        assert_eq!(sol.sheet_count(), 1);
+        assert!(sol.sheets[0].placements[0].rotated);
+    }
+
+    #[test]
+    fn test_no_demands() {
+        let solver = Solver::new(
+            Rect::new(100, 100),
+            0,
+            CutDirection::Auto,
+            StockGrain::None,
+            vec![],
+        );
+        let sol = solver.solve();
+        assert_solution_valid(&sol, 0);
+    }
+
+    #[test]
+    fn test_kerf_reduces_capacity() {
+        // Without kerf: 2 pieces of 50x100 fit in 100x100
+        let solver_no_kerf = Solver::new(
+            Rect::new(100, 100),
+            0,
+            CutDirection::Auto,
+            StockGrain::None,
+            vec![Demand {
+                rect: Rect::new(50, 100),
+                qty: 2,
+                allow_rotate: false,
+                grain: PieceGrain::Auto,
+                ..Default::default()
+            }],
+        );
+        let sol_no_kerf = solver_no_kerf.solve();
+        assert_solution_valid(&sol_no_kerf, 2);
+        assert_eq!(sol_no_kerf.sheet_count(), 1);
+
+        // With kerf of 5: 50 + 5 + 50 = 105 > 100, needs 2 sheets
+        let solver_kerf = Solver::new(
+            Rect::new(100, 100),
+            5,
+            CutDirection::Auto,
+            StockGrain::None,
+            vec![Demand {
+                rect: Rect::new(50, 100),
+                qty: 2,
+                allow_rotate: false,
+                grain: PieceGrain::Auto,
+                ..Default::default()
+            }],
+        );
+        let sol_kerf = solver_kerf.solve();
+        assert_solution_valid(&sol_kerf, 2);
+        assert_eq!(sol_kerf.sheet_count(), 2);
+    }
+
+    #[test]
+    fn test_waste_percent() {
+        let solver = Solver::new(
+            Rect::new(100, 100),
+            0,
+            CutDirection::Auto,
+            StockGrain::None,
+            vec![Demand {
+                rect: Rect::new(100, 100),
+                qty: 1,
+                allow_rotate: false,
+                grain: PieceGrain::Auto,
+                ..Default::default()
+            }],
+        );
+        let sol = solver.solve();
+        assert_solution_valid(&sol, 1);
+        assert!((sol.total_waste_percent() - 0.0).abs() < 0.01);
+    }
+
+    /// 30 pieces, 6 different sizes, standard plywood sheet 2440x1220, no kerf.
+    /// Verifies all pieces are placed and no placement overlaps or exceeds the stock.
+    #[test]
+    fn test_complex_mixed_sizes_no_kerf() {
+        let stock = Rect::new(2440, 1220);
+        let demands = vec![
+            Demand {
+                rect: Rect::new(800, 600),
+                qty: 5,
+                allow_rotate: true,
+                grain: PieceGrain::Auto,
+                ..Default::default()
+            },
+            Demand {
+                rect: Rect::new(400, 300),
+                qty: 8,
+                allow_rotate: true,
+                grain: PieceGrain::Auto,
+                ..Default::default()
+            },
+            Demand {
+                rect: Rect::new(600, 400),
+                qty: 4,
+                allow_rotate: true,
+                grain: PieceGrain::Auto,
+                ..Default::default()
+            },
+            Demand {
+                rect: Rect::new(1200, 600),
+                qty: 3,
+                allow_rotate: true,
+                grain: PieceGrain::Auto,
+                ..Default::default()
+            },
+            Demand {
+                rect: Rect::new(300, 200),
+                qty: 6,
+                allow_rotate: true,
+                grain: PieceGrain::Auto,
+                ..Default::default()
+            },
+            Demand {
+                rect: Rect::new(500, 500),
+                qty: 4,
+                allow_rotate: false,
+                grain: PieceGrain::Auto,
+                ..Default::default()
+            },
+        ];
+        let total_pieces: u32 = demands.iter().map(|d| d.qty).sum();
+        assert_eq!(total_pieces, 30);
+
+        let solver = Solver::new(stock, 0, CutDirection::Auto, StockGrain::None, demands);
+        let sol = solver.solve();
+        assert_solution_valid(&sol, 30);
+
+        // Lower bound: total piece area / stock area
+        let total_area: u64 = sol
+            .sheets
+            .iter()
+            .flat_map(|s| &s.placements)
+            .map(|p| p.rect.area())
+            .sum();
+        let min_sheets = total_area.div_ceil(stock.area()) as usize;
+        assert!(sol.sheet_count() >= min_sheets);
+    }
+
+    /// 35 pieces, 7 different sizes, with kerf=3.
+    /// Kerf eats into available space, so more sheets are needed.
+    #[test]
+    fn test_complex_mixed_sizes_with_kerf() {
+        let stock = Rect::new(2440, 1220);
+        let demands = vec![
+            Demand {
+                rect: Rect::new(700, 500),
+                qty: 6,
+                allow_rotate: true,
+                grain: PieceGrain::Auto,
+                ..Default::default()
+            },
+            Demand {
+                rect: Rect::new(350, 250),
+                qty: 5,
+                allow_rotate: true,
+                grain: PieceGrain::Auto,
+                ..Default::default()
+            },
+            Demand {
+                rect: Rect::new(1000, 400),
+                qty: 3,
+                allow_rotate: true,
+                grain: PieceGrain::Auto,
+                ..Default::default()
+            },
+            Demand {
+                rect: Rect::new(450, 450),
+                qty: 4,
+                allow_rotate: false,
+                grain: PieceGrain::Auto,
+                ..Default::default()
+            },
+            Demand {
+                rect: Rect::new(600, 300),
+                qty: 7,
+                allow_rotate: true,
+                grain: PieceGrain::Auto,
+                ..Default::default()
+            },
+            Demand {
+                rect: Rect::new(250, 150),
+                qty: 5,
+                allow_rotate: true,
+                grain: PieceGrain::Auto,
+                ..Default::default()
+            },
+            Demand {
+                rect: Rect::new(800, 400),
+                qty: 5,
+                allow_rotate: true,
+                grain: PieceGrain::Auto,
+                ..Default::default()
+            },
+        ];
+        let total_pieces: u32 = demands.iter().map(|d| d.qty).sum();
+        assert_eq!(total_pieces, 35);
+
+        let solver = Solver::new(stock, 3, CutDirection::Auto, StockGrain::None, demands);
+        let sol = solver.solve();
+        assert_solution_valid(&sol, 35);
+    }
+
+    /// 40 pieces, 8 different sizes, rotation disabled for all.
+    /// Without rotation the solver has less flexibility, requiring more sheets.
+    #[test]
+    fn test_complex_no_rotation() {
+        let stock = Rect::new(2440, 1220);
+        let demands = vec![
+            Demand {
+                rect: Rect::new(1200, 600),
+                qty: 4,
+                allow_rotate: false,
+                grain: PieceGrain::Auto,
+                ..Default::default()
+            },
+            Demand {
+                rect: Rect::new(800, 400),
+                qty: 6,
+                allow_rotate: false,
+                grain: PieceGrain::Auto,
+                ..Default::default()
+            },
+            Demand {
+                rect: Rect::new(600, 300),
+                qty: 5,
+                allow_rotate: false,
+                grain: PieceGrain::Auto,
+                ..Default::default()
+            },
+            Demand {
+                rect: Rect::new(400, 400),
+                qty: 3,
+                allow_rotate: false,
+                grain: PieceGrain::Auto,
+                ..Default::default()
+            },
+            Demand {
+                rect: Rect::new(500, 250),
+                qty: 7,
+                allow_rotate: false,
+                grain: PieceGrain::Auto,
+                ..Default::default()
+            },
+            Demand {
+                rect: Rect::new(300, 200),
+                qty: 5,
+                allow_rotate: false,
+                grain: PieceGrain::Auto,
+                ..Default::default()
+            },
+            Demand {
+                rect: Rect::new(700, 350),
+                qty: 6,
+                allow_rotate: false,
+                grain: PieceGrain::Auto,
+                ..Default::default()
+            },
+            Demand {
+                rect: Rect::new(250, 150),
+                qty: 4,
+                allow_rotate: false,
+                grain: PieceGrain::Auto,
+                ..Default::default()
+            },
+        ];
+        let total_pieces: u32 = demands.iter().map(|d| d.qty).sum();
+        assert_eq!(total_pieces, 40);
+
+        let solver = Solver::new(
+            stock,
+            0,
+            CutDirection::Auto,
+            StockGrain::None,
+            demands.clone(),
+        );
+        let sol_no_rot = solver.solve();
+        assert_solution_valid(&sol_no_rot, 40);
+
+        // Compare with rotation enabled — should use <= sheets
+        let demands_rot: Vec<Demand> = demands
+            .into_iter()
+            .map(|d| Demand {
+                allow_rotate: true,
+                ..d
+            })
+            .collect();
+        let solver_rot = Solver::new(stock, 0, CutDirection::Auto, StockGrain::None, demands_rot);
+        let sol_rot = solver_rot.solve();
+        assert_solution_valid(&sol_rot, 40);
+        assert!(sol_rot.sheet_count() <= sol_no_rot.sheet_count());
+    }
+
+    /// 50 pieces, 10 different sizes, kerf=4, mix of rotation allowed/disallowed.
+    #[test]
+    fn test_complex_large_batch_mixed_rotation() {
+        let stock = Rect::new(3000, 1500);
+        let demands = vec![
+            Demand {
+                rect: Rect::new(900, 600),
+                qty: 5,
+                allow_rotate: true,
+                grain: PieceGrain::Auto,
+                ..Default::default()
+            },
+            Demand {
+                rect: Rect::new(500, 400),
+                qty: 6,
+                allow_rotate: false,
+                grain: PieceGrain::Auto,
+                ..Default::default()
+            },
+            Demand {
+                rect: Rect::new(700, 350),
+                qty: 4,
+                allow_rotate: true,
+                grain: PieceGrain::Auto,
+                ..Default::default()
+            },
+            Demand {
+                rect: Rect::new(1200, 500),
+                qty: 3,
+                allow_rotate: true,
+                grain: PieceGrain::Auto,
+                ..Default::default()
+            },
+            Demand {
+                rect: Rect::new(300, 300),
+                qty: 8,
+                allow_rotate: false,
+                grain: PieceGrain::Auto,
+                ..Default::default()
+            },
+            Demand {
+                rect: Rect::new(450, 200),
+                qty: 6,
+                allow_rotate: true,
+                grain: PieceGrain::Auto,
+                ..Default::default()
+            },
+            Demand {
+                rect: Rect::new(600, 450),
+                qty: 5,
+                allow_rotate: false,
+                grain: PieceGrain::Auto,
+                ..Default::default()
+            },
+            Demand {
+                rect: Rect::new(800, 300),
+                qty: 4,
+                allow_rotate: true,
+                grain: PieceGrain::Auto,
+                ..Default::default()
+            },
+            Demand {
+                rect: Rect::new(350, 250),
+                qty: 5,
+                allow_rotate: true,
+                grain: PieceGrain::Auto,
+                ..Default::default()
+            },
+            Demand {
+                rect: Rect::new(1000, 700),
+                qty: 4,
+                allow_rotate: false,
+                grain: PieceGrain::Auto,
+                ..Default::default()
+            },
+        ];
+        let total_pieces: u32 = demands.iter().map(|d| d.qty).sum();
+        assert_eq!(total_pieces, 50);
+
+        let solver = Solver::new(stock, 4, CutDirection::Auto, StockGrain::None, demands);
+        let sol = solver.solve();
+        assert_solution_valid(&sol, 50);
+
+        assert!(sol.total_waste_percent() < 100.0);
+        assert!(sol.total_waste_percent() >= 0.0);
+    }
+
+    /// 32 pieces, 5 different sizes, small stock forcing many sheets.
+    #[test]
+    fn test_complex_small_stock_many_sheets() {
+        let stock = Rect::new(500, 400);
+        let demands = vec![
+            Demand {
+                rect: Rect::new(200, 150),
+                qty: 8,
+                allow_rotate: true,
+                grain: PieceGrain::Auto,
+                ..Default::default()
+            },
+            Demand {
+                rect: Rect::new(300, 200),
+                qty: 6,
+                allow_rotate: true,
+                grain: PieceGrain::Auto,
+                ..Default::default()
+            },
+            Demand {
+                rect: Rect::new(150, 100),
+                qty: 7,
+                allow_rotate: true,
+                grain: PieceGrain::Auto,
+                ..Default::default()
+            },
+            Demand {
+                rect: Rect::new(250, 180),
+                qty: 5,
+                allow_rotate: true,
+                grain: PieceGrain::Auto,
+                ..Default::default()
+            },
+            Demand {
+                rect: Rect::new(400, 300),
+                qty: 6,
+                allow_rotate: true,
+                grain: PieceGrain::Auto,
+                ..Default::default()
+            },
+        ];
+        let total_pieces: u32 = demands.iter().map(|d| d.qty).sum();
+        assert_eq!(total_pieces, 32);
+
+        let solver = Solver::new(stock, 0, CutDirection::Auto, StockGrain::None, demands);
+        let sol = solver.solve();
+        assert_solution_valid(&sol, 32);
+
+        // With small stock and large pieces, we need many sheets
+        assert!(sol.sheet_count() >= 5);
+    }
+
+    /// Real-world test from CSV: 473x14:4, 473x196:4, 473x158:12, 100x100:8, 742x473:8
+    /// on 2500x1200 stock with kerf=3.
+    /// Verifies all 3 cut directions produce valid solutions and that
+    /// AlongLength and AlongWidth produce different placements.
+    #[test]
+    fn test_cut_direction_csv_data() {
+        let stock = Rect::new(2500, 1200);
+        let demands = vec![
+            Demand {
+                rect: Rect::new(473, 14),
+                qty: 4,
+                allow_rotate: true,
+                grain: PieceGrain::Auto,
+                ..Default::default()
+            },
+            Demand {
+                rect: Rect::new(473, 196),
+                qty: 4,
+                allow_rotate: true,
+                grain: PieceGrain::Auto,
+                ..Default::default()
+            },
+            Demand {
+                rect: Rect::new(473, 158),
+                qty: 12,
+                allow_rotate: true,
+                grain: PieceGrain::Auto,
+                ..Default::default()
+            },
+            Demand {
+                rect: Rect::new(100, 100),
+                qty: 8,
+                allow_rotate: true,
+                grain: PieceGrain::Auto,
+                ..Default::default()
+            },
+            Demand {
+                rect: Rect::new(742, 473),
+                qty: 8,
+                allow_rotate: true,
+                grain: PieceGrain::Auto,
+                ..Default::default()
+            },
+        ];
+        let total_pieces: u32 = demands.iter().map(|d| d.qty).sum();
+        assert_eq!(total_pieces, 36);
+
+        let sol_auto = Solver::new(
+            stock,
+            3,
+            CutDirection::Auto,
+            StockGrain::None,
+            demands.clone(),
+        )
+        .solve();
+        let sol_length = Solver::new(
+            stock,
+            3,
+            CutDirection::AlongLength,
+            StockGrain::None,
+            demands.clone(),
+        )
+        .solve();
+        let sol_width = Solver::new(
+            stock,
+            3,
+            CutDirection::AlongWidth,
+            StockGrain::None,
+            demands.clone(),
+        )
+        .solve();
+
+        // All solutions must be valid
+        assert_solution_valid(&sol_auto, 36);
+        assert_solution_valid(&sol_length, 36);
+        assert_solution_valid(&sol_width, 36);
+
+        // Collect all placements (x, y) per direction to verify they differ
+        let coords = |sol: &Solution| -> Vec<Vec<(u32, u32)>> {
+            sol.sheets
+                .iter()
+                .map(|s| {
+                    let mut c: Vec<(u32, u32)> = s.placements.iter().map(|p| (p.x, p.y)).collect();
+                    c.sort();
+                    c
+                })
+                .collect()
+        };
+        let coords_length = coords(&sol_length);
+        let coords_width = coords(&sol_width);
+
+        assert_ne!(
+            coords_length, coords_width,
+            "AlongLength and AlongWidth should produce different placement layouts"
+        );
+    }
+
+    /// Verifies that each CutDirection produces valid, non-overlapping solutions
+    /// for a simple case where the split direction clearly matters.
+    #[test]
+    fn test_cut_direction_all_modes_valid() {
+        let stock = Rect::new(1000, 500);
+        let demands = vec![
+            Demand {
+                rect: Rect::new(400, 200),
+                qty: 4,
+                allow_rotate: true,
+                grain: PieceGrain::Auto,
+                ..Default::default()
+            },
+            Demand {
+                rect: Rect::new(300, 150),
+                qty: 3,
+                allow_rotate: true,
+                grain: PieceGrain::Auto,
+                ..Default::default()
+            },
+        ];
+
+        for &dir in &[
+            CutDirection::Auto,
+            CutDirection::AlongLength,
+            CutDirection::AlongWidth,
+        ] {
+            let sol = Solver::new(stock, 3, dir, StockGrain::None, demands.clone()).solve();
+            assert_solution_valid(&sol, 7);
+        }
+    }
+
+    // ── Grain direction tests ──────────────────────────────────────
+
+    #[test]
+    fn test_grain_length_along_length_no_rotate() {
+        // Piece grain=Length, stock grain=AlongLength → piece must NOT be rotated
+        let stock = Rect::new(100, 50);
+        let solver = Solver::new(
+            stock,
+            0,
+            CutDirection::Auto,
+            StockGrain::AlongLength,
+            vec![Demand {
+                rect: Rect::new(100, 50),
+                qty: 1,
+                allow_rotate: true,
+                grain: PieceGrain::Length,
+                ..Default::default()
+            }],
+        );
+        let sol = solver.solve();
+        assert_solution_valid(&sol, 1);
+        assert!(!sol.sheets[0].placements[0].rotated);
+    }
+
+    #[test]
+    fn test_grain_length_along_width_force_rotate() {
+        // Piece grain=Length, stock grain=AlongWidth → piece MUST be rotated
+        // Stock 100x50, piece 50x100: needs rotation to fit (50x100 rotated → 100x50)
+        let stock = Rect::new(100, 50);
+        let solver = Solver::new(
+            stock,
+            0,
+            CutDirection::Auto,
+            StockGrain::AlongWidth,
+            vec![Demand {
+                rect: Rect::new(50, 100),
+                qty: 1,
+                allow_rotate: true,
+                grain: PieceGrain::Length,
+                ..Default::default()
+            }],
+        );
+        let sol = solver.solve();
+        assert_solution_valid(&sol, 1);
+        assert!(sol.sheets[0].placements[0].rotated);
+    }
+
+    #[test]
+    fn test_rotated_placement_sizes() {
+        // 50x100 only fits the 100x50 sheet turned
+        let demand = Rect::new(50, 100);
+        let solver = Solver::new(
+            Rect::new(100, 50),
+            0,
+            CutDirection::Auto,
+            StockGrain::None,
+            vec![Demand::new(demand, 1)],
+        );
+        let sol = solver.solve();
+        let p = &sol.sheets[0].placements[0];
+        assert!(p.rotated);
+        assert_eq!(p.rect, demand.rotated());
+        assert_eq!(p.original_size(), demand);
+    }
+
+    #[test]
+    fn test_grain_width_along_length_force_rotate() {
+        // Piece grain=Width, stock grain=AlongLength → piece MUST be rotated
+        // Stock 100x50, piece 50x100: rotated → 100x50 fits
+        let stock = Rect::new(100, 50);
+        let solver = Solver::new(
+            stock,
+            0,
+            CutDirection::Auto,
+            StockGrain::AlongLength,
+            vec![Demand {
+                rect: Rect::new(50, 100),
+                qty: 1,
+                allow_rotate: true,
+                grain: PieceGrain::Width,
+                ..Default::default()
+            }],
+        );
+        let sol = solver.solve();
+        assert_solution_valid(&sol, 1);
+        assert!(sol.sheets[0].placements[0].rotated);
+    }
+
+    #[test]
+    fn test_grain_width_along_width_no_rotate() {
+        // Piece grain=Width, stock grain=AlongWidth → piece must NOT be rotated
+        let stock = Rect::new(100, 50);
+        let solver = Solver::new(
+            stock,
+            0,
+            CutDirection::Auto,
+            StockGrain::AlongWidth,
+            vec![Demand {
+                rect: Rect::new(100, 50),
+                qty: 1,
+                allow_rotate: true,
+                grain: PieceGrain::Width,
+                ..Default::default()
+            }],
+        );
+        let sol = solver.solve();
+        assert_solution_valid(&sol, 1);
+        assert!(!sol.sheets[0].placements[0].rotated);
+    }
+
+    #[test]
+    fn test_grain_auto_free_rotation() {
+        // Piece grain=Auto with any stock grain → free rotation (optimizer chooses)
+        // Stock 100x50, piece 50x100: only fits rotated
+        let stock = Rect::new(100, 50);
+        let solver = Solver::new(
+            stock,
+            0,
+            CutDirection::Auto,
+            StockGrain::AlongLength,
+            vec![Demand {
+                rect: Rect::new(50, 100),
+                qty: 1,
+                allow_rotate: true,
+                grain: PieceGrain::Auto,
+                ..Default::default()
+            }],
+        );
+        let sol = solver.solve();
+        assert_solution_valid(&sol, 1);
+        assert!(sol.sheets[0].placements[0].rotated);
+    }
+
+    #[test]
+    fn test_grain_none_stock_ignores_piece_grain() {
+        // stock grain=None → all piece grains ignored, free rotation
+        // Piece 50x100 with grain=Length in stock 100x50: should still rotate to fit
+        let stock = Rect::new(100, 50);
+        let solver = Solver::new(
+            stock,
+            0,
+            CutDirection::Auto,
+            StockGrain::None,
+            vec![Demand {
+                rect: Rect::new(50, 100),
+                qty: 1,
+                allow_rotate: true,
+                grain: PieceGrain::Length,
+                ..Default::default()
+            }],
+        );
+        let sol = solver.solve();
+        assert_solution_valid(&sol, 1);
+        // With grain=None, optimizer is free to rotate — and must rotate to fit
+        assert!(sol.sheets[0].placements[0].rotated);
+    }
+
+    #[test]
+    fn test_grain_constraint_reduces_flexibility() {
+        // With grain constraints, some pieces lose rotation flexibility → may need more sheets
+        let stock = Rect::new(200, 100);
+        // Two 100x200 pieces: without grain, both rotate to 200x100 and fit on 1 sheet each
+        // With grain=Length + stock=AlongLength → NoRotate → 100x200 doesn't fit in 200x100 stock
+        // because piece.length=100 < stock.length=200 ✓ but piece.width=200 > stock.width=100 ✗
+        // So each piece must be ForceRotated or can't be placed depending on grain
+        let solver_no_grain = Solver::new(
+            stock,
+            0,
+            CutDirection::Auto,
+            StockGrain::None,
+            vec![Demand {
+                rect: Rect::new(100, 200),
+                qty: 1,
+                allow_rotate: true,
+                grain: PieceGrain::Auto,
+                ..Default::default()
+            }],
+        );
+        let sol_free = solver_no_grain.solve();
+        assert_solution_valid(&sol_free, 1);
+        // Piece 100x200 rotated → 200x100, fits in 200x100 stock
+        assert!(sol_free.sheets[0].placements[0].rotated);
+
+        // With grain=Length + stock=AlongLength → NoRotate: 100x200 doesn't fit (width 200 > stock width 100)
+        // This would panic at "piece larger than stock" — so use grain=Width to force rotate
+        let solver_grain = Solver::new(
+            stock,
+            0,
+            CutDirection::Auto,
+            StockGrain::AlongLength,
+            vec![Demand {
+                rect: Rect::new(100, 200),
+                qty: 1,
+                allow_rotate: true,
+                grain: PieceGrain::Width,
+                ..Default::default()
+            }],
+        );
+        let sol_grain = solver_grain.solve();
+        assert_solution_valid(&sol_grain, 1);
+        // ForceRotate: 100x200 rotated → 200x100 fits
+        assert!(sol_grain.sheets[0].placements[0].rotated);
+    }
+
+    // ── Cut direction rotation constraint tests ──────────────────
+
+    #[test]
+    fn test_cut_direction_along_length_forces_orientation() {
+        // AlongLength: pieces should be oriented with length >= width.
+        // Piece 30x50 (length < width) → ForceRotate → placed as 50x30.
+        let stock = Rect::new(100, 100);
+        let solver = Solver::new(
+            stock,
+            0,
+            CutDirection::AlongLength,
+            StockGrain::None,
+            vec![Demand {
+                rect: Rect::new(30, 50),
+                qty: 1,
+                allow_rotate: true,
+                grain: PieceGrain::Auto,
+                ..Default::default()
+            }],
+        );
+        let sol = solver.solve();
+        assert_solution_valid(&sol, 1);
+        let p = &sol.sheets[0].placements[0];
+        // After forced rotation: placed length=50, width=30
+        assert!(
+            p.rect.length >= p.rect.width,
+            "AlongLength: placed piece should have length >= width, got {}x{}",
+            p.rect.length,
+            p.rect.width
+        );
+    }
+
+    #[test]
+    fn test_cut_direction_along_width_forces_orientation() {
+        // AlongWidth: pieces should be oriented with width >= length.
+        // Piece 50x30 (width < length) → ForceRotate → placed as 30x50.
+        let stock = Rect::new(100, 100);
+        let solver = Solver::new(
+            stock,
+            0,
+            CutDirection::AlongWidth,
+            StockGrain::None,
+            vec![Demand {
+                rect: Rect::new(50, 30),
+                qty: 1,
+                allow_rotate: true,
+                grain: PieceGrain::Auto,
+                ..Default::default()
+            }],
+        );
+        let sol = solver.solve();
+        assert_solution_valid(&sol, 1);
+        let p = &sol.sheets[0].placements[0];
+        // After forced rotation: placed width=50, length=30
+        assert!(
+            p.rect.width >= p.rect.length,
+            "AlongWidth: placed piece should have width >= length, got {}x{}",
+            p.rect.length,
+            p.rect.width
+        );
+    }
+
+    #[test]
+    fn test_cut_direction_grain_takes_priority() {
+        // Grain constraint (NoRotate) should override cut_direction.
+        // Piece 30x50 with grain=Length, stock=AlongLength → NoRotate (natural alignment)
+        // Even though AlongLength would want ForceRotate for this piece shape.
+        let stock = Rect::new(100, 100);
+        let solver = Solver::new(
+            stock,
+            0,
+            CutDirection::AlongLength,
+            StockGrain::AlongLength,
+            vec![Demand {
+                rect: Rect::new(30, 50),
+                qty: 1,
+                allow_rotate: true,
+                grain: PieceGrain::Length,
+                ..Default::default()
+            }],
+        );
+        let sol = solver.solve();
+        assert_solution_valid(&sol, 1);
+        // Grain=Length + Stock=AlongLength → NoRotate, so piece stays 30x50
+        assert!(!sol.sheets[0].placements[0].rotated);
+    }
+
+    #[test]
+    fn test_cut_direction_along_length_all_pieces_oriented() {
+        // Multiple non-square pieces with AlongLength: all should be placed length >= width
+        let stock = Rect::new(2440, 1220);
+        let demands = vec![
+            Demand {
+                rect: Rect::new(800, 600),
+                qty: 3,
+                allow_rotate: true,
+                grain: PieceGrain::Auto,
+                ..Default::default()
+            },
+            Demand {
+                rect: Rect::new(300, 500),
+                qty: 4,
+                allow_rotate: true,
+                grain: PieceGrain::Auto,
+                ..Default::default()
+            },
+        ];
+        let solver = Solver::new(
+            stock,
+            0,
+            CutDirection::AlongLength,
+            StockGrain::None,
+            demands,
+        );
+        let sol = solver.solve();
+        assert_solution_valid(&sol, 7);
+        for sheet in &sol.sheets {
+            for p in &sheet.placements {
+                assert!(
+                    p.rect.length >= p.rect.width,
+                    "AlongLength: all placed pieces should have length >= width, got {}x{}",
+                    p.rect.length,
+                    p.rect.width
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_cut_direction_along_width_all_pieces_oriented() {
+        // Multiple non-square pieces with AlongWidth: all should be placed width >= length
+        let stock = Rect::new(2440, 1220);
+        let demands = vec![
+            Demand {
+                rect: Rect::new(800, 600),
+                qty: 3,
+                allow_rotate: true,
+                grain: PieceGrain::Auto,
+                ..Default::default()
+            },
+            Demand {
+                rect: Rect::new(300, 500),
+                qty: 4,
+                allow_rotate: true,
+                grain: PieceGrain::Auto,
+                ..Default::default()
+            },
+        ];
+        let solver = Solver::new(
+            stock,
+            0,
+            CutDirection::AlongWidth,
+            StockGrain::None,
+            demands,
+        );
+        let sol = solver.solve();
+        assert_solution_valid(&sol, 7);
+        for sheet in &sol.sheets {
+            for p in &sheet.placements {
+                assert!(
+                    p.rect.width >= p.rect.length,
+                    "AlongWidth: all placed pieces should have width >= length, got {}x{}",
+                    p.rect.length,
+                    p.rect.width
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_grain_mixed_pieces() {
+        // Mix of grain-constrained and auto pieces
+        let stock = Rect::new(2440, 1220);
+        let demands = vec![
+            Demand {
+                rect: Rect::new(800, 600),
+                qty: 3,
+                allow_rotate: true,
+                grain: PieceGrain::Length, // must align length with stock grain
+                ..Default::default()
+            },
+            Demand {
+                rect: Rect::new(400, 300),
+                qty: 4,
+                allow_rotate: true,
+                grain: PieceGrain::Auto, // free rotation
+                ..Default::default()
+            },
+            Demand {
+                rect: Rect::new(600, 400),
+                qty: 2,
                 allow_rotate: true,
+                grain: PieceGrain::Width, // must align width with stock grain
+                ..Default::default()
+            },
+        ];
+        let total_pieces: u32 = demands.iter().map(|d| d.qty).sum();
+
+        let sol = Solver::new(
+            stock,
+            0,
+            CutDirection::Auto,
+            StockGrain::AlongLength,
+            demands,
+        )
+        .solve();
+        assert_solution_valid(&sol, total_pieces as usize);
+
+        // Verify grain constraints on placements
+        for sheet in &sol.sheets {
+            for p in &sheet.placements {
+                // All pieces should fit within stock
+                assert!(p.x + p.rect.length <= stock.length);
+                assert!(p.y + p.rect.width <= stock.width);
+            }
+        }
+    }
+
+    #[test]
+    fn test_parallel_matches_serial() {
+        type Sizes = Vec<(u32, u32, u32)>;
+        let instances: Vec<(Rect, u32, CutDirection, Sizes)> = vec![
+            (
+                Rect::new(2440, 1220),
+                0,
+                CutDirection::Auto,
+                vec![
+                    (800, 600, 5),
+                    (400, 300, 10),
+                    (1200, 400, 4),
+                    (300, 300, 11),
+                ],
+            ),
+            (
+                Rect::new(2500, 1200),
+                3,
+                CutDirection::Auto,
+                vec![(473, 14, 4), (473, 196, 4), (473, 158, 12), (742, 473, 8)],
+            ),
+            (
+                Rect::new(500, 400),
+                0,
+                CutDirection::AlongWidth,
+                vec![(200, 150, 8), (120, 80, 12), (90, 90, 12)],
+            ),
+        ];
+
+        for (stock, kerf, dir, sizes) in instances {
+            let demands: Vec<Demand> = sizes
+                .iter()
+                .map(|&(l, w, qty)| Demand {
+                    rect: Rect::new(l, w),
+                    qty,
+                    allow_rotate: true,
+                    grain: PieceGrain::Auto,
+                    ..Default::default()
+                })
+                .collect();
+            let total: u32 = sizes.iter().map(|s| s.2).sum();
+
+            let serial = Solver::new(stock, kerf, dir, StockGrain::None, demands.clone())
+                .with_parallel(false)
+                .solve();
+            let parallel = Solver::new(stock, kerf, dir, StockGrain::None, demands)
+                .with_parallel(true)
+                .solve();
+
+            assert_solution_valid(&parallel, total as usize);
+            assert_eq!(serial.sheet_count(), parallel.sheet_count());
+            assert_eq!(serial.total_waste_percent(), parallel.total_waste_percent());
+        }
+    }
+
+    #[test]
+    fn test_parallel_material_partitions_match_serial() {
+        let stock = Rect::new(2440, 1220);
+        let demands: Vec<Demand> = [
+            ("oak", 800, 600, 4),
+            ("birch", 400, 300, 9),
+            ("oak", 1200, 400, 3),
+            ("mdf", 300, 300, 12),
+            ("birch", 700, 500, 2),
+        ]
+        .iter()
+        .map(|&(m, l, w, qty)| Demand::new(Rect::new(l, w), qty).with_material(m))
+        .collect();
+        let total = demands.iter().map(|d| d.qty as usize).sum();
+        let solve = |parallel| {
+            Solver::new(
+                stock,
+                3,
+                CutDirection::Auto,
+                StockGrain::None,
+                demands.clone(),
+            )
+            .with_parallel(parallel)
+            .solve()
+        };
+        let serial = solve(false);
+        let parallel = solve(true);
+
+        assert_solution_valid(&parallel, total);
+        assert_eq!(serial.sheet_count(), parallel.sheet_count());
+        assert_eq!(serial.total_waste_percent(), parallel.total_waste_percent());
+        let layout = |sol: &Solution| -> Vec<(usize, Option<String>, u32, u32, Rect)> {
+            sol.sheets
+                .iter()
+                .enumerate()
+                .flat_map(|(i, s)| {
+                    s.placements
+                        .iter()
+                        .map(move |p| (i, s.material.clone(), p.x, p.y, p.rect))
+                })
+                .collect()
+        };
+        assert_eq!(layout(&serial), layout(&parallel));
+    }
+
+    #[test]
+    fn test_size_tolerance_merges_near_duplicates() {
+        let demands = vec![
+            Demand {
+                rect: Rect::new(400, 300),
+                qty: 3,
+                allow_rotate: false,
                 grain: PieceGrain::Auto,
+                ..Default::default()
             },
             Demand {
-                rect: Rect::new(500, 500),
-                qty: 4,
+                rect: Rect::new(402, 298),
+                qty: 2,
+                allow_rotate: false,
+                grain: PieceGrain::Auto,
+                ..Default::default()
+            },
+            Demand {
+                rect: Rect::new(200, 100),
+                qty: 1,
                 allow_rotate: false,
                 grain: PieceGrain::Auto,
+                ..Default::default()
             },
         ];
-        let total_pieces: u32 = demands.iter().map(|d| d.qty).sum();
-        assert_eq!(total_pieces, 30);
+        let solver = Solver::new(
+            Rect::new(1210, 1000),
+            0,
+            CutDirection::Auto,
+            StockGrain::None,
+            demands,
+        )
+        .with_size_tolerance(3);
+
+        // Both near-identical demands snap to one 402x300 size class
+        assert_eq!(
+            solver.snapped_sizes(&solver.demands),
+            vec![
+                Rect::new(402, 300),
+                Rect::new(402, 300),
+                Rect::new(200, 100)
+            ]
+        );
+
+        let sol = solver.solve();
+        assert_solution_valid(&sol, 6);
+        let placements: Vec<&Placement> = sol.sheets.iter().flat_map(|s| &s.placements).collect();
+        let class: Vec<_> = placements
+            .iter()
+            .filter(|p| p.requested != Rect::new(200, 100))
+            .collect();
+        assert_eq!(class.len(), 5);
+        assert!(class.iter().all(|p| p.rect == Rect::new(402, 300)));
+
+        // Original sizes are preserved on the placements
+        let count = |r: Rect| placements.iter().filter(|p| p.requested == r).count();
+        assert_eq!(count(Rect::new(400, 300)), 3);
+        assert_eq!(count(Rect::new(402, 298)), 2);
+        assert_eq!(count(Rect::new(200, 100)), 1);
+    }
+
+    #[test]
+    fn test_far_edge_waste_tiebreak() {
+        let stock = Rect::new(100, 100);
+        let sheet = |x: u32| SheetResult {
+            stock,
+            stock_rotated: false,
+            placements: vec![Placement {
+                rect: Rect::new(60, 100),
+                x,
+                y: 0,
+                rotated: false,
+                requested: Rect::new(60, 100),
+                orientation_deg: 0,
+                label: None,
+                is_filler: false,
+                directional: false,
+                group_hint: None,
+            }],
+            waste_area: 4000,
+            material: None,
+            offcuts: vec![],
+            reserved: None,
+            cut_direction: CutDirection::Auto,
+        };
+        let last = SheetResult {
+            stock,
+            stock_rotated: false,
+            placements: vec![],
+            waste_area: 10000,
+            material: None,
+            offcuts: vec![],
+            reserved: None,
+            cut_direction: CutDirection::Auto,
+        };
+        // Same sheet count and same last sheet; only first-sheet waste differs
+        let near_origin_waste = Solution {
+            sheets: vec![sheet(40), last.clone()],
+            stock,
+            warnings: vec![],
+            unplaced: vec![],
+        };
+        let far_edge_waste = Solution {
+            sheets: vec![sheet(0), last],
+            stock,
+            warnings: vec![],
+            unplaced: vec![],
+        };
+
+        let solver = Solver::new(stock, 0, CutDirection::Auto, StockGrain::None, vec![]);
+        // Default tiebreak: a tie keeps the earlier solution
+        assert!(solver.is_dominated(&far_edge_waste, &near_origin_waste));
+
+        let solver = solver.with_far_edge_waste(true);
+        assert!(!solver.is_dominated(&far_edge_waste, &near_origin_waste));
+        assert!(solver.is_dominated(&near_origin_waste, &far_edge_waste));
+    }
+
+    #[test]
+    fn test_square_offcuts_tiebreak() {
+        let stock = Rect::new(2000, 1000);
+        let piece = |x: u32, y: u32, rect: Rect| Placement::new(rect, x, y);
+        let solution = |placements: Vec<Placement>, offcut: Offcut| Solution {
+            sheets: vec![SheetResult {
+                stock,
+                stock_rotated: false,
+                waste_area: offcut.rect.area(),
+                placements,
+                material: None,
+                offcuts: vec![offcut],
+                reserved: None,
+                cut_direction: CutDirection::Auto,
+            }],
+            stock,
+            warnings: vec![],
+            unplaced: vec![],
+        };
+        let sliver = solution(
+            vec![piece(0, 0, Rect::new(2000, 950))],
+            Offcut {
+                x: 0,
+                y: 950,
+                rect: Rect::new(2000, 50),
+            },
+        );
+        let drop = solution(
+            vec![
+                piece(0, 0, Rect::new(1300, 1000)),
+                piece(1300, 0, Rect::new(700, 400)),
+            ],
+            Offcut {
+                x: 1300,
+                y: 400,
+                rect: Rect::new(700, 600),
+            },
+        );
+
+        let solver = Solver::new(stock, 0, CutDirection::Auto, StockGrain::None, vec![]);
+        // Default tiebreak: the sliver layout is more compact
+        assert!(solver.is_dominated(&drop, &sliver));
+
+        let solver = solver.with_square_offcuts(true);
+        assert!(!solver.is_dominated(&drop, &sliver));
+        assert!(solver.is_dominated(&sliver, &drop));
+    }
+
+    #[test]
+    fn test_usable_window() {
+        let stock = Rect::new(1000, 500);
+        let solver = Solver::new(
+            stock,
+            0,
+            CutDirection::Auto,
+            StockGrain::None,
+            vec![Demand::new(Rect::new(200, 100), 6)],
+        )
+        .with_usable_window(300, 100, Rect::new(400, 300));
+        let sol = solver.solve();
+        assert_solution_valid(&sol, 6);
+
+        // The 400x300 window holds 6 pieces of 200x100
+        assert_eq!(sol.sheet_count(), 1);
+        for p in &sol.sheets[0].placements {
+            assert!(
+                p.x >= 300 && p.x + p.rect.length <= 700,
+                "{p:?} outside window"
+            );
+            assert!(
+                p.y >= 100 && p.y + p.rect.width <= 400,
+                "{p:?} outside window"
+            );
+        }
+
+        // Waste counts everything outside the pieces, including outside the window
+        assert_eq!(sol.sheets[0].waste_area, 1000 * 500 - 6 * 200 * 100);
+    }
+
+    #[test]
+    fn test_strip_threshold_prepass() {
+        let stock = Rect::new(2440, 1220);
+        let demands = vec![
+            Demand::new(Rect::new(1434, 14), 13),
+            Demand::new(Rect::new(2278, 11), 6),
+            Demand::new(Rect::new(1541, 1027), 3),
+            Demand::new(Rect::new(1067, 862), 4),
+        ];
+        let total: u32 = demands.iter().map(|d| d.qty).sum();
+
+        // Few sizes, so branch and bound would run: compare the greedy
+        // passes on their own
+        let default = Solver::new(
+            stock,
+            3,
+            CutDirection::Auto,
+            StockGrain::None,
+            demands.clone(),
+        )
+        .with_branch_and_bound(false)
+        .solve();
+        let strips = Solver::new(stock, 3, CutDirection::Auto, StockGrain::None, demands)
+            .with_strip_threshold(Some(50))
+            .with_branch_and_bound(false)
+            .solve();
+
+        assert_solution_valid(&default, total as usize);
+        assert_solution_valid(&strips, total as usize);
+        assert!(
+            strips.sheet_count() < default.sheet_count(),
+            "strip pre-pass used {} sheets, default {}",
+            strips.sheet_count(),
+            default.sheet_count()
+        );
+    }
+
+    #[test]
+    fn test_materials_never_share_a_sheet() {
+        let demands = vec![
+            Demand::new(Rect::new(50, 50), 3).with_material("oak"),
+            Demand::new(Rect::new(50, 50), 3).with_material("birch"),
+            Demand::new(Rect::new(30, 20), 2).with_material("oak"),
+        ];
+        let sol = Solver::new(
+            Rect::new(100, 100),
+            0,
+            CutDirection::Auto,
+            StockGrain::None,
+            demands,
+        )
+        .solve();
+        assert_solution_valid(&sol, 8);
+
+        let oak: Vec<_> = sol
+            .sheets
+            .iter()
+            .filter(|s| s.material.as_deref() == Some("oak"))
+            .collect();
+        let birch: Vec<_> = sol
+            .sheets
+            .iter()
+            .filter(|s| s.material.as_deref() == Some("birch"))
+            .collect();
+        assert_eq!(oak.len() + birch.len(), sol.sheet_count());
+        assert_eq!(oak.iter().map(|s| s.placements.len()).sum::<usize>(), 5);
+        assert_eq!(birch.iter().map(|s| s.placements.len()).sum::<usize>(), 3);
+        // Birch sheets only hold 50x50 pieces
+        assert!(
+            birch
+                .iter()
+                .flat_map(|s| &s.placements)
+                .all(|p| p.requested == Rect::new(50, 50))
+        );
+    }
+
+    #[test]
+    fn test_on_improvement_reports_bb_progress() {
+        use std::sync::{Arc, Mutex};
+
+        let demands = vec![
+            Demand::new(Rect::new(74, 38), 1),
+            Demand::new(Rect::new(39, 75), 3),
+            Demand::new(Rect::new(54, 52), 4),
+        ];
+        let reported = Arc::new(Mutex::new(Vec::new()));
+        let sink = reported.clone();
+        let sol = Solver::new(
+            Rect::new(100, 100),
+            0,
+            CutDirection::Auto,
+            StockGrain::None,
+            demands,
+        )
+        .with_on_improvement(move |s| sink.lock().unwrap().push(s.clone()))
+        .solve();
+
+        let reported = reported.lock().unwrap();
+        assert!(!reported.is_empty(), "B&B should improve on greedy here");
+        for s in reported.iter() {
+            assert_solution_valid(s, 8);
+        }
+        let counts: Vec<usize> = reported.iter().map(|s| s.sheet_count()).collect();
+        assert!(
+            counts.windows(2).all(|w| w[1] < w[0]),
+            "sheet counts must strictly decrease: {counts:?}"
+        );
+        assert_eq!(*counts.last().unwrap(), sol.sheet_count());
+    }
+
+    #[test]
+    fn test_resume_from_checkpoint() {
+        let solver = |demands: Vec<Demand>| {
+            Solver::new(
+                Rect::new(100, 100),
+                0,
+                CutDirection::Auto,
+                StockGrain::None,
+                demands,
+            )
+        };
+        let demands = vec![
+            Demand::new(Rect::new(74, 38), 1),
+            Demand::new(Rect::new(39, 75), 3),
+            Demand::new(Rect::new(54, 52), 4),
+        ];
+        let full = solver(demands.clone()).solve();
+
+        let saved = serde_json::to_string(&solver(demands.clone()).checkpoint(&full)).unwrap();
+        let checkpoint: Checkpoint = serde_json::from_str(&saved).unwrap();
+        // A resumed solve cut short still returns the checkpointed best
+        let expired = Some(Instant::now());
+        assert_eq!(
+            solver(demands.clone())
+                .with_deadline(expired)
+                .try_solve()
+                .unwrap_err(),
+            SolveError::DeadlineExceeded
+        );
+        let resumed = solver(demands.clone())
+            .with_deadline(expired)
+            .with_resume(Some(checkpoint.clone()))
+            .solve();
+        assert_solution_valid(&resumed, 8);
+        assert_eq!(resumed.sheet_count(), full.sheet_count());
+        let resumed = solver(demands.clone())
+            .with_resume(Some(checkpoint.clone()))
+            .solve();
+        assert!(resumed.sheet_count() <= full.sheet_count());
+
+        // A checkpoint of another job is ignored
+        let mut other = demands;
+        other[0].qty = 2;
+        let resumed = solver(other).with_resume(Some(checkpoint)).solve();
+        assert_solution_valid(&resumed, 9);
+    }
+
+    #[test]
+    fn test_symmetry_breaking_prunes_bb() {
+        let demands = vec![
+            Demand::new(Rect::new(60, 45), 6),
+            Demand::new(Rect::new(35, 30), 8),
+        ];
+        let solver = |symmetry_breaking: bool| {
+            Solver::new(
+                Rect::new(100, 100),
+                0,
+                CutDirection::Auto,
+                StockGrain::None,
+                demands.clone(),
+            )
+            .with_symmetry_breaking(symmetry_breaking)
+        };
+        let pieces = solver(true).expand_demands(&demands);
+        // No greedy upper bound, so B&B has to search from scratch
+        let upper_bound = pieces.len() + 1;
+        let (plain, plain_nodes) =
+            solver(false).branch_and_bound_counted(&pieces, upper_bound, None);
+        let (pruned, pruned_nodes) =
+            solver(true).branch_and_bound_counted(&pieces, upper_bound, None);
+        assert_eq!(pruned.sheet_count(), plain.sheet_count());
+        assert_solution_valid(&pruned, pieces.len());
+        assert!(
+            pruned_nodes * 10 < plain_nodes,
+            "expected far fewer nodes: {pruned_nodes} vs {plain_nodes}"
+        );
+    }
+
+    #[test]
+    fn test_branch_and_bound_undo() {
+        // Placements are made and taken back in place: the search must
+        // visit the same nodes and find the same layout as when every
+        // branch worked on its own copy of the bins
+        let demands = vec![
+            Demand::new(Rect::new(60, 45), 6),
+            Demand::new(Rect::new(35, 30), 8),
+        ];
+        let solver = Solver::new(
+            Rect::new(100, 100),
+            2,
+            CutDirection::Auto,
+            StockGrain::None,
+            demands.clone(),
+        );
+        let pieces = solver.expand_demands(&demands);
+        let (solution, nodes) = solver.branch_and_bound_counted(&pieces, pieces.len() + 1, None);
+        assert_eq!(nodes, 492);
+        assert_eq!(solution.sheet_count(), 3);
+        assert_solution_valid(&solution, pieces.len());
+    }
+
+    #[test]
+    fn test_max_sheets_by_value() {
+        // Four tins fill the sheet on their own; the gold piece is smaller
+        // but worth more than two of them
+        let demands = vec![
+            Demand::new(Rect::new(50, 50), 4).with_label("tin"),
+            Demand::new(Rect::new(45, 45), 1).with_label("gold"),
+        ];
+        let values = HashMap::from([("tin".to_string(), 1.0), ("gold".to_string(), 10.0)]);
+        let solver = Solver::new(
+            Rect::new(100, 100),
+            0,
+            CutDirection::Auto,
+            StockGrain::None,
+            demands,
+        )
+        .with_max_sheets(Some(1));
+
+        let by_size = solver.solve();
+        assert_eq!(by_size.sheet_count(), 1);
+        assert_eq!(by_size.sheets[0].placements.len(), 4);
+        assert_eq!(by_size.placed_value(&values), 4.0);
+        assert_eq!(
+            by_size.unplaced,
+            [Unplaced {
+                rect: Rect::new(45, 45),
+                label: Some("gold".to_string()),
+                qty: 1,
+            }]
+        );
+
+        let by_value = solver.with_piece_values(values.clone()).solve();
+        assert_eq!(by_value.sheet_count(), 1);
+        assert_eq!(by_value.sheets[0].placements.len(), 3);
+        assert_eq!(by_value.placed_value(&values), 12.0);
+        assert_eq!(
+            by_value.unplaced,
+            [Unplaced {
+                rect: Rect::new(50, 50),
+                label: Some("tin".to_string()),
+                qty: 2,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_max_total_area() {
+        // Each piece needs its own sheet; the budget covers two and a half
+        let demands = vec![
+            Demand::new(Rect::new(60, 60), 2).with_material("oak"),
+            Demand::new(Rect::new(60, 60), 2).with_material("ash"),
+        ];
+        let solver = Solver::new(
+            Rect::new(100, 100),
+            0,
+            CutDirection::Auto,
+            StockGrain::None,
+            demands,
+        );
+        assert_eq!(solver.solve().sheet_count(), 4);
+
+        let budget = 25_000;
+        let solution = solver.with_max_total_area(Some(budget)).solve();
+        assert_eq!(solution.sheet_count(), 2);
+        let used: u64 = solution.sheets.iter().map(|s| s.stock.area()).sum();
+        assert!(used <= budget);
+        // The budget is shared, so the second material gets nothing
+        assert!(
+            solution
+                .sheets
+                .iter()
+                .all(|s| s.material.as_deref() == Some("oak"))
+        );
+        let unplaced: u32 = solution.unplaced.iter().map(|u| u.qty).sum();
+        assert_eq!(unplaced, 2);
+    }
+
+    #[test]
+    fn test_max_cuts_per_sheet() {
+        let stock = Rect::new(100, 100);
+        let solver = |max_cuts| {
+            Solver::new(
+                stock,
+                3,
+                CutDirection::Auto,
+                StockGrain::None,
+                vec![Demand::new(Rect::new(30, 30), 9)],
+            )
+            .with_max_cuts_per_sheet(max_cuts)
+        };
+        let cuts = |sheet: &SheetResult| sheet.cut_tree(stock, 3).unwrap().cut_count();
+        let free = solver(None).solve();
+        assert_eq!(free.sheet_count(), 1);
+        assert!(cuts(&free.sheets[0]) > 4);
+
+        // Fewer cuts per sheet, more sheets
+        for branch_and_bound in [false, true] {
+            let limited = solver(Some(4))
+                .with_branch_and_bound(branch_and_bound)
+                .solve();
+            assert_solution_valid(&limited, 9);
+            assert!(limited.sheet_count() > 1);
+            assert!(limited.sheets.iter().all(|s| cuts(s) <= 4));
+        }
+    }
+
+    #[test]
+    fn test_allow_stock_rotation() {
+        // Grain-locked along its 2000 side, which only the stock's width
+        // can take once the sheet is turned
+        let solver = |allow| {
+            Solver::new(
+                Rect::new(3000, 1500),
+                0,
+                CutDirection::Auto,
+                StockGrain::None,
+                vec![
+                    Demand::new(Rect::new(1000, 2000), 1).with_allow_rotate(false),
+                    Demand::new(Rect::new(400, 300), 4),
+                ],
+            )
+            .with_allow_stock_rotation(allow)
+        };
+        assert_eq!(
+            solver(false).try_solve().unwrap_err(),
+            SolveError::PieceTooLarge {
+                rect: Rect::new(1000, 2000)
+            }
+        );
+
+        let sol = solver(true).solve();
+        assert_solution_valid(&sol, 5);
+        assert_eq!(sol.validate(), Ok(()));
+        assert_eq!(sol.sheet_count(), 1);
+        let sheet = &sol.sheets[0];
+        assert!(sheet.stock_rotated);
+        assert_eq!(sheet.stock, Rect::new(1500, 3000));
+        assert!(
+            sheet
+                .placements
+                .iter()
+                .any(|p| p.rect == Rect::new(1000, 2000))
+        );
+    }
+
+    #[test]
+    fn test_template_sheet() {
+        let stock = Rect::new(100, 100);
+        let piece = |length: u32, x: u32| Placement::new(Rect::new(length, 40), x, 0);
+        let sheet = |placements: Vec<Placement>| SheetResult {
+            stock,
+            stock_rotated: false,
+            waste_area: stock.area() - placements.iter().map(|p| p.rect.area()).sum::<u64>(),
+            placements,
+            material: None,
+            offcuts: vec![],
+            reserved: None,
+            cut_direction: CutDirection::Auto,
+        };
+        // Same two pieces, swapped, then a sheet with other pieces
+        let sheets = vec![
+            sheet(vec![piece(60, 0), piece(40, 60)]),
+            sheet(vec![piece(40, 0), piece(60, 40)]),
+            sheet(vec![piece(30, 0), piece(60, 30)]),
+        ];
+        let coords =
+            |sol: &Solution, i: usize| serde_json::to_string(&sol.sheets[i].placements).unwrap();
+        let finish = |template: bool| {
+            Solver::new(stock, 0, CutDirection::Auto, StockGrain::None, vec![])
+                .with_template_sheet(template)
+                .finish_sheets(sheets.clone(), vec![])
+        };
+
+        let free = finish(false);
+        assert_ne!(coords(&free, 0), coords(&free, 1));
+        let templated = finish(true);
+        assert_eq!(coords(&templated, 0), coords(&templated, 1));
+        assert_eq!(coords(&templated, 2), coords(&free, 2));
+        assert_eq!(templated.validate(), Ok(()));
+    }
+
+    #[test]
+    fn test_single_orientation_per_sheet() {
+        let solver = |single| {
+            Solver::new(
+                Rect::new(100, 100),
+                0,
+                CutDirection::Auto,
+                StockGrain::None,
+                vec![Demand::new(Rect::new(60, 40), 5)],
+            )
+            .with_single_orientation_per_sheet(single)
+        };
+        let mixed = |sol: &Solution| {
+            sol.sheets.iter().any(|s| {
+                let rotated = s.placements.iter().filter(|p| p.rotated).count();
+                rotated > 0 && rotated < s.placements.len()
+            })
+        };
+        // Two 60x40 stacked, then one turned in the 40 mm left beside them
+        let free = solver(false).solve();
+        assert_solution_valid(&free, 5);
+        assert!(mixed(&free));
+
+        for branch_and_bound in [false, true] {
+            let single = solver(true).with_branch_and_bound(branch_and_bound).solve();
+            assert_solution_valid(&single, 5);
+            assert!(!mixed(&single));
+        }
+    }
+
+    #[test]
+    fn test_orientation_tie_follows_grain() {
+        // Either way round the piece leaves the same short side
+        let solve = |grain| {
+            Solver::new(
+                Rect::new(100, 100),
+                0,
+                CutDirection::Auto,
+                grain,
+                vec![Demand::new(Rect::new(48, 50), 1)],
+            )
+            .solve()
+        };
+        let placed = |sol: &Solution| sol.sheets[0].placements[0].rect;
+        assert_eq!(placed(&solve(StockGrain::None)), Rect::new(48, 50));
+        assert_eq!(placed(&solve(StockGrain::AlongLength)), Rect::new(50, 48));
+        assert_eq!(placed(&solve(StockGrain::AlongWidth)), Rect::new(48, 50));
+    }
+
+    #[test]
+    fn test_group_hint() {
+        // Two wide pieces open a sheet each; each narrow one then fits
+        // either sheet equally well
+        let solve = |hints: bool| {
+            let demand = |length, hint: u32| {
+                let d =
+                    Demand::new(Rect::new(length, 100), 1).with_label(format!("setup {}", hint));
+                if hints { d.with_group_hint(hint) } else { d }
+            };
+            Solver::new(
+                Rect::new(100, 100),
+                0,
+                CutDirection::AlongLength,
+                StockGrain::None,
+                vec![demand(60, 1), demand(60, 2), demand(40, 2), demand(40, 1)],
+            )
+            .solve()
+        };
+        let grouped = |sol: &Solution| {
+            sol.sheets.iter().all(|s| {
+                let label = &s.placements[0].label;
+                s.placements.iter().all(|p| &p.label == label)
+            })
+        };
 
-        let solver = Solver::new(stock, 0, CutDirection::Auto, StockGrain::None, demands);
-        let sol = solver.solve();
-        assert_solution_valid(&sol, 30);
+        let plain = solve(false);
+        assert_eq!(plain.sheet_count(), 2);
+        assert!(!grouped(&plain));
 
-        // Lower bound: total piece area / stock area
-        let total_area: u64 = sol
-            .sheets
-            .iter()
-            .flat_map(|s| &s.placements)
-            .map(|p| p.rect.area())
-            .sum();
-        let min_sheets = total_area.div_ceil(stock.area()) as usize;
-        assert!(sol.sheet_count() >= min_sheets);
+        let hinted = solve(true);
+        assert_solution_valid(&hinted, 4);
+        assert_eq!(hinted.sheet_count(), 2);
+        assert!(grouped(&hinted));
     }
 
-    /// 35 pieces, 7 different sizes, with kerf=3.
-    /// Kerf eats into available space, so more sheets are needed.
     #[test]
-    fn test_complex_mixed_sizes_with_kerf() {
-        let stock = Rect::new(2440, 1220);
+    fn test_grain_group() {
+        let solve = |stock, demands| {
+            Solver::new(stock, 0, CutDirection::Auto, StockGrain::None, demands).solve()
+        };
+        let rotated = |sol: &Solution| -> Vec<bool> {
+            sol.sheets
+                .iter()
+                .flat_map(|s| &s.placements)
+                .map(|p| p.rotated)
+                .collect()
+        };
+
+        // The same panel ordered both ways round: one of them has to turn
+        // to line up with the other, unless they are grouped
+        let panels = |group: bool| {
+            let demand = |rect| {
+                let d = Demand::new(rect, 2);
+                if group { d.with_grain_group(1) } else { d }
+            };
+            vec![demand(Rect::new(50, 40)), demand(Rect::new(40, 50))]
+        };
+        let stock = Rect::new(100, 80);
+        assert!(rotated(&solve(stock, panels(false))).contains(&true));
+        let grouped = solve(stock, panels(true));
+        assert_solution_valid(&grouped, 4);
+        assert!(rotated(&grouped).iter().all(|&r| !r));
+
+        // Only the rotated orientation fits the long piece, so the whole
+        // group turns
         let demands = vec![
-            Demand {
-                rect: Rect::new(700, 500),
-                qty: 6,
-                allow_rotate: true,
-                grain: PieceGrain::Auto,
-            },
-            Demand {
-                rect: Rect::new(350, 250),
-                qty: 5,
-                allow_rotate: true,
-                grain: PieceGrain::Auto,
-            },
-            Demand {
-                rect: Rect::new(1000, 400),
-                qty: 3,
-                allow_rotate: true,
-                grain: PieceGrain::Auto,
-            },
-            Demand {
-                rect: Rect::new(450, 450),
-                qty: 4,
-                allow_rotate: false,
-                grain: PieceGrain::Auto,
-            },
-            Demand {
-                rect: Rect::new(600, 300),
-                qty: 7,
-                allow_rotate: true,
-                grain: PieceGrain::Auto,
-            },
-            Demand {
-                rect: Rect::new(250, 150),
-                qty: 5,
-                allow_rotate: true,
-                grain: PieceGrain::Auto,
-            },
-            Demand {
-                rect: Rect::new(800, 400),
-                qty: 5,
-                allow_rotate: true,
-                grain: PieceGrain::Auto,
-            },
+            Demand::new(Rect::new(80, 30), 1).with_grain_group(7),
+            Demand::new(Rect::new(40, 20), 2).with_grain_group(7),
         ];
-        let total_pieces: u32 = demands.iter().map(|d| d.qty).sum();
-        assert_eq!(total_pieces, 35);
+        let turned = solve(Rect::new(60, 100), demands);
+        assert_solution_valid(&turned, 3);
+        assert!(rotated(&turned).iter().all(|&r| r));
+    }
 
-        let solver = Solver::new(stock, 3, CutDirection::Auto, StockGrain::None, demands);
-        let sol = solver.solve();
-        assert_solution_valid(&sol, 35);
+    #[test]
+    fn test_solve_fixed() {
+        let solver = Solver::new(
+            Rect::new(100, 100),
+            0,
+            CutDirection::Auto,
+            StockGrain::None,
+            vec![Demand::new(Rect::new(50, 50), 5)],
+        );
+        let spread = |solution: &Solution| {
+            let used: Vec<u64> = solution
+                .sheets
+                .iter()
+                .map(|s| s.stock.area() - s.waste_area)
+                .collect();
+            used.iter().max().unwrap() - used.iter().min().unwrap()
+        };
+        let minimal = solver.solve();
+        assert_eq!(minimal.sheet_count(), 2);
+        assert!(solver.solve_fixed(1).is_none());
+
+        let fixed = solver.solve_fixed(3).unwrap();
+        assert_eq!(fixed.sheet_count(), 3);
+        assert_solution_valid(&fixed, 5);
+        assert!(
+            spread(&fixed) < spread(&minimal),
+            "{} vs {}",
+            spread(&fixed),
+            spread(&minimal)
+        );
+        assert_eq!(spread(&fixed), 2500);
     }
 
-    /// 40 pieces, 8 different sizes, rotation disabled for all.
-    /// Without rotation the solver has less flexibility, requiring more sheets.
     #[test]
-    fn test_complex_no_rotation() {
-        let stock = Rect::new(2440, 1220);
+    fn test_strategy_weights() {
+        let demands = vec![Demand::new(Rect::new(50, 50), 4)];
+        let solver = || {
+            Solver::new(
+                Rect::new(100, 100),
+                0,
+                CutDirection::Auto,
+                StockGrain::None,
+                demands.clone(),
+            )
+        };
+        let pieces = solver().expand_demands(&demands);
+        let (all, all_runs) = solver().greedy_best_counted(&pieces, None);
+        assert_eq!(all_runs, 6);
+
+        // The first combination already reaches the one-sheet bound
+        let (first, runs) = solver()
+            .with_strategy_weights(vec![(
+                CutDirection::AlongWidth,
+                ScoreStrategy::BestShortSideFit,
+                10,
+            )])
+            .greedy_best_counted(&pieces, None);
+        assert_eq!(runs, 1);
+        assert_solution_valid(&first, pieces.len());
+        assert_eq!(first.sheet_count(), all.sheet_count());
+    }
+
+    #[test]
+    fn test_target_sheets() {
         let demands = vec![
-            Demand {
-                rect: Rect::new(1200, 600),
-                qty: 4,
-                allow_rotate: false,
-                grain: PieceGrain::Auto,
-            },
-            Demand {
-                rect: Rect::new(800, 400),
-                qty: 6,
-                allow_rotate: false,
-                grain: PieceGrain::Auto,
-            },
-            Demand {
-                rect: Rect::new(600, 300),
-                qty: 5,
-                allow_rotate: false,
-                grain: PieceGrain::Auto,
-            },
-            Demand {
-                rect: Rect::new(400, 400),
-                qty: 3,
-                allow_rotate: false,
-                grain: PieceGrain::Auto,
-            },
-            Demand {
-                rect: Rect::new(500, 250),
-                qty: 7,
-                allow_rotate: false,
-                grain: PieceGrain::Auto,
-            },
-            Demand {
-                rect: Rect::new(300, 200),
-                qty: 5,
-                allow_rotate: false,
-                grain: PieceGrain::Auto,
-            },
-            Demand {
-                rect: Rect::new(700, 350),
-                qty: 6,
-                allow_rotate: false,
-                grain: PieceGrain::Auto,
-            },
-            Demand {
-                rect: Rect::new(250, 150),
-                qty: 4,
-                allow_rotate: false,
-                grain: PieceGrain::Auto,
-            },
+            Demand::new(Rect::new(60, 45), 6),
+            Demand::new(Rect::new(35, 30), 8),
         ];
-        let total_pieces: u32 = demands.iter().map(|d| d.qty).sum();
-        assert_eq!(total_pieces, 40);
+        let solver = |target| {
+            Solver::new(
+                Rect::new(100, 100),
+                0,
+                CutDirection::Auto,
+                StockGrain::None,
+                demands.clone(),
+            )
+            .with_target_sheets(target)
+        };
+        let pieces = solver(None).expand_demands(&demands);
+        let upper_bound = pieces.len() + 1;
+        let (optimal, all_nodes) =
+            solver(None).branch_and_bound_counted(&pieces, upper_bound, None);
+        // Any layout will do: the first one found ends the search
+        let (quick, quick_nodes) =
+            solver(Some(pieces.len())).branch_and_bound_counted(&pieces, upper_bound, None);
+        assert_solution_valid(&quick, pieces.len());
+        assert!(quick.sheet_count() >= optimal.sheet_count());
+        assert!(
+            quick_nodes * 10 < all_nodes,
+            "expected far fewer nodes: {quick_nodes} vs {all_nodes}"
+        );
+
+        let greedy = solver(None).with_branch_and_bound(false).solve();
+        let target = greedy.sheet_count();
+        let solution = solver(Some(target)).solve();
+        assert!(solution.sheet_count() <= target);
+    }
+
+    #[test]
+    fn test_cluster_labels() {
+        // Summed distance between same-label centroids
+        fn spread(sol: &Solution) -> f64 {
+            let mut total = 0.0;
+            for sheet in &sol.sheets {
+                for (i, a) in sheet.placements.iter().enumerate() {
+                    for b in &sheet.placements[i + 1..] {
+                        if a.label.is_some() && a.label == b.label {
+                            let dx = a.x as f64 - b.x as f64;
+                            let dy = a.y as f64 - b.y as f64;
+                            total += (dx * dx + dy * dy).sqrt();
+                        }
+                    }
+                }
+            }
+            total
+        }
 
+        let piece = |label: &str| Demand::new(Rect::new(50, 50), 1).with_label(label);
+        let demands = vec![piece("A"), piece("B"), piece("A"), piece("B")];
         let solver = Solver::new(
-            stock,
+            Rect::new(200, 50),
             0,
             CutDirection::Auto,
             StockGrain::None,
-            demands.clone(),
+            demands,
         );
-        let sol_no_rot = solver.solve();
-        assert_solution_valid(&sol_no_rot, 40);
+        let plain = solver.solve();
+        let clustered = solver.with_cluster_labels(true).solve();
 
-        // Compare with rotation enabled — should use <= sheets
-        let demands_rot: Vec<Demand> = demands
-            .into_iter()
-            .map(|d| Demand {
-                allow_rotate: true,
-                ..d
-            })
-            .collect();
-        let solver_rot = Solver::new(stock, 0, CutDirection::Auto, StockGrain::None, demands_rot);
-        let sol_rot = solver_rot.solve();
-        assert_solution_valid(&sol_rot, 40);
-        assert!(sol_rot.sheet_count() <= sol_no_rot.sheet_count());
+        assert!(
+            spread(&clustered) < spread(&plain),
+            "{} vs {}",
+            spread(&clustered),
+            spread(&plain)
+        );
+        assert_eq!(clustered.sheet_count(), plain.sheet_count());
+        assert_solution_valid(&clustered, 4);
+        assert_eq!(clustered.validate(), Ok(()));
+        // Same layout, only the labels moved
+        for (a, b) in plain.sheets[0]
+            .placements
+            .iter()
+            .zip(&clustered.sheets[0].placements)
+        {
+            assert_eq!((a.x, a.y, a.rect), (b.x, b.y, b.rect));
+        }
     }
 
-    /// 50 pieces, 10 different sizes, kerf=4, mix of rotation allowed/disallowed.
     #[test]
-    fn test_complex_large_batch_mixed_rotation() {
-        let stock = Rect::new(3000, 1500);
-        let demands = vec![
-            Demand {
-                rect: Rect::new(900, 600),
-                qty: 5,
-                allow_rotate: true,
-                grain: PieceGrain::Auto,
-            },
-            Demand {
-                rect: Rect::new(500, 400),
-                qty: 6,
-                allow_rotate: false,
-                grain: PieceGrain::Auto,
-            },
-            Demand {
-                rect: Rect::new(700, 350),
-                qty: 4,
-                allow_rotate: true,
-                grain: PieceGrain::Auto,
-            },
-            Demand {
-                rect: Rect::new(1200, 500),
-                qty: 3,
-                allow_rotate: true,
-                grain: PieceGrain::Auto,
-            },
-            Demand {
-                rect: Rect::new(300, 300),
-                qty: 8,
-                allow_rotate: false,
-                grain: PieceGrain::Auto,
-            },
-            Demand {
-                rect: Rect::new(450, 200),
-                qty: 6,
-                allow_rotate: true,
-                grain: PieceGrain::Auto,
-            },
-            Demand {
-                rect: Rect::new(600, 450),
-                qty: 5,
-                allow_rotate: false,
-                grain: PieceGrain::Auto,
-            },
-            Demand {
-                rect: Rect::new(800, 300),
-                qty: 4,
-                allow_rotate: true,
-                grain: PieceGrain::Auto,
-            },
-            Demand {
-                rect: Rect::new(350, 250),
-                qty: 5,
-                allow_rotate: true,
-                grain: PieceGrain::Auto,
-            },
-            Demand {
-                rect: Rect::new(1000, 700),
-                qty: 4,
-                allow_rotate: false,
-                grain: PieceGrain::Auto,
-            },
+    fn test_max_expanded_pieces() {
+        let solver = Solver::new(
+            Rect::new(100, 100),
+            0,
+            CutDirection::Auto,
+            StockGrain::None,
+            vec![
+                Demand::new(Rect::new(10, 10), u32::MAX),
+                Demand::new(Rect::new(20, 20), u32::MAX),
+            ],
+        );
+        // Rejected up front, without expanding ~8.6 billion pieces
+        assert_eq!(
+            solver.try_solve().unwrap_err(),
+            SolveError::TooManyPieces {
+                requested: 2 * u32::MAX as u64,
+                max: DEFAULT_MAX_EXPANDED_PIECES,
+            }
+        );
+
+        let capped = Solver::new(
+            Rect::new(100, 100),
+            0,
+            CutDirection::Auto,
+            StockGrain::None,
+            vec![Demand::new(Rect::new(10, 10), 5)],
+        )
+        .with_max_expanded_pieces(4);
+        assert!(matches!(
+            capped.try_solve(),
+            Err(SolveError::TooManyPieces {
+                requested: 5,
+                max: 4
+            })
+        ));
+        assert_eq!(
+            capped
+                .with_max_expanded_pieces(5)
+                .try_solve()
+                .unwrap()
+                .sheet_count(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_area_demand_strips() {
+        let stock = Rect::new(2440, 1220);
+        let area = 3_000_000;
+        let solver = Solver::new(
+            stock,
+            3,
+            CutDirection::Auto,
+            StockGrain::None,
+            vec![Demand::new(Rect::new(1200, 1000), 1)],
+        )
+        .with_area_demands(vec![AreaDemand {
+            width: 600,
+            total_area: area,
+        }]);
+        let sol = solver.try_solve().unwrap();
+
+        let strips: Vec<&Placement> = sol
+            .sheets
+            .iter()
+            .flat_map(|s| &s.placements)
+            .filter(|p| p.requested.width == 600 && p.requested != Rect::new(1200, 1000))
+            .collect();
+        assert!(!strips.is_empty());
+        let strip_area: u64 = strips.iter().map(|p| p.rect.area()).sum();
+        // Covers the area, overshooting by less than one mm of strip length
+        assert!(
+            strip_area >= area && strip_area < area + 600,
+            "{strip_area}"
+        );
+        for p in &strips {
+            let across = if p.rotated {
+                p.rect.length
+            } else {
+                p.rect.width
+            };
+            assert_eq!(across, 600);
+        }
+        assert_eq!(sol.validate(), Ok(()));
+        assert_solution_valid(&sol, 1 + strips.len());
+
+        assert_eq!(
+            solver
+                .with_area_demands(vec![AreaDemand {
+                    width: 3000,
+                    total_area: 1,
+                }])
+                .try_solve()
+                .unwrap_err(),
+            SolveError::InvalidStripWidth { width: 3000 }
+        );
+    }
+
+    #[test]
+    fn test_minimal_single_sheet() {
+        let squares = [Demand::new(Rect::new(50, 50), 4)];
+        assert_eq!(
+            Solver::minimal_single_sheet(&squares, 0),
+            Some(Rect::new(100, 100))
+        );
+        // With kerf, one row (three kerfs) beats a 2x2 grid (two each way)
+        assert_eq!(
+            Solver::minimal_single_sheet(&squares, 3),
+            Some(Rect::new(50, 209))
+        );
+
+        let mixed = [
+            Demand::new(Rect::new(80, 40), 2),
+            Demand::new(Rect::new(40, 40), 1),
         ];
-        let total_pieces: u32 = demands.iter().map(|d| d.qty).sum();
-        assert_eq!(total_pieces, 50);
+        let sheet = Solver::minimal_single_sheet(&mixed, 0).unwrap();
+        assert_eq!(sheet.area(), 80 * 100);
+        let sol = Solver::new(
+            sheet,
+            0,
+            CutDirection::Auto,
+            StockGrain::None,
+            mixed.to_vec(),
+        )
+        .solve();
+        assert_eq!(sol.sheets.len(), 1);
 
-        let solver = Solver::new(stock, 4, CutDirection::Auto, StockGrain::None, demands);
-        let sol = solver.solve();
-        assert_solution_valid(&sol, 50);
+        assert_eq!(Solver::minimal_single_sheet(&[], 0), None);
+    }
 
-        assert!(sol.total_waste_percent() < 100.0);
-        assert!(sol.total_waste_percent() >= 0.0);
+    #[test]
+    fn test_row_lengths() {
+        let footprints = [(Rect::new(50, 30), true, 2)];
+        assert_eq!(row_lengths(&footprints, 0), [30, 50, 60, 80, 100]);
+        assert_eq!(row_lengths(&footprints, 5), [30, 50, 65, 85, 105]);
     }
 
-    /// 32 pieces, 5 different sizes, small stock forcing many sheets.
     #[test]
-    fn test_complex_small_stock_many_sheets() {
-        let stock = Rect::new(500, 400);
+    fn test_corner_radius() {
+        let stock = Rect::new(1000, 500);
+        let solve = |radius, piece: Rect| {
+            Solver::new(
+                stock,
+                0,
+                CutDirection::Auto,
+                StockGrain::None,
+                vec![Demand::new(piece, 1).with_allow_rotate(false)],
+            )
+            .with_corner_radius(radius)
+            .try_solve()
+        };
+
+        let square = solve(0, Rect::new(300, 200)).unwrap();
+        let p = &square.sheets[0].placements[0];
+        assert_eq!((p.x, p.y), (0, 0));
+
+        // The corner is dead: the piece moves off it by the radius
+        let rounded = solve(20, Rect::new(300, 200)).unwrap();
+        let p = &rounded.sheets[0].placements[0];
+        assert_eq!((p.x, p.y), (0, 20));
+        for (cx, cy) in [(0, 0), (980, 0), (0, 480), (980, 480)] {
+            let corner = Placement {
+                rect: Rect::new(20, 20),
+                x: cx,
+                y: cy,
+                rotated: false,
+                requested: Rect::new(20, 20),
+                orientation_deg: 0,
+                label: None,
+                is_filler: false,
+                directional: false,
+                group_hint: None,
+            };
+            assert!(!p.overlaps(&corner, 0));
+        }
+
+        // A full-size piece no longer fits
+        assert_eq!(
+            solve(20, stock).unwrap_err(),
+            SolveError::PieceTooLarge { rect: stock }
+        );
+    }
+
+    #[test]
+    fn test_directional() {
+        // Along-width cutting would turn these pieces upright
+        let stock = Rect::new(1000, 500);
         let demands = vec![
-            Demand {
-                rect: Rect::new(200, 150),
-                qty: 8,
-                allow_rotate: true,
-                grain: PieceGrain::Auto,
-            },
-            Demand {
-                rect: Rect::new(300, 200),
-                qty: 6,
-                allow_rotate: true,
-                grain: PieceGrain::Auto,
-            },
-            Demand {
-                rect: Rect::new(150, 100),
-                qty: 7,
-                allow_rotate: true,
-                grain: PieceGrain::Auto,
-            },
-            Demand {
-                rect: Rect::new(250, 180),
-                qty: 5,
-                allow_rotate: true,
-                grain: PieceGrain::Auto,
-            },
-            Demand {
-                rect: Rect::new(400, 300),
-                qty: 6,
-                allow_rotate: true,
-                grain: PieceGrain::Auto,
-            },
+            Demand::new(Rect::new(400, 100), 3).with_directional(true),
+            Demand::new(Rect::new(300, 100), 2),
         ];
-        let total_pieces: u32 = demands.iter().map(|d| d.qty).sum();
-        assert_eq!(total_pieces, 32);
+        let solution = Solver::new(
+            stock,
+            3,
+            CutDirection::AlongWidth,
+            StockGrain::None,
+            demands,
+        )
+        .try_solve()
+        .unwrap();
+        let placements: Vec<&Placement> =
+            solution.sheets.iter().flat_map(|s| &s.placements).collect();
+        assert_eq!(placements.len(), 5);
+        for p in &placements {
+            assert_eq!(p.directional, p.requested == Rect::new(400, 100));
+            assert_eq!(p.rotated, !p.directional, "{:?}", p);
+        }
 
-        let solver = Solver::new(stock, 0, CutDirection::Auto, StockGrain::None, demands);
-        let sol = solver.solve();
-        assert_solution_valid(&sol, 32);
+        let sheet = &solution.sheets[0];
+        let layout = crate::render::render_sheet(sheet.stock, &sheet.placements);
+        let arrows = layout.matches('^').count();
+        assert_eq!(
+            arrows,
+            sheet.placements.iter().filter(|p| p.directional).count()
+        );
+        assert!(arrows > 0);
+    }
 
-        // With small stock and large pieces, we need many sheets
-        assert!(sol.sheet_count() >= 5);
+    #[test]
+    fn test_strip_boundaries() {
+        let demands = vec![
+            Demand::new(Rect::new(300, 180), 4),
+            Demand::new(Rect::new(250, 140), 5),
+        ];
+        for anchor in [None, Some(Anchor::BottomRight)] {
+            let solution = Solver::new(
+                Rect::new(1000, 500),
+                3,
+                CutDirection::AlongLength,
+                StockGrain::None,
+                demands.clone(),
+            )
+            .with_strip_boundaries(vec![200, 350])
+            .with_anchor(anchor)
+            .try_solve()
+            .unwrap();
+            assert_solution_valid(&solution, 9);
+            for p in solution.sheets.iter().flat_map(|s| &s.placements) {
+                let (bottom, top) = (p.y, p.y + p.rect.width);
+                for boundary in [200, 350] {
+                    assert!(top <= boundary || bottom >= boundary + 3, "{:?}", p);
+                }
+            }
+        }
     }
 
-    /// Real-world test from CSV: 473x14:4, 473x196:4, 473x158:12, 100x100:8, 742x473:8
-    /// on 2500x1200 stock with kerf=3.
-    /// Verifies all 3 cut directions produce valid solutions and that
-    /// AlongLength and AlongWidth produce different placements.
     #[test]
-    fn test_cut_direction_csv_data() {
-        let stock = Rect::new(2500, 1200);
+    fn test_first_cut() {
+        let stock = Rect::new(1000, 500);
         let demands = vec![
+            Demand::new(Rect::new(500, 250), 2).with_allow_rotate(false),
+            Demand::new(Rect::new(300, 200), 6),
+        ];
+        for anchor in [None, Some(Anchor::BottomRight)] {
+            let solution = Solver::new(
+                stock,
+                3,
+                CutDirection::Auto,
+                StockGrain::None,
+                demands.clone(),
+            )
+            .with_first_cut(Some((Axis::X, 400)))
+            .with_anchor(anchor)
+            .try_solve()
+            .unwrap();
+            assert_eq!(
+                solution
+                    .sheets
+                    .iter()
+                    .map(|s| s.placements.len())
+                    .sum::<usize>(),
+                8
+            );
+            for p in solution.sheets.iter().flat_map(|s| &s.placements) {
+                assert!(p.x + p.rect.length <= 400 || p.x >= 403, "{:?}", p);
+            }
+            for o in solution.sheets.iter().flat_map(|s| &s.offcuts) {
+                assert!(o.x + o.rect.length <= 400 || o.x >= 403, "{:?}", o);
+            }
+        }
+    }
+
+    #[test]
+    fn test_job_hash() {
+        let stock = Rect::new(2440, 1220);
+        let a = Demand::new(Rect::new(800, 600), 3).with_material("oak");
+        let b = Demand::new(Rect::new(400, 300), 5).with_label("shelf");
+        let job = |demands: Vec<Demand>, kerf| {
+            Solver::new(stock, kerf, CutDirection::Auto, StockGrain::None, demands)
+                .with_strip_threshold(Some(100))
+                .job_hash()
+        };
+
+        let base = job(vec![a.clone(), b.clone()], 3);
+        assert_eq!(base, job(vec![a.clone(), b.clone()], 3));
+        assert_eq!(base, job(vec![b.clone(), a.clone()], 3));
+        // Same pieces split across two demands
+        let split = vec![
             Demand {
-                rect: Rect::new(473, 14),
-                qty: 4,
-                allow_rotate: true,
-                grain: PieceGrain::Auto,
-            },
-            Demand {
-                rect: Rect::new(473, 196),
-                qty: 4,
-                allow_rotate: true,
-                grain: PieceGrain::Auto,
-            },
-            Demand {
-                rect: Rect::new(473, 158),
-                qty: 12,
-                allow_rotate: true,
-                grain: PieceGrain::Auto,
-            },
-            Demand {
-                rect: Rect::new(100, 100),
-                qty: 8,
-                allow_rotate: true,
-                grain: PieceGrain::Auto,
+                qty: 1,
+                ..a.clone()
             },
+            b.clone(),
             Demand {
-                rect: Rect::new(742, 473),
-                qty: 8,
-                allow_rotate: true,
-                grain: PieceGrain::Auto,
+                qty: 2,
+                ..a.clone()
             },
         ];
-        let total_pieces: u32 = demands.iter().map(|d| d.qty).sum();
-        assert_eq!(total_pieces, 36);
-
-        let sol_auto = Solver::new(
+        assert_eq!(base, job(split, 3));
+        // Runtime-only settings don't count
+        let solver = Solver::new(
             stock,
             3,
             CutDirection::Auto,
             StockGrain::None,
-            demands.clone(),
-        )
-        .solve();
-        let sol_length = Solver::new(
-            stock,
-            3,
-            CutDirection::AlongLength,
-            StockGrain::None,
-            demands.clone(),
+            vec![a.clone(), b.clone()],
         )
-        .solve();
-        let sol_width = Solver::new(
+        .with_strip_threshold(Some(100))
+        .with_parallel(false)
+        .with_deadline(Some(Instant::now()));
+        assert_eq!(base, solver.job_hash());
+
+        assert_ne!(base, job(vec![a.clone(), b.clone()], 4));
+        assert_ne!(base, job(vec![a.clone(), b.clone().with_label("door")], 3));
+        assert_ne!(base, job(vec![a, Demand { qty: 6, ..b }], 3));
+    }
+
+    #[test]
+    fn test_tight_fit_warnings() {
+        let stock = Rect::new(1000, 500);
+        let solve = |piece: Rect| {
+            Solver::new(
+                stock,
+                3,
+                CutDirection::Auto,
+                StockGrain::None,
+                vec![Demand::new(piece, 1).with_allow_rotate(false)],
+            )
+            .with_tight_fit_margin(2)
+            .solve()
+        };
+
+        // 1 mm short of the full length
+        let tight = solve(Rect::new(999, 300));
+        assert_eq!(
+            tight.warnings,
+            [Warning::TightFit {
+                sheet: 0,
+                piece: 0,
+                spare: 1
+            }]
+        );
+        assert_eq!(
+            tight.warnings[0].to_string(),
+            "sheet 0: piece 0 fits with only 1 mm to spare"
+        );
+
+        let comfortable = solve(Rect::new(900, 300));
+        assert!(comfortable.warnings.is_empty());
+
+        // A piece followed by another is bounded by its cut, not the edge
+        let sol = Solver::new(
             stock,
             3,
-            CutDirection::AlongWidth,
+            CutDirection::Auto,
             StockGrain::None,
-            demands.clone(),
+            vec![Demand::new(Rect::new(498, 400), 2).with_allow_rotate(false)],
         )
+        .with_tight_fit_margin(2)
         .solve();
-
-        // All solutions must be valid
-        assert_solution_valid(&sol_auto, 36);
-        assert_solution_valid(&sol_length, 36);
-        assert_solution_valid(&sol_width, 36);
-
-        // Collect all placements (x, y) per direction to verify they differ
-        let coords = |sol: &Solution| -> Vec<Vec<(u32, u32)>> {
-            sol.sheets
-                .iter()
-                .map(|s| {
-                    let mut c: Vec<(u32, u32)> = s.placements.iter().map(|p| (p.x, p.y)).collect();
-                    c.sort();
-                    c
-                })
-                .collect()
+        let [
+            Warning::TightFit {
+                sheet,
+                piece,
+                spare,
+            },
+        ] = sol.warnings[..]
+        else {
+            panic!("expected one warning, got {:?}", sol.warnings);
         };
-        let coords_length = coords(&sol_length);
-        let coords_width = coords(&sol_width);
-
-        assert_ne!(
-            coords_length, coords_width,
-            "AlongLength and AlongWidth should produce different placement layouts"
-        );
+        assert_eq!(sol.sheets[sheet].placements[piece].x, 501);
+        assert_eq!(spare, 1);
     }
 
-    /// Verifies that each CutDirection produces valid, non-overlapping solutions
-    /// for a simple case where the split direction clearly matters.
     #[test]
-    fn test_cut_direction_all_modes_valid() {
-        let stock = Rect::new(1000, 500);
+    fn test_reserve_offcut() {
+        let stock = Rect::new(2440, 1220);
+        let drop = Rect::new(1200, 600);
+        let intact = |sheet: &SheetResult| {
+            let r = sheet.reserved.expect("drop reserved");
+            assert!(r.rect == drop || r.rect == drop.rotated());
+            assert!(r.rect.fits_in(&stock));
+            let reserved = Placement {
+                rect: r.rect,
+                x: r.x,
+                y: r.y,
+                rotated: false,
+                requested: r.rect,
+                orientation_deg: 0,
+                label: None,
+                is_filler: false,
+                directional: false,
+                group_hint: None,
+            };
+            assert!(sheet.placements.iter().all(|p| !p.overlaps(&reserved, 3)));
+        };
+
         let demands = vec![
-            Demand {
-                rect: Rect::new(400, 200),
-                qty: 4,
-                allow_rotate: true,
-                grain: PieceGrain::Auto,
-            },
-            Demand {
-                rect: Rect::new(300, 150),
-                qty: 3,
-                allow_rotate: true,
-                grain: PieceGrain::Auto,
-            },
+            Demand::new(Rect::new(800, 600), 3),
+            Demand::new(Rect::new(400, 300), 4),
         ];
+        let sol = Solver::new(stock, 3, CutDirection::Auto, StockGrain::None, demands)
+            .with_reserve_offcut(Some(drop))
+            .solve();
+        assert_solution_valid(&sol, 7);
+        assert_eq!(sol.sheets.len(), 1);
+        intact(&sol.sheets[0]);
+        assert!(sol.sheets[0].waste_area >= drop.area());
 
-        for &dir in &[
-            CutDirection::Auto,
-            CutDirection::AlongLength,
-            CutDirection::AlongWidth,
-        ] {
-            let sol = Solver::new(stock, 3, dir, StockGrain::None, demands.clone()).solve();
-            assert_solution_valid(&sol, 7);
-        }
+        // Seven drop-sized pieces: reserving on every sheet would need a
+        // third sheet, so only the half-empty second sheet keeps a drop
+        let demands = vec![Demand::new(drop, 7)];
+        let sol = Solver::new(stock, 3, CutDirection::Auto, StockGrain::None, demands)
+            .with_reserve_offcut(Some(drop))
+            .solve();
+        assert_solution_valid(&sol, 7);
+        assert_eq!(sol.sheets.len(), 2);
+        let full = sol.sheets.iter().find(|s| s.placements.len() == 4).unwrap();
+        assert!(full.reserved.is_none());
+        intact(sol.sheets.iter().find(|s| s.placements.len() == 3).unwrap());
     }
 
-    // ── Grain direction tests ──────────────────────────────────────
+    #[test]
+    fn test_soft_grain_penalty() {
+        // Four 250x400 pieces, grain along their length: aligned, only
+        // three fit across the 1000 mm sheet; turned off-grain, all four do
+        let stock = Rect::new(1000, 600);
+        let demands = vec![Demand::new(Rect::new(250, 400), 4).with_grain(PieceGrain::Length)];
+        let solver = |grain, penalty| {
+            Solver::new(stock, 3, CutDirection::Auto, grain, demands.clone())
+                .with_grain_penalty(penalty)
+                .solve()
+        };
+
+        let strict = solver(StockGrain::AlongLength, Some(u64::MAX));
+        assert_solution_valid(&strict, 4);
+        assert_eq!(strict.sheets.len(), 2);
+        assert!(
+            strict
+                .sheets
+                .iter()
+                .flat_map(|s| &s.placements)
+                .all(|p| !p.rotated)
+        );
+
+        // Zero penalty lays out exactly as if the stock had no grain
+        let ignored = solver(StockGrain::AlongLength, Some(0));
+        let no_grain = solver(StockGrain::None, None);
+        assert_solution_valid(&ignored, 4);
+        assert_eq!(ignored.sheets.len(), 1);
+        let layout = |sol: &Solution| -> Vec<(u32, u32, bool)> {
+            sol.sheets
+                .iter()
+                .flat_map(|s| &s.placements)
+                .map(|p| (p.x, p.y, p.rotated))
+                .collect()
+        };
+        assert_eq!(layout(&ignored), layout(&no_grain));
 
-    #[test]
-    fn test_grain_length_along_length_no_rotate() {
-        // Piece grain=Length, stock grain=AlongLength → piece must NOT be rotated
-        let stock = Rect::new(100, 50);
-        let solver = Solver::new(
+        // Soft grain never conflicts with disabled rotation
+        let demands = vec![
+            Demand::new(Rect::new(250, 400), 1)
+                .with_allow_rotate(false)
+                .with_grain(PieceGrain::Width),
+        ];
+        let sol = Solver::new(
             stock,
-            0,
+            3,
             CutDirection::Auto,
             StockGrain::AlongLength,
-            vec![Demand {
-                rect: Rect::new(100, 50),
-                qty: 1,
-                allow_rotate: true,
-                grain: PieceGrain::Length,
-            }],
-        );
-        let sol = solver.solve();
-        assert_solution_valid(&sol, 1);
+            demands,
+        )
+        .with_grain_penalty(Some(100))
+        .try_solve()
+        .unwrap();
         assert!(!sol.sheets[0].placements[0].rotated);
     }
 
     #[test]
-    fn test_grain_length_along_width_force_rotate() {
-        // Piece grain=Length, stock grain=AlongWidth → piece MUST be rotated
-        // Stock 100x50, piece 50x100: needs rotation to fit (50x100 rotated → 100x50)
-        let stock = Rect::new(100, 50);
+    fn test_grain_conflict_is_an_error() {
+        let stock = Rect::new(2440, 1220);
+        let demands = vec![
+            Demand::new(Rect::new(800, 600), 2),
+            Demand::new(Rect::new(400, 300), 1)
+                .with_allow_rotate(false)
+                .with_grain(PieceGrain::Width),
+        ];
         let solver = Solver::new(
             stock,
-            0,
+            3,
             CutDirection::Auto,
-            StockGrain::AlongWidth,
-            vec![Demand {
-                rect: Rect::new(50, 100),
-                qty: 1,
-                allow_rotate: true,
-                grain: PieceGrain::Length,
-            }],
+            StockGrain::AlongLength,
+            demands,
         );
-        let sol = solver.solve();
-        assert_solution_valid(&sol, 1);
-        assert!(sol.sheets[0].placements[0].rotated);
+        let err = solver.try_solve().unwrap_err();
+        assert_eq!(
+            err,
+            SolveError::GrainConflict {
+                demand: 1,
+                conflict: GrainConflict {
+                    stock_grain: StockGrain::AlongLength,
+                    piece_grain: PieceGrain::Width,
+                },
+            }
+        );
+        assert!(err.to_string().starts_with("demand 1: piece grain Width"));
     }
 
     #[test]
-    fn test_grain_width_along_length_force_rotate() {
-        // Piece grain=Width, stock grain=AlongLength → piece MUST be rotated
-        // Stock 100x50, piece 50x100: rotated → 100x50 fits
-        let stock = Rect::new(100, 50);
-        let solver = Solver::new(
+    fn test_grid_pitch() {
+        let stock = Rect::new(1000, 500);
+        let solve = |demands: Vec<Demand>| {
+            Solver::new(stock, 3, CutDirection::Auto, StockGrain::None, demands)
+                .with_grid_pitch(Some((50, 50)))
+                .try_solve()
+        };
+        let solution = solve(vec![
+            Demand::new(Rect::new(200, 100), 5),
+            Demand::new(Rect::new(150, 50), 4),
+            Demand::new(Rect::new(300, 250), 2),
+        ])
+        .unwrap();
+        let placements: Vec<&Placement> =
+            solution.sheets.iter().flat_map(|s| &s.placements).collect();
+        assert_eq!(placements.len(), 11);
+        for p in placements {
+            assert_eq!((p.x % 50, p.y % 50), (0, 0), "{:?}", p);
+        }
+        assert_eq!(solution.validate(), Ok(()));
+
+        assert_eq!(
+            solve(vec![Demand::new(Rect::new(120, 100), 1)]).unwrap_err(),
+            SolveError::OffGrid {
+                rect: Rect::new(120, 100),
+                pitch: (50, 50)
+            }
+        );
+        let err = Solver::new(
             stock,
-            0,
+            3,
             CutDirection::Auto,
-            StockGrain::AlongLength,
-            vec![Demand {
-                rect: Rect::new(50, 100),
-                qty: 1,
-                allow_rotate: true,
-                grain: PieceGrain::Width,
-            }],
-        );
-        let sol = solver.solve();
-        assert_solution_valid(&sol, 1);
-        assert!(sol.sheets[0].placements[0].rotated);
+            StockGrain::None,
+            vec![Demand::new(Rect::new(200, 100), 1)],
+        )
+        .with_grid_pitch(Some((0, 50)))
+        .try_solve()
+        .unwrap_err();
+        assert_eq!(err, SolveError::InvalidGridPitch { pitch: (0, 50) });
     }
 
     #[test]
-    fn test_grain_width_along_width_no_rotate() {
-        // Piece grain=Width, stock grain=AlongWidth → piece must NOT be rotated
-        let stock = Rect::new(100, 50);
-        let solver = Solver::new(
-            stock,
-            0,
-            CutDirection::Auto,
-            StockGrain::AlongWidth,
-            vec![Demand {
-                rect: Rect::new(100, 50),
-                qty: 1,
-                allow_rotate: true,
-                grain: PieceGrain::Width,
-            }],
+    fn test_kerf_too_large() {
+        let stock = Rect::new(100, 100);
+        let demands = vec![Demand::new(Rect::new(10, 10), 2)];
+        let solve = |stock, kerf| {
+            Solver::new(
+                stock,
+                kerf,
+                CutDirection::Auto,
+                StockGrain::None,
+                demands.clone(),
+            )
+            .try_solve()
+        };
+        let err = solve(stock, 200).unwrap_err();
+        assert_eq!(err, SolveError::KerfTooLarge { kerf: 200, stock });
+        assert_eq!(
+            err.to_string(),
+            "kerf 200 mm must be smaller than the shorter side of stock 100x100 (100 mm)"
         );
-        let sol = solver.solve();
-        assert_solution_valid(&sol, 1);
-        assert!(!sol.sheets[0].placements[0].rotated);
+        // The shorter side is what counts
+        assert!(solve(Rect::new(1000, 60), 60).is_err());
+        assert!(solve(Rect::new(1000, 60), 59).is_ok());
     }
 
     #[test]
-    fn test_grain_auto_free_rotation() {
-        // Piece grain=Auto with any stock grain → free rotation (optimizer chooses)
-        // Stock 100x50, piece 50x100: only fits rotated
-        let stock = Rect::new(100, 50);
-        let solver = Solver::new(
-            stock,
-            0,
-            CutDirection::Auto,
-            StockGrain::AlongLength,
-            vec![Demand {
-                rect: Rect::new(50, 100),
-                qty: 1,
-                allow_rotate: true,
-                grain: PieceGrain::Auto,
-            }],
+    fn test_back_face() {
+        // Fits either way and the front has no grain: the back decides
+        let stock = Rect::new(1000, 900);
+        let piece = Demand::new(Rect::new(800, 300), 2);
+        let solve = |front: StockGrain, piece: Demand| {
+            Solver::new(stock, 3, CutDirection::Auto, front, vec![piece])
+                .with_back_grain(StockGrain::AlongLength)
+                .try_solve()
+        };
+        for (back_grain, rotated) in [(PieceGrain::Length, false), (PieceGrain::Width, true)] {
+            let solution =
+                solve(StockGrain::None, piece.clone().with_back_grain(back_grain)).unwrap();
+            let placements = &solution.sheets[0].placements;
+            assert_eq!(placements.len(), 2);
+            assert!(placements.iter().all(|p| p.rotated == rotated));
+        }
+
+        // Front grain keeps it unrotated, back grain turns it
+        let conflicting = piece
+            .with_grain(PieceGrain::Length)
+            .with_back_grain(PieceGrain::Width);
+        assert_eq!(
+            solve(StockGrain::AlongLength, conflicting).unwrap_err(),
+            SolveError::FaceGrainConflict { demand: 0 }
         );
-        let sol = solver.solve();
-        assert_solution_valid(&sol, 1);
-        assert!(sol.sheets[0].placements[0].rotated);
     }
 
     #[test]
-    fn test_grain_none_stock_ignores_piece_grain() {
-        // stock grain=None → all piece grains ignored, free rotation
-        // Piece 50x100 with grain=Length in stock 100x50: should still rotate to fit
-        let stock = Rect::new(100, 50);
-        let solver = Solver::new(
-            stock,
-            0,
-            CutDirection::Auto,
-            StockGrain::None,
-            vec![Demand {
-                rect: Rect::new(50, 100),
-                qty: 1,
-                allow_rotate: true,
-                grain: PieceGrain::Length,
-            }],
+    fn test_defects() {
+        let stock = Rect::new(1000, 500);
+        let defect = Placement {
+            rect: Rect::new(50, 50),
+            x: 0,
+            y: 0,
+            rotated: false,
+            requested: Rect::new(50, 50),
+            orientation_deg: 0,
+            label: None,
+            is_filler: false,
+            directional: false,
+            group_hint: None,
+        };
+        for anchor in [None, Some(Anchor::BottomRight)] {
+            let defect = Placement {
+                x: if anchor.is_some() { 950 } else { 0 },
+                ..defect.clone()
+            };
+            let solution = Solver::new(
+                stock,
+                3,
+                CutDirection::Auto,
+                StockGrain::None,
+                vec![Demand::new(Rect::new(400, 400), 2)],
+            )
+            .with_defects(vec![(defect.x, defect.y, defect.rect)])
+            .with_anchor(anchor)
+            .try_solve()
+            .unwrap();
+            // Both fit in the band beside the defect
+            assert_eq!(solution.sheet_count(), 1);
+            assert_eq!(solution.sheets[0].placements.len(), 2);
+            for p in solution.sheets.iter().flat_map(|s| &s.placements) {
+                assert!(!p.overlaps(&defect, 0), "{:?}", p);
+            }
+        }
+    }
+
+    #[test]
+    fn test_relax_grain_on_infeasible() {
+        // 1300 along the grain is longer than the sheet is wide
+        let stock = Rect::new(2440, 1220);
+        let demands = vec![
+            Demand::new(Rect::new(800, 1300), 1).with_grain(PieceGrain::Length),
+            Demand::new(Rect::new(400, 300), 2).with_grain(PieceGrain::Length),
+        ];
+        let solver = |relax| {
+            Solver::new(
+                stock,
+                3,
+                CutDirection::Auto,
+                StockGrain::AlongLength,
+                demands.clone(),
+            )
+            .with_relax_grain_on_infeasible(relax)
+        };
+        assert_eq!(
+            solver(false).try_solve().unwrap_err(),
+            SolveError::PieceTooLarge {
+                rect: Rect::new(800, 1300)
+            }
         );
-        let sol = solver.solve();
-        assert_solution_valid(&sol, 1);
-        // With grain=None, optimizer is free to rotate — and must rotate to fit
-        assert!(sol.sheets[0].placements[0].rotated);
+
+        let solution = solver(true).try_solve().unwrap();
+        assert_eq!(
+            solution.warnings,
+            [Warning::GrainRelaxed {
+                demand: 0,
+                rect: Rect::new(800, 1300)
+            }]
+        );
+        let placements: Vec<&Placement> =
+            solution.sheets.iter().flat_map(|s| &s.placements).collect();
+        assert_eq!(placements.len(), 3);
+        for p in placements {
+            // Only the relaxed piece turns
+            assert_eq!(p.rotated, p.requested == Rect::new(800, 1300), "{:?}", p);
+        }
     }
 
     #[test]
-    fn test_grain_constraint_reduces_flexibility() {
-        // With grain constraints, some pieces lose rotation flexibility → may need more sheets
-        let stock = Rect::new(200, 100);
-        // Two 100x200 pieces: without grain, both rotate to 200x100 and fit on 1 sheet each
-        // With grain=Length + stock=AlongLength → NoRotate → 100x200 doesn't fit in 200x100 stock
-        // because piece.length=100 < stock.length=200 ✓ but piece.width=200 > stock.width=100 ✗
-        // So each piece must be ForceRotated or can't be placed depending on grain
-        let solver_no_grain = Solver::new(
+    fn test_anchor_corner() {
+        let stock = Rect::new(2440, 1220);
+        let demands = vec![
+            Demand::new(Rect::new(1200, 800), 1),
+            Demand::new(Rect::new(400, 300), 6),
+        ];
+        let solve = |anchor: Anchor| {
+            Solver::new(
+                stock,
+                3,
+                CutDirection::Auto,
+                StockGrain::None,
+                demands.clone(),
+            )
+            .with_anchor(Some(anchor))
+            .solve()
+        };
+        let largest = |sol: &Solution| {
+            sol.sheets[0]
+                .placements
+                .iter()
+                .max_by_key(|p| p.rect.area())
+                .cloned()
+                .unwrap()
+        };
+
+        let sol = solve(Anchor::TopLeft);
+        let big = largest(&sol);
+        assert_eq!((big.x, big.y), (0, 0));
+        assert_solution_valid(&sol, 7);
+
+        let sol = solve(Anchor::TopRight);
+        let big = largest(&sol);
+        assert_eq!((big.x + big.rect.length, big.y), (stock.length, 0));
+        assert_solution_valid(&sol, 7);
+
+        let sol = solve(Anchor::BottomRight);
+        let big = largest(&sol);
+        assert_eq!(big.x + big.rect.length, stock.length);
+        assert_eq!(big.y + big.rect.width, stock.width);
+        assert_solution_valid(&sol, 7);
+
+        // A usable window stays where it is after mirroring
+        let sol = Solver::new(
             stock,
-            0,
+            3,
             CutDirection::Auto,
             StockGrain::None,
-            vec![Demand {
-                rect: Rect::new(100, 200),
-                qty: 1,
-                allow_rotate: true,
-                grain: PieceGrain::Auto,
-            }],
+            demands.clone(),
+        )
+        .with_anchor(Some(Anchor::BottomRight))
+        .with_usable_window(100, 50, Rect::new(2000, 1000))
+        .solve();
+        for p in sol.sheets.iter().flat_map(|s| &s.placements) {
+            assert!(p.x >= 100 && p.x + p.rect.length <= 2100);
+            assert!(p.y >= 50 && p.y + p.rect.width <= 1050);
+        }
+        let big = largest(&sol);
+        assert_eq!(
+            (big.x + big.rect.length, big.y + big.rect.width),
+            (2100, 1050)
         );
-        let sol_free = solver_no_grain.solve();
-        assert_solution_valid(&sol_free, 1);
-        // Piece 100x200 rotated → 200x100, fits in 200x100 stock
-        assert!(sol_free.sheets[0].placements[0].rotated);
+    }
 
-        // With grain=Length + stock=AlongLength → NoRotate: 100x200 doesn't fit (width 200 > stock width 100)
-        // This would panic at "piece larger than stock" — so use grain=Width to force rotate
-        let solver_grain = Solver::new(
-            stock,
-            0,
-            CutDirection::Auto,
-            StockGrain::AlongLength,
-            vec![Demand {
-                rect: Rect::new(100, 200),
-                qty: 1,
-                allow_rotate: true,
-                grain: PieceGrain::Width,
-            }],
+    #[test]
+    fn test_deadline() {
+        let solver = || {
+            Solver::new(
+                Rect::new(2440, 1220),
+                3,
+                CutDirection::Auto,
+                StockGrain::None,
+                vec![
+                    Demand::new(Rect::new(800, 600), 5),
+                    Demand::new(Rect::new(400, 300), 7),
+                ],
+            )
+        };
+        assert_eq!(
+            solver()
+                .with_deadline(Some(Instant::now()))
+                .try_solve()
+                .unwrap_err(),
+            SolveError::DeadlineExceeded
         );
-        let sol_grain = solver_grain.solve();
-        assert_solution_valid(&sol_grain, 1);
-        // ForceRotate: 100x200 rotated → 200x100 fits
-        assert!(sol_grain.sheets[0].placements[0].rotated);
-    }
 
-    // ── Cut direction rotation constraint tests ──────────────────
+        let far = Instant::now() + std::time::Duration::from_secs(3600);
+        let full = solver().with_deadline(Some(far)).try_solve().unwrap();
+        let greedy = solver().with_branch_and_bound(false).try_solve().unwrap();
+        assert_solution_valid(&full, 12);
+        assert_solution_valid(&greedy, 12);
+        assert!(full.sheet_count() <= greedy.sheet_count());
+        assert!(solver().lower_bound_sheets() <= full.sheet_count());
+        assert_eq!(solver().lower_bound_sheets(), 2);
+    }
 
     #[test]
-    fn test_cut_direction_along_length_forces_orientation() {
-        // AlongLength: pieces should be oriented with length >= width.
-        // Piece 30x50 (length < width) → ForceRotate → placed as 50x30.
-        let stock = Rect::new(100, 100);
+    fn test_exact_tiling() {
+        // With a 3 mm kerf, 50x50 pieces tile a 103x103 sheet as a 2x2 grid
+        let stock = Rect::new(103, 103);
         let solver = Solver::new(
             stock,
-            0,
-            CutDirection::AlongLength,
+            3,
+            CutDirection::Auto,
             StockGrain::None,
-            vec![Demand {
-                rect: Rect::new(30, 50),
-                qty: 1,
-                allow_rotate: true,
-                grain: PieceGrain::Auto,
-            }],
+            vec![Demand::new(Rect::new(50, 50), 9)],
         );
+        let pieces = solver.expand_demands(&solver.demands);
+        assert!(solver.exact_tiling(&pieces, None).is_some());
+
         let sol = solver.solve();
-        assert_solution_valid(&sol, 1);
-        let p = &sol.sheets[0].placements[0];
-        // After forced rotation: placed length=50, width=30
-        assert!(
-            p.rect.length >= p.rect.width,
-            "AlongLength: placed piece should have length >= width, got {}x{}",
-            p.rect.length,
-            p.rect.width
+        assert_solution_valid(&sol, 9);
+        assert_eq!(sol.sheet_count(), 3);
+        let positions: Vec<(u32, u32)> = sol.sheets[0]
+            .placements
+            .iter()
+            .map(|p| (p.x, p.y))
+            .collect();
+        assert_eq!(positions, [(0, 0), (53, 0), (0, 53), (53, 53)]);
+        // Only the kerfs are lost on a full sheet
+        assert_eq!(sol.sheets[0].waste_area, 103 * 103 - 4 * 50 * 50);
+        let last = &sol.sheets[2];
+        assert_eq!(last.placements.len(), 1);
+        let offcuts: Vec<(u32, u32, Rect)> =
+            last.offcuts.iter().map(|o| (o.x, o.y, o.rect)).collect();
+        assert_eq!(
+            offcuts,
+            [(53, 0, Rect::new(50, 50)), (0, 53, Rect::new(103, 50))]
         );
-    }
 
-    #[test]
-    fn test_cut_direction_along_width_forces_orientation() {
-        // AlongWidth: pieces should be oriented with width >= length.
-        // Piece 50x30 (width < length) → ForceRotate → placed as 30x50.
-        let stock = Rect::new(100, 100);
+        // Many pieces are laid out without any search
+        let sol = Solver::new(
+            Rect::new(100, 60),
+            0,
+            CutDirection::Auto,
+            StockGrain::None,
+            vec![Demand::new(Rect::new(20, 10), 3000)],
+        )
+        .solve();
+        assert_eq!(sol.sheet_count(), 100);
+        assert_eq!(sol.total_waste_percent(), 0.0);
+
+        // 60 doesn't divide 100 either way: left to the general solver
         let solver = Solver::new(
-            stock,
+            Rect::new(100, 100),
             0,
-            CutDirection::AlongWidth,
+            CutDirection::Auto,
             StockGrain::None,
-            vec![Demand {
-                rect: Rect::new(50, 30),
-                qty: 1,
-                allow_rotate: true,
-                grain: PieceGrain::Auto,
-            }],
+            vec![Demand::new(Rect::new(60, 30), 2)],
+        );
+        let pieces = solver.expand_demands(&solver.demands);
+        assert!(solver.exact_tiling(&pieces, None).is_none());
+    }
+
+    #[test]
+    fn test_mixed_cut_direction() {
+        let demands = vec![
+            Demand::new(Rect::new(85, 14), 1),
+            Demand::new(Rect::new(11, 49), 1).with_allow_rotate(false),
+            Demand::new(Rect::new(66, 43), 2),
+        ];
+        // Greedy only: branch and bound already picks a direction per sheet
+        let solve = |direction| {
+            Solver::new(
+                Rect::new(100, 60),
+                0,
+                direction,
+                StockGrain::None,
+                demands.clone(),
+            )
+            .with_branch_and_bound(false)
+            .solve()
+        };
+        let auto = solve(CutDirection::Auto);
+        let mixed = solve(CutDirection::Mixed);
+        assert_solution_valid(&mixed, 4);
+        assert_eq!(auto.sheet_count(), 3);
+        assert_eq!(mixed.sheet_count(), 2);
+        let directions: Vec<CutDirection> = mixed.sheets.iter().map(|s| s.cut_direction).collect();
+        assert_eq!(
+            directions,
+            [CutDirection::AlongLength, CutDirection::AlongWidth]
         );
-        let sol = solver.solve();
-        assert_solution_valid(&sol, 1);
-        let p = &sol.sheets[0].placements[0];
-        // After forced rotation: placed width=50, length=30
         assert!(
-            p.rect.width >= p.rect.length,
-            "AlongWidth: placed piece should have width >= length, got {}x{}",
-            p.rect.length,
-            p.rect.width
+            auto.sheets
+                .iter()
+                .all(|s| s.cut_direction == auto.sheets[0].cut_direction)
         );
     }
 
     #[test]
-    fn test_cut_direction_grain_takes_priority() {
-        // Grain constraint (NoRotate) should override cut_direction.
-        // Piece 30x50 with grain=Length, stock=AlongLength → NoRotate (natural alignment)
-        // Even though AlongLength would want ForceRotate for this piece shape.
-        let stock = Rect::new(100, 100);
-        let solver = Solver::new(
-            stock,
-            0,
-            CutDirection::AlongLength,
-            StockGrain::AlongLength,
-            vec![Demand {
-                rect: Rect::new(30, 50),
-                qty: 1,
-                allow_rotate: true,
-                grain: PieceGrain::Length,
-            }],
-        );
-        let sol = solver.solve();
-        assert_solution_valid(&sol, 1);
-        // Grain=Length + Stock=AlongLength → NoRotate, so piece stays 30x50
-        assert!(!sol.sheets[0].placements[0].rotated);
+    fn test_fillers() {
+        let stock = Rect::new(1000, 500);
+        let demands = vec![
+            Demand::new(Rect::new(600, 500), 1),
+            Demand::new(Rect::new(300, 200), 3),
+        ];
+        let solver = || {
+            Solver::new(
+                stock,
+                3,
+                CutDirection::Auto,
+                StockGrain::None,
+                demands.clone(),
+            )
+        };
+        let plain = solver().solve();
+        let filled = solver()
+            .with_fillers(vec![Rect::new(50, 20), Rect::new(100, 80)])
+            .solve();
+        assert!(filled.validate().is_ok());
+        assert_eq!(filled.sheet_count(), plain.sheet_count());
+
+        for (before, after) in plain.sheets.iter().zip(&filled.sheets) {
+            // Demand pieces stay exactly where they were
+            let (fillers, pieces): (Vec<&Placement>, Vec<&Placement>) =
+                after.placements.iter().partition(|p| p.is_filler);
+            let key = |p: &&Placement| (p.x, p.y, p.rect);
+            assert_eq!(
+                pieces.iter().map(key).collect::<Vec<_>>(),
+                before
+                    .placements
+                    .iter()
+                    .map(|p| key(&p))
+                    .collect::<Vec<_>>()
+            );
+            // Every filler sits inside one of the offcuts left before
+            for f in &fillers {
+                assert!(before.offcuts.iter().any(|o| {
+                    f.x >= o.x
+                        && f.y >= o.y
+                        && f.x + f.rect.length <= o.x + o.rect.length
+                        && f.y + f.rect.width <= o.y + o.rect.width
+                }));
+            }
+            let filler_area: u64 = fillers.iter().map(|f| f.rect.area()).sum();
+            assert_eq!(after.waste_area, before.waste_area - filler_area);
+        }
+        let fillers = filled.sheets[0].placements.iter().filter(|p| p.is_filler);
+        assert!(fillers.count() > 0);
     }
 
     #[test]
-    fn test_cut_direction_along_length_all_pieces_oriented() {
-        // Multiple non-square pieces with AlongLength: all should be placed length >= width
-        let stock = Rect::new(2440, 1220);
+    fn test_bill_of_materials_matches_demands() {
         let demands = vec![
-            Demand {
-                rect: Rect::new(800, 600),
-                qty: 3,
-                allow_rotate: true,
-                grain: PieceGrain::Auto,
-            },
-            Demand {
-                rect: Rect::new(300, 500),
-                qty: 4,
-                allow_rotate: true,
-                grain: PieceGrain::Auto,
-            },
+            Demand::new(Rect::new(600, 400), 3).with_label("door"),
+            Demand::new(Rect::new(600, 400), 2),
+            Demand::new(Rect::new(300, 200), 5).with_label("shelf"),
         ];
+        let solution = Solver::new(
+            Rect::new(1000, 1000),
+            3,
+            CutDirection::Auto,
+            StockGrain::None,
+            demands.clone(),
+        )
+        .solve();
+        assert_solution_valid(&solution, 10);
+
+        let mut bom = solution.bill_of_materials();
+        bom.sort_by_key(|row| (row.size.area(), row.label.clone()));
+        let rows: Vec<_> = bom
+            .iter()
+            .map(|row| (row.size, row.label.as_deref(), row.count, row.total_area))
+            .collect();
+        assert_eq!(
+            rows,
+            vec![
+                (Rect::new(300, 200), Some("shelf"), 5, 300_000),
+                (Rect::new(600, 400), None, 2, 480_000),
+                (Rect::new(600, 400), Some("door"), 3, 720_000),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_optional_overflow_fills_waste_only() {
+        let solve = |overflow: u32| {
+            Solver::new(
+                Rect::new(1000, 1000),
+                0,
+                CutDirection::Auto,
+                StockGrain::None,
+                vec![
+                    Demand::new(Rect::new(1000, 600), 1),
+                    Demand::new(Rect::new(1000, 200), 1)
+                        .with_label("rail")
+                        .with_optional_overflow(overflow),
+                ],
+            )
+            .solve()
+        };
+        let rails = |sol: &Solution| {
+            sol.sheets
+                .iter()
+                .flat_map(|s| &s.placements)
+                .filter(|p| p.label.as_deref() == Some("rail"))
+                .count()
+        };
+
+        let plain = solve(0);
+        assert_eq!((plain.sheet_count(), rails(&plain)), (1, 1));
+        // The 1000x200 left over takes one more rail; the other four would
+        // need a new sheet and are dropped
+        let filled = solve(5);
+        assert_eq!((filled.sheet_count(), rails(&filled)), (1, 2));
+        assert!(filled.validate().is_ok());
+        assert_eq!(filled.sheets[0].waste_area, 0);
+    }
+
+    #[test]
+    fn test_strip_row_ends_best_fit() {
+        // Two rows of 600x40; in width order the 150x38 would take the first
+        // row end and push a 400x30 onto a second sheet
         let solver = Solver::new(
-            stock,
+            Rect::new(1000, 80),
             0,
             CutDirection::AlongLength,
             StockGrain::None,
-            demands,
+            vec![
+                Demand::new(Rect::new(600, 40), 2),
+                Demand::new(Rect::new(150, 38), 1),
+                Demand::new(Rect::new(400, 30), 2),
+            ],
         );
-        let sol = solver.solve();
-        assert_solution_valid(&sol, 7);
-        for sheet in &sol.sheets {
-            for p in &sheet.placements {
-                assert!(
-                    p.rect.length >= p.rect.width,
-                    "AlongLength: all placed pieces should have length >= width, got {}x{}",
-                    p.rect.length,
-                    p.rect.width
-                );
-            }
-        }
+        let pieces = solver.expand_demands(&solver.demands);
+        let solution = solver.greedy_solve(
+            &pieces,
+            ScoreStrategy::BestAreaFit,
+            CutDirection::AlongLength,
+            Some(50),
+            None,
+        );
+        assert_solution_valid(&solution, 5);
+        let sizes = |sheet: &SheetResult| {
+            let mut sizes: Vec<Rect> = sheet.placements.iter().map(|p| p.rect).collect();
+            sizes.sort_by_key(|r| (r.length, r.width));
+            sizes
+        };
+        assert_eq!(
+            sizes(&solution.sheets[0]),
+            vec![
+                Rect::new(400, 30),
+                Rect::new(400, 30),
+                Rect::new(600, 40),
+                Rect::new(600, 40)
+            ]
+        );
+        assert_eq!(sizes(&solution.sheets[1]), vec![Rect::new(150, 38)]);
     }
 
     #[test]
-    fn test_cut_direction_along_width_all_pieces_oriented() {
-        // Multiple non-square pieces with AlongWidth: all should be placed width >= length
-        let stock = Rect::new(2440, 1220);
+    fn test_bb_runs_on_low_variety() {
+        // 24 pieces, more than a flat piece limit would let through, but
+        // only two sizes: the search is small and beats greedy
         let demands = vec![
-            Demand {
-                rect: Rect::new(800, 600),
-                qty: 3,
-                allow_rotate: true,
-                grain: PieceGrain::Auto,
-            },
-            Demand {
-                rect: Rect::new(300, 500),
-                qty: 4,
-                allow_rotate: true,
-                grain: PieceGrain::Auto,
-            },
+            Demand::new(Rect::new(56, 23), 10),
+            Demand::new(Rect::new(47, 41), 14),
         ];
-        let solver = Solver::new(
-            stock,
-            0,
-            CutDirection::AlongWidth,
+        let solver = || {
+            Solver::new(
+                Rect::new(100, 100),
+                0,
+                CutDirection::Auto,
+                StockGrain::None,
+                demands.clone(),
+            )
+        };
+        let pieces = solver().expand_demands(&demands);
+        assert!(solver().bb_search_size(&pieces) <= BB_SEARCH_BUDGET);
+
+        let greedy = solver().with_branch_and_bound(false).solve();
+        let solution = solver().solve();
+        assert_solution_valid(&solution, 24);
+        assert_eq!((greedy.sheet_count(), solution.sheet_count()), (6, 5));
+
+        // As many different sizes are too many to search
+        let varied: Vec<Demand> = (0..24)
+            .map(|i| Demand::new(Rect::new(20 + i, 30 + 2 * i), 1))
+            .collect();
+        let pieces = solver().expand_demands(&varied);
+        assert!(solver().bb_search_size(&pieces) > BB_SEARCH_BUDGET);
+    }
+
+    #[test]
+    fn test_offcuts_are_free_regions() {
+        let demands = vec![
+            Demand::new(Rect::new(700, 400), 3),
+            Demand::new(Rect::new(450, 300), 5),
+            Demand::new(Rect::new(200, 150), 7),
+        ];
+        let solution = Solver::new(
+            Rect::new(2000, 1000),
+            4,
+            CutDirection::Auto,
             StockGrain::None,
             demands,
-        );
-        let sol = solver.solve();
-        assert_solution_valid(&sol, 7);
-        for sheet in &sol.sheets {
-            for p in &sheet.placements {
+        )
+        .solve();
+        assert_solution_valid(&solution, 15);
+
+        let disjoint = |(ax, ay, a): (u32, u32, Rect), (bx, by, b): (u32, u32, Rect)| {
+            ax + a.length <= bx || bx + b.length <= ax || ay + a.width <= by || by + b.width <= ay
+        };
+        for sheet in &solution.sheets {
+            assert!(!sheet.offcuts.is_empty());
+            for (i, o) in sheet.offcuts.iter().enumerate() {
                 assert!(
-                    p.rect.width >= p.rect.length,
-                    "AlongWidth: all placed pieces should have width >= length, got {}x{}",
-                    p.rect.length,
-                    p.rect.width
+                    o.x + o.rect.length <= sheet.stock.length,
+                    "{o:?} outside stock"
                 );
+                assert!(
+                    o.y + o.rect.width <= sheet.stock.width,
+                    "{o:?} outside stock"
+                );
+                for other in &sheet.offcuts[i + 1..] {
+                    assert!(
+                        disjoint((o.x, o.y, o.rect), (other.x, other.y, other.rect)),
+                        "{o:?} overlaps {other:?}"
+                    );
+                }
+                for p in &sheet.placements {
+                    assert!(
+                        disjoint((o.x, o.y, o.rect), (p.x, p.y, p.rect)),
+                        "{o:?} overlaps {p:?}"
+                    );
+                }
             }
         }
     }
 
     #[test]
-    fn test_grain_mixed_pieces() {
-        // Mix of grain-constrained and auto pieces
-        let stock = Rect::new(2440, 1220);
+    fn test_direction_tie_break_lower_waste() {
         let demands = vec![
-            Demand {
-                rect: Rect::new(800, 600),
-                qty: 3,
-                allow_rotate: true,
-                grain: PieceGrain::Length, // must align length with stock grain
-            },
-            Demand {
-                rect: Rect::new(400, 300),
-                qty: 4,
-                allow_rotate: true,
-                grain: PieceGrain::Auto, // free rotation
-            },
-            Demand {
-                rect: Rect::new(600, 400),
-                qty: 2,
-                allow_rotate: true,
-                grain: PieceGrain::Width, // must align width with stock grain
-            },
+            Demand::new(Rect::new(141, 365), 2),
+            Demand::new(Rect::new(362, 334), 3),
         ];
-        let total_pieces: u32 = demands.iter().map(|d| d.qty).sum();
+        let solver = |tie_break| {
+            Solver::new(
+                Rect::new(1000, 600),
+                0,
+                CutDirection::Auto,
+                StockGrain::None,
+                demands.clone(),
+            )
+            .with_direction_tie_break(tie_break)
+        };
+        let waste =
+            |sol: &Solution| solver(None).tie_break_rank(DirectionTieBreak::PreferLowerWaste, sol);
 
-        let sol = Solver::new(
-            stock,
-            0,
+        // Both directions need two sheets; by default Auto keeps the
+        // along-length layout
+        let plain = solver(None).solve();
+        let lower_waste = solver(Some(DirectionTieBreak::PreferLowerWaste)).solve();
+        assert_solution_valid(&lower_waste, 5);
+        assert_eq!(plain.sheet_count(), 2);
+        assert_eq!(lower_waste.sheet_count(), 2);
+        assert!(
+            plain
+                .sheets
+                .iter()
+                .all(|s| s.cut_direction == CutDirection::AlongLength)
+        );
+        assert!(
+            lower_waste
+                .sheets
+                .iter()
+                .all(|s| s.cut_direction == CutDirection::AlongWidth)
+        );
+        assert!(waste(&lower_waste) < waste(&plain));
+    }
+
+    #[test]
+    fn test_diagnose() {
+        let stock = Rect::new(1000, 500);
+        let solver = |demands: Vec<Demand>| {
+            Solver::new(
+                stock,
+                0,
+                CutDirection::Auto,
+                StockGrain::AlongLength,
+                demands,
+            )
+        };
+
+        let oversize = solver(vec![
+            Demand::new(Rect::new(300, 200), 2),
+            Demand::new(Rect::new(1200, 300), 1),
+        ]);
+        assert_eq!(
+            oversize.diagnose(),
+            vec![Infeasibility::Oversize {
+                demand: 1,
+                rect: Rect::new(1200, 300),
+            }]
+        );
+
+        // Grain along its 800 mm side: only fits across the sheet's grain
+        let locked = solver(vec![
+            Demand::new(Rect::new(800, 400), 1).with_grain(PieceGrain::Width),
+            Demand::new(Rect::new(800, 400), 1).with_grain(PieceGrain::Length),
+        ]);
+        let diagnosis = locked.diagnose();
+        assert_eq!(
+            diagnosis,
+            vec![Infeasibility::GrainLocked {
+                demand: 0,
+                rect: Rect::new(800, 400),
+            }]
+        );
+        assert_eq!(
+            diagnosis[0].to_string(),
+            "demand 0 (800x400): fits only in an orientation its grain or rotation settings forbid"
+        );
+        let relaxed = locked.with_relax_grain_on_infeasible(true);
+        assert_eq!(relaxed.diagnose(), vec![]);
+
+        // One sheet holds the valued piece, then only one of the others
+        let values = HashMap::from([("door".to_string(), 10.0)]);
+        let capped = solver(vec![
+            Demand::new(Rect::new(400, 400), 2),
+            Demand::new(Rect::new(500, 500), 1).with_label("door"),
+        ])
+        .with_piece_values(values);
+        assert_eq!(capped.diagnose(), vec![]);
+        let capped = capped.with_max_sheets(Some(1));
+        assert_eq!(
+            capped.diagnose(),
+            vec![Infeasibility::ExcludedByCap {
+                demand: 0,
+                rect: Rect::new(400, 400),
+                sheets: 1,
+            }]
+        );
+        let solution = capped.solve();
+        assert_eq!(solution.unplaced[0].rect, Rect::new(400, 400));
+    }
+
+    #[test]
+    fn test_reserve_offcut_piece_too_large_beside_drop() {
+        // 146x60 fits the sheet, but not beside a 41x60 drop in either
+        // orientation: those sheets are opened without the drop
+        for dir in [CutDirection::Auto, CutDirection::Mixed] {
+            let solution = Solver::new(
+                Rect::new(174, 68),
+                0,
+                dir,
+                StockGrain::None,
+                vec![Demand::new(Rect::new(146, 60), 3)],
+            )
+            .with_reserve_offcut(Some(Rect::new(41, 60)))
+            .solve();
+            assert_solution_valid(&solution, 3);
+            assert_eq!(solution.sheet_count(), 3);
+            assert!(solution.sheets.iter().all(|s| s.reserved.is_none()));
+        }
+    }
+
+    #[test]
+    fn test_corner_radius_keeps_kerf() {
+        let solution = Solver::new(
+            Rect::new(100, 100),
+            3,
             CutDirection::Auto,
-            StockGrain::AlongLength,
-            demands,
+            StockGrain::None,
+            vec![
+                Demand::new(Rect::new(80, 10), 1).with_allow_rotate(false),
+                Demand::new(Rect::new(100, 70), 1).with_allow_rotate(false),
+            ],
         )
+        .with_corner_radius(10)
         .solve();
-        assert_solution_valid(&sol, total_pieces as usize);
+        assert_solution_valid(&solution, 2);
+        for sheet in &solution.sheets {
+            for (i, p) in sheet.placements.iter().enumerate() {
+                for q in &sheet.placements[i + 1..] {
+                    assert!(!p.overlaps(q, 3), "{p:?} within a kerf of {q:?}");
+                }
+            }
+        }
+    }
 
-        // Verify grain constraints on placements
-        for sheet in &sol.sheets {
-            for p in &sheet.placements {
-                // All pieces should fit within stock
-                assert!(p.x + p.rect.length <= stock.length);
-                assert!(p.y + p.rect.width <= stock.width);
+    #[test]
+    fn test_defects_keep_kerf() {
+        let kerf = 3;
+        let defect = Placement {
+            rect: Rect::new(1, 9),
+            x: 145,
+            y: 13,
+            rotated: false,
+            requested: Rect::new(1, 9),
+            orientation_deg: 0,
+            label: None,
+            is_filler: false,
+            directional: false,
+            group_hint: None,
+        };
+        let solution = Solver::new(
+            Rect::new(183, 85),
+            kerf,
+            CutDirection::AlongLength,
+            StockGrain::None,
+            vec![Demand::new(Rect::new(92, 8), 3)],
+        )
+        .with_defects(vec![(defect.x, defect.y, defect.rect)])
+        .solve();
+        assert_solution_valid(&solution, 3);
+        for sheet in &solution.sheets {
+            for (i, p) in sheet.placements.iter().enumerate() {
+                assert!(
+                    !p.overlaps(&defect, kerf),
+                    "{p:?} within a kerf of the defect"
+                );
+                for q in &sheet.placements[i + 1..] {
+                    assert!(!p.overlaps(q, kerf), "{p:?} within a kerf of {q:?}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_first_cut_kerf_past_edge() {
+        let solver = |first_cut: u32, anchor| {
+            Solver::new(
+                Rect::new(85, 98),
+                2,
+                CutDirection::Auto,
+                StockGrain::None,
+                vec![Demand::new(Rect::new(28, 85), 1)],
+            )
+            .with_first_cut(Some((Axis::X, first_cut)))
+            .with_anchor(anchor)
+        };
+        // Mirrored, the kerf of a cut at 84 would start before x = 0
+        assert_eq!(
+            solver(84, Some(Anchor::BottomRight))
+                .try_solve()
+                .unwrap_err(),
+            SolveError::CutOutsideStock {
+                axis: Axis::X,
+                position: 84,
             }
+        );
+        assert!(solver(84, None).try_solve().is_err());
+
+        // At 83 the kerf ends on the edge: mirrored, pieces stay right of it
+        let solution = solver(83, Some(Anchor::BottomRight)).try_solve().unwrap();
+        assert_solution_valid(&solution, 1);
+        for p in solution.sheets.iter().flat_map(|s| &s.placements) {
+            assert!(p.x >= 2, "{p:?} straddles the first cut");
         }
     }
 }
@@ -1,4 +1,5 @@
 use serde::{Deserialize, Deserializer, Serialize};
+use std::collections::HashMap;
 
 pub fn deserialize_u32_from_number<'de, D: Deserializer<'de>>(
     deserializer: D,
@@ -24,7 +25,7 @@ pub fn deserialize_u32_from_number<'de, D: Deserializer<'de>>(
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct Rect {
     #[serde(deserialize_with = "deserialize_u32_from_number")]
     pub length: u32,
@@ -48,21 +49,66 @@ impl Rect {
         }
     }
 
+    /// Size reserved for this rect with `clearance` (`[left, right, top, bottom]`) around it.
+    pub fn with_clearance(&self, clearance: [u32; 4]) -> Self {
+        let [left, right, top, bottom] = clearance;
+        Rect::new(
+            self.length.saturating_add(left).saturating_add(right),
+            self.width.saturating_add(top).saturating_add(bottom),
+        )
+    }
+
     pub fn fits_in(&self, other: &Rect) -> bool {
         self.length <= other.length && self.width <= other.width
     }
+
+    /// Both sides multiplied by `num / den`, rounded to the nearest unit
+    /// (halves up) and capped at `u32::MAX`. Panics if `den` is zero.
+    pub fn scale(&self, num: u32, den: u32) -> Rect {
+        Rect::new(scale(self.length, num, den), scale(self.width, num, den))
+    }
 }
 
-#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+/// `value * num / den`, rounded to nearest, halves up.
+pub(crate) fn scale(value: u32, num: u32, den: u32) -> u32 {
+    let den = den as u64;
+    let scaled = (value as u64 * num as u64 + den / 2) / den;
+    scaled.min(u32::MAX as u64) as u32
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum CutDirection {
     #[default]
     Auto,
     AlongLength,
     AlongWidth,
+    /// Like `Auto`, but each sheet picks its own direction instead of the
+    /// whole solution sharing one.
+    Mixed,
+}
+
+impl CutDirection {
+    pub const ALL: [Self; 4] = [Self::Auto, Self::AlongLength, Self::AlongWidth, Self::Mixed];
+}
+
+/// How `Auto` picks between layouts that use the same number of sheets,
+/// e.g. one cut along the length and one along the width.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DirectionTieBreak {
+    /// Fewest cuts in the sheets' cut plans.
+    PreferFewerCuts,
+    /// Most sheets cut `AlongLength`.
+    PreferLength,
+    /// Most sheets cut `AlongWidth`.
+    PreferWidth,
+    /// Least waste within the area the pieces span on each sheet, leaving
+    /// the rest as one clean drop.
+    PreferLowerWaste,
 }
 
-#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum StockGrain {
     #[default]
@@ -71,7 +117,11 @@ pub enum StockGrain {
     AlongWidth,
 }
 
-#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+impl StockGrain {
+    pub const ALL: [Self; 3] = [Self::None, Self::AlongLength, Self::AlongWidth];
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum PieceGrain {
     #[default]
@@ -80,6 +130,42 @@ pub enum PieceGrain {
     Width,
 }
 
+impl PieceGrain {
+    pub const ALL: [Self; 3] = [Self::Auto, Self::Length, Self::Width];
+}
+
+/// Sheet corner the layout is pulled toward, e.g. to register big pieces
+/// against the fence. Named as the layout is drawn, with (0, 0) at the
+/// top-left corner.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Anchor {
+    BottomLeft,
+    BottomRight,
+    TopLeft,
+    TopRight,
+}
+
+impl Anchor {
+    pub const ALL: [Self; 4] = [
+        Self::BottomLeft,
+        Self::BottomRight,
+        Self::TopLeft,
+        Self::TopRight,
+    ];
+}
+
+/// Axis a guillotine cut is made across.
+///
+/// - `X`: the cut is a line at a fixed x position (crosses the length axis).
+/// - `Y`: the cut is a line at a fixed y position (crosses the width axis).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Axis {
+    X,
+    Y,
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum RotationConstraint {
     Free,
@@ -87,21 +173,60 @@ pub enum RotationConstraint {
     ForceRotate,
 }
 
+/// A piece whose grain can only be matched by rotating it, while rotation
+/// is disabled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GrainConflict {
+    pub stock_grain: StockGrain,
+    pub piece_grain: PieceGrain,
+}
+
+impl std::fmt::Display for GrainConflict {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "piece grain {:?} needs a 90° rotation on {:?} stock, but rotation is disabled",
+            self.piece_grain, self.stock_grain
+        )
+    }
+}
+
+impl std::error::Error for GrainConflict {}
+
 impl RotationConstraint {
+    /// Rotation allowed for a piece. Grain takes precedence over
+    /// `allow_rotate = false` when it keeps the piece unrotated; when the
+    /// grain can only be matched by rotating, the two conflict and this
+    /// returns an error rather than silently ignoring either.
     pub fn from_grain(
         stock_grain: StockGrain,
         piece_grain: PieceGrain,
         allow_rotate: bool,
-    ) -> Self {
-        if !allow_rotate {
-            return Self::NoRotate;
-        }
-        match (stock_grain, piece_grain) {
+    ) -> Result<Self, GrainConflict> {
+        let constraint = match (stock_grain, piece_grain) {
             (StockGrain::None, _) | (_, PieceGrain::Auto) => Self::Free,
             (StockGrain::AlongLength, PieceGrain::Length)
             | (StockGrain::AlongWidth, PieceGrain::Width) => Self::NoRotate,
             (StockGrain::AlongLength, PieceGrain::Width)
             | (StockGrain::AlongWidth, PieceGrain::Length) => Self::ForceRotate,
+        };
+        match (constraint, allow_rotate) {
+            (_, true) => Ok(constraint),
+            (Self::ForceRotate, false) => Err(GrainConflict {
+                stock_grain,
+                piece_grain,
+            }),
+            (_, false) => Ok(Self::NoRotate),
+        }
+    }
+
+    /// What satisfies both constraints, `None` if one forbids the rotation
+    /// the other forces.
+    pub fn intersect(self, other: Self) -> Option<Self> {
+        match (self, other) {
+            (Self::Free, c) | (c, Self::Free) => Some(c),
+            (a, b) if a == b => Some(a),
+            _ => None,
         }
     }
 
@@ -115,7 +240,7 @@ impl RotationConstraint {
             return self;
         }
         match cut_direction {
-            CutDirection::Auto => Self::Free,
+            CutDirection::Auto | CutDirection::Mixed => Self::Free,
             CutDirection::AlongLength => {
                 if piece.length > piece.width {
                     Self::NoRotate
@@ -144,55 +269,712 @@ impl std::fmt::Display for Rect {
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct Demand {
     pub rect: Rect,
     pub qty: u32,
     pub allow_rotate: bool,
     #[serde(default)]
     pub grain: PieceGrain,
+    /// Pieces of different materials are never placed on the same sheet.
+    #[serde(default)]
+    pub material: Option<String>,
+    /// Extra gap kept free on each side of the piece, as
+    /// `[left, right, top, bottom]` (left/top being the low x/y sides,
+    /// before rotation). Counted on top of the kerf.
+    #[serde(default)]
+    pub clearance: [u32; 4],
+    /// Free-form identifier (part number, ...) copied to each placement.
+    #[serde(default)]
+    pub label: Option<String>,
+    /// Grain wanted on the back face, against the stock's back grain (see
+    /// `Solver::with_back_grain`).
+    #[serde(default)]
+    pub back_grain: PieceGrain,
+    /// Printed or brushed face whose top edge must point up: the piece is
+    /// never rotated, whatever `allow_rotate` and the cut direction say.
+    #[serde(default)]
+    pub directional: bool,
+    /// Machine setup the piece is cut at: pieces sharing a hint are kept
+    /// on the same sheets where that costs nothing, unlike `material`
+    /// which never mixes.
+    #[serde(default)]
+    pub group_hint: Option<u32>,
+    /// Pieces joined into one panel (e.g. book-matched): every demand of a
+    /// group is cut in the same orientation, all rotated or none.
+    #[serde(default)]
+    pub grain_group: Option<u32>,
+    /// Thin or brittle piece: placed where its edges border other pieces
+    /// or the sheet edge rather than large offcuts, when there's a choice.
+    #[serde(default)]
+    pub fragile: bool,
+    /// Extra pieces cut only out of the waste of sheets already used, up
+    /// to this many; they never open a sheet.
+    #[serde(default)]
+    pub optional_overflow: u32,
+}
+
+impl Default for Demand {
+    fn default() -> Self {
+        Self {
+            rect: Rect::default(),
+            qty: 1,
+            allow_rotate: true,
+            grain: PieceGrain::Auto,
+            material: None,
+            clearance: [0; 4],
+            label: None,
+            back_grain: PieceGrain::Auto,
+            directional: false,
+            group_hint: None,
+            grain_group: None,
+            fragile: false,
+            optional_overflow: 0,
+        }
+    }
+}
+
+impl Demand {
+    /// A demand for `qty` pieces of `rect`, rotation allowed, no grain constraint.
+    pub fn new(rect: Rect, qty: u32) -> Self {
+        Self {
+            rect,
+            qty,
+            ..Self::default()
+        }
+    }
+
+    pub fn with_allow_rotate(mut self, allow_rotate: bool) -> Self {
+        self.allow_rotate = allow_rotate;
+        self
+    }
+
+    pub fn with_grain(mut self, grain: PieceGrain) -> Self {
+        self.grain = grain;
+        self
+    }
+
+    pub fn with_material(mut self, material: impl Into<String>) -> Self {
+        self.material = Some(material.into());
+        self
+    }
+
+    pub fn with_clearance(mut self, clearance: [u32; 4]) -> Self {
+        self.clearance = clearance;
+        self
+    }
+
+    pub fn with_label(mut self, label: impl Into<String>) -> Self {
+        self.label = Some(label.into());
+        self
+    }
+
+    pub fn with_back_grain(mut self, back_grain: PieceGrain) -> Self {
+        self.back_grain = back_grain;
+        self
+    }
+
+    pub fn with_directional(mut self, directional: bool) -> Self {
+        self.directional = directional;
+        self
+    }
+
+    pub fn with_group_hint(mut self, group_hint: u32) -> Self {
+        self.group_hint = Some(group_hint);
+        self
+    }
+
+    pub fn with_grain_group(mut self, grain_group: u32) -> Self {
+        self.grain_group = Some(grain_group);
+        self
+    }
+
+    pub fn with_fragile(mut self, fragile: bool) -> Self {
+        self.fragile = fragile;
+        self
+    }
+
+    pub fn with_optional_overflow(mut self, optional_overflow: u32) -> Self {
+        self.optional_overflow = optional_overflow;
+        self
+    }
+
+    /// Whether the piece may be turned 90°: `allow_rotate`, unless it is
+    /// [`directional`](Self::directional).
+    pub fn can_rotate(&self) -> bool {
+        self.allow_rotate && !self.directional
+    }
+}
+
+/// Material ordered by total area rather than piece count: strips of
+/// `width`, cut to whatever length fits, until `total_area` is covered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct AreaDemand {
+    pub width: u32,
+    pub total_area: u64,
 }
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Placement {
+    /// Size as laid out on the sheet: `length` along x, `width` along y.
+    /// For a rotated piece this is the demand's size turned 90°; see
+    /// [`original_size`](Self::original_size) for the unturned one.
     pub rect: Rect,
     pub x: u32,
     pub y: u32,
     pub rotated: bool,
+    /// Size as requested by the demand, before any tolerance snapping
+    /// (never rotated).
+    pub requested: Rect,
+    /// Rotation relative to the requested orientation, in degrees.
+    /// Currently 0 or 90, mirroring `rotated`.
+    pub orientation_deg: u32,
+    /// Label of the demand this piece comes from.
+    #[serde(default)]
+    pub label: Option<String>,
+    /// Filler cut from leftover space (see `Solver::with_fillers`) rather
+    /// than a demanded piece.
+    #[serde(default)]
+    pub is_filler: bool,
+    /// From a [`Demand::directional`] piece: its top edge is the low-y side,
+    /// marked with an arrow in layouts.
+    #[serde(default)]
+    pub directional: bool,
+    /// [`Demand::group_hint`] of the piece.
+    #[serde(default)]
+    pub group_hint: Option<u32>,
+}
+
+impl Placement {
+    /// A piece of `rect` at `(x, y)`, as requested: not rotated, labelled,
+    /// grouped or directional, and not a filler.
+    pub fn new(rect: Rect, x: u32, y: u32) -> Self {
+        Self {
+            rect,
+            x,
+            y,
+            rotated: false,
+            requested: rect,
+            orientation_deg: 0,
+            label: None,
+            is_filler: false,
+            directional: false,
+            group_hint: None,
+        }
+    }
+
+    /// Size in the demand's orientation: `rect`, turned back if the piece
+    /// was rotated. Unlike `requested`, it reflects tolerance snapping.
+    pub fn original_size(&self) -> Rect {
+        if self.rotated {
+            self.rect.rotated()
+        } else {
+            self.rect
+        }
+    }
+
+    /// Human-readable orientation, e.g. `"90°"`.
+    pub fn orientation_label(&self) -> &'static str {
+        match self.orientation_deg {
+            0 => "0°",
+            90 => "90°",
+            180 => "180°",
+            270 => "270°",
+            _ => "?",
+        }
+    }
+
+    /// Whether the two placements are closer than `kerf` on both axes, i.e.
+    /// there is no room for a saw cut between them. With a zero kerf this is
+    /// plain overlap: touching edges don't count.
+    pub fn overlaps(&self, other: &Placement, kerf: u32) -> bool {
+        let kerf = kerf as u64;
+        let (ax, ay) = (self.x as u64, self.y as u64);
+        let (bx, by) = (other.x as u64, other.y as u64);
+        let a_x_end = ax + self.rect.length as u64;
+        let a_y_end = ay + self.rect.width as u64;
+        let b_x_end = bx + other.rect.length as u64;
+        let b_y_end = by + other.rect.width as u64;
+        ax < b_x_end + kerf && bx < a_x_end + kerf && ay < b_y_end + kerf && by < a_y_end + kerf
+    }
+
+    /// Whether `(x, y)` lies on the piece (far edges excluded).
+    pub fn contains_point(&self, x: u32, y: u32) -> bool {
+        let (x, y) = (x as u64, y as u64);
+        x >= self.x as u64
+            && y >= self.y as u64
+            && x < self.x as u64 + self.rect.length as u64
+            && y < self.y as u64 + self.rect.width as u64
+    }
+}
+
+/// Unused region left on a sheet after packing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Offcut {
+    pub x: u32,
+    pub y: u32,
+    pub rect: Rect,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SheetResult {
+    /// Stock sheet these placements are cut from.
+    #[serde(default)]
+    pub stock: Rect,
+    /// Whether the sheet is fed turned a quarter, `stock` being the turned
+    /// size; see `Solver::with_allow_stock_rotation`.
+    #[serde(default)]
+    pub stock_rotated: bool,
     pub placements: Vec<Placement>,
     #[allow(dead_code)]
     pub waste_area: u64,
+    /// Material of every piece on this sheet, if the demands specify one.
+    #[serde(default)]
+    pub material: Option<String>,
+    /// Free regions left once the sheet is cut (kerf lines excluded):
+    /// within the stock, disjoint, and clear of every placement, so extra
+    /// parts can be placed by hand in any of them.
+    #[serde(default)]
+    pub offcuts: Vec<Offcut>,
+    /// Drop kept intact for reuse, see `Solver::with_reserve_offcut`.
+    /// Not part of `offcuts`.
+    #[serde(default)]
+    pub reserved: Option<Offcut>,
+    /// Direction the sheet was packed with; `Auto` when its layout doesn't
+    /// depend on one (exact tilings, sheets of strips only).
+    #[serde(default)]
+    pub cut_direction: CutDirection,
+}
+
+/// What makes two sheets identical for [`Solution::identical_sheet_groups`]:
+/// stock, material, and each placement's position, size, rotation, label
+/// and kind, sorted.
+type SheetKey<'a> = (
+    Rect,
+    Option<&'a str>,
+    Vec<(u32, u32, u32, u32, bool, Option<&'a str>, bool)>,
+);
+
+impl SheetResult {
+    fn key(&self) -> SheetKey<'_> {
+        let mut placements: Vec<_> = self
+            .placements
+            .iter()
+            .map(|p| {
+                (
+                    p.x,
+                    p.y,
+                    p.rect.length,
+                    p.rect.width,
+                    p.rotated,
+                    p.label.as_deref(),
+                    p.is_filler,
+                )
+            })
+            .collect();
+        placements.sort_unstable();
+        (self.stock, self.material.as_deref(), placements)
+    }
+}
+
+/// A geometric defect found by [`Solution::validate`].
+/// Indices are zero-based sheet and placement positions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Violation {
+    OutOfBounds {
+        sheet: usize,
+        piece: usize,
+    },
+    Overlap {
+        sheet: usize,
+        first: usize,
+        second: usize,
+    },
+}
+
+impl std::fmt::Display for Violation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match *self {
+            Violation::OutOfBounds { sheet, piece } => {
+                write!(f, "sheet {}: piece {} is out of bounds", sheet, piece)
+            }
+            Violation::Overlap {
+                sheet,
+                first,
+                second,
+            } => write!(
+                f,
+                "sheet {}: pieces {} and {} overlap",
+                sheet, first, second
+            ),
+        }
+    }
+}
+
+/// An advisory finding about a placement or a demand, reported on
+/// [`Solution::warnings`]. Indices are zero-based sheet, placement and
+/// demand positions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Warning {
+    /// The piece ends less than the margin away from a sheet edge, with
+    /// nothing in between: measurement error may leave it short.
+    TightFit {
+        sheet: usize,
+        piece: usize,
+        spare: u32,
+    },
+    /// The demand fits no sheet along its grain, so the grain was ignored
+    /// (see `Solver::with_relax_grain_on_infeasible`).
+    GrainRelaxed { demand: usize, rect: Rect },
+}
+
+impl std::fmt::Display for Warning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match *self {
+            Warning::TightFit {
+                sheet,
+                piece,
+                spare,
+            } => write!(
+                f,
+                "sheet {}: piece {} fits with only {} mm to spare",
+                sheet, piece, spare
+            ),
+            Warning::GrainRelaxed { demand, rect } => write!(
+                f,
+                "demand {}: grain ignored, {} only fits the sheet across it",
+                demand, rect
+            ),
+        }
+    }
+}
+
+impl SheetResult {
+    /// Smallest rectangle enclosing every placement, as `(x, y, size)`.
+    /// An empty sheet gives `(0, 0, 0x0)`.
+    pub fn used_bounds(&self) -> (u32, u32, Rect) {
+        if self.placements.is_empty() {
+            return (0, 0, Rect::default());
+        }
+        let min_x = self.placements.iter().map(|p| p.x).min().unwrap_or(0);
+        let min_y = self.placements.iter().map(|p| p.y).min().unwrap_or(0);
+        let max_x = self
+            .placements
+            .iter()
+            .map(|p| p.x + p.rect.length)
+            .max()
+            .unwrap_or(0);
+        let max_y = self
+            .placements
+            .iter()
+            .map(|p| p.y + p.rect.width)
+            .max()
+            .unwrap_or(0);
+        (min_x, min_y, Rect::new(max_x - min_x, max_y - min_y))
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Solution {
     pub sheets: Vec<SheetResult>,
     pub stock: Rect,
+    /// Advisory findings, see `Solver::with_tight_fit_margin`.
+    #[serde(default)]
+    pub warnings: Vec<Warning>,
+    /// Pieces left out to stay within `Solver::with_max_sheets`.
+    #[serde(default)]
+    pub unplaced: Vec<Unplaced>,
+}
+
+/// Identical pieces left out of a [`Solution`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Unplaced {
+    /// Size as requested.
+    pub rect: Rect,
+    pub label: Option<String>,
+    pub qty: u32,
+}
+
+/// Placed pieces of one size and label, see
+/// [`Solution::bill_of_materials`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct BomRow {
+    /// Size as requested.
+    pub size: Rect,
+    pub label: Option<String>,
+    pub count: u32,
+    /// Area of the pieces in mm², `count` times that of `size`.
+    pub total_area: u64,
 }
 
 impl Solution {
+    /// Total value of the placed pieces, by label. Fillers and pieces
+    /// whose label isn't in `values` count for nothing.
+    pub fn placed_value(&self, values: &HashMap<String, f64>) -> f64 {
+        self.sheets
+            .iter()
+            .flat_map(|s| &s.placements)
+            .filter(|p| !p.is_filler)
+            .filter_map(|p| values.get(p.label.as_ref()?))
+            .sum()
+    }
+
     pub fn sheet_count(&self) -> usize {
         self.sheets.len()
     }
 
     pub fn total_waste_percent(&self) -> f64 {
-        let stock_area = self.stock.area();
-        let total_stock_area = stock_area * self.sheets.len() as u64;
-        let total_used: u64 = self
+        // A single area fits in u64, but sums over many sheets may not
+        let total_stock_area: u128 = self.sheets.iter().map(|s| s.stock.area() as u128).sum();
+        let total_used: u128 = self
             .sheets
             .iter()
             .flat_map(|s| &s.placements)
-            .map(|p| p.rect.area())
+            .map(|p| p.rect.area() as u128)
             .sum();
         if total_stock_area == 0 {
             return 0.0;
         }
         (total_stock_area - total_used) as f64 / total_stock_area as f64 * 100.0
     }
+
+    /// Number of sheets used per stock size, in order of first use.
+    pub fn stock_usage(&self) -> Vec<(Rect, usize)> {
+        let mut usage: Vec<(Rect, usize)> = Vec::new();
+        for sheet in &self.sheets {
+            match usage.iter_mut().find(|(stock, _)| *stock == sheet.stock) {
+                Some((_, count)) => *count += 1,
+                None => usage.push((sheet.stock, 1)),
+            }
+        }
+        usage
+    }
+
+    /// Indices of identical sheets, grouped in order of first appearance.
+    /// Sheets are identical when they have the same stock, material and
+    /// placements, in any order.
+    pub fn identical_sheet_groups(&self) -> Vec<Vec<usize>> {
+        let mut groups: Vec<(SheetKey, Vec<usize>)> = Vec::new();
+        for (i, sheet) in self.sheets.iter().enumerate() {
+            let key = sheet.key();
+            match groups.iter_mut().find(|(k, _)| *k == key) {
+                Some((_, indices)) => indices.push(i),
+                None => groups.push((key, vec![i])),
+            }
+        }
+        groups.into_iter().map(|(_, indices)| indices).collect()
+    }
+
+    /// Each distinct sheet layout with the number of sheets cut to it, see
+    /// [`identical_sheet_groups`](Self::identical_sheet_groups).
+    pub fn group_identical_sheets(&self) -> Vec<(SheetResult, usize)> {
+        self.identical_sheet_groups()
+            .into_iter()
+            .map(|indices| (self.sheets[indices[0]].clone(), indices.len()))
+            .collect()
+    }
+
+    /// Placed pieces across all sheets, one row per requested size and
+    /// label in order of first appearance. Fillers are left out.
+    pub fn bill_of_materials(&self) -> Vec<BomRow> {
+        let mut rows: Vec<BomRow> = Vec::new();
+        for p in self.sheets.iter().flat_map(|s| &s.placements) {
+            if p.is_filler {
+                continue;
+            }
+            match rows
+                .iter_mut()
+                .find(|r| r.size == p.requested && r.label == p.label)
+            {
+                Some(row) => row.count += 1,
+                None => rows.push(BomRow {
+                    size: p.requested,
+                    label: p.label.clone(),
+                    count: 1,
+                    total_area: 0,
+                }),
+            }
+        }
+        for row in &mut rows {
+            row.total_area = row.size.area() * row.count as u64;
+        }
+        rows
+    }
+
+    /// Turn the whole layout a quarter for display, e.g. for viewers that
+    /// want the long axis vertical: x and y, and lengths and widths, are
+    /// swapped for the stock, every placement and every offcut. Pieces flip
+    /// their `rotated` flag to match. The swap is its own inverse, so
+    /// calling this twice gives back the original layout.
+    pub fn rotate_layout_90(&mut self) {
+        self.stock = self.stock.rotated();
+        for sheet in &mut self.sheets {
+            sheet.stock = sheet.stock.rotated();
+            for p in &mut sheet.placements {
+                (p.x, p.y) = (p.y, p.x);
+                p.rect = p.rect.rotated();
+                p.rotated = !p.rotated;
+                p.orientation_deg = if p.rotated { 90 } else { 0 };
+            }
+            for o in sheet.offcuts.iter_mut().chain(&mut sheet.reserved) {
+                (o.x, o.y) = (o.y, o.x);
+                o.rect = o.rect.rotated();
+            }
+            sheet.cut_direction = match sheet.cut_direction {
+                CutDirection::AlongLength => CutDirection::AlongWidth,
+                CutDirection::AlongWidth => CutDirection::AlongLength,
+                dir => dir,
+            };
+        }
+    }
+
+    /// Number of offcuts across all sheets per area bucket. `buckets` are
+    /// increasing upper bounds in mm² (inclusive); the extra last count is
+    /// for offcuts larger than every bound.
+    pub fn offcut_histogram(&self, buckets: &[u64]) -> Vec<usize> {
+        let mut counts = vec![0; buckets.len() + 1];
+        for o in self.sheets.iter().flat_map(|s| &s.offcuts) {
+            counts[buckets.partition_point(|&b| b < o.rect.area())] += 1;
+        }
+        counts
+    }
+
+    /// Checks that every placement lies inside its sheet's stock and that no two
+    /// placements on a sheet overlap.
+    pub fn validate(&self) -> Result<(), Vec<Violation>> {
+        let mut violations = Vec::new();
+        for (si, sheet) in self.sheets.iter().enumerate() {
+            for (pi, p) in sheet.placements.iter().enumerate() {
+                let x_end = p.x as u64 + p.rect.length as u64;
+                let y_end = p.y as u64 + p.rect.width as u64;
+                if x_end > sheet.stock.length as u64 || y_end > sheet.stock.width as u64 {
+                    violations.push(Violation::OutOfBounds {
+                        sheet: si,
+                        piece: pi,
+                    });
+                }
+                for (qi, q) in sheet.placements.iter().enumerate().skip(pi + 1) {
+                    if p.overlaps(q, 0) {
+                        violations.push(Violation::Overlap {
+                            sheet: si,
+                            first: pi,
+                            second: qi,
+                        });
+                    }
+                }
+            }
+        }
+        if violations.is_empty() {
+            Ok(())
+        } else {
+            Err(violations)
+        }
+    }
+
+    /// What changed from `self` to `other`: sheet count and waste deltas
+    /// (`other` minus `self`), and every demanded piece that moved, appeared
+    /// or disappeared. Pieces are matched by label and requested size, those
+    /// left in place first, then the rest in sheet order. Fillers are left
+    /// out.
+    pub fn diff(&self, other: &Solution) -> SolutionDiff {
+        let mut after = other.piece_positions();
+        let mut changes = Vec::new();
+        for ((label, requested), mut before) in self.piece_positions() {
+            let mut moved_to = match after.iter().position(|(k, _)| *k == (label, requested)) {
+                Some(i) => after.remove(i).1,
+                None => vec![],
+            };
+            before.retain(|p| match moved_to.iter().position(|q| q == p) {
+                Some(i) => {
+                    moved_to.remove(i);
+                    false
+                }
+                None => true,
+            });
+            let len = before.len().max(moved_to.len());
+            for i in 0..len {
+                changes.push(PieceChange {
+                    label: label.clone(),
+                    requested,
+                    before: before.get(i).copied(),
+                    after: moved_to.get(i).copied(),
+                });
+            }
+        }
+        for ((label, requested), added) in after {
+            changes.extend(added.into_iter().map(|p| PieceChange {
+                label: label.clone(),
+                requested,
+                before: None,
+                after: Some(p),
+            }));
+        }
+        SolutionDiff {
+            sheet_count_delta: other.sheet_count() as i64 - self.sheet_count() as i64,
+            waste_percent_delta: other.total_waste_percent() - self.total_waste_percent(),
+            changes,
+        }
+    }
+
+    /// Where the demanded pieces are, grouped by label and requested size
+    /// in order of first appearance.
+    fn piece_positions(&self) -> Vec<(PieceKey<'_>, Vec<PiecePosition>)> {
+        let mut by_key: Vec<(PieceKey, Vec<PiecePosition>)> = Vec::new();
+        for (sheet, s) in self.sheets.iter().enumerate() {
+            for p in s.placements.iter().filter(|p| !p.is_filler) {
+                let position = PiecePosition {
+                    sheet,
+                    x: p.x,
+                    y: p.y,
+                    rotated: p.rotated,
+                };
+                let key = (&p.label, p.requested);
+                match by_key.iter_mut().find(|(k, _)| *k == key) {
+                    Some((_, list)) => list.push(position),
+                    None => by_key.push((key, vec![position])),
+                }
+            }
+        }
+        by_key
+    }
+}
+
+/// Label and requested size, what [`Solution::diff`] matches pieces by.
+type PieceKey<'a> = (&'a Option<String>, Rect);
+
+/// Result of [`Solution::diff`].
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SolutionDiff {
+    pub sheet_count_delta: i64,
+    /// In percentage points.
+    pub waste_percent_delta: f64,
+    pub changes: Vec<PieceChange>,
+}
+
+impl SolutionDiff {
+    /// Same sheet count and waste, and every piece where it was.
+    pub fn is_empty(&self) -> bool {
+        self.sheet_count_delta == 0 && self.waste_percent_delta == 0.0 && self.changes.is_empty()
+    }
+}
+
+/// A piece placed differently in the two solutions of a
+/// [`SolutionDiff`]: `None` on the side it is missing from.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PieceChange {
+    pub label: Option<String>,
+    pub requested: Rect,
+    pub before: Option<PiecePosition>,
+    pub after: Option<PiecePosition>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct PiecePosition {
+    pub sheet: usize,
+    pub x: u32,
+    pub y: u32,
+    pub rotated: bool,
 }
 
 #[cfg(test)]
@@ -203,57 +985,134 @@ mod tests {
     fn test_rotation_constraint_from_grain() {
         use RotationConstraint::*;
 
-        // allow_rotate=false always → NoRotate
+        // allow_rotate=false → NoRotate, unless the grain forces a rotation
         assert_eq!(
             RotationConstraint::from_grain(StockGrain::AlongLength, PieceGrain::Length, false),
-            NoRotate
+            Ok(NoRotate)
         );
         assert_eq!(
             RotationConstraint::from_grain(StockGrain::AlongWidth, PieceGrain::Width, false),
-            NoRotate
+            Ok(NoRotate)
         );
 
         // stock grain=None → Free regardless of piece grain
         assert_eq!(
             RotationConstraint::from_grain(StockGrain::None, PieceGrain::Length, true),
-            Free
+            Ok(Free)
         );
         assert_eq!(
             RotationConstraint::from_grain(StockGrain::None, PieceGrain::Width, true),
-            Free
+            Ok(Free)
         );
 
         // piece grain=Auto → Free regardless of stock grain
         assert_eq!(
             RotationConstraint::from_grain(StockGrain::AlongLength, PieceGrain::Auto, true),
-            Free
+            Ok(Free)
         );
         assert_eq!(
             RotationConstraint::from_grain(StockGrain::AlongWidth, PieceGrain::Auto, true),
-            Free
+            Ok(Free)
         );
 
         // Natural alignment → NoRotate
         assert_eq!(
             RotationConstraint::from_grain(StockGrain::AlongLength, PieceGrain::Length, true),
-            NoRotate
+            Ok(NoRotate)
         );
         assert_eq!(
             RotationConstraint::from_grain(StockGrain::AlongWidth, PieceGrain::Width, true),
-            NoRotate
+            Ok(NoRotate)
         );
 
         // Cross alignment → ForceRotate
         assert_eq!(
             RotationConstraint::from_grain(StockGrain::AlongLength, PieceGrain::Width, true),
-            ForceRotate
+            Ok(ForceRotate)
         );
         assert_eq!(
             RotationConstraint::from_grain(StockGrain::AlongWidth, PieceGrain::Length, true),
-            ForceRotate
+            Ok(ForceRotate)
+        );
+
+        // Grain needs a rotation that allow_rotate=false forbids
+        let conflict =
+            RotationConstraint::from_grain(StockGrain::AlongLength, PieceGrain::Width, false)
+                .unwrap_err();
+        assert_eq!(
+            conflict.to_string(),
+            "piece grain Width needs a 90° rotation on AlongLength stock, but rotation is disabled"
+        );
+        assert!(
+            RotationConstraint::from_grain(StockGrain::AlongWidth, PieceGrain::Length, false)
+                .is_err()
+        );
+        assert_eq!(
+            RotationConstraint::from_grain(StockGrain::AlongLength, PieceGrain::Auto, false),
+            Ok(NoRotate)
         );
     }
 
+    #[test]
+    fn test_orientation_label() {
+        use crate::guillotine::{GuillotineBin, ScoreStrategy};
+
+        let mut bin = GuillotineBin::new(Rect::new(100, 100), 0, CutDirection::Auto);
+        let piece = Rect::new(40, 20);
+        let scored = bin
+            .find_best(
+                piece,
+                RotationConstraint::NoRotate,
+                ScoreStrategy::BestAreaFit,
+            )
+            .unwrap();
+        let p = bin.place(scored, piece);
+        assert!(!p.rotated);
+        assert_eq!(p.orientation_deg, 0);
+        assert_eq!(p.orientation_label(), "0°");
+
+        let scored = bin
+            .find_best(
+                piece,
+                RotationConstraint::ForceRotate,
+                ScoreStrategy::BestAreaFit,
+            )
+            .unwrap();
+        let p = bin.place(scored, piece);
+        assert!(p.rotated);
+        assert_eq!(p.orientation_deg, 90);
+        assert_eq!(p.orientation_label(), "90°");
+    }
+
+    #[test]
+    fn test_demand_builder() {
+        let manual = Demand {
+            rect: Rect::new(800, 600),
+            qty: 3,
+            allow_rotate: false,
+            grain: PieceGrain::Length,
+            material: None,
+            clearance: [0; 4],
+            label: None,
+            back_grain: PieceGrain::Auto,
+            directional: false,
+            group_hint: None,
+            grain_group: None,
+            fragile: false,
+            optional_overflow: 0,
+        };
+        let built = Demand::new(Rect::new(800, 600), 3)
+            .with_allow_rotate(false)
+            .with_grain(PieceGrain::Length);
+        assert_eq!(built, manual);
+
+        // Defaults: rotation allowed, no grain constraint
+        let default = Demand::new(Rect::new(800, 600), 3);
+        assert!(default.allow_rotate);
+        assert_eq!(default.grain, PieceGrain::Auto);
+        assert_eq!(Demand::default().qty, 1);
+    }
+
     #[test]
     fn test_with_cut_direction() {
         use RotationConstraint::*;
@@ -306,4 +1165,347 @@ mod tests {
             Free
         );
     }
+
+    fn placed(length: u32, width: u32, x: u32, y: u32) -> Placement {
+        Placement::new(Rect::new(length, width), x, y)
+    }
+
+    fn one_sheet(stock: Rect, placements: Vec<Placement>) -> Solution {
+        Solution {
+            sheets: vec![SheetResult {
+                stock,
+                stock_rotated: false,
+                placements,
+                waste_area: 0,
+                material: None,
+                offcuts: vec![],
+                reserved: None,
+                cut_direction: CutDirection::Auto,
+            }],
+            stock,
+            warnings: vec![],
+            unplaced: vec![],
+        }
+    }
+
+    #[test]
+    fn test_group_identical_sheets() {
+        let stock = Rect::new(100, 100);
+        let mut solution = one_sheet(stock, vec![placed(50, 50, 0, 0), placed(50, 40, 50, 0)]);
+        // Same placements listed the other way round
+        let mut swapped = solution.sheets[0].clone();
+        swapped.placements.reverse();
+        for _ in 0..3 {
+            solution.sheets.push(solution.sheets[0].clone());
+            solution.sheets.push(swapped.clone());
+        }
+        let mut other = solution.sheets[0].clone();
+        other.placements[1].y = 10;
+        solution.sheets.insert(2, other);
+        assert_eq!(solution.sheet_count(), 8);
+
+        let groups = solution.group_identical_sheets();
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups[0].1, 7);
+        assert_eq!(groups[0].0.placements.len(), 2);
+        assert_eq!(groups[1].1, 1);
+        assert_eq!(groups[1].0.placements[1].y, 10);
+        assert_eq!(
+            solution.identical_sheet_groups(),
+            [vec![0, 1, 3, 4, 5, 6, 7], vec![2]]
+        );
+    }
+
+    #[test]
+    fn test_solution_diff() {
+        let labelled = |x, y| Placement {
+            label: Some("A".to_string()),
+            ..placed(50, 50, x, y)
+        };
+        let stock = Rect::new(100, 100);
+        let base = one_sheet(
+            stock,
+            vec![labelled(0, 0), labelled(50, 0), placed(20, 20, 0, 50)],
+        );
+        assert!(base.diff(&base).is_empty());
+
+        // One A goes to a new sheet, the 20x20 moves, a 10x10 appears
+        let mut modified = base.clone();
+        modified.sheets[0].placements.remove(1);
+        modified.sheets[0].placements[1].x = 60;
+        let mut second = modified.sheets[0].clone();
+        second.placements = vec![labelled(0, 0), placed(10, 10, 50, 0)];
+        modified.sheets.push(second);
+
+        let diff = base.diff(&modified);
+        assert_eq!(diff.sheet_count_delta, 1);
+        assert!((diff.waste_percent_delta - 26.5).abs() < 1e-9);
+        let at = |sheet, x, y| {
+            Some(PiecePosition {
+                sheet,
+                x,
+                y,
+                rotated: false,
+            })
+        };
+        assert_eq!(
+            diff.changes,
+            [
+                PieceChange {
+                    label: Some("A".to_string()),
+                    requested: Rect::new(50, 50),
+                    before: at(0, 50, 0),
+                    after: at(1, 0, 0),
+                },
+                PieceChange {
+                    label: None,
+                    requested: Rect::new(20, 20),
+                    before: at(0, 0, 50),
+                    after: at(0, 60, 50),
+                },
+                PieceChange {
+                    label: None,
+                    requested: Rect::new(10, 10),
+                    before: None,
+                    after: at(1, 50, 0),
+                },
+            ]
+        );
+        assert!(!diff.is_empty());
+    }
+
+    #[test]
+    fn test_validate_accepts_solver_output() {
+        use crate::solver::Solver;
+
+        let solution = Solver::new(
+            Rect::new(2440, 1220),
+            3,
+            CutDirection::Auto,
+            StockGrain::None,
+            vec![
+                Demand::new(Rect::new(800, 600), 5),
+                Demand::new(Rect::new(400, 300), 7),
+            ],
+        )
+        .solve();
+        assert_eq!(solution.validate(), Ok(()));
+    }
+
+    #[test]
+    fn test_rotate_layout_90() {
+        use crate::solver::Solver;
+
+        let stock = Rect::new(2440, 1220);
+        let solution = Solver::new(
+            stock,
+            3,
+            CutDirection::AlongLength,
+            StockGrain::None,
+            vec![
+                Demand::new(Rect::new(800, 600), 5),
+                Demand::new(Rect::new(400, 300), 7),
+            ],
+        )
+        .solve();
+
+        let mut turned = solution.clone();
+        turned.rotate_layout_90();
+        assert_eq!(turned.stock, Rect::new(1220, 2440));
+        assert_eq!(turned.validate(), Ok(()));
+        for (sheet, original) in turned.sheets.iter().zip(&solution.sheets) {
+            assert_eq!(sheet.cut_direction, CutDirection::AlongWidth);
+            for (p, q) in sheet.placements.iter().zip(&original.placements) {
+                assert!(p.x + p.rect.length <= sheet.stock.length);
+                assert!(p.y + p.rect.width <= sheet.stock.width);
+                assert_eq!((p.x, p.y, p.rotated), (q.y, q.x, !q.rotated));
+                assert_eq!(p.original_size(), q.original_size());
+            }
+        }
+
+        turned.rotate_layout_90();
+        assert_eq!(
+            serde_json::to_value(&turned).unwrap(),
+            serde_json::to_value(&solution).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_validate_reports_overlap_and_out_of_bounds() {
+        let solution = one_sheet(
+            Rect::new(100, 100),
+            vec![
+                placed(50, 50, 0, 0),
+                placed(50, 50, 50, 0),
+                placed(50, 50, 25, 25),
+                placed(30, 30, 80, 80),
+            ],
+        );
+        let violations = solution.validate().unwrap_err();
+        assert_eq!(
+            violations,
+            vec![
+                Violation::Overlap {
+                    sheet: 0,
+                    first: 0,
+                    second: 2
+                },
+                Violation::Overlap {
+                    sheet: 0,
+                    first: 1,
+                    second: 2
+                },
+                Violation::OutOfBounds { sheet: 0, piece: 3 },
+            ]
+        );
+        assert_eq!(violations[0].to_string(), "sheet 0: pieces 0 and 2 overlap");
+
+        // Touching edges is not an overlap.
+        let touching = one_sheet(
+            Rect::new(100, 100),
+            vec![placed(50, 100, 0, 0), placed(50, 100, 50, 0)],
+        );
+        assert_eq!(touching.validate(), Ok(()));
+    }
+
+    #[test]
+    fn test_stock_usage() {
+        let sheet = |stock: Rect, placements: Vec<Placement>| SheetResult {
+            stock,
+            stock_rotated: false,
+            waste_area: stock.area() - placements.iter().map(|p| p.rect.area()).sum::<u64>(),
+            placements,
+            material: None,
+            offcuts: vec![],
+            reserved: None,
+            cut_direction: CutDirection::Auto,
+        };
+        let small = Rect::new(2440, 1220);
+        let large = Rect::new(3050, 1525);
+        let full = || vec![placed(2440, 1220, 0, 0)];
+        let solution = Solution {
+            sheets: vec![
+                sheet(small, full()),
+                sheet(large, vec![]),
+                sheet(small, full()),
+                sheet(small, full()),
+            ],
+            stock: small,
+            warnings: vec![],
+            unplaced: vec![],
+        };
+        let usage = solution.stock_usage();
+        assert_eq!(usage, vec![(small, 3), (large, 1)]);
+        let total: usize = usage.iter().map(|(_, n)| n).sum();
+        assert_eq!(total, solution.sheet_count());
+        // Only the large sheet is waste, weighed against each sheet's own stock
+        let expected = large.area() as f64 / (3 * small.area() + large.area()) as f64 * 100.0;
+        assert!((solution.total_waste_percent() - expected).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_offcut_histogram() {
+        let offcut = |l, w| Offcut {
+            x: 0,
+            y: 0,
+            rect: Rect::new(l, w),
+        };
+        let stock = Rect::new(1000, 1000);
+        let sheet = |offcuts| SheetResult {
+            stock,
+            stock_rotated: false,
+            placements: vec![],
+            waste_area: stock.area(),
+            material: None,
+            offcuts,
+            reserved: None,
+            cut_direction: CutDirection::Auto,
+        };
+        let solution = Solution {
+            sheets: vec![
+                sheet(vec![offcut(10, 10), offcut(100, 100), offcut(500, 300)]),
+                sheet(vec![offcut(20, 5), offcut(100, 101)]),
+            ],
+            stock,
+            warnings: vec![],
+            unplaced: vec![],
+        };
+        // Areas 100, 10000, 150000, 100 and 10100
+        assert_eq!(
+            solution.offcut_histogram(&[100, 10_000, 100_000]),
+            [2, 1, 1, 1]
+        );
+        assert_eq!(solution.offcut_histogram(&[]), [5]);
+    }
+
+    #[test]
+    fn test_waste_percent_huge_stock() {
+        // 4 sheets of 1.6e19 mm² each: the total overflows u64
+        let stock = Rect::new(4_000_000_000, 4_000_000_000);
+        let half = placed(2_000_000_000, 4_000_000_000, 0, 0);
+        let sheet = SheetResult {
+            stock,
+            stock_rotated: false,
+            waste_area: stock.area() - half.rect.area(),
+            placements: vec![half],
+            material: None,
+            offcuts: vec![],
+            reserved: None,
+            cut_direction: CutDirection::Auto,
+        };
+        let solution = Solution {
+            sheets: vec![sheet; 4],
+            stock,
+            warnings: vec![],
+            unplaced: vec![],
+        };
+        assert!((solution.total_waste_percent() - 50.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_placement_overlaps() {
+        let a = placed(50, 50, 0, 0);
+
+        // Touching edges: fine without kerf, too close with one
+        let touching = placed(50, 50, 50, 0);
+        assert!(!a.overlaps(&touching, 0));
+        assert!(a.overlaps(&touching, 3));
+
+        // Separated by exactly the kerf
+        let kerf_apart = placed(50, 50, 53, 0);
+        assert!(!a.overlaps(&kerf_apart, 3));
+        assert!(!kerf_apart.overlaps(&a, 3));
+        assert!(a.overlaps(&kerf_apart, 4));
+
+        // Diagonal neighbour, kerf apart on one axis only
+        let diagonal = placed(50, 50, 51, 53);
+        assert!(!a.overlaps(&diagonal, 3));
+
+        let overlapping = placed(50, 50, 25, 25);
+        assert!(a.overlaps(&overlapping, 0));
+        assert!(overlapping.overlaps(&a, 0));
+    }
+
+    #[test]
+    fn test_placement_contains_point() {
+        let p = placed(50, 30, 10, 20);
+        assert!(p.contains_point(10, 20));
+        assert!(p.contains_point(59, 49));
+        assert!(!p.contains_point(60, 20));
+        assert!(!p.contains_point(10, 50));
+        assert!(!p.contains_point(9, 25));
+    }
+
+    #[test]
+    fn test_used_bounds() {
+        let stock = Rect::new(200, 100);
+        let solution = one_sheet(stock, vec![placed(40, 30, 0, 0), placed(50, 60, 30, 40)]);
+        let (x, y, bounds) = solution.sheets[0].used_bounds();
+        assert_eq!((x, y), (0, 0));
+        assert_eq!(bounds, Rect::new(80, 100));
+        assert!(bounds.length < stock.length);
+
+        let empty = one_sheet(stock, vec![]);
+        assert_eq!(empty.sheets[0].used_bounds(), (0, 0, Rect::new(0, 0)));
+    }
 }
@@ -0,0 +1,94 @@
+//! Conversions between millimetres, what the solver works in, and inches.
+//!
+//! Dimensions stay integers: inches are counted on a grid of
+//! [`INCH_GRID`] steps per inch, so `96x48` inches is `1536x768`.
+
+use crate::types::{self, Demand, Rect};
+use std::fmt;
+
+/// Steps per inch on the inch grid.
+pub const INCH_GRID: u32 = 16;
+
+/// Tenths of a millimetre per inch, so conversions are exact ratios.
+const TENTHS_MM_PER_INCH: u32 = 254;
+
+/// Unit dimensions are given in.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Units {
+    #[default]
+    Mm,
+    Inch,
+}
+
+impl fmt::Display for Units {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Units::Mm => write!(f, "mm"),
+            Units::Inch => write!(f, "in"),
+        }
+    }
+}
+
+/// A rect in millimetres, on the inch grid.
+pub fn rect_to_inch_grid(rect: Rect) -> Rect {
+    rect.scale(INCH_GRID * 10, TENTHS_MM_PER_INCH)
+}
+
+/// A rect on the inch grid, in millimetres.
+pub fn rect_to_mm(rect: Rect) -> Rect {
+    rect.scale(TENTHS_MM_PER_INCH, INCH_GRID * 10)
+}
+
+/// Demands sized in millimetres, resized on the inch grid. Clearances are
+/// converted too; everything else is kept.
+pub fn mm_to_inch_grid(demands: &[Demand]) -> Vec<Demand> {
+    convert(demands, INCH_GRID * 10, TENTHS_MM_PER_INCH)
+}
+
+/// Demands sized on the inch grid, resized in millimetres.
+pub fn inch_to_mm(demands: &[Demand]) -> Vec<Demand> {
+    convert(demands, TENTHS_MM_PER_INCH, INCH_GRID * 10)
+}
+
+fn convert(demands: &[Demand], num: u32, den: u32) -> Vec<Demand> {
+    demands
+        .iter()
+        .map(|d| Demand {
+            rect: d.rect.scale(num, den),
+            clearance: d.clearance.map(|c| types::scale(c, num, den)),
+            ..d.clone()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rect_round_trip() {
+        // Inch grid -> mm -> inch grid is exact: a grid step is over 1 mm
+        for rect in [Rect::new(1536, 768), Rect::new(1, 3), Rect::new(917, 45)] {
+            assert_eq!(rect_to_inch_grid(rect_to_mm(rect)), rect);
+        }
+        // mm -> inch grid -> mm is off by at most half a grid step
+        for rect in [Rect::new(2440, 1220), Rect::new(7, 1), Rect::new(613, 299)] {
+            let back = rect_to_mm(rect_to_inch_grid(rect));
+            assert!(back.length.abs_diff(rect.length) <= 1, "{rect} -> {back}");
+            assert!(back.width.abs_diff(rect.width) <= 1, "{rect} -> {back}");
+        }
+        assert_eq!(Rect::new(10, 15).scale(1, 10), Rect::new(1, 2));
+        assert_eq!(Rect::new(u32::MAX, 1).scale(2, 1), Rect::new(u32::MAX, 2));
+    }
+
+    #[test]
+    fn test_convert_demands() {
+        let demands =
+            vec![Demand::new(Rect::new(96 * INCH_GRID, 48 * INCH_GRID), 2).with_label("door")];
+        let mm = inch_to_mm(&demands);
+        assert_eq!(mm[0].rect, Rect::new(2438, 1219));
+        assert_eq!(mm[0].qty, 2);
+        assert_eq!(mm[0].label.as_deref(), Some("door"));
+        assert_eq!(mm_to_inch_grid(&mm)[0].rect, demands[0].rect);
+    }
+}